@@ -0,0 +1,253 @@
+use crate::error::ESResult;
+use crate::foojay::ArchiveType;
+use crate::jobs;
+use derive_more::Display;
+use error_stack::{Context, Report, ResultExt};
+use indicatif::ProgressBar;
+use owo_colors::{OwoColorize, Stream};
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use tracing::warn;
+
+#[derive(Debug, Display)]
+#[display("Archive extraction error")]
+pub struct ArchiveError;
+
+impl Context for ArchiveError {}
+
+/// Extract the archive read from `reader` into `dest`. Entries with paths leaving `dest` are
+/// skipped. `progress` counts the extracted bytes, where the format allows, and shows the entry
+/// being extracted.
+pub fn extract<R: Read + Seek>(
+    archive_type: &ArchiveType,
+    reader: R,
+    dest: &Path,
+    progress: &ProgressBar,
+) -> ESResult<(), ArchiveError> {
+    match archive_type {
+        ArchiveType::TarGz => extract_tar(flate2::read::GzDecoder::new(reader), dest, progress),
+        ArchiveType::TarZst => extract_tar(
+            zstd::stream::read::Decoder::new(reader)
+                .change_context(ArchiveError)
+                .attach_printable("Could not start zstd decompression")?,
+            dest,
+            progress,
+        ),
+        ArchiveType::Zip => extract_zip(reader, dest, progress),
+        ArchiveType::Unknown(archive_type) => Err(Report::new(ArchiveError)
+            .attach_printable(format!("Unsupported archive type {}", archive_type))),
+    }
+}
+
+fn extract_tar(
+    reader: impl Read,
+    dest: &Path,
+    progress: &ProgressBar,
+) -> ESResult<(), ArchiveError> {
+    let mut archive = tar::Archive::new(progress.wrap_read(reader));
+    archive.set_preserve_permissions(true);
+    archive.set_overwrite(true);
+    let entries = archive
+        .entries()
+        .change_context(ArchiveError)
+        .attach_printable("Could not read tar archive")?;
+    for entry in entries {
+        jobs::check_cancelled().change_context(ArchiveError)?;
+        let mut entry = entry
+            .change_context(ArchiveError)
+            .attach_printable("Could not read tar entry")?;
+        let archive_path = entry
+            .path()
+            .change_context(ArchiveError)
+            .attach_printable("Could not read tar entry path")?
+            .into_owned();
+        show_entry(progress, &archive_path.display().to_string());
+        let unpacked = entry
+            .unpack_in(dest)
+            .change_context(ArchiveError)
+            .attach_printable_lazy(|| format!("Could not extract {:?}", archive_path))?;
+        if !unpacked {
+            warn!("Not extracting file with unsafe path: {:?}", archive_path);
+        }
+    }
+    Ok(())
+}
+
+fn extract_zip(
+    reader: impl Read + Seek,
+    dest: &Path,
+    progress: &ProgressBar,
+) -> ESResult<(), ArchiveError> {
+    let mut archive = zip::ZipArchive::new(reader)
+        .change_context(ArchiveError)
+        .attach_printable("Could not read ZIP archive")?;
+    for i in 0..archive.len() {
+        jobs::check_cancelled().change_context(ArchiveError)?;
+        let mut file = archive
+            .by_index(i)
+            .change_context(ArchiveError)
+            .attach_printable_lazy(|| format!("Could not read ZIP entry {}", i))?;
+        let Some(archive_path) = file.enclosed_name() else {
+            warn!("Not extracting file with unsafe path: {:?}", file.name());
+            continue;
+        };
+        show_entry(progress, file.name());
+        let path = dest.join(&archive_path);
+        if file.is_dir() {
+            std::fs::create_dir_all(&path)
+                .change_context(ArchiveError)
+                .attach_printable_lazy(|| format!("Could not create directory {:?}", path))?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .change_context(ArchiveError)
+                .attach_printable_lazy(|| format!("Could not create directory {:?}", parent))?;
+        }
+        let mut extracted_file = File::create(&path)
+            .change_context(ArchiveError)
+            .attach_printable_lazy(|| format!("Could not create extracted file {:?}", path))?;
+        std::io::copy(&mut progress.wrap_read(&mut file), &mut extracted_file)
+            .change_context(ArchiveError)
+            .attach_printable_lazy(|| format!("Could not write extracted file {:?}", path))?;
+        // Keep `bin/java` and friends executable.
+        if let Some(mode) = file.unix_mode() {
+            extracted_file
+                .set_permissions(std::fs::Permissions::from_mode(mode & 0o7777))
+                .change_context(ArchiveError)
+                .attach_printable_lazy(|| format!("Could not set permissions of {:?}", path))?;
+        }
+    }
+    Ok(())
+}
+
+fn show_entry(progress: &ProgressBar, name: &str) {
+    progress.set_message(
+        format!(
+            "Extracting {}",
+            name.if_supports_color(Stream::Stderr, |s| s.cyan())
+        )
+        .if_supports_color(Stream::Stderr, |s| s.green())
+        .to_string(),
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{Cursor, Write};
+
+    /// `(path, contents, mode)` of the files in the test archives.
+    const FILES: [(&str, &str, u32); 2] = [
+        ("jdk-17/bin/java", "#!/bin/sh\n", 0o755),
+        ("jdk-17/release", "JAVA_VERSION=\"17.0.2\"\n", 0o644),
+    ];
+
+    fn tar<W: Write>(writer: W) -> W {
+        let mut builder = tar::Builder::new(writer);
+        for (path, contents, mode) in FILES {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(mode);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, contents.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn tar_gz() -> Vec<u8> {
+        tar(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        ))
+        .finish()
+        .unwrap()
+    }
+
+    fn tar_zst() -> Vec<u8> {
+        tar(zstd::stream::write::Encoder::new(Vec::new(), 0).unwrap())
+            .finish()
+            .unwrap()
+    }
+
+    fn zip() -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        writer
+            .add_directory("jdk-17/", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        for (path, contents, mode) in FILES {
+            writer
+                .start_file(
+                    path,
+                    zip::write::SimpleFileOptions::default().unix_permissions(mode),
+                )
+                .unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn assert_extracted(dest: &Path) {
+        for (path, contents, mode) in FILES {
+            let path = dest.join(path);
+            assert_eq!(contents, std::fs::read_to_string(&path).unwrap());
+            let actual = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode, actual & 0o777, "mode of {:?}", path);
+        }
+    }
+
+    #[test]
+    fn test_extract_tar_gz() {
+        let dest = tempfile::tempdir().unwrap();
+        extract(
+            &ArchiveType::TarGz,
+            Cursor::new(tar_gz()),
+            dest.path(),
+            &ProgressBar::hidden(),
+        )
+        .unwrap();
+        assert_extracted(dest.path());
+    }
+
+    #[test]
+    fn test_extract_tar_zst() {
+        let dest = tempfile::tempdir().unwrap();
+        extract(
+            &ArchiveType::TarZst,
+            Cursor::new(tar_zst()),
+            dest.path(),
+            &ProgressBar::hidden(),
+        )
+        .unwrap();
+        assert_extracted(dest.path());
+    }
+
+    #[test]
+    fn test_extract_zip() {
+        let dest = tempfile::tempdir().unwrap();
+        extract(
+            &ArchiveType::Zip,
+            Cursor::new(zip()),
+            dest.path(),
+            &ProgressBar::hidden(),
+        )
+        .unwrap();
+        assert_extracted(dest.path());
+    }
+
+    #[test]
+    fn test_extract_unknown() {
+        let dest = tempfile::tempdir().unwrap();
+        assert!(extract(
+            &ArchiveType::Unknown("rar".to_string()),
+            Cursor::new(Vec::new()),
+            dest.path(),
+            &ProgressBar::hidden(),
+        )
+        .is_err());
+    }
+}
@@ -1,45 +1,150 @@
-use digest::{Digest, DynDigest};
+use crate::error::ESResult;
+use crate::foojay::ChecksumType;
+use derive_more::Display;
+use digest::DynDigest;
+use error_stack::{Context, Report};
 use std::io::Write;
 
-pub struct ChecksumVerifier<T, W> {
-    checksum: Box<[u8]>,
-    checksummer: Box<T>,
+#[derive(Debug, Display)]
+#[display("Invalid checksum")]
+pub struct ChecksumError;
+
+impl Context for ChecksumError {}
+
+/// Creates the digest of a checksum algorithm.
+type DigestFactory = fn() -> Box<dyn DynDigest + Send>;
+
+/// The algorithms downloads can be verified with, by their Foojay name. A new algorithm only needs
+/// an entry here.
+const DIGESTS: &[(&str, DigestFactory)] = &[
+    ("sha256", || Box::new(sha2::Sha256::default())),
+    ("sha512", || Box::new(sha2::Sha512::default())),
+    #[cfg(feature = "sha1")]
+    ("sha1", || Box::new(sha1::Sha1::default())),
+];
+
+fn digest_factory(checksum_type: &ChecksumType) -> Option<DigestFactory> {
+    DIGESTS
+        .iter()
+        .find(|(name, _)| *name == checksum_type.name())
+        .map(|(_, factory)| *factory)
+}
+
+/// Check that downloads can be verified against `checksums`: each has a supported type, and is a
+/// well-formed digest of that type.
+pub fn validate(checksums: &[Checksum]) -> ESResult<(), ChecksumError> {
+    ChecksumVerifier::new(checksums, std::io::sink()).map(|_| ())
+}
+
+/// A checksum a download is expected to have.
+#[derive(Debug, Clone, Display)]
+#[display("{checksum_type} {value}")]
+pub struct Checksum {
+    pub checksum_type: ChecksumType,
+    /// Hex-encoded.
+    pub value: String,
+}
+
+/// Verifies everything written through it against one or more checksums.
+pub struct ChecksumVerifier<W> {
+    digests: Vec<ExpectedDigest>,
     delegate: W,
 }
 
-impl<T: DynDigest, W: Write> ChecksumVerifier<T, W> {
-    pub fn new(checksum: &str, checksummer: Box<T>, delegate: W) -> Self {
-        let checksum = hex::decode(checksum)
-            .unwrap_or_else(|_| {
-                panic!("Failed to decode checksum: {}", checksum);
-            })
-            .into_boxed_slice();
-        if checksum.len() != checksummer.output_size() {
-            panic!(
-                "Checksum has incorrect length: expected {}, got {}",
-                checksummer.output_size(),
-                checksum.len()
-            );
-        }
-        Self {
-            checksum,
-            checksummer,
-            delegate,
+struct ExpectedDigest {
+    checksum: Checksum,
+    expected: Box<[u8]>,
+    digest: Box<dyn DynDigest + Send>,
+}
+
+impl<W: Write> ChecksumVerifier<W> {
+    pub fn new(checksums: &[Checksum], delegate: W) -> ESResult<Self, ChecksumError> {
+        let mut digests = Vec::with_capacity(checksums.len());
+        for checksum in checksums {
+            let digest = digest_factory(&checksum.checksum_type).ok_or_else(|| {
+                Report::new(ChecksumError).attach_printable(format!(
+                    "Unsupported checksum type {}",
+                    checksum.checksum_type
+                ))
+            })?();
+            let expected = hex::decode(&checksum.value)
+                .ok()
+                .filter(|e| e.len() == digest.output_size())
+                .ok_or_else(|| {
+                    Report::new(ChecksumError)
+                        .attach_printable(format!("Malformed checksum {}", checksum))
+                })?;
+            digests.push(ExpectedDigest {
+                checksum: checksum.clone(),
+                expected: expected.into_boxed_slice(),
+                digest,
+            });
         }
+        Ok(Self { digests, delegate })
     }
 
-    pub fn verify(self) -> bool {
-        self.checksummer.finalize() == self.checksum
+    /// Check everything written against all checksums, returning the first that didn't match.
+    pub fn verify(self) -> Result<(), Checksum> {
+        for expected in self.digests {
+            if expected.digest.finalize() != expected.expected {
+                return Err(expected.checksum);
+            }
+        }
+        Ok(())
     }
 }
 
-impl<T: Digest, W: Write> Write for ChecksumVerifier<T, W> {
+impl<W: Write> Write for ChecksumVerifier<W> {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        self.checksummer.update(buf);
-        self.delegate.write(buf)
+        let written = self.delegate.write(buf)?;
+        for expected in &mut self.digests {
+            expected.digest.update(&buf[..written]);
+        }
+        Ok(written)
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
         self.delegate.flush()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn checksum(checksum_type: &str, value: &str) -> Checksum {
+        Checksum {
+            checksum_type: ChecksumType::Unknown(checksum_type.to_string()),
+            value: value.to_string(),
+        }
+    }
+
+    fn verify(checksums: &[Checksum], data: &[u8]) -> Result<(), Checksum> {
+        let mut verifier = ChecksumVerifier::new(checksums, std::io::sink()).unwrap();
+        verifier.write_all(data).unwrap();
+        verifier.verify()
+    }
+
+    const SHA256_ABC: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+    const SHA512_ABC: &str = "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f";
+
+    #[test]
+    fn test_verify_multiple() {
+        let both = [
+            checksum("sha256", SHA256_ABC),
+            checksum("sha512", SHA512_ABC),
+        ];
+        assert!(verify(&both, b"abc").is_ok());
+        let failed = verify(&both, b"abd").unwrap_err();
+        assert_eq!("sha256", failed.checksum_type.name());
+    }
+
+    #[test]
+    fn test_rejects_unusable_checksums() {
+        assert!(validate(&[checksum("sha256", SHA256_ABC)]).is_ok());
+        assert!(validate(&[checksum("md2", SHA256_ABC)]).is_err());
+        assert!(validate(&[checksum("sha256", "abc")]).is_err());
+        assert!(validate(&[checksum("sha256", &SHA256_ABC.replace('a', "g"))]).is_err());
+        assert!(validate(&[checksum("sha512", SHA256_ABC)]).is_err());
+    }
+}
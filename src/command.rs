@@ -1,19 +1,50 @@
 use crate::config::JpreConfig;
 use crate::error::{ESResult, JpreError};
+use crate::java_version::key::VersionKey;
+use crate::jdk_manager::JDK_MANAGER;
+use crate::platform::{self, Platform, ResolvedPlatform};
 use enum_dispatch::enum_dispatch;
+use error_stack::ResultExt;
 
+pub(super) mod api_dump;
+pub(super) mod available;
+pub(super) mod bench;
+pub(super) mod cache;
+pub(super) mod clean_downloads;
+pub(super) mod complete_versions;
 pub(super) mod current;
 pub(super) mod debug;
+pub(super) mod direnv;
+pub(super) mod doctor;
+pub(super) mod explain;
+pub(super) mod gc;
 pub(super) mod get_context_id;
+pub(super) mod hook_env;
+pub(super) mod info;
+pub(super) mod install;
 pub(super) mod java_home;
 pub(super) mod list_distributions;
 pub(super) mod list_installed;
 pub(super) mod list_versions;
+pub(super) mod migrate_from;
+pub(super) mod migrate_markers;
+pub(super) mod pack;
+pub(super) mod print_context_path;
+pub(super) mod prompt_segment;
+pub(super) mod relocate;
 pub(super) mod remove_jdk;
+pub(super) mod sbom;
+pub(super) mod search;
+pub(super) mod serve;
 pub(super) mod set_default;
 pub(super) mod set_distributions;
+pub(super) mod shell;
+pub(super) mod stats;
+pub(super) mod unpack;
 pub(super) mod update;
 pub(super) mod use_jdk;
+pub(super) mod verify;
+pub(super) mod verify_store;
 
 #[enum_dispatch]
 pub trait JpreCommand {
@@ -22,4 +53,21 @@ pub trait JpreCommand {
 
 pub struct Context {
     pub config: JpreConfig,
+    /// The platform JDKs are downloaded for, resolved once at startup.
+    pub platform: ResolvedPlatform,
+}
+
+impl Context {
+    /// The default JDK, taking `default_auto_latest_lts` into account.
+    pub fn default_jdk(&self) -> ESResult<Option<VersionKey>, JpreError> {
+        JDK_MANAGER
+            .resolve_default_jdk(&self.config)
+            .change_context(JpreError::Unexpected)
+            .attach_printable("Failed to resolve default JDK")
+    }
+
+    /// The platform JDKs are downloaded for, or a user error if it couldn't be detected.
+    pub fn platform(&self) -> ESResult<&Platform, JpreError> {
+        platform::require(&self.platform).change_context(JpreError::UserError)
+    }
 }
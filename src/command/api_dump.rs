@@ -0,0 +1,52 @@
+use crate::command::{Context, JpreCommand};
+use crate::error::{ESResult, JpreError};
+use crate::foojay::FOOJAY_API;
+use crate::java_version::key::VersionKey;
+use clap::Args;
+use error_stack::ResultExt;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Dump the raw Foojay responses for the package queries `use` would make. Useful for reporting
+/// upstream API issues.
+#[derive(Debug, Args)]
+pub struct ApiDump {
+    /// The JDK to query for.
+    jdk: VersionKey,
+    /// Write the dump to this file instead of stdout.
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+}
+
+impl JpreCommand for ApiDump {
+    fn run(self, context: Context) -> ESResult<(), JpreError> {
+        eprintln!("Querying packages for JDK {}...", self.jdk);
+        let dump = FOOJAY_API
+            .dump_package_queries(&context.config, context.platform()?, &self.jdk)
+            .change_context(JpreError::Unexpected)
+            .attach_printable("Failed to query Foojay API")?;
+        let mut json = serde_json::to_string_pretty(&dump)
+            .change_context(JpreError::Unexpected)
+            .attach_printable("Failed to serialize API dump")?;
+        json.push('\n');
+        match &self.output {
+            Some(path) => {
+                std::fs::write(path, json)
+                    .change_context(JpreError::Unexpected)
+                    .attach_printable_lazy(|| {
+                        format!("Failed to write API dump to {}", path.display())
+                    })?;
+                eprintln!("Wrote API dump to {}", path.display());
+            }
+            None => {
+                let mut stdout = std::io::stdout();
+                stdout
+                    .write_all(json.as_bytes())
+                    .and_then(|_| stdout.flush())
+                    .change_context(JpreError::Unexpected)
+                    .attach_printable("Failed to write API dump to stdout")?;
+            }
+        }
+        Ok(())
+    }
+}
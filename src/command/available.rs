@@ -0,0 +1,75 @@
+use crate::command::{Context, JpreCommand};
+use crate::error::{ESResult, JpreError};
+use crate::foojay::{FoojayMajorVersion, TermOfSupport, FOOJAY_API};
+use crate::tui::jdk_color;
+use clap::Args;
+use error_stack::ResultExt;
+use owo_colors::{OwoColorize, Stream};
+
+/// List all Java major versions, in any distribution. Use `list-versions` for the version keys of
+/// a single distribution.
+#[derive(Debug, Args)]
+pub struct Available {
+    /// Only show major versions that still get updates.
+    #[clap(long)]
+    maintained: bool,
+}
+
+impl JpreCommand for Available {
+    fn run(self, _context: Context) -> ESResult<(), JpreError> {
+        let mut majors = FOOJAY_API
+            .list_major_versions()
+            .change_context(JpreError::Unexpected)
+            .attach_printable("Failed to list major versions")?;
+        majors.sort_by_key(|m| std::cmp::Reverse(m.major_version));
+        for major in majors {
+            if self.maintained && !major.maintained {
+                continue;
+            }
+            println!(
+                "- {} ({})",
+                major
+                    .major_version
+                    .if_supports_color(Stream::Stdout, |s| s.color(jdk_color())),
+                annotations(&major).join(", ")
+            );
+        }
+        Ok(())
+    }
+}
+
+/// The release status, term of support and maintenance of a major version.
+fn annotations(major: &FoojayMajorVersion) -> Vec<&str> {
+    let mut annotations = vec![if major.early_access_only { "EA" } else { "GA" }];
+    match &major.term_of_support {
+        TermOfSupport::Lts => annotations.push("LTS"),
+        TermOfSupport::Mts => annotations.push("MTS"),
+        TermOfSupport::Sts => annotations.push("STS"),
+        TermOfSupport::Unknown(term) if !term.is_empty() => annotations.push(term),
+        TermOfSupport::Unknown(_) => {}
+    }
+    annotations.push(if major.maintained {
+        "maintained"
+    } else {
+        "end-of-life"
+    });
+    annotations
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_annotations() {
+        let majors: Vec<FoojayMajorVersion> = serde_json::from_str(
+            r#"[
+                {"major_version": 25, "term_of_support": "LTS", "maintained": true, "early_access_only": true},
+                {"major_version": 22, "term_of_support": "STS", "maintained": false}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(vec!["EA", "LTS", "maintained"], annotations(&majors[0]));
+        assert_eq!(vec!["GA", "STS", "end-of-life"], annotations(&majors[1]));
+    }
+}
@@ -0,0 +1,261 @@
+use crate::command::use_jdk::UseTarget;
+use crate::command::{Context, JpreCommand};
+use crate::error::{ESResult, JpreError, UserMessage};
+use crate::java_version::key::VersionKey;
+use crate::jdk_manager::JDK_MANAGER;
+use crate::tui::{jdk_color, new_spinner};
+use clap::Args;
+use error_stack::{Report, ResultExt};
+use indicatif::ProgressBar;
+use owo_colors::{OwoColorize, Stream};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Compare JVM startup times of installed JDKs.
+#[derive(Debug, Args)]
+pub struct Bench {
+    /// The JDKs to benchmark. Version keys or 'default'.
+    #[clap(required = true)]
+    jdks: Vec<UseTarget>,
+    /// How many timed runs per measurement.
+    #[clap(short = 'n', long, default_value_t = 10)]
+    runs: u32,
+    /// Untimed runs before each measurement, to warm up disk caches.
+    #[clap(long, default_value_t = 1)]
+    warmup: u32,
+    /// Also time a HelloWorld program, with and without class data sharing (`-Xshare`).
+    #[clap(long)]
+    hello_world: bool,
+}
+
+const HELLO_WORLD_SOURCE: &str = "public class HelloWorld {\n    \
+    public static void main(String[] args) {\n        \
+        System.out.println(\"Hello, World!\");\n    \
+    }\n\
+}\n";
+
+impl JpreCommand for Bench {
+    fn run(self, context: Context) -> ESResult<(), JpreError> {
+        if self.runs == 0 {
+            return Err(Report::new(JpreError::UserError).attach(UserMessage {
+                message: "At least one run is needed".to_string(),
+            }));
+        }
+        let installed = JDK_MANAGER
+            .get_installed_jdks()
+            .change_context(JpreError::Unexpected)
+            .attach_printable("Failed to get installed JDKs")?;
+        let mut jdks = Vec::new();
+        for target in &self.jdks {
+            let jdk = target.resolve(&context)?;
+            if !installed.contains(&jdk) {
+                return Err(Report::new(JpreError::UserError).attach(UserMessage {
+                    message: format!("JDK {} is not installed", jdk),
+                }));
+            }
+            if !jdks.contains(&jdk) {
+                jdks.push(jdk);
+            }
+        }
+
+        let work_dir = tempfile::tempdir()
+            .change_context(JpreError::Unexpected)
+            .attach_printable("Failed to create temporary directory")?;
+        if self.hello_world {
+            std::fs::write(work_dir.path().join("HelloWorld.java"), HELLO_WORLD_SOURCE)
+                .change_context(JpreError::Unexpected)
+                .attach_printable("Failed to write HelloWorld.java")?;
+        }
+
+        for jdk in jdks {
+            self.bench_jdk(&jdk, work_dir.path())?;
+        }
+        Ok(())
+    }
+}
+
+impl Bench {
+    fn bench_jdk(&self, jdk: &VersionKey, work_dir: &Path) -> ESResult<(), JpreError> {
+        let java_home = JDK_MANAGER.get_jdk_install_path(jdk);
+        let java = java_home.join("bin/java");
+        let full_version = JDK_MANAGER
+            .get_full_version(jdk)
+            .change_context(JpreError::Unexpected)
+            .attach_printable_lazy(|| format!("Failed to get full version for {}", jdk))?;
+        println!(
+            "JDK {}{}",
+            jdk.if_supports_color(Stream::Stdout, |s| s.color(jdk_color())),
+            full_version
+                .map(|v| format!(" ({})", v))
+                .unwrap_or_default()
+        );
+
+        let mut measurements = vec![("java -version", vec!["-version".to_string()])];
+        if self.hello_world {
+            match Self::compile_hello_world(&java_home, jdk, work_dir) {
+                Ok(classes) => {
+                    let classes = classes.to_string_lossy().into_owned();
+                    for share in ["-Xshare:auto", "-Xshare:off"] {
+                        measurements.push((
+                            share,
+                            vec![
+                                share.to_string(),
+                                "-cp".to_string(),
+                                classes.clone(),
+                                "HelloWorld".to_string(),
+                            ],
+                        ));
+                    }
+                }
+                Err(e) => warn!("Not timing HelloWorld for {}: {:?}", jdk, e),
+            }
+        }
+
+        let progress = new_spinner(format!("Benchmarking JDK {}", jdk));
+        progress.set_length(measurements.len() as u64 * u64::from(self.warmup + self.runs));
+        let mut results = Vec::new();
+        for (name, args) in &measurements {
+            let samples = self.time_runs(&java, args, &progress)?;
+            results.push((
+                *name,
+                StartupStats::from_samples(&samples).expect("at least one run"),
+            ));
+        }
+        progress.finish_and_clear();
+
+        let width = results.iter().map(|(n, _)| n.len()).max().unwrap_or(0);
+        for (name, stats) in results {
+            println!("  {:width$}  {}", name, stats, width = width);
+        }
+        Ok(())
+    }
+
+    fn time_runs(
+        &self,
+        java: &Path,
+        args: &[String],
+        progress: &ProgressBar,
+    ) -> ESResult<Vec<Duration>, JpreError> {
+        let mut samples = Vec::with_capacity(self.runs as usize);
+        for i in 0..self.warmup + self.runs {
+            let start = Instant::now();
+            let status = Command::new(java)
+                .args(args)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .change_context(JpreError::Unexpected)
+                .attach_printable_lazy(|| format!("Failed to run {:?}", java))?;
+            let elapsed = start.elapsed();
+            if !status.success() {
+                return Err(Report::new(JpreError::Unexpected)
+                    .attach_printable(format!("{:?} {:?} failed: {}", java, args, status)));
+            }
+            if i >= self.warmup {
+                samples.push(elapsed);
+            }
+            progress.inc(1);
+        }
+        Ok(samples)
+    }
+
+    /// Compile HelloWorld with the JDK's own `javac`, so the class file version always fits.
+    fn compile_hello_world(
+        java_home: &Path,
+        jdk: &VersionKey,
+        work_dir: &Path,
+    ) -> ESResult<PathBuf, JpreError> {
+        let classes = work_dir.join(jdk.to_string());
+        let javac = java_home.join("bin/javac");
+        let status = Command::new(&javac)
+            .arg("-d")
+            .arg(&classes)
+            .arg(work_dir.join("HelloWorld.java"))
+            .stdin(Stdio::null())
+            .status()
+            .change_context(JpreError::Unexpected)
+            .attach_printable_lazy(|| format!("Failed to run {:?}", javac))?;
+        if !status.success() {
+            return Err(Report::new(JpreError::Unexpected)
+                .attach_printable(format!("{:?} failed: {}", javac, status)));
+        }
+        Ok(classes)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct StartupStats {
+    runs: usize,
+    min: Duration,
+    median: Duration,
+    mean: Duration,
+    max: Duration,
+    std_dev: Duration,
+}
+
+impl StartupStats {
+    fn from_samples(samples: &[Duration]) -> Option<StartupStats> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        let n = sorted.len();
+        let median = if n.is_multiple_of(2) {
+            (sorted[n / 2 - 1] + sorted[n / 2]) / 2
+        } else {
+            sorted[n / 2]
+        };
+        let mean = sorted.iter().map(Duration::as_secs_f64).sum::<f64>() / n as f64;
+        let variance = sorted
+            .iter()
+            .map(|d| (d.as_secs_f64() - mean).powi(2))
+            .sum::<f64>()
+            / n as f64;
+        Some(StartupStats {
+            runs: n,
+            min: sorted[0],
+            median,
+            mean: Duration::from_secs_f64(mean),
+            max: sorted[n - 1],
+            std_dev: Duration::from_secs_f64(variance.sqrt()),
+        })
+    }
+}
+
+impl std::fmt::Display for StartupStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "mean {:.1?} ± {:.1?}, median {:.1?}, min {:.1?}, max {:.1?} ({} runs)",
+            self.mean, self.std_dev, self.median, self.min, self.max, self.runs
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ms(ms: &[u64]) -> Vec<Duration> {
+        ms.iter().copied().map(Duration::from_millis).collect()
+    }
+
+    #[test]
+    fn test_startup_stats() {
+        assert_eq!(None, StartupStats::from_samples(&[]));
+        let stats = StartupStats::from_samples(&ms(&[50, 40, 30, 20])).unwrap();
+        assert_eq!(4, stats.runs);
+        assert_eq!(Duration::from_millis(20), stats.min);
+        assert_eq!(Duration::from_millis(35), stats.median);
+        assert_eq!(Duration::from_millis(35), stats.mean);
+        assert_eq!(Duration::from_millis(50), stats.max);
+        assert_eq!(11_180, stats.std_dev.as_micros());
+
+        let stats = StartupStats::from_samples(&ms(&[30, 10, 20])).unwrap();
+        assert_eq!(Duration::from_millis(20), stats.median);
+    }
+}
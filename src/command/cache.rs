@@ -0,0 +1,67 @@
+use crate::command::{Context, JpreCommand};
+use crate::error::{ESResult, JpreError};
+use crate::jdk_manager::{CachedArchiveStatus, JDK_MANAGER};
+use crate::tui::format_bytes;
+use clap::{Args, Subcommand};
+use error_stack::ResultExt;
+use owo_colors::{OwoColorize, Stream};
+
+/// Manage the download cache.
+#[derive(Debug, Args)]
+pub struct Cache {
+    #[clap(subcommand)]
+    subcommand: CacheSubcommand,
+}
+
+/// Cache subcommands.
+#[derive(Debug, Subcommand)]
+enum CacheSubcommand {
+    /// Check each cached archive against its checksum, and remove the corrupted ones so they're
+    /// downloaded again on the next install.
+    VerifyDownloads,
+}
+
+impl JpreCommand for Cache {
+    fn run(self, _context: Context) -> ESResult<(), JpreError> {
+        match self.subcommand {
+            CacheSubcommand::VerifyDownloads => verify_downloads(),
+        }
+    }
+}
+
+fn verify_downloads() -> ESResult<(), JpreError> {
+    let checks = JDK_MANAGER
+        .verify_downloads()
+        .change_context(JpreError::Unexpected)
+        .attach_printable("Failed to verify downloads")?;
+    let mut removed = 0;
+    let mut reclaimed_bytes = 0;
+    for check in &checks {
+        match check.status {
+            CachedArchiveStatus::Valid => {}
+            CachedArchiveStatus::Corrupted => {
+                removed += 1;
+                reclaimed_bytes += check.size;
+                eprintln!(
+                    "Removed corrupted {} ({})",
+                    check
+                        .archive
+                        .display()
+                        .if_supports_color(Stream::Stderr, |s| s.yellow()),
+                    format_bytes(check.size)
+                );
+            }
+            CachedArchiveStatus::Unverifiable => eprintln!(
+                "Skipped {}, its checksum type isn't supported",
+                check.archive.display()
+            ),
+        }
+    }
+    eprintln!(
+        "Verified {} cached archive(s), removed {} corrupted, reclaimed {}",
+        checks.len(),
+        removed,
+        format_bytes(reclaimed_bytes)
+    );
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use crate::command::{Context, JpreCommand};
+use crate::error::{ESResult, JpreError};
+use crate::jdk_manager::{DownloadCleanupPolicy, JDK_MANAGER};
+use crate::tui::format_bytes;
+use clap::Args;
+use error_stack::ResultExt;
+use std::time::Duration;
+
+/// Remove old download artifacts, such as cached archives and leftovers from interrupted
+/// downloads. Limits default to the ones in the config.
+#[derive(Debug, Args)]
+pub struct CleanDownloads {
+    /// Remove artifacts older than this many days.
+    #[clap(long)]
+    max_age_days: Option<u64>,
+    /// Remove the oldest artifacts until the rest fit in this many MiB.
+    #[clap(long)]
+    max_size_mb: Option<u64>,
+}
+
+impl JpreCommand for CleanDownloads {
+    fn run(self, context: Context) -> ESResult<(), JpreError> {
+        let mut policy = DownloadCleanupPolicy::from_config(&context.config);
+        if let Some(days) = self.max_age_days {
+            policy.max_age = Some(Duration::from_secs(days * 24 * 60 * 60));
+        }
+        if let Some(mb) = self.max_size_mb {
+            policy.max_size = Some(mb * 1024 * 1024);
+        }
+        let report = JDK_MANAGER
+            .clean_downloads(&policy)
+            .change_context(JpreError::Unexpected)
+            .attach_printable("Failed to clean downloads")?;
+        eprintln!(
+            "Removed {} download artifact(s), reclaimed {}",
+            report.removed,
+            format_bytes(report.reclaimed_bytes)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,27 @@
+use crate::command::{Context, JpreCommand};
+use crate::completion_index::CompletionIndex;
+use crate::error::{ESResult, JpreError};
+use crate::jdk_manager::JDK_MANAGER;
+use clap::Args;
+
+/// Print version keys for shell completion, from installed JDKs and the cached index. Never uses
+/// the network.
+#[derive(Debug, Args)]
+pub struct CompleteVersions {
+    /// Only print keys starting with this.
+    #[clap(default_value = "")]
+    prefix: String,
+}
+
+impl JpreCommand for CompleteVersions {
+    fn run(self, _context: Context) -> ESResult<(), JpreError> {
+        // Completion must stay quiet and fast, so errors just mean fewer candidates.
+        let mut installed = JDK_MANAGER.get_installed_jdks().unwrap_or_default();
+        installed.sort();
+        let index = CompletionIndex::read().unwrap_or_default();
+        for candidate in index.candidates(&installed, &self.prefix) {
+            println!("{}", candidate);
+        }
+        Ok(())
+    }
+}
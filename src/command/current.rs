@@ -2,35 +2,74 @@ use crate::command::{Context, JpreCommand};
 use crate::context_id::get_context_path;
 use crate::error::{ESResult, JpreError};
 use crate::jdk_manager::JDK_MANAGER;
-use clap::Args;
+use clap::{Args, ValueEnum};
 use error_stack::ResultExt;
+use std::path::PathBuf;
 
-/// Emit the full current Java version.
+/// Exit code of `current` when no JDK is active, so scripts can tell it apart from errors.
+pub const NO_ACTIVE_JDK_EXIT_CODE: i32 = 3;
+
+/// Emit the full current Java version. Exits with code 3 if no JDK is active.
 #[derive(Debug, Args)]
-pub struct Current {}
+pub struct Current {
+    /// Print nothing, only set the exit code.
+    #[clap(short, long)]
+    quiet: bool,
+    /// What to report if no JDK is set for this context.
+    #[clap(long, value_enum, default_value_t = CurrentFallback::None)]
+    fallback: CurrentFallback,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum CurrentFallback {
+    /// Report that no JDK is active.
+    None,
+    /// Report the default JDK, if it's installed.
+    Default,
+}
 
 impl JpreCommand for Current {
-    fn run(self, _context: Context) -> ESResult<(), JpreError> {
-        let path = get_context_path();
-        if !path.exists() {
-            println!("<unknown>");
-            return Ok(());
-        }
-        let link_target = std::fs::read_link(&path)
-            .change_context(JpreError::Unexpected)
-            .attach_printable_lazy(|| format!("Failed to read link target of {:?}", path))?;
+    fn run(self, context: Context) -> ESResult<(), JpreError> {
+        let Some(jdk_path) = self.active_jdk_path(&context)? else {
+            if !self.quiet {
+                println!("<unknown>");
+            }
+            std::process::exit(NO_ACTIVE_JDK_EXIT_CODE);
+        };
         let full_version = JDK_MANAGER
-            .get_full_version_from_path(&link_target)
+            .get_full_version_from_path(&jdk_path)
             .change_context(JpreError::Unexpected)
-            .attach_printable_lazy(|| format!("Failed to get full version of {:?}", link_target))?;
+            .attach_printable_lazy(|| format!("Failed to get full version of {:?}", jdk_path))?;
 
-        println!(
-            "{}",
-            full_version
-                .map(|v| v.to_string())
-                .unwrap_or("<unknown>".to_string())
-        );
+        if !self.quiet {
+            println!(
+                "{}",
+                full_version
+                    .map(|v| v.to_string())
+                    .unwrap_or("<unknown>".to_string())
+            );
+        }
 
         Ok(())
     }
 }
+
+impl Current {
+    /// The JDK the context link points to, or the default JDK if asked to fall back to it.
+    fn active_jdk_path(&self, context: &Context) -> ESResult<Option<PathBuf>, JpreError> {
+        let path = get_context_path(&context.config);
+        if path.exists() {
+            let link_target = std::fs::read_link(&path)
+                .change_context(JpreError::Unexpected)
+                .attach_printable_lazy(|| format!("Failed to read link target of {:?}", path))?;
+            return Ok(Some(link_target));
+        }
+        if self.fallback == CurrentFallback::None {
+            return Ok(None);
+        }
+        Ok(context
+            .default_jdk()?
+            .filter(|jdk| JDK_MANAGER.is_installed(jdk))
+            .map(|jdk| JDK_MANAGER.get_jdk_install_path(&jdk)))
+    }
+}
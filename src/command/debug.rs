@@ -1,8 +1,14 @@
 use crate::command::{Context, JpreCommand};
-use crate::context_id::get_context_id;
+use crate::config::config_path;
+use crate::context_id::{get_context_path, resolve_context_id, state_dir, CONTEXT_LINKS_DIR_NAME};
 use crate::error::{ESResult, JpreError};
+use crate::jdk_manager::{jdk_store_path, JDK_DOWNLOADS_DIR_NAME};
+use crate::json_output;
+use crate::progress_events::EventLine;
+use crate::relocation::{find_old_locations, Locations};
 use clap::{Args, Subcommand};
 use owo_colors::{OwoColorize, Stream};
+use std::path::Path;
 
 /// Debug commands.
 #[derive(Debug, Args)]
@@ -16,16 +22,69 @@ pub struct Debug {
 enum DebugSubcommand {
     /// Show context ID.
     ContextId,
+    /// Show the directories and files jpre uses.
+    Paths,
+    /// Print the JSON Schema of the events written to `--progress-fd`.
+    ProgressEventSchema,
 }
 
 impl JpreCommand for Debug {
-    fn run(self, _context: Context) -> ESResult<(), JpreError> {
+    fn run(self, context: Context) -> ESResult<(), JpreError> {
         match self.subcommand {
             DebugSubcommand::ContextId => {
+                let (strategy, context_id) = resolve_context_id(&context.config);
                 println!(
                     "Context ID: {}",
-                    get_context_id().if_supports_color(Stream::Stdout, |s| s.red())
+                    context_id.if_supports_color(Stream::Stdout, |s| s.red())
                 );
+                println!(
+                    "Strategy: {} (configured: {})",
+                    strategy.if_supports_color(Stream::Stdout, |s| s.cyan()),
+                    context
+                        .config
+                        .context_id_strategies
+                        .iter()
+                        .map(|s| s.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+            DebugSubcommand::Paths => {
+                let locations = Locations::current();
+                let path = |p: &Path| p.display().to_string();
+                let rows = [
+                    ("Config file", path(config_path())),
+                    ("Cache dir", path(&locations.cache_dir)),
+                    ("JDK store", path(jdk_store_path())),
+                    (
+                        "Downloads",
+                        path(&locations.cache_dir.join(JDK_DOWNLOADS_DIR_NAME)),
+                    ),
+                    ("State dir", path(&state_dir())),
+                    (
+                        "Context links",
+                        path(&state_dir().join(CONTEXT_LINKS_DIR_NAME)),
+                    ),
+                    ("Context link", path(&get_context_path(&context.config))),
+                ];
+                for (name, value) in rows {
+                    println!(
+                        "{}: {}",
+                        name,
+                        value.if_supports_color(Stream::Stdout, |s| s.cyan())
+                    );
+                }
+                for old in find_old_locations() {
+                    println!(
+                        "Old location: {} (run `jpre relocate` to move it)",
+                        old.jdk_store()
+                            .display()
+                            .if_supports_color(Stream::Stdout, |s| s.yellow())
+                    );
+                }
+            }
+            DebugSubcommand::ProgressEventSchema => {
+                json_output::print_schema::<EventLine>()?;
             }
         }
         Ok(())
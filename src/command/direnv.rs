@@ -0,0 +1,64 @@
+use crate::command::use_jdk::{project_requirement, resolve_requirement, UseTarget};
+use crate::command::{Context, JpreCommand};
+use crate::error::{ESResult, JpreError, UserMessage};
+use crate::jdk_manager::{install_error, JDK_MANAGER};
+use crate::project_pin::PROJECT_PIN_FILE_NAME;
+use clap::Args;
+use error_stack::{Report, ResultExt};
+use tracing::debug;
+
+/// Emit a `use_jpre` function for direnv, so `use jpre` in an `.envrc` sets `JAVA_HOME` for the
+/// project. Put the output in e.g. `~/.config/direnv/lib/jpre.sh`.
+#[derive(Debug, Args)]
+pub struct Direnv {
+    /// Instead, print the Java home of the project's JDK (or the given one), installing it if
+    /// needed. Used by `use_jpre`.
+    #[clap(long)]
+    java_home: bool,
+    /// The JDK to use instead of the project's. Version key or 'default'.
+    #[clap(requires = "java_home")]
+    jdk: Option<UseTarget>,
+}
+
+impl JpreCommand for Direnv {
+    fn run(self, context: Context) -> ESResult<(), JpreError> {
+        if !self.java_home {
+            print!(
+                "use_jpre() {{\n\
+                 \x20 local java_home pin\n\
+                 \x20 java_home=\"$(jpre direnv --java-home \"$@\")\" || return 1\n\
+                 \x20 export JAVA_HOME=\"$java_home\"\n\
+                 \x20 PATH_add \"$JAVA_HOME/bin\"\n\
+                 \x20 pin=\"$(find_up {})\" && watch_file \"$pin\"\n\
+                 }}\n",
+                PROJECT_PIN_FILE_NAME
+            );
+            return Ok(());
+        }
+
+        let jdk = match self.jdk {
+            Some(target) => target.resolve(&context)?,
+            None => match project_requirement(&context)? {
+                Some((requirement, source)) => {
+                    let jdk = resolve_requirement(&context, &requirement, None)?;
+                    debug!("Using JDK {} for {} from {}", jdk, requirement, source);
+                    jdk
+                }
+                None => context.default_jdk()?.ok_or_else(|| {
+                    Report::new(JpreError::UserError).attach(UserMessage {
+                        message: format!(
+                            "No {} in this directory or its parents, and no default JDK set",
+                            PROJECT_PIN_FILE_NAME
+                        ),
+                    })
+                })?,
+            },
+        };
+        let path = JDK_MANAGER
+            .get_jdk_path(&context.config, &context.platform, &jdk, None)
+            .map_err(install_error)
+            .attach_printable_lazy(|| format!("Failed to get path for JDK {}", jdk))?;
+        println!("{}", path.display());
+        Ok(())
+    }
+}
@@ -0,0 +1,205 @@
+use crate::command::{Context, JpreCommand};
+use crate::context_id::get_context_path;
+use crate::error::{ESResult, JpreError, UserMessage};
+use crate::foojay::FOOJAY_BASE_URL;
+use crate::http_client::{probe_address_families, ConnectProbe};
+use crate::java_home_management::{conflicting_java_home, java_home_conflict_message};
+use crate::jdk_manager::JDK_MANAGER;
+use crate::release_file::ReleaseFile;
+use crate::rosetta;
+use clap::Args;
+use error_stack::Report;
+use owo_colors::{OwoColorize, Stream};
+use std::path::{Path, PathBuf};
+
+/// Check that the shell is set up to use the JDKs jpre links, and explain what to change if not.
+#[derive(Debug, Args)]
+pub struct Doctor {}
+
+/// The outcome of one check.
+enum Check {
+    Ok(String),
+    /// Not necessarily wrong, but worth knowing.
+    Warning(String),
+    Problem(String),
+}
+
+impl JpreCommand for Doctor {
+    fn run(self, context: Context) -> ESResult<(), JpreError> {
+        let context_path = get_context_path(&context.config);
+        let mut checks = vec![
+            check_java_home(&context, &context_path),
+            check_context_link(&context_path),
+            check_path(&context_path),
+            check_platform(&context),
+            check_connectivity(&context),
+        ];
+        if rosetta::is_apple_silicon() {
+            checks.push(check_emulation());
+        }
+        let mut problems = 0;
+        for check in checks {
+            match check {
+                Check::Ok(message) => eprintln!(
+                    "{} {}",
+                    "ok:".if_supports_color(Stream::Stderr, |s| s.green()),
+                    message
+                ),
+                Check::Warning(message) => eprintln!(
+                    "{} {}",
+                    "warning:".if_supports_color(Stream::Stderr, |s| s.yellow()),
+                    message
+                ),
+                Check::Problem(message) => {
+                    eprintln!(
+                        "{} {}",
+                        "problem:".if_supports_color(Stream::Stderr, |s| s.red()),
+                        message
+                    );
+                    problems += 1;
+                }
+            }
+        }
+        if problems > 0 {
+            return Err(Report::new(JpreError::UserError).attach(UserMessage {
+                message: format!("Found {} problem(s)", problems),
+            }));
+        }
+        Ok(())
+    }
+}
+
+fn check_java_home(context: &Context, context_path: &Path) -> Check {
+    if let Some(java_home) = conflicting_java_home(&context.config) {
+        return Check::Problem(java_home_conflict_message(&context.config, &java_home));
+    }
+    if std::env::var_os("JAVA_HOME").is_none_or(|v| v.is_empty()) {
+        return Check::Warning(
+            "JAVA_HOME is not set, tools that look for it won't find jpre's JDK. Set \
+             `JAVA_HOME=\"$(jpre java-home)\"` in your shell's startup files"
+                .to_string(),
+        );
+    }
+    Check::Ok(format!("JAVA_HOME is {}", context_path.display()))
+}
+
+fn check_context_link(context_path: &Path) -> Check {
+    match std::fs::read_link(context_path) {
+        Ok(target) if target.join("bin/java").exists() => {
+            Check::Ok(format!("The context links to {}", target.display()))
+        }
+        Ok(target) => Check::Problem(format!(
+            "The context links to {}, which is not a JDK anymore. Run `jpre use` with another JDK",
+            target.display()
+        )),
+        Err(_) => Check::Warning(
+            "No JDK is used in this context, run `jpre use` or set a default with `jpre default`"
+                .to_string(),
+        ),
+    }
+}
+
+/// Whether the first `java` on `PATH` is the one from the context.
+fn check_path(context_path: &Path) -> Check {
+    let Some(java) = find_in_path("java") else {
+        return Check::Warning(format!(
+            "No `java` on PATH, add {} to it",
+            context_path.join("bin").display()
+        ));
+    };
+    if java.parent() == Some(&context_path.join("bin")) {
+        Check::Ok(format!("`java` on PATH is {}", java.display()))
+    } else {
+        Check::Problem(format!(
+            "`java` on PATH is {}, put {} before it",
+            java.display(),
+            context_path.join("bin").display()
+        ))
+    }
+}
+
+fn check_platform(context: &Context) -> Check {
+    match context.platform() {
+        Ok(platform) => Check::Ok(format!("JDKs are downloaded for {}", platform)),
+        Err(e) => Check::Problem(
+            e.frames()
+                .find_map(|f| f.downcast_ref::<UserMessage>())
+                .map(|m| m.message.clone())
+                .unwrap_or_else(|| "Could not detect the platform".to_string()),
+        ),
+    }
+}
+
+/// Whether Foojay can be reached over IPv4 and IPv6. When only IPv6 is broken, connections still
+/// work but may be slow to set up.
+fn check_connectivity(context: &Context) -> Check {
+    let url = url::Url::parse(FOOJAY_BASE_URL).expect("Foojay base URL is valid");
+    let host = url.host_str().expect("Foojay base URL has a host");
+    let (ipv4, ipv6) = match probe_address_families(host, 443) {
+        Ok(probes) => probes,
+        Err(e) => return Check::Problem(format!("Could not resolve {}: {}", host, e)),
+    };
+    let describe = |family: &str, probe: &ConnectProbe| match probe {
+        ConnectProbe::NoAddress => format!("{} has no {} address", host, family),
+        ConnectProbe::Connected(time) => {
+            format!("{} connects in {} ms", family, time.as_millis())
+        }
+        ConnectProbe::Failed(e) => format!("{} doesn't connect ({})", family, e),
+    };
+    let summary = format!("{}, {}", describe("IPv4", &ipv4), describe("IPv6", &ipv6));
+    match (&ipv4, &ipv6) {
+        (ConnectProbe::Connected(_), ConnectProbe::Failed(_)) if !context.config.prefer_ipv4 => {
+            Check::Warning(format!(
+                "{}. Set prefer_ipv4 = true in the config to skip IPv6",
+                summary
+            ))
+        }
+        (ConnectProbe::Connected(_), _) | (_, ConnectProbe::Connected(_)) => {
+            Check::Ok(format!("Foojay is reachable: {}", summary))
+        }
+        _ => Check::Problem(format!("Foojay is unreachable: {}", summary)),
+    }
+}
+
+/// Which installed JDKs run emulated by Rosetta 2, and whether jpre itself does.
+fn check_emulation() -> Check {
+    if rosetta::is_translated() {
+        return Check::Warning(
+            "jpre runs under Rosetta 2, install an arm64 build of jpre so it picks native JDKs"
+                .to_string(),
+        );
+    }
+    let installed = JDK_MANAGER.get_installed_jdks().unwrap_or_default();
+    let emulated = installed
+        .iter()
+        .filter(|jdk| {
+            ReleaseFile::read(&JDK_MANAGER.get_jdk_install_path(jdk))
+                .ok()
+                .flatten()
+                .and_then(|release| {
+                    release
+                        .get("OS_ARCH")
+                        .map(rosetta::is_emulated_release_arch)
+                })
+                .unwrap_or(false)
+        })
+        .map(|jdk| jdk.to_string())
+        .collect::<Vec<_>>();
+    if emulated.is_empty() {
+        return Check::Ok(format!(
+            "All {} installed JDK(s) run natively on Apple Silicon",
+            installed.len()
+        ));
+    }
+    Check::Warning(format!(
+        "JDK(s) {} are x64 builds and run emulated by Rosetta 2. Reinstall them with \
+         forced_architecture unset or \"arm64\"",
+        emulated.join(", ")
+    ))
+}
+
+fn find_in_path(name: &str) -> Option<PathBuf> {
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
@@ -0,0 +1,90 @@
+use crate::command::{Context, JpreCommand};
+use crate::error::{ESResult, JpreError, UserMessage};
+use crate::java_version::JavaVersion;
+use crate::jdk_manager::JDK_MANAGER;
+use crate::tui::jdk_color;
+use clap::Args;
+use error_stack::ResultExt;
+use owo_colors::{OwoColorize, Stream};
+use std::cmp::Ordering;
+use std::str::FromStr;
+use tracing::warn;
+
+/// Show how jpre reads a Java version string, and how it compares to the installed JDKs.
+#[derive(Debug, Args)]
+pub struct Explain {
+    /// The version string, e.g. `17.0.10+7` or `1.8.0_402-b06`.
+    version: String,
+    /// Also show the version as normalized for this distribution, which changes how some vendor
+    /// versions compare.
+    #[clap(long, visible_alias = "dist")]
+    distribution: Option<String>,
+}
+
+impl JpreCommand for Explain {
+    fn run(self, _context: Context) -> ESResult<(), JpreError> {
+        let version = JavaVersion::from_str(&self.version)
+            .change_context(JpreError::UserError)
+            .attach(UserMessage {
+                message: format!("Could not parse Java version {:?}", self.version),
+            })?;
+        println!(
+            "{} ({})",
+            version.if_supports_color(Stream::Stdout, |s| s.color(jdk_color())),
+            match version {
+                JavaVersion::OldScheme(_) => "old 1.x scheme",
+                JavaVersion::NewScheme(_) => "JEP 322 scheme",
+            }
+        );
+        for (component, value) in version.components() {
+            println!("  {:<12} {}", format!("{}:", component), value);
+        }
+        let version = match &self.distribution {
+            Some(distribution) => {
+                let normalized = version.normalize_for_distribution(distribution);
+                if normalized == version {
+                    println!("Unchanged when normalized for {}", distribution);
+                } else {
+                    println!("Normalized for {}: {}", distribution, normalized);
+                }
+                normalized
+            }
+            None => version,
+        };
+
+        let mut installed = JDK_MANAGER
+            .get_installed_jdks()
+            .change_context(JpreError::Unexpected)
+            .attach_printable("Failed to get installed JDKs")?;
+        if installed.is_empty() {
+            return Ok(());
+        }
+        installed.sort();
+        println!("Compared to installed JDKs:");
+        for jdk in installed {
+            let full_version = match JDK_MANAGER.get_full_version(&jdk) {
+                Ok(Some(full_version)) => full_version,
+                Ok(None) => {
+                    println!("  {}: no full version recorded", jdk);
+                    continue;
+                }
+                Err(e) => {
+                    warn!("Failed to get full version for {}: {:?}", jdk, e);
+                    continue;
+                }
+            };
+            let ordering = match version.compare(&full_version) {
+                Ordering::Less => "older than",
+                Ordering::Equal => "same as",
+                Ordering::Greater => "newer than",
+            };
+            println!(
+                "  {} {} ({})",
+                ordering,
+                jdk.if_supports_color(Stream::Stdout, |s| s.color(jdk_color())),
+                full_version
+            );
+        }
+        Ok(())
+    }
+}
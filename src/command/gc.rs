@@ -0,0 +1,26 @@
+use crate::command::{Context, JpreCommand};
+use crate::error::{ESResult, JpreError};
+use crate::{remembered_jdk, temp_context};
+use clap::Args;
+use error_stack::ResultExt;
+
+/// Remove temporary contexts that expired or whose creator exited, and JDKs remembered for shells
+/// that exited.
+#[derive(Debug, Args)]
+pub struct Gc {}
+
+impl JpreCommand for Gc {
+    fn run(self, _context: Context) -> ESResult<(), JpreError> {
+        let removed = temp_context::gc()
+            .change_context(JpreError::Unexpected)
+            .attach_printable("Failed to remove temporary contexts")?;
+        eprintln!("Removed {} temporary context(s)", removed);
+        let forgotten = remembered_jdk::gc()
+            .change_context(JpreError::Unexpected)
+            .attach_printable("Failed to remove remembered JDKs")?;
+        if forgotten > 0 {
+            eprintln!("Removed {} remembered JDK(s) of exited shells", forgotten);
+        }
+        Ok(())
+    }
+}
@@ -10,8 +10,8 @@ use clap::Args;
 pub struct GetContextId {}
 
 impl JpreCommand for GetContextId {
-    fn run(self, _context: Context) -> ESResult<(), JpreError> {
-        println!("{}", get_context_id());
+    fn run(self, context: Context) -> ESResult<(), JpreError> {
+        println!("{}", get_context_id(&context.config));
         Ok(())
     }
 }
@@ -0,0 +1,86 @@
+use crate::command::{Context, JpreCommand};
+use crate::context_id::get_context_path;
+use crate::error::{ESResult, JpreError};
+use crate::java_home_management::set_context_path_to_java_home;
+use clap::{Args, ValueEnum};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use tracing::debug;
+
+/// Emit shell code that exports `JAVA_HOME`, but only when the JDK of this context changed since
+/// the last call in this shell. Meant to run before every prompt, e.g. `eval "$(jpre hook-env)"`.
+#[derive(Debug, Args)]
+pub struct HookEnv {
+    /// The shell to emit code for.
+    #[clap(long, value_enum, default_value_t = HookShell::Posix)]
+    shell: HookShell,
+}
+
+/// Set by the emitted code to what it exported, so the next call knows whether anything changed.
+const HOOK_STATE_ENV: &str = "JPRE_HOOK_STATE";
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum HookShell {
+    /// bash, zsh, and other POSIX-like shells.
+    Posix,
+    Fish,
+}
+
+impl JpreCommand for HookEnv {
+    fn run(self, context: Context) -> ESResult<(), JpreError> {
+        let context_path = get_context_path(&context.config);
+        // Unlike `java-home`, never reset an existing link, only fill in the default.
+        if std::fs::symlink_metadata(&context_path).is_err() {
+            if let Some(default) = context.default_jdk()? {
+                set_context_path_to_java_home(&context, &default, None)?;
+            }
+        }
+
+        let target = std::fs::read_link(&context_path).ok();
+        let mut hasher = Sha256::new();
+        hasher.update(context_path.as_os_str().as_encoded_bytes());
+        hasher.update([0]);
+        if let Some(target) = &target {
+            hasher.update(target.as_os_str().as_encoded_bytes());
+        }
+        let hash = hex::encode(hasher.finalize());
+
+        // Kept in the shell rather than a file, so a new shell that reuses a context ID still gets
+        // its export.
+        if std::env::var(HOOK_STATE_ENV).is_ok_and(|h| h == hash) {
+            debug!("JDK unchanged, nothing to export");
+            return Ok(());
+        }
+
+        print!("{}", self.shell.export_code(&context_path, &hash));
+        Ok(())
+    }
+}
+
+impl HookShell {
+    /// `state` is a hex hash, so it needs no quoting.
+    fn export_code(self, java_home: &Path, state: &str) -> String {
+        let java_home = java_home.to_string_lossy();
+        match self {
+            HookShell::Posix => {
+                let quoted = format!("'{}'", java_home.replace('\'', r"'\''"));
+                format!(
+                    "export JAVA_HOME={0};\n\
+                     case \":$PATH:\" in *:{0}/bin:*) ;; *) export PATH={0}/bin:\"$PATH\";; esac;\n\
+                     export {1}={2};\n\
+                     hash -r;\n",
+                    quoted, HOOK_STATE_ENV, state
+                )
+            }
+            HookShell::Fish => {
+                let quoted = format!("'{}'", java_home.replace('\\', r"\\").replace('\'', r"\'"));
+                format!(
+                    "set -gx JAVA_HOME {0};\n\
+                     contains -- {0}/bin $PATH; or set -gx PATH {0}/bin $PATH;\n\
+                     set -gx {1} {2};\n",
+                    quoted, HOOK_STATE_ENV, state
+                )
+            }
+        }
+    }
+}
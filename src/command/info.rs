@@ -0,0 +1,151 @@
+use crate::command::set_distributions::validate_distributions;
+use crate::command::use_jdk::UseTarget;
+use crate::command::{Context, JpreCommand};
+use crate::error::{ESResult, JpreError, UserMessage};
+use crate::foojay::{ChecksumType, FoojayPackageInfo, FoojayPackageListInfo, FOOJAY_API};
+use crate::java_version::key::VersionKey;
+use crate::jdk_manager::JDK_MANAGER;
+use crate::package_manifest::{ManifestPackage, PackageManifest};
+use crate::release_notes::release_notes_url;
+use crate::tui::{format_bytes, format_duration, jdk_color};
+use clap::Args;
+use error_stack::{Report, ResultExt};
+use owo_colors::{OwoColorize, Stream};
+use std::time::Duration;
+
+/// Show information about a JDK package, or with `--installed`, about how it was installed.
+#[derive(Debug, Args)]
+pub struct Info {
+    /// The JDK to show. Version key or 'default'.
+    jdk: UseTarget,
+    /// Show what was recorded when the installed JDK was installed.
+    #[clap(long, conflicts_with = "distribution")]
+    installed: bool,
+    /// The distribution to look up, instead of the configured ones.
+    #[clap(long, visible_alias = "dist")]
+    distribution: Option<String>,
+    /// Print the package as a manifest for `jpre use --from-manifest`, to install it on machines
+    /// without access to Foojay. Append the output of several runs to one file for more JDKs.
+    #[clap(long, conflicts_with = "installed")]
+    manifest: bool,
+}
+
+impl JpreCommand for Info {
+    fn run(self, context: Context) -> ESResult<(), JpreError> {
+        let jdk = self.jdk.resolve(&context)?;
+        if self.installed {
+            return Self::print_installed(&jdk);
+        }
+        if let Some(distribution) = &self.distribution {
+            validate_distributions(std::slice::from_ref(distribution))?;
+        }
+        let (list_info, info) = FOOJAY_API
+            .get_latest_package_info_using_priority(
+                &context.config,
+                context.platform()?,
+                &jdk,
+                self.distribution.as_deref(),
+            )
+            .change_context(JpreError::Unexpected)
+            .attach_printable_lazy(|| format!("Failed to get package info for {}", jdk))?;
+        if self.manifest {
+            return Self::print_manifest(&jdk, &list_info, &info);
+        }
+        println!(
+            "JDK {}",
+            jdk.if_supports_color(Stream::Stdout, |s| s.color(jdk_color()))
+        );
+        println!("  Distribution: {}", list_info.distribution);
+        println!("  Version: {}", list_info.java_version);
+        println!("  Archive type: {:?}", list_info.archive_type);
+        if list_info.size > 0 {
+            println!("  Archive size: {}", format_bytes(list_info.size));
+        }
+        println!("  Download: {}", info.direct_download_uri);
+        for checksum in info.checksums() {
+            println!("  Checksum: {}", checksum);
+        }
+        if let Some(signature) = info.signature_uri() {
+            println!("  Signature: {}", signature);
+        }
+        if !list_info.free_use_in_production {
+            println!(
+                "  License: must be accepted before use in production, see {}",
+                info.license_uri(&list_info.distribution)
+                    .unwrap_or("the vendor's website")
+            );
+        }
+        if let Some(release_notes) = release_notes_url(&list_info) {
+            println!("  Release notes: {}", release_notes);
+        }
+        Ok(())
+    }
+}
+
+impl Info {
+    fn print_manifest(
+        jdk: &VersionKey,
+        list_info: &FoojayPackageListInfo,
+        info: &FoojayPackageInfo,
+    ) -> ESResult<(), JpreError> {
+        if !matches!(info.checksum_type, ChecksumType::Sha256) || info.checksum.is_empty() {
+            return Err(Report::new(JpreError::UserError).attach(UserMessage {
+                message: format!(
+                    "The package of JDK {} has no SHA-256 checksum, so it can't be verified when \
+                     installed from a manifest",
+                    jdk
+                ),
+            }));
+        }
+        let manifest = PackageManifest::single_to_toml(
+            jdk,
+            ManifestPackage::from_package_info(list_info, info),
+        )
+        .change_context(JpreError::Unexpected)?;
+        print!("{}", manifest);
+        Ok(())
+    }
+
+    fn print_installed(jdk: &VersionKey) -> ESResult<(), JpreError> {
+        if !JDK_MANAGER.is_installed(jdk) {
+            return Err(Report::new(JpreError::UserError).attach(UserMessage {
+                message: format!("JDK {} is not installed", jdk),
+            }));
+        }
+        println!(
+            "JDK {}",
+            jdk.if_supports_color(Stream::Stdout, |s| s.color(jdk_color()))
+        );
+        println!(
+            "  Path: {}",
+            JDK_MANAGER.get_jdk_install_path(jdk).display()
+        );
+        let Some(metadata) = JDK_MANAGER
+            .get_install_metadata(jdk)
+            .change_context(JpreError::Unexpected)
+            .attach_printable_lazy(|| format!("Failed to get install metadata for {}", jdk))?
+        else {
+            println!("  No install metadata, the JDK was installed by an older jpre");
+            return Ok(());
+        };
+        println!("  Distribution: {}", metadata.distribution);
+        println!("  Version: {}", metadata.java_version);
+        println!("  Downloaded from: {}", metadata.download_uri);
+        println!("  Installed at: {} (Unix time)", metadata.installed_at);
+        println!("  Archive size: {}", format_bytes(metadata.archive_size));
+        match metadata.download_speed() {
+            Some(speed) => println!(
+                "  Download time: {} ({}/s)",
+                format_duration(Duration::from_secs_f64(metadata.download_secs)),
+                format_bytes(speed)
+            ),
+            None => println!("  Download time: none, taken from download cache"),
+        }
+        println!(
+            "  Extraction time: {}",
+            format_duration(Duration::from_secs_f64(metadata.extraction_secs))
+        );
+        println!("  Size on disk: {}", format_bytes(metadata.installed_size));
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+use crate::command::set_distributions::validate_distributions;
+use crate::command::use_jdk::UseTarget;
+use crate::command::{Context, JpreCommand};
+use crate::completion_index;
+use crate::error::{ESResult, JpreError};
+use crate::jdk_manager::{install_error, JDK_MANAGER};
+use crate::tui::jdk_color;
+use clap::Args;
+use error_stack::ResultExt;
+use owo_colors::{OwoColorize, Stream};
+
+/// Install JDKs without using them, e.g. to provision a machine.
+#[derive(Debug, Args)]
+pub struct Install {
+    /// The JDK(s) to install. Version key or 'default'.
+    #[clap(required = true, num_args = 1..)]
+    jdks: Vec<UseTarget>,
+    /// The distribution to download from, instead of the configured ones.
+    #[clap(long, visible_alias = "dist")]
+    distribution: Option<String>,
+}
+
+impl JpreCommand for Install {
+    fn run(self, context: Context) -> ESResult<(), JpreError> {
+        if let Some(distribution) = &self.distribution {
+            validate_distributions(std::slice::from_ref(distribution))?;
+        }
+        completion_index::refresh_if_stale(&context.config);
+        for target in self.jdks {
+            let jdk = target.resolve(&context)?;
+            JDK_MANAGER
+                .get_jdk_path(
+                    &context.config,
+                    &context.platform,
+                    &jdk,
+                    self.distribution.as_deref(),
+                )
+                .map_err(install_error)
+                .attach_printable_lazy(|| format!("Failed to install JDK {}", jdk))?;
+            eprintln!(
+                "Installed JDK {}",
+                jdk.if_supports_color(Stream::Stderr, |s| s.color(jdk_color()))
+            );
+        }
+        Ok(())
+    }
+}
@@ -1,7 +1,10 @@
+use crate::command::shell::SHELL_ENV;
 use crate::command::{Context, JpreCommand};
 use crate::context_id::get_context_path;
 use crate::error::{ESResult, JpreError};
 use crate::java_home_management::{clear_context_path, set_context_path_to_java_home};
+use crate::jdk_manager::JDK_MANAGER;
+use crate::remembered_jdk;
 use clap::Args;
 use error_stack::ResultExt;
 use std::io::Write;
@@ -14,16 +17,37 @@ pub struct JavaHome {}
 
 impl JpreCommand for JavaHome {
     fn run(self, context: Context) -> ESResult<(), JpreError> {
-        clear_context_path()?;
-
-        debug!("Setting to default if necessary");
-        if let Some(default) = context.config.default_jdk.clone() {
-            set_context_path_to_java_home(&context, &default)?;
+        // Startup files of a `jpre shell` subshell shouldn't reset the JDK it was started with.
+        let in_shell =
+            std::env::var_os(SHELL_ENV).is_some() && get_context_path(&context.config).exists();
+        if !in_shell {
+            // A JDK remembered for this context wins over the default, unless it was removed.
+            let remembered =
+                remembered_jdk::read(&context.config).filter(|jdk| JDK_MANAGER.is_installed(jdk));
+            let jdk = match remembered {
+                Some(jdk) => {
+                    debug!("Setting to JDK {} remembered for this context", jdk);
+                    Some(jdk)
+                }
+                None => {
+                    debug!("Setting to default if necessary");
+                    context.default_jdk()?
+                }
+            };
+            // Replacing the link in place keeps it valid for concurrent calls in this context.
+            match jdk {
+                Some(jdk) => set_context_path_to_java_home(&context, &jdk, None)?,
+                None => clear_context_path(&context.config)?,
+            }
         }
 
         (|| -> std::io::Result<()> {
             let mut stdout = std::io::stdout();
-            stdout.write_all(get_context_path().into_os_string().as_bytes())?;
+            stdout.write_all(
+                get_context_path(&context.config)
+                    .into_os_string()
+                    .as_bytes(),
+            )?;
             stdout.write_all(b"\n")?;
             stdout.flush()?;
             Ok(())
@@ -10,20 +10,33 @@ pub struct ListDistributions {
     /// Show synonyms.
     #[clap(long, action = clap::ArgAction::Set, default_value = "false", default_missing_value = "true", num_args = 0..=1)]
     synonyms: bool,
+    /// Show the major versions each distribution offers.
+    #[clap(long)]
+    versions: bool,
 }
 
 impl JpreCommand for ListDistributions {
     fn run(self, _context: Context) -> ESResult<(), JpreError> {
         eprintln!("Listing distributions...");
-        let mut distributions = Vec::from_iter(
-            FOOJAY_API
-                .list_distributions()
-                .change_context(JpreError::Unexpected)
-                .attach_printable("Failed to list distributions")?,
-        );
+        let distributions = if self.versions {
+            FOOJAY_API.list_distributions_with_versions()
+        } else {
+            FOOJAY_API.list_distributions()
+        };
+        let mut distributions = distributions
+            .change_context(JpreError::Unexpected)
+            .attach_printable("Failed to list distributions")?;
         distributions.sort();
         for distribution in distributions {
-            println!("- {}", distribution.name);
+            if self.versions {
+                println!(
+                    "- {} ({})",
+                    distribution.name,
+                    format_majors(&distribution.major_versions())
+                );
+            } else {
+                println!("- {}", distribution.name);
+            }
             if !self.synonyms {
                 continue;
             }
@@ -39,3 +52,40 @@ impl JpreCommand for ListDistributions {
         Ok(())
     }
 }
+
+/// Format sorted major versions compactly, collapsing runs: `8, 11, 17, 21-23`.
+fn format_majors(majors: &[u32]) -> String {
+    if majors.is_empty() {
+        return "no versions".to_string();
+    }
+    let mut runs: Vec<(u32, u32)> = Vec::new();
+    for &major in majors {
+        match runs.last_mut() {
+            Some((_, end)) if *end + 1 == major => *end = major,
+            _ => runs.push((major, major)),
+        }
+    }
+    runs.iter()
+        .map(|&(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{}-{}", start, end)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_majors() {
+        assert_eq!("8, 11, 17, 21-23", format_majors(&[8, 11, 17, 21, 22, 23]));
+        assert_eq!("17-18", format_majors(&[17, 18]));
+        assert_eq!("21", format_majors(&[21]));
+        assert_eq!("no versions", format_majors(&[]));
+    }
+}
@@ -1,39 +1,121 @@
 use crate::command::{Context, JpreCommand};
-use crate::error::{ESResult, JpreError};
+use crate::context_id::{contexts_by_jdk, format_context_count};
+use crate::eol::SupportStatus;
+use crate::error::{ESResult, JpreError, UserMessage};
+use crate::foojay::FOOJAY_API;
 use crate::jdk_manager::JDK_MANAGER;
+use crate::release_file::ReleaseFile;
 use crate::tui::jdk_color;
 use clap::Args;
-use error_stack::ResultExt;
+use error_stack::{Report, ResultExt};
 use owo_colors::{OwoColorize, Stream};
+use tracing::debug;
 
 /// List all installed Java versions.
 #[derive(Debug, Args)]
-pub struct ListInstalled {}
+pub struct ListInstalled {
+    /// Show the path, vendor and tools of each JDK.
+    #[clap(long)]
+    detailed: bool,
+    /// Fail if any installed JDK is past end-of-life, e.g. for CI policy checks.
+    #[clap(long)]
+    fail_on_eol: bool,
+}
 
 impl JpreCommand for ListInstalled {
     fn run(self, _context: Context) -> ESResult<(), JpreError> {
         let mut installed = JDK_MANAGER
             .get_installed_jdks()
             .change_context(JpreError::Unexpected)
-            .attach_printable("Failed to get installed JDKs")?;
+            .attach_printable("Failed to get installed JDKs")?
+            .into_iter()
+            .map(|jdk| {
+                let full = JDK_MANAGER
+                    .get_full_version(&jdk)
+                    .change_context(JpreError::Unexpected)
+                    .attach_printable_lazy(|| {
+                        format!("Failed to get full version for JDK {}", jdk)
+                    })?;
+                Ok((jdk, full))
+            })
+            .collect::<ESResult<Vec<_>, JpreError>>()?;
 
-        installed.sort();
+        // Unknown versions first, as they're most likely to be old.
+        installed.sort_by(|(a_jdk, a_full), (b_jdk, b_full)| {
+            a_full.cmp(b_full).then_with(|| a_jdk.cmp(b_jdk))
+        });
 
-        eprintln!("Installed JDKs:");
-        for jdk in installed {
-            let full = JDK_MANAGER
-                .get_full_version(&jdk)
+        // Listing shouldn't need the network, so end-of-life data comes from the cache unless
+        // it's checked for.
+        let majors = if self.fail_on_eol {
+            FOOJAY_API
+                .list_major_versions()
                 .change_context(JpreError::Unexpected)
-                .attach_printable_lazy(|| format!("Failed to get full version for JDK {}", jdk))?;
+                .attach_printable("Failed to get end-of-life data")?
+        } else {
+            FOOJAY_API.cached_major_versions().unwrap_or_else(|| {
+                debug!("No cached end-of-life data, not flagging end-of-life JDKs");
+                Vec::new()
+            })
+        };
+
+        let contexts = contexts_by_jdk();
+        eprintln!("Installed JDKs:");
+        let mut eol = Vec::new();
+        for (jdk, full) in installed {
+            let support = SupportStatus::of(&majors, &jdk);
+            let in_use = contexts
+                .get(&JDK_MANAGER.get_jdk_install_path(&jdk))
+                .map(|&count| format!(" (in use by {})", format_context_count(count)))
+                .unwrap_or_default();
             println!(
-                "- {} (full: {})",
+                "- {} (full: {}){}{}",
                 jdk.if_supports_color(Stream::Stdout, |s| s.color(jdk_color())),
                 full.map(|f| f.to_string())
                     .unwrap_or_else(|| "<unknown>".to_string())
-                    .if_supports_color(Stream::Stdout, |s| s.color(jdk_color()))
+                    .if_supports_color(Stream::Stdout, |s| s.color(jdk_color())),
+                in_use,
+                if support.is_eol() {
+                    format!(" [{}]", support)
+                        .if_supports_color(Stream::Stdout, |s| s.red())
+                        .to_string()
+                } else {
+                    String::new()
+                }
             );
+            if support.is_eol() {
+                eol.push(jdk.clone());
+            }
+            if self.detailed {
+                let path = JDK_MANAGER.get_jdk_install_path(&jdk);
+                let vendor = ReleaseFile::read(&path)
+                    .ok()
+                    .flatten()
+                    .and_then(|r| r.get("IMPLEMENTOR").map(str::to_string));
+                println!("  Path: {}", path.display());
+                println!("  Vendor: {}", vendor.as_deref().unwrap_or("<unknown>"));
+                println!(
+                    "  native-image: {}",
+                    if JDK_MANAGER.has_native_image(&jdk) {
+                        "yes"
+                    } else {
+                        "no"
+                    }
+                );
+            }
         }
 
+        if self.fail_on_eol && !eol.is_empty() {
+            return Err(Report::new(JpreError::UserError).attach(UserMessage {
+                message: format!(
+                    "Installed JDKs past end-of-life: {}",
+                    eol.iter()
+                        .map(|jdk| jdk.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            }));
+        }
         Ok(())
     }
 }
@@ -1,11 +1,17 @@
 use crate::command::{Context, JpreCommand};
 use crate::error::{ESResult, JpreError, UserMessage};
-use crate::foojay::{FoojayDiscoApiError, FOOJAY_API};
-use crate::java_version::PreRelease;
+use crate::foojay::{FoojayDiscoApiError, FoojayMajorVersion, TermOfSupport, FOOJAY_API};
+use crate::java_version::key::VersionKey;
+use crate::java_version::{JavaVersion, PreRelease};
+use crate::jobs;
+use crate::platform::Platform;
+use crate::tui::new_spinner;
 use clap::ArgAction;
 use clap::Args;
+use error_stack::ResultExt;
+use tracing::warn;
 
-/// List all available version keys.
+/// List all available version keys, LTS versions first.
 #[derive(Debug, Args)]
 pub struct ListVersions {
     /// The distribution to list versions for.
@@ -18,8 +24,18 @@ pub struct ListVersions {
     /// Show General Availability versions. Defaults to `true`.
     #[clap(long, action = ArgAction::Set, default_value = "true", default_missing_value = "true", num_args = 0..=1)]
     ga: bool,
+    /// Show the latest full version of each key, for every configured distribution (or only the
+    /// given one).
+    #[clap(long)]
+    latest: bool,
+    /// Only show versions with long-term support.
+    #[clap(long)]
+    lts_only: bool,
 }
 
+/// How many package queries to run at once for `--latest`.
+const LATEST_QUERY_CONCURRENCY: usize = 8;
+
 impl JpreCommand for ListVersions {
     fn run(self, context: Context) -> ESResult<(), JpreError> {
         let distribution = self
@@ -48,16 +64,165 @@ impl JpreCommand for ListVersions {
                     .attach_printable("Failed to list versions"))
             }
         };
-        major_versions.sort();
-        for version in major_versions {
-            if !self.pre_release && version.pre_release != PreRelease::None {
-                continue;
+        major_versions.retain(|version| {
+            if version.pre_release == PreRelease::None {
+                self.ga
+            } else {
+                self.pre_release
             }
-            if !self.ga && version.pre_release == PreRelease::None {
-                continue;
+        });
+
+        let majors = match FOOJAY_API.list_major_versions() {
+            Ok(majors) => Some(majors),
+            Err(e) if self.lts_only => {
+                return Err(e
+                    .change_context(JpreError::Unexpected)
+                    .attach_printable("Failed to get term of support data"))
+            }
+            Err(e) => {
+                warn!("Could not get term of support data: {:?}", e);
+                None
+            }
+        };
+        let group_of = |version: &VersionKey| {
+            majors
+                .as_ref()
+                .map(|majors| SupportGroup::of(majors, version))
+        };
+        major_versions.sort_by_key(|version| (group_of(version), version.clone()));
+        if self.lts_only {
+            major_versions.retain(|version| group_of(version) == Some(SupportGroup::Lts));
+        }
+
+        let latest = if self.latest {
+            let distributions = match &self.distribution {
+                Some(distribution) => vec![distribution.clone()],
+                None => context.config.distributions.clone(),
+            };
+            Some(Self::query_latest(
+                context.platform()?,
+                &distributions,
+                &major_versions,
+            )?)
+        } else {
+            None
+        };
+
+        let mut current_group = None;
+        for (i, version) in major_versions.iter().enumerate() {
+            let group = group_of(version);
+            if let Some(group) = group.filter(|_| group != current_group) {
+                eprintln!("{}:", group);
+                current_group = Some(group);
             }
-            println!("- {}", version);
+            let Some(latest) = &latest else {
+                println!("- {}", version);
+                continue;
+            };
+            let latest = latest[i]
+                .iter()
+                .map(|(dist, v)| format!("{} ({})", v, dist))
+                .collect::<Vec<_>>();
+            println!(
+                "- {} -> {}",
+                version,
+                if latest.is_empty() {
+                    "<none>".to_string()
+                } else {
+                    latest.join(", ")
+                }
+            );
         }
         Ok(())
     }
 }
+
+impl ListVersions {
+    /// Find the latest version of each key for each distribution. The result is in the same order
+    /// as `keys`, and only contains the distributions that have a package.
+    fn query_latest(
+        platform: &Platform,
+        distributions: &[String],
+        keys: &[VersionKey],
+    ) -> ESResult<Vec<Vec<(String, JavaVersion)>>, JpreError> {
+        let progress = new_spinner(format!(
+            "Querying latest versions from {}",
+            distributions.join(", ")
+        ));
+        progress.set_length(keys.len() as u64);
+        let results = jobs::run_bounded(keys.iter().collect(), LATEST_QUERY_CONCURRENCY, |key| {
+            let mut latest = Vec::new();
+            for dist in distributions {
+                match FOOJAY_API.get_latest_package_list_info(platform, dist, key) {
+                    Ok(Some(info)) => latest.push((dist.clone(), info.java_version)),
+                    Ok(None) => {}
+                    Err(e) => return Err(e.attach_printable(format!("JDK {} in {}", key, dist))),
+                }
+            }
+            progress.inc(1);
+            Ok(latest)
+        });
+        progress.finish_and_clear();
+        results
+            .change_context(JpreError::Unexpected)?
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .change_context(JpreError::Unexpected)
+            .attach_printable("Failed to query latest versions")
+    }
+}
+
+/// The groups `list-versions` shows versions in, in display order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum SupportGroup {
+    Lts,
+    /// Interim releases, which only get updates until the next release.
+    NonLts,
+    /// Foojay doesn't know the major version.
+    Unknown,
+}
+
+impl SupportGroup {
+    fn of(majors: &[FoojayMajorVersion], version: &VersionKey) -> SupportGroup {
+        match majors.iter().find(|m| m.major_version == version.major) {
+            Some(major) if major.term_of_support == TermOfSupport::Lts => SupportGroup::Lts,
+            Some(_) => SupportGroup::NonLts,
+            None => SupportGroup::Unknown,
+        }
+    }
+}
+
+impl std::fmt::Display for SupportGroup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SupportGroup::Lts => write!(f, "LTS"),
+            SupportGroup::NonLts => write!(f, "Non-LTS"),
+            SupportGroup::Unknown => write!(f, "Unknown term of support"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_support_group_order() {
+        let majors: Vec<FoojayMajorVersion> = serde_json::from_str(
+            r#"[
+                {"major_version": 22, "term_of_support": "STS", "maintained": false},
+                {"major_version": 21, "term_of_support": "LTS", "maintained": true}
+            ]"#,
+        )
+        .unwrap();
+        let mut versions = ["22", "21", "99-ea"]
+            .map(|key| VersionKey::from_str(key).unwrap())
+            .to_vec();
+        versions.sort_by_key(|version| (SupportGroup::of(&majors, version), version.clone()));
+        assert_eq!(
+            vec!["21", "22", "99-ea"],
+            versions.iter().map(|v| v.to_string()).collect::<Vec<_>>()
+        );
+    }
+}
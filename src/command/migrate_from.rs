@@ -0,0 +1,120 @@
+use crate::command::{Context, JpreCommand};
+use crate::error::{ESResult, JpreError, UserMessage};
+use crate::java_version::key::VersionKey;
+use crate::jdk_manager::JDK_MANAGER;
+use crate::migration::{discover, find_version_files, ForeignJdk, MigrationSource};
+use crate::project_pin::{ProjectPin, PROJECT_PIN_FILE_NAME};
+use crate::tui::jdk_color;
+use clap::Args;
+use error_stack::ResultExt;
+use owo_colors::{OwoColorize, Stream};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Take over the JDKs installed by SDKMAN, jenv, or asdf, and optionally their project version
+/// files.
+#[derive(Debug, Args)]
+pub struct MigrateFrom {
+    /// The tool to migrate from.
+    #[clap(value_enum)]
+    source: MigrationSource,
+    /// Link to the JDKs instead of copying them. They then break if the other tool removes them.
+    #[clap(long)]
+    link: bool,
+    /// Also convert `.sdkmanrc` and `.java-version` files in this directory tree to jpre pins.
+    #[clap(long, value_name = "DIR")]
+    pins: Option<PathBuf>,
+}
+
+impl JpreCommand for MigrateFrom {
+    fn run(self, _context: Context) -> ESResult<(), JpreError> {
+        let foreign = discover(self.source)
+            .change_context(JpreError::Unexpected)
+            .attach_printable_lazy(|| format!("Failed to find JDKs of {:?}", self.source))?;
+        let installed = JDK_MANAGER
+            .get_installed_jdks()
+            .change_context(JpreError::Unexpected)
+            .attach_printable("Failed to get installed JDKs")?;
+
+        // Only one JDK per key fits in the store, take the newest. `discover` sorts by version.
+        let mut by_key = BTreeMap::<VersionKey, &ForeignJdk>::new();
+        for jdk in &foreign {
+            by_key.insert(VersionKey::from(jdk.version.clone()), jdk);
+        }
+
+        let mut imported = 0;
+        for jdk in &foreign {
+            let key = VersionKey::from(jdk.version.clone());
+            let colored_key = key.if_supports_color(Stream::Stderr, |s| s.color(jdk_color()));
+            if !std::ptr::eq(by_key[&key], jdk) {
+                eprintln!(
+                    "Skipped {}: JDK {} is taken from {}",
+                    jdk.name, colored_key, by_key[&key].name
+                );
+                continue;
+            }
+            if installed.contains(&key) {
+                eprintln!(
+                    "Skipped {}: JDK {} is already installed",
+                    jdk.name, colored_key
+                );
+                continue;
+            }
+            JDK_MANAGER
+                .import_jdk(&key, &jdk.java_home, &jdk.version, self.link)
+                .change_context(JpreError::Unexpected)
+                .attach_printable_lazy(|| format!("Failed to import {:?}", jdk.java_home))?;
+            eprintln!(
+                "{} {} as JDK {} ({})",
+                if self.link { "Linked" } else { "Copied" },
+                jdk.name,
+                colored_key,
+                jdk.version
+            );
+            imported += 1;
+        }
+
+        let mut pinned = 0;
+        if let Some(root) = &self.pins {
+            let version_files = find_version_files(root)
+                .change_context(JpreError::UserError)
+                .attach(UserMessage {
+                    message: format!("Could not search {} for version files", root.display()),
+                })?;
+            for file in version_files {
+                let dir = file
+                    .path
+                    .parent()
+                    .expect("version files are in a directory");
+                if dir.join(PROJECT_PIN_FILE_NAME).exists() {
+                    eprintln!("Skipped {}: already pinned", file.path.display());
+                    continue;
+                }
+                let pin = ProjectPin::write(dir, &file.jdk)
+                    .change_context(JpreError::Unexpected)
+                    .attach_printable("Failed to write pin file")?;
+                eprintln!(
+                    "Pinned {} to JDK {}, from {}",
+                    pin.path.display(),
+                    file.jdk
+                        .if_supports_color(Stream::Stderr, |s| s.color(jdk_color())),
+                    file.path.display()
+                );
+                pinned += 1;
+            }
+        }
+
+        eprintln!(
+            "Found {} JDK(s) from {:?}, imported {}{}",
+            foreign.len(),
+            self.source,
+            imported,
+            if self.pins.is_some() {
+                format!(", wrote {} pin file(s)", pinned)
+            } else {
+                String::new()
+            }
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,40 @@
+use crate::command::{Context, JpreCommand};
+use crate::error::{ESResult, JpreError};
+use crate::jdk_manager::{MarkerMigration, JDK_MANAGER};
+use crate::tui::jdk_color;
+use clap::Args;
+use error_stack::ResultExt;
+use owo_colors::{OwoColorize, Stream};
+use tracing::warn;
+
+/// Record the version of JDKs installed by old jpre versions, which only left a marker without
+/// one, from their `release` file. Also done on every run, this reports what was migrated.
+#[derive(Debug, Args)]
+pub struct MigrateMarkers {}
+
+impl JpreCommand for MigrateMarkers {
+    fn run(self, _context: Context) -> ESResult<(), JpreError> {
+        let migrations = JDK_MANAGER
+            .migrate_legacy_markers()
+            .change_context(JpreError::Unexpected)
+            .attach_printable("Failed to migrate legacy markers")?;
+        if migrations.is_empty() {
+            eprintln!("No JDKs with a legacy marker");
+            return Ok(());
+        }
+        for (jdk, migration) in migrations {
+            let jdk = jdk.if_supports_color(Stream::Stderr, |s| s.color(jdk_color()));
+            match migration {
+                MarkerMigration::Migrated(version) => {
+                    eprintln!("Migrated JDK {}, recorded version {}", jdk, version)
+                }
+                MarkerMigration::NoVersion => warn!(
+                    "JDK {} has no version in its release file, reinstall it with `jpre update {}`",
+                    jdk, jdk
+                ),
+                MarkerMigration::Failed(e) => warn!("Could not migrate JDK {}: {:?}", jdk, e),
+            }
+        }
+        Ok(())
+    }
+}
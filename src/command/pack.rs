@@ -0,0 +1,48 @@
+use crate::command::use_jdk::UseTarget;
+use crate::command::{Context, JpreCommand};
+use crate::error::{ESResult, JpreError, UserMessage};
+use crate::jdk_archive;
+use crate::jdk_manager::JDK_MANAGER;
+use crate::tui::jdk_color;
+use clap::Args;
+use error_stack::{Report, ResultExt};
+use owo_colors::{OwoColorize, Stream};
+use std::path::PathBuf;
+
+/// Write an installed JDK to a `.tar.gz`, for `jpre unpack` on machines without internet.
+#[derive(Debug, Args)]
+pub struct Pack {
+    /// The JDK to pack. Version key or 'default'.
+    jdk: UseTarget,
+    /// Where to write the archive.
+    #[clap(short, long)]
+    output: PathBuf,
+    /// Use fixed mtimes and owners, so packing the same JDK again gives the same archive.
+    #[clap(long)]
+    reproducible: bool,
+}
+
+impl JpreCommand for Pack {
+    fn run(self, context: Context) -> ESResult<(), JpreError> {
+        let jdk = self.jdk.resolve(&context)?;
+        if !JDK_MANAGER.is_installed(&jdk) {
+            return Err(Report::new(JpreError::UserError).attach(UserMessage {
+                message: format!("JDK {} is not installed", jdk),
+            }));
+        }
+        jdk_archive::pack(
+            &JDK_MANAGER.get_jdk_install_path(&jdk),
+            &jdk.to_string(),
+            &self.output,
+            self.reproducible,
+        )
+        .change_context(JpreError::Unexpected)
+        .attach_printable_lazy(|| format!("Failed to pack JDK {}", jdk))?;
+        eprintln!(
+            "Packed JDK {} to {}",
+            jdk.if_supports_color(Stream::Stderr, |s| s.color(jdk_color())),
+            self.output.display()
+        );
+        Ok(())
+    }
+}
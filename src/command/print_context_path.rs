@@ -0,0 +1,47 @@
+use crate::command::current::NO_ACTIVE_JDK_EXIT_CODE;
+use crate::command::{Context, JpreCommand};
+use crate::config::JpreConfig;
+use crate::context_id::get_context_path;
+use crate::error::{ESResult, JpreError};
+use clap::Args;
+use error_stack::ResultExt;
+use std::io::Write;
+use std::os::unix::ffi::OsStrExt;
+
+/// Print the path `JAVA_HOME` should be set to for this context, like `java-home` but without
+/// setting the JDK. Never writes anything, not even the config. With `--resolve`, exits with code
+/// 3 if no JDK is active.
+#[derive(Debug, Args)]
+pub struct PrintContextPath {
+    /// Print the JDK the context path links to instead.
+    #[clap(long)]
+    resolve: bool,
+}
+
+impl JpreCommand for PrintContextPath {
+    fn run(self, context: Context) -> ESResult<(), JpreError> {
+        self.print(&context.config)
+    }
+}
+
+impl PrintContextPath {
+    /// Print the path. `main` runs this before its startup maintenance, with a config loaded
+    /// read-only.
+    pub fn print(&self, config: &JpreConfig) -> ESResult<(), JpreError> {
+        let mut path = get_context_path(config);
+        if self.resolve {
+            match std::fs::read_link(&path) {
+                Ok(target) if path.exists() => path = target,
+                _ => std::process::exit(NO_ACTIVE_JDK_EXIT_CODE),
+            }
+        }
+        (|| -> std::io::Result<()> {
+            let mut stdout = std::io::stdout();
+            stdout.write_all(path.as_os_str().as_bytes())?;
+            stdout.write_all(b"\n")?;
+            stdout.flush()
+        })()
+        .change_context(JpreError::Unexpected)
+        .attach_printable("Failed to write context path to stdout")
+    }
+}
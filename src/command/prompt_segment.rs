@@ -0,0 +1,98 @@
+use crate::command::{Context, JpreCommand};
+use crate::error::{ESResult, JpreError};
+use crate::java_version::key::VersionKey;
+use crate::update_state::UpdateState;
+use clap::{Args, ValueEnum};
+use std::io::IsTerminal;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Print a short segment for shell prompts, like `☕17`. Only reads the context link in
+/// `JAVA_HOME`, so it's cheap enough to run for every prompt. Prints nothing without a JDK.
+#[derive(Debug, Args)]
+pub struct PromptSegment {
+    /// The shell the segment is for, so colors don't break the prompt width.
+    #[clap(long, value_enum, default_value_t = PromptShell::Plain)]
+    shell: PromptShell,
+    /// Mark the JDK if the last `jpre update --check` found an update for it.
+    #[clap(long)]
+    updates: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum PromptShell {
+    /// Colored only when printing to a terminal.
+    Plain,
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl JpreCommand for PromptSegment {
+    fn run(self, _context: Context) -> ESResult<(), JpreError> {
+        self.print();
+        Ok(())
+    }
+}
+
+impl PromptSegment {
+    /// Print the segment. Needs neither the config nor the network, so `main` runs this before
+    /// loading them.
+    pub fn print(&self) {
+        let Some(jdk) = std::env::var_os("JAVA_HOME").and_then(|p| linked_jdk(Path::new(&p)))
+        else {
+            return;
+        };
+        let update = self
+            .updates
+            .then(UpdateState::read)
+            .flatten()
+            .is_some_and(|state| state.available.contains_key(&jdk));
+        let shell = match self.shell {
+            PromptShell::Plain if !std::io::stdout().is_terminal() => None,
+            shell => Some(shell),
+        };
+        println!("{}", format_segment(&jdk, update, shell));
+    }
+}
+
+/// The JDK a context link points to, from the name of the JDK directory.
+fn linked_jdk(link: &Path) -> Option<VersionKey> {
+    let target = std::fs::read_link(link).ok()?;
+    VersionKey::from_str(target.file_name()?.to_str()?).ok()
+}
+
+/// The segment text, colored for `shell` or uncolored without one.
+fn format_segment(jdk: &VersionKey, update: bool, shell: Option<PromptShell>) -> String {
+    let text = format!("☕{}{}", jdk, if update { " ⬆" } else { "" });
+    match shell {
+        None => text,
+        Some(PromptShell::Zsh) => format!("%F{{blue}}{}%f", text),
+        Some(PromptShell::Bash) => format!("\\[\\e[94m\\]{}\\[\\e[0m\\]", text),
+        Some(PromptShell::Plain | PromptShell::Fish) => format!("\x1b[94m{}\x1b[0m", text),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_segment() {
+        let jdk = VersionKey::from_str("17").unwrap();
+        assert_eq!("☕17", format_segment(&jdk, false, None));
+        assert_eq!("☕17 ⬆", format_segment(&jdk, true, None));
+        assert_eq!(
+            "%F{blue}☕17%f",
+            format_segment(&jdk, false, Some(PromptShell::Zsh))
+        );
+        assert_eq!(
+            "\\[\\e[94m\\]☕17\\[\\e[0m\\]",
+            format_segment(&jdk, false, Some(PromptShell::Bash))
+        );
+        assert_eq!(
+            "\x1b[94m☕17 ⬆\x1b[0m",
+            format_segment(&jdk, true, Some(PromptShell::Fish))
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use crate::command::{Context, JpreCommand};
+use crate::error::{ESResult, JpreError};
+use crate::relocation::{find_old_locations, relocate, Locations};
+use clap::Args;
+use error_stack::ResultExt;
+use std::path::PathBuf;
+
+/// Move JDKs and context links from an old location, e.g. after changing `XDG_CACHE_HOME` or
+/// `XDG_STATE_HOME`.
+#[derive(Debug, Args)]
+pub struct Relocate {
+    /// The old cache directory, containing `jdks`. Detected if not given.
+    #[clap(long)]
+    from_cache: Option<PathBuf>,
+    /// The old state directory, containing the context links. Detected if not given.
+    #[clap(long)]
+    from_state: Option<PathBuf>,
+}
+
+impl JpreCommand for Relocate {
+    fn run(self, _context: Context) -> ESResult<(), JpreError> {
+        let current = Locations::current();
+        let old = if self.from_cache.is_some() || self.from_state.is_some() {
+            Locations {
+                cache_dir: self.from_cache.unwrap_or(current.cache_dir.clone()),
+                state_dir: self.from_state.unwrap_or(current.state_dir.clone()),
+            }
+        } else {
+            match find_old_locations().into_iter().next() {
+                Some(old) => old,
+                None => {
+                    eprintln!(
+                        "No old JDK store found, JDKs are in {}",
+                        current.jdk_store().display()
+                    );
+                    return Ok(());
+                }
+            }
+        };
+        if old == current {
+            eprintln!("Nothing to relocate, the old and new locations are the same");
+            return Ok(());
+        }
+        if old.cache_dir != current.cache_dir {
+            eprintln!(
+                "Moving JDK store from {} to {}",
+                old.jdk_store().display(),
+                current.jdk_store().display()
+            );
+        }
+        if old.state_dir != current.state_dir {
+            eprintln!(
+                "Moving context links from {} to {}",
+                old.context_links().display(),
+                current.context_links().display()
+            );
+        }
+        let report = relocate(&old)
+            .change_context(JpreError::Unexpected)
+            .attach_printable("Failed to relocate JDK store")?;
+        eprintln!(
+            "Moved {} item(s), skipped {} already present, updated {} context link(s)",
+            report.moved, report.skipped, report.relinked
+        );
+        Ok(())
+    }
+}
@@ -1,33 +1,264 @@
 use crate::command::{Context, JpreCommand};
-use crate::error::{ESResult, JpreError};
+use crate::context_id::{contexts_by_jdk, format_context_count};
+use crate::error::{ESResult, JpreError, UserMessage};
 use crate::java_version::key::VersionKey;
-use crate::jdk_manager::JDK_MANAGER;
-use crate::tui::jdk_color;
+use crate::java_version::JavaVersion;
+use crate::jdk_manager::{jdk_store_path, retained_jdks_in, RetainedJdk, JDK_MANAGER};
+use crate::tui::{confirm, format_bytes, jdk_color};
 use clap::Args;
-use error_stack::ResultExt;
+use error_stack::{Report, ResultExt};
 use owo_colors::{OwoColorize, Stream};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::warn;
 
-/// Remove an installed JDK.
+/// Remove installed JDKs.
 #[derive(Debug, Args)]
 pub struct RemoveJdk {
     /// The JDK to remove.
-    jdk: VersionKey,
+    #[clap(required_unless_present_any = ["all", "keep_latest_per_major"])]
+    jdk: Option<VersionKey>,
+    /// Remove all installed JDKs, and the old versions kept after updates.
+    #[clap(long, conflicts_with_all = ["jdk", "keep_latest_per_major"])]
+    all: bool,
+    /// Remove the old versions kept after updates with `keep_previous_versions`, keeping only the
+    /// newest version of each JDK. Early access and special builds like `21-ea` and `21-crac` are
+    /// kept apart from the regular ones. Old versions of a JDK whose full version is unknown are
+    /// kept.
+    #[clap(long, conflicts_with = "jdk")]
+    keep_latest_per_major: bool,
+    /// Don't ask before removing. Without a terminal to ask on, nothing is removed unless this is
+    /// given.
+    #[clap(short, long, visible_alias = "assume-yes")]
+    yes: bool,
 }
 
 impl JpreCommand for RemoveJdk {
-    fn run(self, context: Context) -> ESResult<(), JpreError> {
-        let path = JDK_MANAGER
-            .get_jdk_path(&context.config, &self.jdk)
-            .change_context(JpreError::Unexpected)
-            .attach_printable_lazy(|| format!("Failed to get path for JDK {}", self.jdk))?;
-        std::fs::remove_dir_all(&path)
-            .change_context(JpreError::Unexpected)
-            .attach_printable_lazy(|| format!("Failed to remove JDK at {}", path.display()))?;
+    fn run(self, _context: Context) -> ESResult<(), JpreError> {
+        let mut to_remove = if let Some(jdk) = &self.jdk {
+            if !JDK_MANAGER.is_installed(jdk) {
+                return Err(Report::new(JpreError::UserError).attach(UserMessage {
+                    message: format!("JDK {} is not installed", jdk),
+                }));
+            }
+            vec![Removal::Installed(jdk.clone())]
+        } else {
+            let installed = JDK_MANAGER
+                .get_installed_jdks()
+                .change_context(JpreError::Unexpected)
+                .attach_printable("Failed to get installed JDKs")?;
+            if self.keep_latest_per_major {
+                let (older, unknown) = older_per_major(jdk_store_path(), &installed)?;
+                for jdk in unknown {
+                    warn!(
+                        "Keeping the old versions of JDK {}, its full version is unknown. \
+                         `jpre migrate-markers` may find it",
+                        jdk
+                    );
+                }
+                older.into_iter().map(Removal::Retained).collect()
+            } else {
+                installed
+                    .into_iter()
+                    .map(Removal::Installed)
+                    .chain(
+                        JDK_MANAGER
+                            .get_retained_jdks()
+                            .into_iter()
+                            .map(Removal::Retained),
+                    )
+                    .collect::<Vec<_>>()
+            }
+        };
+        to_remove.sort_by_cached_key(Removal::sort_key);
+        if to_remove.is_empty() {
+            eprintln!("Nothing to remove");
+            return Ok(());
+        }
+
+        let sizes = to_remove.iter().map(Removal::size).collect::<Vec<_>>();
         eprintln!(
-            "Removed JDK {}",
-            self.jdk
-                .if_supports_color(Stream::Stderr, |s| s.color(jdk_color()))
+            "Removing {} JDK(s), {} in total:",
+            to_remove.len(),
+            format_bytes(sizes.iter().sum())
         );
+        let contexts = contexts_by_jdk();
+        for (removal, size) in to_remove.iter().zip(&sizes) {
+            let path = removal.path();
+            let in_use = contexts
+                .get(&path)
+                .map(|&count| {
+                    format!(", in use by {}", format_context_count(count))
+                        .if_supports_color(Stream::Stderr, |s| s.yellow())
+                        .to_string()
+                })
+                .unwrap_or_default();
+            eprintln!(
+                "- {} ({}, {}{})",
+                removal.describe(),
+                path.display(),
+                format_bytes(*size),
+                in_use
+            );
+        }
+        if to_remove
+            .iter()
+            .any(|removal| contexts.contains_key(&removal.path()))
+        {
+            warn!("Shells using these JDKs will be left without a working JAVA_HOME");
+        }
+        if !self.yes && !confirm("Remove these JDKs?") {
+            return Err(Report::new(JpreError::UserError).attach(UserMessage {
+                message: "Nothing removed, pass --yes to remove without asking".to_string(),
+            }));
+        }
+        for removal in &to_remove {
+            removal.remove()?;
+        }
+        Ok(())
+    }
+}
+
+/// What `remove` removes.
+enum Removal {
+    Installed(VersionKey),
+    /// An old version of a JDK kept after an update.
+    Retained(RetainedJdk),
+}
+
+impl Removal {
+    /// Installed JDKs first, then their old versions, oldest first.
+    fn sort_key(&self) -> (VersionKey, Option<JavaVersion>) {
+        match self {
+            Removal::Installed(jdk) => (jdk.clone(), None),
+            Removal::Retained(retained) => (retained.jdk.clone(), Some(retained.version.clone())),
+        }
+    }
+
+    fn path(&self) -> PathBuf {
+        match self {
+            Removal::Installed(jdk) => JDK_MANAGER.get_jdk_install_path(jdk),
+            Removal::Retained(retained) => retained.path.clone(),
+        }
+    }
+
+    fn size(&self) -> u64 {
+        match self {
+            Removal::Installed(jdk) => JDK_MANAGER.installed_size(jdk),
+            Removal::Retained(retained) => JDK_MANAGER.retained_size(retained),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Removal::Installed(jdk) => jdk
+                .if_supports_color(Stream::Stderr, |s| s.color(jdk_color()))
+                .to_string(),
+            Removal::Retained(retained) => format!(
+                "{} {}",
+                retained
+                    .jdk
+                    .if_supports_color(Stream::Stderr, |s| s.color(jdk_color())),
+                format!("(previous version {})", retained.version)
+                    .if_supports_color(Stream::Stderr, |s| s.dimmed())
+            ),
+        }
+    }
+
+    fn remove(&self) -> ESResult<(), JpreError> {
+        match self {
+            Removal::Installed(jdk) => JDK_MANAGER.remove_jdk(jdk),
+            Removal::Retained(retained) => JDK_MANAGER.remove_retained_jdk(retained),
+        }
+        .change_context(JpreError::Unexpected)
+        .attach_printable_lazy(|| format!("Failed to remove JDK at {}", self.path().display()))?;
+        eprintln!("Removed JDK {}", self.describe());
         Ok(())
     }
 }
+
+/// The old versions of JDKs retained in `store` that aren't the newest of their JDK, and the
+/// installed JDKs whose old versions are all kept because their full version is unknown, so it's
+/// not known whether they're older. Of a JDK that isn't `installed` anymore, the newest retained
+/// version is kept.
+fn older_per_major(
+    store: &Path,
+    installed: &[VersionKey],
+) -> ESResult<(Vec<RetainedJdk>, Vec<VersionKey>), JpreError> {
+    let mut newest = BTreeMap::new();
+    for jdk in installed {
+        let full = JDK_MANAGER
+            .get_full_version_from_path(&store.join(jdk.to_string()))
+            .change_context(JpreError::Unexpected)
+            .attach_printable_lazy(|| format!("Failed to get full version for JDK {}", jdk))?;
+        newest.insert(jdk.clone(), full);
+    }
+    let mut older = Vec::new();
+    let mut unknown = Vec::new();
+    // Newest first, so the first old version of a JDK that isn't installed is the one kept.
+    for retained in retained_jdks_in(store) {
+        match newest.get(&retained.jdk) {
+            None => {
+                newest.insert(retained.jdk.clone(), Some(retained.version));
+            }
+            Some(None) => {
+                if !unknown.contains(&retained.jdk) {
+                    unknown.push(retained.jdk);
+                }
+            }
+            Some(Some(newest)) if retained.version < *newest => older.push(retained),
+            // Newer than the installed version, e.g. after pinning an older one.
+            Some(Some(_)) => {}
+        }
+    }
+    Ok((older, unknown))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::jdk_manager::JdkManager;
+    use std::str::FromStr;
+
+    fn add_jdk(store: &Path, name: &str, full: Option<&str>) {
+        let path = store.join(name);
+        std::fs::create_dir(&path).unwrap();
+        if let Some(full) = full {
+            JdkManager::write_marker(&path, &JavaVersion::from_str(full).unwrap()).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_older_per_major() {
+        let store = tempfile::tempdir().unwrap();
+        let store = store.path();
+        add_jdk(store, "21", Some("21.0.2+13"));
+        add_jdk(store, "21.previous-21.0.1+12", None);
+        add_jdk(store, "21.previous-21+35", None);
+        add_jdk(store, "21-ea", Some("21-ea+30"));
+        add_jdk(store, "21-ea.previous-21-ea+31", None);
+        add_jdk(store, "17", None);
+        add_jdk(store, "17.previous-17.0.9+9", None);
+        add_jdk(store, "11.previous-11.0.21+9", None);
+        add_jdk(store, "11.previous-11.0.20+8", None);
+        let installed = ["21", "21-ea", "17"].map(|k| VersionKey::from_str(k).unwrap());
+
+        let (older, unknown) = older_per_major(store, &installed).unwrap();
+        let mut older = older
+            .iter()
+            .map(|r| r.path.file_name().unwrap().to_str().unwrap())
+            .collect::<Vec<_>>();
+        older.sort();
+        // The newest of 11 stays although 11 isn't installed, the newer 21-ea build stays too.
+        assert_eq!(
+            vec![
+                "11.previous-11.0.20+8",
+                "21.previous-21+35",
+                "21.previous-21.0.1+12"
+            ],
+            older
+        );
+        // The legacy 17 keeps its old version and is reported.
+        assert_eq!(vec![VersionKey::from_str("17").unwrap()], unknown);
+    }
+}
@@ -0,0 +1,66 @@
+use crate::command::use_jdk::UseTarget;
+use crate::command::{Context, JpreCommand};
+use crate::error::{ESResult, JpreError, UserMessage};
+use crate::jdk_manager::JDK_MANAGER;
+use crate::sbom::cyclonedx_bom;
+use clap::Args;
+use error_stack::{Report, ResultExt};
+use std::time::SystemTime;
+use tracing::warn;
+
+/// Print a CycloneDX SBOM recording where installed JDKs came from.
+#[derive(Debug, Args)]
+pub struct Sbom {
+    /// The JDK to describe. Version key or 'default'. All installed JDKs if not given.
+    jdk: Option<UseTarget>,
+}
+
+impl JpreCommand for Sbom {
+    fn run(self, context: Context) -> ESResult<(), JpreError> {
+        let installed = JDK_MANAGER
+            .get_installed_jdks()
+            .change_context(JpreError::Unexpected)
+            .attach_printable("Failed to get installed JDKs")?;
+        let mut jdks = match self.jdk {
+            Some(target) => {
+                let jdk = target.resolve(&context)?;
+                if !installed.contains(&jdk) {
+                    return Err(Report::new(JpreError::UserError).attach(UserMessage {
+                        message: format!("JDK {} is not installed", jdk),
+                    }));
+                }
+                vec![jdk]
+            }
+            None => installed,
+        };
+        jdks.sort();
+
+        let mut described = Vec::new();
+        for jdk in jdks {
+            let metadata = JDK_MANAGER
+                .get_install_metadata(&jdk)
+                .change_context(JpreError::Unexpected)
+                .attach_printable_lazy(|| format!("Failed to get install metadata for {}", jdk))?;
+            match metadata {
+                Some(metadata) => described.push((jdk, metadata)),
+                None => warn!(
+                    "JDK {} was installed by an older jpre without provenance, reinstall it with `jpre update {}`",
+                    jdk, jdk
+                ),
+            }
+        }
+
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let bom = cyclonedx_bom(&described, now);
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&bom)
+                .change_context(JpreError::Unexpected)
+                .attach_printable("Failed to serialize SBOM")?
+        );
+        Ok(())
+    }
+}
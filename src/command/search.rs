@@ -0,0 +1,103 @@
+use crate::command::{Context, JpreCommand};
+use crate::error::{ESResult, JpreError, UserMessage};
+use crate::foojay::FOOJAY_API;
+use crate::tui::{jdk_color, new_spinner};
+use clap::Args;
+use error_stack::{Report, ResultExt};
+use owo_colors::{OwoColorize, Stream};
+use tracing::warn;
+
+/// Search distributions and their version keys. Words starting with a digit match version keys by
+/// prefix, other words match distribution names and synonyms. Without any distribution words, only
+/// the configured distributions are searched.
+#[derive(Debug, Args)]
+pub struct Search {
+    /// The search terms, e.g. `zulu 17` or `graal`.
+    #[clap(required = true)]
+    query: Vec<String>,
+}
+
+impl JpreCommand for Search {
+    fn run(self, context: Context) -> ESResult<(), JpreError> {
+        let (version_terms, name_terms): (Vec<_>, Vec<_>) = self
+            .query
+            .iter()
+            .map(|t| t.to_ascii_lowercase())
+            .partition(|t| t.starts_with(|c: char| c.is_ascii_digit()));
+
+        let distributions = if name_terms.is_empty() {
+            context.config.distributions.clone()
+        } else {
+            let mut distributions = FOOJAY_API
+                .list_distributions()
+                .change_context(JpreError::Unexpected)
+                .attach_printable("Failed to list distributions")?;
+            distributions.sort();
+            distributions
+                .into_iter()
+                .filter(|d| {
+                    name_terms.iter().all(|term| {
+                        std::iter::once(&d.name)
+                            .chain(&d.synonyms)
+                            .any(|name| fuzzy_matches(&name.to_ascii_lowercase(), term))
+                    })
+                })
+                .map(|d| d.name)
+                .collect()
+        };
+        if distributions.is_empty() {
+            return Err(Report::new(JpreError::UserError).attach(UserMessage {
+                message: format!("No distributions match '{}'", name_terms.join(" ")),
+            }));
+        }
+
+        let progress = new_spinner("Searching distributions".to_string());
+        progress.set_length(distributions.len() as u64);
+        let mut matches = Vec::new();
+        for distribution in &distributions {
+            progress.set_message(format!("Searching {}", distribution));
+            match FOOJAY_API.list_dist_version_keys(distribution) {
+                Ok(keys) => {
+                    let mut keys = keys
+                        .into_iter()
+                        .filter(|k| {
+                            let k = k.to_string();
+                            version_terms
+                                .iter()
+                                .all(|term| k.starts_with(term.as_str()))
+                        })
+                        .collect::<Vec<_>>();
+                    keys.sort();
+                    matches.extend(keys.into_iter().rev().map(|k| (distribution, k)));
+                }
+                // Some distributions are listed but have no versions for us, don't fail the search.
+                Err(e) => warn!("Failed to list versions of {}: {:?}", distribution, e),
+            }
+            progress.inc(1);
+        }
+        progress.finish_and_clear();
+
+        if matches.is_empty() {
+            eprintln!("No matches found");
+            return Ok(());
+        }
+        for (distribution, key) in matches {
+            // Ready to paste after `jpre use`.
+            println!(
+                "{} --distribution {}",
+                key.if_supports_color(Stream::Stdout, |s| s.color(jdk_color())),
+                distribution
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Whether `needle` is a substring of `haystack`, or failing that, its characters appear in order.
+fn fuzzy_matches(haystack: &str, needle: &str) -> bool {
+    if haystack.contains(needle) {
+        return true;
+    }
+    let mut chars = haystack.chars();
+    needle.chars().all(|c| chars.any(|h| h == c))
+}
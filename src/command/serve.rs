@@ -0,0 +1,261 @@
+use crate::command::{Context, JpreCommand};
+use crate::config::{JpreConfig, PROJECT_DIRS};
+use crate::context_id::{context_path_for_id, get_context_id};
+use crate::error::{ESResult, JpreError, UserMessage};
+use crate::java_version::key::VersionKey;
+use crate::jdk_manager::JDK_MANAGER;
+use clap::Args;
+use error_stack::{Report, ResultExt};
+use serde::{Deserialize, Deserializer};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+/// Answer read-only queries of editor integrations on a Unix socket, so they don't have to run jpre
+/// for each one. Speaks JSON-RPC 2.0, one request per line. Methods: `installed`, `resolve` with a
+/// `jdk` key or 'default', and `current` with an optional `context_id`. JDKs are objects with
+/// `key`, `version` and `path`, or null if there's none.
+#[derive(Debug, Args)]
+pub struct Serve {
+    /// The socket to listen on. Defaults to `jpre.sock` in jpre's cache directory.
+    #[clap(long, value_name = "PATH")]
+    socket: Option<PathBuf>,
+}
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    /// `None` for notifications, which get no response. A null ID is still an ID.
+    #[serde(default, deserialize_with = "deserialize_id")]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+fn deserialize_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Value>, D::Error> {
+    Value::deserialize(deserializer).map(Some)
+}
+
+/// An error code and message.
+type CallError = (i64, String);
+
+impl JpreCommand for Serve {
+    fn run(self, context: Context) -> ESResult<(), JpreError> {
+        let socket = self
+            .socket
+            .unwrap_or_else(|| PROJECT_DIRS.cache_dir().join("jpre.sock"));
+        let listener = bind(&socket)?;
+        eprintln!("Listening on {}", socket.display());
+        let context = Arc::new(context);
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let context = context.clone();
+                    std::thread::spawn(move || {
+                        if let Err(e) = serve_connection(&context, stream) {
+                            debug!("Connection closed: {}", e);
+                        }
+                    });
+                }
+                Err(e) => warn!("Could not accept connection: {}", e),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Listen on `socket`, replacing a socket left behind by a server that's gone. Only this user may
+/// connect.
+fn bind(socket: &Path) -> ESResult<UnixListener, JpreError> {
+    match std::fs::symlink_metadata(socket) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            if UnixStream::connect(socket).is_ok() {
+                return Err(Report::new(JpreError::UserError).attach(UserMessage {
+                    message: format!("Another server is listening on {}", socket.display()),
+                }));
+            }
+            debug!("Removing stale socket {:?}", socket);
+            std::fs::remove_file(socket)
+                .change_context(JpreError::Unexpected)
+                .attach_printable_lazy(|| format!("Failed to remove stale socket {:?}", socket))?;
+        }
+        Ok(_) => {
+            return Err(Report::new(JpreError::UserError).attach(UserMessage {
+                message: format!("{} exists and is not a socket", socket.display()),
+            }))
+        }
+        Err(_) => {}
+    }
+    let parent = socket
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    std::fs::create_dir_all(parent)
+        .change_context(JpreError::Unexpected)
+        .attach_printable_lazy(|| format!("Failed to create directory {:?}", parent))?;
+    // Bound in a directory only this user can enter, and moved into place once its permissions
+    // are set, so nobody else can connect in between.
+    let private = tempfile::tempdir_in(parent)
+        .change_context(JpreError::Unexpected)
+        .attach_printable_lazy(|| format!("Failed to create directory in {:?}", parent))?;
+    std::fs::set_permissions(private.path(), std::fs::Permissions::from_mode(0o700))
+        .change_context(JpreError::Unexpected)
+        .attach_printable_lazy(|| format!("Failed to set permissions of {:?}", private.path()))?;
+    let bound = private.path().join("jpre.sock");
+    let listener = UnixListener::bind(&bound)
+        .change_context(JpreError::UserError)
+        .attach(UserMessage {
+            message: format!("Could not listen on {}", socket.display()),
+        })?;
+    std::fs::set_permissions(&bound, std::fs::Permissions::from_mode(0o600))
+        .change_context(JpreError::Unexpected)
+        .attach_printable_lazy(|| format!("Failed to set permissions of {:?}", bound))?;
+    std::fs::rename(&bound, socket)
+        .change_context(JpreError::UserError)
+        .attach(UserMessage {
+            message: format!("Could not listen on {}", socket.display()),
+        })?;
+    Ok(listener)
+}
+
+fn serve_connection(context: &Context, stream: UnixStream) -> std::io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Some(response) = handle_line(context, &line) else {
+            continue;
+        };
+        writeln!(writer, "{}", response)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+/// The response to a request, `None` for a notification.
+fn handle_line(context: &Context, line: &str) -> Option<Value> {
+    let request = match serde_json::from_str::<Request>(line) {
+        Ok(request) => request,
+        Err(e) => {
+            return Some(error_response(
+                Value::Null,
+                (PARSE_ERROR, format!("Invalid request: {}", e)),
+            ))
+        }
+    };
+    // The methods only read, so there's nothing to do for a notification.
+    let id = request.id?;
+    debug!("Request {}: {}", id, request.method);
+    // Read for each request, so a default changed since the server started is seen.
+    let context = match JpreConfig::load_read_only() {
+        Ok(config) => Context {
+            config,
+            platform: context.platform.clone(),
+        },
+        Err(e) => return Some(error_response(id, internal_error(e))),
+    };
+    Some(match call(&context, &request.method, &request.params) {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => error_response(id, error),
+    })
+}
+
+fn error_response(id: Value, (code, message): CallError) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn call(context: &Context, method: &str, params: &Value) -> Result<Value, CallError> {
+    match method {
+        "installed" => installed(),
+        "resolve" => {
+            let jdk = string_param(params, "jdk")?
+                .ok_or_else(|| (INVALID_PARAMS, "Missing parameter jdk".to_string()))?;
+            resolve(context, jdk)
+        }
+        "current" => current(context, string_param(params, "context_id")?),
+        _ => Err((METHOD_NOT_FOUND, format!("Unknown method {}", method))),
+    }
+}
+
+fn string_param<'a>(params: &'a Value, name: &str) -> Result<Option<&'a str>, CallError> {
+    match params.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value)),
+        Some(_) => Err((
+            INVALID_PARAMS,
+            format!("Parameter {} must be a string", name),
+        )),
+    }
+}
+
+fn installed() -> Result<Value, CallError> {
+    let mut installed = JDK_MANAGER.get_installed_jdks().map_err(internal_error)?;
+    installed.sort();
+    Ok(installed
+        .iter()
+        .map(|jdk| describe_jdk(&JDK_MANAGER.get_jdk_install_path(jdk)))
+        .collect())
+}
+
+/// The installed JDK for a key or 'default', without installing anything.
+fn resolve(context: &Context, jdk: &str) -> Result<Value, CallError> {
+    let jdk = if jdk == "default" {
+        match context.default_jdk().map_err(internal_error)? {
+            Some(jdk) => jdk,
+            None => return Ok(Value::Null),
+        }
+    } else {
+        VersionKey::from_str(jdk)
+            .map_err(|_| (INVALID_PARAMS, format!("Invalid version key {}", jdk)))?
+    };
+    if !JDK_MANAGER.is_installed(&jdk) {
+        return Ok(Value::Null);
+    }
+    Ok(describe_jdk(&JDK_MANAGER.get_jdk_install_path(&jdk)))
+}
+
+/// The JDK linked for a context, this server's own if none is given.
+fn current(context: &Context, context_id: Option<&str>) -> Result<Value, CallError> {
+    let context_id = match context_id {
+        // It's a file name in the state directory, so it can't lead out of it.
+        Some(id) if id.is_empty() || id.contains('/') || id == "." || id == ".." => {
+            return Err((INVALID_PARAMS, format!("Invalid context ID {:?}", id)))
+        }
+        Some(id) => id.to_string(),
+        None => get_context_id(&context.config),
+    };
+    match std::fs::read_link(context_path_for_id(&context_id)) {
+        Ok(target) if target.exists() => Ok(describe_jdk(&target)),
+        _ => Ok(Value::Null),
+    }
+}
+
+fn describe_jdk(path: &Path) -> Value {
+    let key = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| VersionKey::from_str(name).ok());
+    let version = JDK_MANAGER.get_full_version_from_path(path).ok().flatten();
+    json!({
+        "key": key.map(|k| k.to_string()),
+        "version": version.map(|v| v.to_string()),
+        "path": path,
+    })
+}
+
+fn internal_error<C: error_stack::Context>(e: Report<C>) -> CallError {
+    debug!("Request failed: {:?}", e);
+    (INTERNAL_ERROR, e.current_context().to_string())
+}
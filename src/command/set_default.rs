@@ -1,7 +1,7 @@
 use crate::command::{Context, JpreCommand};
 use crate::error::{ESResult, JpreError};
 use crate::java_version::key::VersionKey;
-use crate::jdk_manager::JDK_MANAGER;
+use crate::jdk_manager::{install_error, JDK_MANAGER};
 use crate::tui::jdk_color;
 use clap::Args;
 use error_stack::ResultExt;
@@ -11,44 +11,72 @@ use owo_colors::{OwoColorize, Stream};
 #[derive(Debug, Args)]
 pub struct SetDefault {
     /// The JDK to use.
-    jdk: VersionKey,
+    #[clap(required_unless_present = "auto_latest_lts")]
+    jdk: Option<VersionKey>,
+    /// Always use the latest installed LTS JDK, re-evaluated as new ones are installed.
+    #[clap(long, conflicts_with = "jdk")]
+    auto_latest_lts: bool,
 }
 
 impl JpreCommand for SetDefault {
     fn run(self, mut context: Context) -> ESResult<(), JpreError> {
-        if context
-            .config
-            .default_jdk
-            .as_ref()
-            .is_some_and(|i| i == &self.jdk)
+        let Some(jdk) = self.jdk else {
+            return Self::set_auto_latest_lts(context);
+        };
+        if !context.config.default_auto_latest_lts
+            && context
+                .config
+                .default_jdk
+                .as_ref()
+                .is_some_and(|i| i == &jdk)
         {
             eprintln!(
                 "Default JDK already set to '{}'",
-                self.jdk
-                    .if_supports_color(Stream::Stderr, |s| s.color(jdk_color()))
+                jdk.if_supports_color(Stream::Stderr, |s| s.color(jdk_color()))
             );
             return Ok(());
         }
         eprintln!(
             "Validating JDK '{}'...",
-            self.jdk
-                .if_supports_color(Stream::Stderr, |s| s.color(jdk_color()))
+            jdk.if_supports_color(Stream::Stderr, |s| s.color(jdk_color()))
         );
         JDK_MANAGER
-            .get_jdk_path(&context.config, &self.jdk)
-            .change_context(JpreError::Unexpected)
-            .attach_printable_lazy(|| format!("Failed to get path for JDK {}", self.jdk))?;
-        context.config.default_jdk = Some(self.jdk.clone());
+            .get_jdk_path(&context.config, &context.platform, &jdk, None)
+            .map_err(install_error)
+            .attach_printable_lazy(|| format!("Failed to get path for JDK {}", jdk))?;
         context
             .config
-            .save()
+            .set_default_jdk(&jdk)
             .change_context(JpreError::Unexpected)
             .attach_printable("Failed to save config")?;
         eprintln!(
             "Default JDK set to '{}'",
-            self.jdk
-                .if_supports_color(Stream::Stderr, |s| s.color(jdk_color()))
+            jdk.if_supports_color(Stream::Stderr, |s| s.color(jdk_color()))
         );
         Ok(())
     }
 }
+
+impl SetDefault {
+    fn set_auto_latest_lts(mut context: Context) -> ESResult<(), JpreError> {
+        context
+            .config
+            .set_key("default_auto_latest_lts", true)
+            .change_context(JpreError::Unexpected)
+            .attach_printable("Failed to save config")?;
+        let latest_lts = JDK_MANAGER
+            .latest_installed_lts()
+            .change_context(JpreError::Unexpected)
+            .attach_printable("Failed to get installed JDKs")?;
+        match latest_lts {
+            Some(jdk) => eprintln!(
+                "Default JDK set to the latest installed LTS, currently '{}'",
+                jdk.if_supports_color(Stream::Stderr, |s| s.color(jdk_color()))
+            ),
+            None => eprintln!(
+                "Default JDK set to the latest installed LTS, but none is installed yet. Install one with e.g. `jpre install 21`"
+            ),
+        }
+        Ok(())
+    }
+}
@@ -1,10 +1,12 @@
 use crate::command::{Context, JpreCommand};
 use crate::error::{ESResult, JpreError, UserMessage};
 use crate::foojay::FOOJAY_API;
+use crate::policy;
 use clap::Args;
 use error_stack::{Report, ResultExt};
 use itertools::Itertools;
 use std::collections::HashSet;
+use toml_edit::Array;
 
 /// Set the distribution(s) to use.
 #[derive(Debug, Args)]
@@ -12,10 +14,19 @@ pub struct SetDistributions {
     /// The distribution(s) to use.
     #[clap(required = true, num_args = 1..)]
     distributions: Vec<String>,
+    /// Add the distribution(s) to the end of the current list instead of replacing it.
+    #[clap(long, conflicts_with = "remove")]
+    add: bool,
+    /// Remove the distribution(s) from the current list instead of replacing it.
+    #[clap(long)]
+    remove: bool,
 }
 
 impl JpreCommand for SetDistributions {
     fn run(self, mut context: Context) -> ESResult<(), JpreError> {
+        if self.add || self.remove {
+            return self.edit_list(context);
+        }
         if self.distributions == context.config.distributions {
             eprintln!(
                 "Distribution(s) already set to '{}'",
@@ -27,41 +38,82 @@ impl JpreCommand for SetDistributions {
             "Validating distribution(s) '{}'...",
             self.distributions.join(", ")
         );
-        let distributions = FOOJAY_API
-            .list_distributions()
+        validate_distributions(&self.distributions)?;
+        context
+            .config
+            .set_key("distributions", Array::from_iter(&self.distributions))
             .change_context(JpreError::Unexpected)
-            .attach_printable("Failed to list distributions")?;
-        let all_names = distributions
-            .iter()
-            .flat_map(|i| &i.synonyms)
-            .map(String::as_str)
-            .collect::<HashSet<_>>();
-        let mut missing_names = self
+            .attach_printable("Failed to save config")?;
+        eprintln!("Distribution(s) set to '{}'", self.distributions.join(", "));
+        Ok(())
+    }
+}
+
+impl SetDistributions {
+    fn edit_list(self, mut context: Context) -> ESResult<(), JpreError> {
+        if self.add {
+            validate_distributions(&self.distributions)?;
+        } else if context
+            .config
             .distributions
             .iter()
-            .map(String::as_str)
-            .filter(|i| !all_names.contains(*i))
-            .collect::<Vec<_>>();
-        if !missing_names.is_empty() {
-            missing_names.sort();
-            return Err(Report::new(JpreError::UserError)
-                .attach(UserMessage {
-                    message: format!("Distribution(s) '{}' not found", missing_names.join(", ")),
-                })
-                .attach(UserMessage {
-                    message: format!(
-                        "Available distributions: {}",
-                        distributions.into_iter().map(|i| i.name).join(", ")
-                    ),
-                }));
+            .all(|d| self.distributions.contains(d))
+        {
+            return Err(Report::new(JpreError::UserError).attach(UserMessage {
+                message: "Cannot remove all distributions".to_string(),
+            }));
         }
-        context.config.distributions = self.distributions.clone();
-        context
-            .config
-            .save()
+        for distribution in &self.distributions {
+            if self.add {
+                context.config.add_distribution(distribution)
+            } else {
+                context.config.remove_distribution(distribution)
+            }
             .change_context(JpreError::Unexpected)
             .attach_printable("Failed to save config")?;
-        eprintln!("Distribution(s) set to '{}'", self.distributions.join(", "));
+        }
+        eprintln!(
+            "Distribution(s) set to '{}'",
+            context.config.distributions.join(", ")
+        );
         Ok(())
     }
 }
+
+/// Check that all `distributions` are allowed by the policy and known to Foojay, by name or
+/// synonym.
+pub(super) fn validate_distributions(distributions: &[String]) -> ESResult<(), JpreError> {
+    for distribution in distributions {
+        policy::current()
+            .check_distribution(distribution)
+            .change_context(JpreError::UserError)?;
+    }
+    let available = FOOJAY_API
+        .list_distributions()
+        .change_context(JpreError::Unexpected)
+        .attach_printable("Failed to list distributions")?;
+    let all_names = available
+        .iter()
+        .flat_map(|i| &i.synonyms)
+        .map(String::as_str)
+        .collect::<HashSet<_>>();
+    let mut missing_names = distributions
+        .iter()
+        .map(String::as_str)
+        .filter(|i| !all_names.contains(*i))
+        .collect::<Vec<_>>();
+    if !missing_names.is_empty() {
+        missing_names.sort();
+        return Err(Report::new(JpreError::UserError)
+            .attach(UserMessage {
+                message: format!("Distribution(s) '{}' not found", missing_names.join(", ")),
+            })
+            .attach(UserMessage {
+                message: format!(
+                    "Available distributions: {}",
+                    available.into_iter().map(|i| i.name).join(", ")
+                ),
+            }));
+    }
+    Ok(())
+}
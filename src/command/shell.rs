@@ -0,0 +1,76 @@
+use crate::command::use_jdk::{run_in_context, UseTarget};
+use crate::command::{Context, JpreCommand};
+use crate::context_id::{context_id_env_applies, context_path_for_id};
+use crate::error::{ESResult, JpreError};
+use crate::jdk_manager::{install_error, JDK_MANAGER};
+use crate::temp_context;
+use crate::tui::jdk_color;
+use clap::Args;
+use error_stack::ResultExt;
+use owo_colors::{OwoColorize, Stream};
+use std::path::PathBuf;
+use tracing::warn;
+
+/// Set in the subshell, to the JDK it was started with.
+pub const SHELL_ENV: &str = "JPRE_SHELL";
+
+/// Start a subshell with a JDK active, in its own context. Leaving the subshell returns to the
+/// current JDK.
+#[derive(Debug, Args)]
+pub struct Shell {
+    /// The JDK to use. Version key or 'default'.
+    jdk: UseTarget,
+    /// The distribution to download the JDK from if it's not installed, instead of the configured
+    /// ones.
+    #[clap(long, visible_alias = "dist")]
+    distribution: Option<String>,
+}
+
+impl JpreCommand for Shell {
+    fn run(self, context: Context) -> ESResult<(), JpreError> {
+        let jdk = self.jdk.resolve(&context)?;
+        let jdk_path = JDK_MANAGER
+            .get_jdk_path(
+                &context.config,
+                &context.platform,
+                &jdk,
+                self.distribution.as_deref(),
+            )
+            .map_err(install_error)
+            .attach_printable_lazy(|| format!("Failed to get path for JDK {}", jdk))?;
+        if !context_id_env_applies(&context.config) {
+            warn!("The `env` context ID strategy is off or comes after one that applies, so `jpre use` in the subshell changes the current context too");
+        }
+
+        // The subshell gets its own context, so `jpre use` in it doesn't leak out. If jpre dies
+        // before removing it, `jpre gc` does.
+        let context_id = temp_context::shell_context_id(std::process::id());
+        let link = context_path_for_id(&context_id);
+        std::fs::create_dir_all(link.parent().unwrap())
+            .change_context(JpreError::Unexpected)
+            .attach_printable_lazy(|| format!("Failed to create {:?}", link.parent()))?;
+        std::os::unix::fs::symlink(&jdk_path, &link)
+            .change_context(JpreError::Unexpected)
+            .attach_printable_lazy(|| format!("Failed to link {:?} to {:?}", link, jdk_path))?;
+
+        let shell = std::env::var_os("SHELL")
+            .filter(|s| !s.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("/bin/sh"));
+        eprintln!(
+            "Starting {} with JDK {}, exit it to go back",
+            shell.display(),
+            jdk.if_supports_color(Stream::Stderr, |s| s.color(jdk_color()))
+        );
+        let mut command = std::process::Command::new(&shell);
+        command
+            .env("JPRE_CONTEXT_ID", &context_id)
+            .env(SHELL_ENV, jdk.to_string());
+        let code = run_in_context(command, &link, || {
+            if let Err(e) = std::fs::remove_file(&link) {
+                warn!("Could not remove context link {:?}: {}", link, e);
+            }
+        })?;
+        std::process::exit(code);
+    }
+}
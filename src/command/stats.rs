@@ -0,0 +1,58 @@
+use crate::command::{Context, JpreCommand};
+use crate::error::{ESResult, JpreError};
+use crate::journal;
+use crate::stats::UsageStats;
+use crate::tui::jdk_color;
+use clap::Args;
+use error_stack::ResultExt;
+use owo_colors::{OwoColorize, Stream};
+use std::time::{Duration, SystemTime};
+
+/// Summarize the local usage journal. Nothing is ever sent anywhere.
+#[derive(Debug, Args)]
+pub struct Stats {}
+
+impl JpreCommand for Stats {
+    fn run(self, context: Context) -> ESResult<(), JpreError> {
+        let entries = journal::read()
+            .change_context(JpreError::Unexpected)
+            .attach_printable("Failed to read the usage journal")?;
+        if entries.is_empty() {
+            if context.config.usage_journal {
+                eprintln!("Nothing recorded yet");
+            } else {
+                eprintln!("The usage journal is off, set `usage_journal = true` in the config to record usage");
+            }
+            return Ok(());
+        }
+        let stats = UsageStats::from_entries(&entries);
+
+        if let Some(since) = stats.since {
+            let days = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH + Duration::from_secs(since))
+                .unwrap_or_default()
+                .as_secs()
+                / (24 * 60 * 60);
+            println!("Recorded over the last {} day(s)", days);
+        }
+        println!("Installs per distribution:");
+        for (distribution, installs) in &stats.installs_per_distribution {
+            println!("  {}: {}", distribution, installs);
+        }
+        if let Some((jdk, uses)) = stats.most_used_jdk() {
+            println!(
+                "Most used JDK: {} ({} time(s))",
+                jdk.if_supports_color(Stream::Stdout, |s| s.color(jdk_color())),
+                uses
+            );
+        }
+        match stats.mean_days_between_updates {
+            Some(days) => println!(
+                "Updates: {}, every {:.1} day(s) on average",
+                stats.updates, days
+            ),
+            None => println!("Updates: {}", stats.updates),
+        }
+        Ok(())
+    }
+}
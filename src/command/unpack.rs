@@ -0,0 +1,31 @@
+use crate::command::{Context, JpreCommand};
+use crate::error::{ESResult, JpreError};
+use crate::jdk_manager::JDK_MANAGER;
+use crate::tui::jdk_color;
+use clap::Args;
+use error_stack::ResultExt;
+use owo_colors::{OwoColorize, Stream};
+use std::path::PathBuf;
+
+/// Install a JDK from an archive written by `jpre pack`.
+#[derive(Debug, Args)]
+pub struct Unpack {
+    /// The archive to install.
+    archive: PathBuf,
+}
+
+impl JpreCommand for Unpack {
+    fn run(self, _context: Context) -> ESResult<(), JpreError> {
+        let (jdk, version) = JDK_MANAGER
+            .install_packed_jdk(&self.archive)
+            .change_context(JpreError::Unexpected)
+            .attach_printable_lazy(|| format!("Failed to unpack {:?}", self.archive))?;
+        eprintln!(
+            "Installed JDK {} ({}) from {}",
+            jdk.if_supports_color(Stream::Stderr, |s| s.color(jdk_color())),
+            version,
+            self.archive.display()
+        );
+        Ok(())
+    }
+}
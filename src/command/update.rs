@@ -1,14 +1,26 @@
+use crate::command::set_distributions::validate_distributions;
 use crate::command::{Context, JpreCommand};
+use crate::completion_index;
 use crate::error::{ESResult, JpreError, UserMessage};
 use crate::foojay::FOOJAY_API;
 use crate::java_version::key::VersionKey;
-use crate::jdk_manager::JDK_MANAGER;
-use crate::tui::jdk_color;
-use clap::Args;
+use crate::java_version::{diff_segments, JavaVersion, OptComparison, PreRelease};
+use crate::jdk_manager::{install_error, JDK_MANAGER};
+use crate::jobs;
+use crate::json_output::{self, SchemaVersion};
+use crate::release_notes::release_notes_url;
+use crate::tui::{eprint_above_progress, format_bytes, jdk_color, OverallProgress};
+use crate::update_schedule::{Frequency, Scheduler};
+use crate::update_state::UpdateState;
+use clap::{Args, ValueEnum};
 use error_stack::{Report, ResultExt};
 use owo_colors::{OwoColorize, Stream};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::BTreeMap;
 use std::str::FromStr;
-use tracing::warn;
+use std::time::Instant;
+use tracing::{debug, warn};
 
 /// Update installed Java versions.
 #[derive(Debug, Args)]
@@ -17,7 +29,46 @@ pub struct UpdateInstalled {
     #[clap(short, long)]
     check: bool,
     /// The JDK to update. Version key, 'all', or 'default'.
-    target: UpdateTarget,
+    #[clap(required_unless_present_any = ["schema", "install_schedule", "remove_schedule"])]
+    target: Option<UpdateTarget>,
+    /// The distribution to update from, instead of the configured ones.
+    #[clap(long, visible_alias = "dist")]
+    distribution: Option<String>,
+    /// How to report what changed. `json` prints an array with the result for each JDK to stdout,
+    /// and carries on with the other JDKs when one fails.
+    #[clap(long, value_enum, default_value_t = UpdateOutput::Text)]
+    output: UpdateOutput,
+    /// Print the JSON Schema of `--output json` instead of updating.
+    #[clap(long, conflicts_with_all = ["check", "distribution", "output"])]
+    schema: bool,
+    /// Only report JDKs that change, and problems.
+    #[clap(short, long)]
+    quiet: bool,
+    /// Only update JDKs that are installed. `update` never installs a JDK, this spells it out for
+    /// scripts and quietly skips a version key that isn't installed instead of warning about it.
+    #[clap(long)]
+    only_installed: bool,
+    /// Run `jpre update all --quiet` this often, with a systemd user timer, a launchd agent on
+    /// macOS, or a crontab entry. Replaces an earlier schedule.
+    #[clap(
+        long,
+        value_enum,
+        value_name = "FREQUENCY",
+        conflicts_with_all = ["target", "check", "distribution", "output", "schema", "quiet"]
+    )]
+    install_schedule: Option<Frequency>,
+    /// Remove the schedule added by `--install-schedule`.
+    #[clap(
+        long,
+        conflicts_with_all = ["target", "check", "distribution", "output", "schema", "quiet", "install_schedule"]
+    )]
+    remove_schedule: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum UpdateOutput {
+    Text,
+    Json,
 }
 
 #[derive(Debug, Clone)]
@@ -45,80 +96,441 @@ impl FromStr for UpdateTarget {
 
 impl JpreCommand for UpdateInstalled {
     fn run(self, context: Context) -> ESResult<(), JpreError> {
+        if let Some(frequency) = self.install_schedule {
+            return install_schedule(frequency);
+        }
+        if self.remove_schedule {
+            return remove_schedule();
+        }
+        let Some(target) = &self.target else {
+            return json_output::print_schema::<UpdateReport>();
+        };
+        if let Some(distribution) = &self.distribution {
+            validate_distributions(std::slice::from_ref(distribution))?;
+        }
         let mut installed = JDK_MANAGER
             .get_installed_jdks()
             .change_context(JpreError::Unexpected)
             .attach_printable("Failed to get installed JDKs")?;
 
-        let retain_fn: Box<dyn Fn(&VersionKey) -> bool> = match self.target {
+        let retain_fn: Box<dyn Fn(&VersionKey) -> bool> = match target.clone() {
             UpdateTarget::All => Box::new(|_| true),
             UpdateTarget::Default => {
-                let Some(default) = context.config.default_jdk.clone() else {
+                let Some(default) = context.default_jdk()? else {
                     return Err(Report::new(JpreError::UserError).attach(UserMessage {
                         message: "No default JDK set".to_string(),
                     }));
                 };
                 Box::new(move |jdk| jdk == &default)
             }
-            UpdateTarget::VersionKey(key) => Box::new(move |jdk| jdk == &key),
+            UpdateTarget::VersionKey(key) => {
+                if !installed.contains(&key) {
+                    if self.only_installed {
+                        debug!("JDK {} is not installed, skipping it", key);
+                    } else {
+                        warn!(
+                            "JDK {} is not installed, and update doesn't install JDKs. Install it \
+                             with `jpre install {}`",
+                            key, key
+                        );
+                    }
+                }
+                Box::new(move |jdk| jdk == &key)
+            }
         };
         installed.retain(retain_fn);
 
-        installed.sort();
+        // By installed version, so the results read oldest first. Unknown versions come first.
+        installed.sort_by_cached_key(|jdk| {
+            (
+                JDK_MANAGER.get_full_version(jdk).ok().flatten(),
+                jdk.clone(),
+            )
+        });
 
-        eprintln!("Checking updates for installed JDKs...");
-        for jdk in installed {
-            eprintln!(
-                "Checking for updates for {}",
-                jdk.if_supports_color(Stream::Stderr, |s| s.color(jdk_color()))
-            );
-            let full_version = match JDK_MANAGER.get_full_version(&jdk) {
-                Ok(full_version) => full_version,
-                Err(err) => {
-                    warn!("Failed to get full version for {}: {}", jdk, err);
-                    continue;
-                }
-            };
-
-            if let Some(full_version) = full_version {
-                let (list_info, _) = FOOJAY_API
-                    .get_latest_package_info_using_priority(&context.config, &jdk)
-                    .change_context(JpreError::Unexpected)
-                    .attach_printable("Failed to get latest package info")?;
-                let latest = list_info.java_version;
-                if latest.compare(&full_version) == std::cmp::Ordering::Greater {
-                    eprintln!(
-                        "  New version available: {}",
-                        latest.if_supports_color(Stream::Stderr, |s| s.color(jdk_color()))
-                    );
-                    if !self.check {
-                        Self::update_jdk(&context, &jdk)?;
+        completion_index::refresh_if_stale(&context.config);
+        if !self.quiet {
+            eprintln!("Checking updates for installed JDKs...");
+        }
+        let distribution = self.distribution.as_deref();
+        // Checks only talk to Foojay, so they can run in parallel. Reporting and downloading stay
+        // sequential to keep the output readable.
+        let checks = jobs::run_bounded(installed, jobs::DEFAULT_CONCURRENCY, |jdk| {
+            let check = Self::check_one(&context, &jdk, distribution);
+            (jdk, check)
+        })
+        .change_context(JpreError::Unexpected)?;
+        // A single JDK has its own download bars, overall progress only helps with several.
+        let overall = (checks.len() > 1 && !self.quiet)
+            .then(|| OverallProgress::start(checks.len() as u64, "Updating"));
+        let json = self.output == UpdateOutput::Json;
+        let mut checked = Vec::new();
+        let mut available = BTreeMap::new();
+        let mut results = Vec::new();
+        let mut first_error = None;
+        for (jdk, check) in checks {
+            let started = Instant::now();
+            let result = check.and_then(|check| {
+                match &check {
+                    UpdateCheck::Skip => {}
+                    UpdateCheck::Available { latest, .. } if self.check => {
+                        available.insert(jdk.clone(), latest.clone());
+                        checked.push(jdk.clone());
                     }
-                } else {
-                    eprintln!(
-                        "  Already up-to-date: {}",
-                        full_version.if_supports_color(Stream::Stderr, |s| s.color(jdk_color()))
-                    );
+                    _ => checked.push(jdk.clone()),
                 }
-            } else {
-                warn!("No full version found for {}", jdk);
-                if !self.check {
-                    warn!("Re-installing JDK {}", jdk);
-                    Self::update_jdk(&context, &jdk)?;
+                Self::apply_check(&context, &jdk, check, self.check, self.quiet, distribution)
+            });
+            match result {
+                Ok(result) => results.push(result),
+                Err(e) if json => {
+                    results.push(UpdateResult {
+                        key: jdk.clone(),
+                        old_version: JDK_MANAGER.get_full_version(&jdk).ok().flatten(),
+                        new_version: None,
+                        action: UpdateAction::Failed,
+                        bytes_downloaded: 0,
+                        duration_secs: started.elapsed().as_secs_f64(),
+                        error: Some(error_summary(&e)),
+                    });
+                    first_error.get_or_insert(e);
                 }
+                Err(e) => return Err(e),
             }
+            if let Some(overall) = &overall {
+                overall.inc();
+            }
+        }
+        // Installed updates aren't available anymore, so only checks leave anything behind.
+        if let Err(e) = UpdateState::record(&checked, available) {
+            warn!("Could not record available updates: {:?}", e);
         }
 
-        Ok(())
+        if json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&UpdateReport {
+                    schema_version: SchemaVersion,
+                    results,
+                })
+                .change_context(JpreError::Unexpected)
+                .attach_printable("Failed to serialize update results")?
+            );
+        }
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
     }
 }
 
+/// The output of `update --output json`.
+#[derive(Debug, Serialize, JsonSchema)]
+struct UpdateReport {
+    schema_version: SchemaVersion,
+    /// One result for each JDK that was checked.
+    results: Vec<UpdateResult>,
+}
+
+/// What `update` did to one JDK.
+#[derive(Debug, Serialize, JsonSchema)]
+struct UpdateResult {
+    key: VersionKey,
+    old_version: Option<JavaVersion>,
+    new_version: Option<JavaVersion>,
+    action: UpdateAction,
+    /// Zero when nothing was downloaded, or the archive came from the download cache.
+    bytes_downloaded: u64,
+    duration_secs: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+enum UpdateAction {
+    Updated,
+    /// Installed again, as the installed version couldn't be determined.
+    Reinstalled,
+    UpToDate,
+    /// An update was found by `--check`.
+    Available,
+    Skipped,
+    Failed,
+}
+
+/// The result of checking one installed JDK for updates.
+enum UpdateCheck {
+    /// The installed version couldn't be read, so there's nothing to compare.
+    Skip,
+    /// The installed JDK has no full version, so it can only be re-installed.
+    Reinstall,
+    UpToDate(JavaVersion),
+    Available {
+        current: JavaVersion,
+        latest: JavaVersion,
+        release_notes: Option<String>,
+        /// Size of the archive in bytes, 0 if unknown.
+        size: u64,
+    },
+}
+
 impl UpdateInstalled {
-    fn update_jdk(context: &Context, jdk: &VersionKey) -> Result<(), Report<JpreError>> {
-        JDK_MANAGER
-            .download_jdk(&context.config, jdk)
+    fn check_one(
+        context: &Context,
+        jdk: &VersionKey,
+        distribution: Option<&str>,
+    ) -> ESResult<UpdateCheck, JpreError> {
+        let full_version = match JDK_MANAGER.get_full_version(jdk) {
+            Ok(full_version) => full_version,
+            Err(err) => {
+                warn!("Failed to get full version for {}: {}", jdk, err);
+                return Ok(UpdateCheck::Skip);
+            }
+        };
+        let Some(full_version) = full_version else {
+            return Ok(UpdateCheck::Reinstall);
+        };
+
+        let (list_info, _) = FOOJAY_API
+            .get_latest_package_info_using_priority(
+                &context.config,
+                context.platform()?,
+                jdk,
+                distribution,
+            )
             .change_context(JpreError::Unexpected)
+            .attach_printable_lazy(|| format!("Failed to get latest package info for {}", jdk))?;
+        let release_notes = release_notes_url(&list_info);
+        let size = list_info.size;
+        let latest = list_info.java_version;
+        if !is_update_candidate(jdk, &latest) {
+            warn!(
+                "Not updating JDK {}, Foojay's latest version {} is for another major version or \
+                 a pre-release",
+                jdk, latest
+            );
+            return Ok(UpdateCheck::Skip);
+        }
+        // Special builds like CRaC can get a new build without a new version, only `opt` changes.
+        let opt_comparison = if jdk.feature().is_some() {
+            OptComparison::Natural
+        } else {
+            OptComparison::Ignore
+        };
+        let ordering = latest.compare_with(&full_version, opt_comparison);
+        // A pinned JDK goes to its pinned version, even if that's a downgrade.
+        let pinned = context.config.pins.contains_key(jdk);
+        if ordering == std::cmp::Ordering::Greater
+            || (pinned && ordering != std::cmp::Ordering::Equal)
+        {
+            Ok(UpdateCheck::Available {
+                current: full_version,
+                latest,
+                release_notes,
+                size,
+            })
+        } else {
+            Ok(UpdateCheck::UpToDate(full_version))
+        }
+    }
+
+    fn apply_check(
+        context: &Context,
+        jdk: &VersionKey,
+        check_result: UpdateCheck,
+        check: bool,
+        quiet: bool,
+        distribution: Option<&str>,
+    ) -> ESResult<UpdateResult, JpreError> {
+        let started = Instant::now();
+        let changes = matches!(
+            check_result,
+            UpdateCheck::Reinstall | UpdateCheck::Available { .. }
+        );
+        if !quiet || changes {
+            eprint_above_progress(format!(
+                "Checking for updates for {}",
+                jdk.if_supports_color(Stream::Stderr, |s| s.color(jdk_color()))
+            ));
+        }
+        let mut result = UpdateResult {
+            key: jdk.clone(),
+            old_version: None,
+            new_version: None,
+            action: UpdateAction::Skipped,
+            bytes_downloaded: 0,
+            duration_secs: 0.0,
+            error: None,
+        };
+        match check_result {
+            UpdateCheck::Skip => {}
+            UpdateCheck::Reinstall => {
+                warn!("No full version found for {}", jdk);
+                if !check {
+                    warn!("Re-installing JDK {}", jdk);
+                    Self::update_jdk(context, jdk, distribution, &mut result)?;
+                    result.action = UpdateAction::Reinstalled;
+                }
+            }
+            UpdateCheck::UpToDate(current) => {
+                if !quiet {
+                    eprint_above_progress(format!(
+                        "  Already up-to-date: {}",
+                        current.if_supports_color(Stream::Stderr, |s| s.color(jdk_color()))
+                    ));
+                }
+                result.old_version = Some(current);
+                result.action = UpdateAction::UpToDate;
+            }
+            UpdateCheck::Available {
+                current,
+                latest,
+                release_notes,
+                size,
+            } => {
+                eprint_above_progress(format!(
+                    "  New version available: {}{}",
+                    format_version_change(&current, &latest),
+                    if size > 0 {
+                        format!(", {} download", format_bytes(size))
+                    } else {
+                        String::new()
+                    }
+                ));
+                if let Some(release_notes) = release_notes {
+                    eprint_above_progress(format!("  Release notes: {}", release_notes));
+                }
+                result.old_version = Some(current);
+                result.new_version = Some(latest);
+                if check {
+                    result.action = UpdateAction::Available;
+                } else {
+                    Self::update_jdk(context, jdk, distribution, &mut result)?;
+                    result.action = UpdateAction::Updated;
+                }
+            }
+        }
+        result.duration_secs = started.elapsed().as_secs_f64();
+        Ok(result)
+    }
+
+    /// Install the latest package of `jdk`, recording the installed version and download size.
+    fn update_jdk(
+        context: &Context,
+        jdk: &VersionKey,
+        distribution: Option<&str>,
+        result: &mut UpdateResult,
+    ) -> Result<(), Report<JpreError>> {
+        JDK_MANAGER
+            .download_jdk(&context.config, context.platform()?, jdk, distribution)
+            .map_err(install_error)
             .attach_printable("Failed to update JDK")?;
+        match JDK_MANAGER.get_install_metadata(jdk) {
+            Ok(Some(metadata)) => {
+                if !metadata.from_download_cache {
+                    result.bytes_downloaded = metadata.archive_size;
+                }
+                result.new_version = Some(metadata.java_version);
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Could not read install metadata of {}: {:?}", jdk, e),
+        }
         Ok(())
     }
 }
+
+fn install_schedule(frequency: Frequency) -> ESResult<(), JpreError> {
+    let scheduler = Scheduler::detect();
+    scheduler
+        .install(frequency)
+        .change_context(JpreError::Unexpected)
+        .attach_printable_lazy(|| format!("Failed to install {}", scheduler))?;
+    eprintln!(
+        "Installed a {} to update all JDKs {}",
+        scheduler,
+        frequency.if_supports_color(Stream::Stderr, |s| s.green())
+    );
+    Ok(())
+}
+
+fn remove_schedule() -> ESResult<(), JpreError> {
+    let scheduler = Scheduler::detect();
+    let removed = scheduler
+        .remove()
+        .change_context(JpreError::Unexpected)
+        .attach_printable_lazy(|| format!("Failed to remove {}", scheduler))?;
+    if removed {
+        eprintln!("Removed the {} for updates", scheduler);
+    } else {
+        eprintln!("No {} for updates was installed", scheduler);
+    }
+    Ok(())
+}
+
+/// A one-line description of a failed update: the message for the user if there is one, else
+/// what was being done.
+fn error_summary(e: &Report<JpreError>) -> String {
+    e.frames()
+        .find_map(|f| f.downcast_ref::<UserMessage>().map(|m| m.message.clone()))
+        .or_else(|| {
+            e.frames().find_map(|f| {
+                f.downcast_ref::<String>()
+                    .cloned()
+                    .or_else(|| f.downcast_ref::<&str>().map(|s| s.to_string()))
+            })
+        })
+        .unwrap_or_else(|| e.current_context().to_string())
+}
+
+/// Whether `latest`, the latest version Foojay has for `jdk`, can replace it. Foojay sometimes
+/// answers with another major version, or with an early access build for a GA key, and updating to
+/// those would put something else under the key than what it names.
+fn is_update_candidate(jdk: &VersionKey, latest: &JavaVersion) -> bool {
+    latest.major() == jdk.major && (!latest.is_pre_release() || jdk.pre_release != PreRelease::None)
+}
+
+/// `old -> new`, with the segments of `new` that changed highlighted, and which part changed.
+fn format_version_change(old: &JavaVersion, new: &JavaVersion) -> String {
+    let new_colored = diff_segments(old, new)
+        .into_iter()
+        .map(|(segment, changed)| {
+            if changed {
+                segment
+                    .if_supports_color(Stream::Stderr, |s| s.yellow().bold().to_string())
+                    .to_string()
+            } else {
+                segment
+                    .if_supports_color(Stream::Stderr, |s| s.color(jdk_color()).to_string())
+                    .to_string()
+            }
+        })
+        .collect::<String>();
+    let component = new
+        .changed_component(old)
+        .map(|c| format!(" ({} changed)", c))
+        .unwrap_or_default();
+    format!(
+        "{} -> {}{}",
+        old.if_supports_color(Stream::Stderr, |s| s.color(jdk_color())),
+        new_colored,
+        component
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_update_candidate() {
+        let candidate = |jdk: &str, latest: &str| {
+            is_update_candidate(&jdk.parse().unwrap(), &latest.parse().unwrap())
+        };
+        assert!(candidate("21", "21.0.2+13"));
+        assert!(candidate("21-ea", "21-ea+30"));
+        assert!(candidate("8", "1.8.0_402-b06"));
+        assert!(!candidate("21", "22.0.1+8"));
+        assert!(!candidate("21", "21-ea+30"));
+    }
+}
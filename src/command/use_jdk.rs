@@ -1,22 +1,85 @@
+use crate::command::set_distributions::validate_distributions;
 use crate::command::{Context, JpreCommand};
+use crate::config::AutoDefault;
+use crate::context_id::{resolve_context_id, ContextIdStrategy};
 use crate::error::{ESResult, JpreError, UserMessage};
-use crate::java_home_management::set_context_path_to_java_home;
+use crate::foojay::FOOJAY_API;
+use crate::java_home_management::{
+    conflicting_java_home, java_home_conflict_message, set_context_path_to_java_home,
+};
 use crate::java_version::key::VersionKey;
-use crate::tui::jdk_color;
+use crate::java_version::range::JdkRequirement;
+use crate::jdk_manager::{install_error, JDK_MANAGER};
+use crate::jobs;
+use crate::journal::{self, JournalEvent};
+use crate::package_manifest::PackageManifest;
+use crate::project_pin::{ProjectPin, PROJECT_PIN_FILE_NAME};
+use crate::remembered_jdk;
+use crate::temp_context;
+use crate::tui::{confirm, format_bytes, jdk_color};
 use clap::Args;
-use error_stack::Report;
+use error_stack::{Report, ResultExt};
 use owo_colors::{OwoColorize, Stream};
+use std::ffi::OsString;
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::Duration;
+use tracing::{debug, warn};
 
 /// Use a JDK in the current context.
 #[derive(Debug, Args)]
 pub struct UseJdk {
-    /// The JDK to use. Version key or 'default'.
-    jdk: UseTarget,
+    /// The JDK to use. Version key or 'default'. Defaults to the project's pin file, or
+    /// `required_java` in the config.
+    jdk: Option<UseTarget>,
+    /// The distribution to download the JDK from if it's not installed, instead of the configured
+    /// ones.
+    #[clap(long, visible_alias = "dist")]
+    distribution: Option<String>,
+    /// Also make the JDK the default.
+    #[clap(long, conflicts_with = "temp")]
+    global: bool,
+    /// Set a newly installed JDK as the default without asking, if `auto_default` would offer to.
+    #[clap(short, long, visible_alias = "assume-yes")]
+    yes: bool,
+    /// Keep using the JDK in this context after it's restarted, e.g. in a new shell with the same
+    /// `JPRE_CONTEXT_ID` or tmux pane, instead of going back to the default.
+    #[clap(long, conflicts_with = "temp")]
+    remember: bool,
+    /// Forget the JDK remembered for this context, so it goes back to the default. A JDK can still
+    /// be given to use it now.
+    #[clap(long, conflicts_with_all = ["temp", "remember"])]
+    forget: bool,
+    /// Use the JDK in a new temporary context instead of the current one. Prints the Java home, or
+    /// with a command, runs it with `JAVA_HOME` set and removes the context when it exits.
+    #[clap(long)]
+    temp: bool,
+    /// Minutes until the temporary context expires, if its creator is still running by then.
+    #[clap(long, requires = "temp", default_value_t = 60)]
+    ttl: u64,
+    /// The command to run in the temporary context.
+    #[clap(last = true, requires = "temp")]
+    command: Vec<OsString>,
+    /// Don't warn that `JAVA_HOME` is set to a JDK jpre doesn't manage, which then keeps this
+    /// shell on that JDK.
+    #[clap(long, conflicts_with = "temp")]
+    force: bool,
+    /// Show what would be downloaded, and how big it is, without downloading or switching.
+    #[clap(long, conflicts_with_all = ["temp", "global", "remember", "forget"])]
+    dry_run: bool,
+    /// Install the JDK, if it's not installed, from the package listed for it in this manifest
+    /// instead of asking Foojay. See `jpre info --manifest`.
+    #[clap(long, value_name = "PATH", conflicts_with_all = ["distribution", "dry_run"])]
+    from_manifest: Option<PathBuf>,
+    /// Only install the JDK if it's missing. If it's installed, nothing is asked from Foojay, not
+    /// even whether `--distribution` exists, so provisioning scripts run the same offline.
+    #[clap(long, conflicts_with = "dry_run")]
+    if_missing: bool,
 }
 
 #[derive(Debug, Clone)]
-enum UseTarget {
+pub(super) enum UseTarget {
     Default,
     VersionKey(VersionKey),
 }
@@ -36,22 +99,349 @@ impl FromStr for UseTarget {
     }
 }
 
-impl JpreCommand for UseJdk {
-    fn run(self, context: Context) -> ESResult<(), JpreError> {
-        let jdk = match self.jdk {
-            UseTarget::Default => context.config.default_jdk.clone().ok_or_else(|| {
+impl UseTarget {
+    pub(super) fn resolve(&self, context: &Context) -> ESResult<VersionKey, JpreError> {
+        match self {
+            UseTarget::Default => context.default_jdk()?.ok_or_else(|| {
                 Report::new(JpreError::UserError).attach(UserMessage {
                     message: "No default JDK set".to_string(),
                 })
-            })?,
-            UseTarget::VersionKey(jdk) => jdk,
+            }),
+            UseTarget::VersionKey(jdk) => Ok(jdk.clone()),
+        }
+    }
+}
+
+/// The project's requirement and where it's from: the nearest pin file, or else `required_java`
+/// in the config.
+pub(super) fn project_requirement(
+    context: &Context,
+) -> ESResult<Option<(JdkRequirement, String)>, JpreError> {
+    let pin = ProjectPin::find_from_current_dir()
+        .change_context(JpreError::UserError)
+        .attach(UserMessage {
+            message: format!("Could not read the project's {}", PROJECT_PIN_FILE_NAME),
+        })?;
+    Ok(match pin {
+        Some(pin) => Some((pin.requirement, pin.path.display().to_string())),
+        None => context
+            .config
+            .required_java
+            .clone()
+            .map(|requirement| (requirement, "required_java".to_string())),
+    })
+}
+
+/// The JDK to use for `requirement`. A range picks the newest installed JDK in it, or if there's
+/// none, the newest one the distribution has.
+pub(super) fn resolve_requirement(
+    context: &Context,
+    requirement: &JdkRequirement,
+    distribution: Option<&str>,
+) -> ESResult<VersionKey, JpreError> {
+    let range = match requirement {
+        JdkRequirement::Key(jdk) => return Ok(jdk.clone()),
+        JdkRequirement::Range(range) => range,
+    };
+    let installed = JDK_MANAGER
+        .get_installed_jdks()
+        .change_context(JpreError::Unexpected)
+        .attach_printable("Failed to get installed JDKs")?;
+    if let Some(jdk) = range.best(&installed) {
+        return Ok(jdk);
+    }
+    let distribution =
+        distribution.unwrap_or_else(|| context.config.distributions.first().unwrap());
+    let available = FOOJAY_API
+        .list_dist_version_keys(distribution)
+        .change_context(JpreError::Unexpected)
+        .attach_printable_lazy(|| format!("Failed to list versions of {}", distribution))?;
+    range.best(&available).ok_or_else(|| {
+        Report::new(JpreError::UserError).attach(UserMessage {
+            message: format!(
+                "No JDK installed or available from {} matches {}",
+                distribution, range
+            ),
+        })
+    })
+}
+
+/// Install `jdk` from the package `manifest` lists for it, unless it's already installed.
+fn install_from_manifest(
+    context: &Context,
+    jdk: &VersionKey,
+    manifest: &Path,
+) -> ESResult<(), JpreError> {
+    if JDK_MANAGER.is_installed(jdk) {
+        return Ok(());
+    }
+    let manifest = PackageManifest::read(manifest)
+        .change_context(JpreError::UserError)
+        .attach(UserMessage {
+            message: format!("Could not read package manifest {}", manifest.display()),
+        })?;
+    let Some(package) = manifest.jdks.get(jdk) else {
+        return Err(Report::new(JpreError::UserError).attach(UserMessage {
+            message: format!("The package manifest has no package for JDK {}", jdk),
+        }));
+    };
+    JDK_MANAGER
+        .install_from_manifest(&context.config, context.platform()?, jdk, package)
+        .map_err(install_error)
+        .attach_printable_lazy(|| format!("Failed to install JDK {} from manifest", jdk))
+}
+
+impl JpreCommand for UseJdk {
+    fn run(self, mut context: Context) -> ESResult<(), JpreError> {
+        if let Some(distribution) = self.distribution.as_ref().filter(|_| !self.if_missing) {
+            validate_distributions(std::slice::from_ref(distribution))?;
+        }
+        if self.remember && resolve_context_id(&context.config).0 == ContextIdStrategy::ParentPid {
+            return Err(Report::new(JpreError::UserError).attach(UserMessage {
+                message: "This context is identified by its shell's PID, so a new shell would \
+                    never get the remembered JDK. Set JPRE_CONTEXT_ID to name the context"
+                    .to_string(),
+            }));
+        }
+        if self.forget {
+            let forgot = remembered_jdk::forget(&context.config)
+                .change_context(JpreError::Unexpected)
+                .attach_printable("Failed to forget the JDK of this context")?;
+            if forgot {
+                eprintln!("Forgot the JDK remembered for this context");
+            }
+            if self.jdk.is_none() {
+                return Ok(());
+            }
+        }
+        let jdk = match &self.jdk {
+            Some(target) => target.resolve(&context)?,
+            None => {
+                let Some((requirement, source)) = project_requirement(&context)? else {
+                    return Err(Report::new(JpreError::UserError).attach(UserMessage {
+                        message: format!(
+                            "No JDK given, and no {} or required_java in the config",
+                            PROJECT_PIN_FILE_NAME
+                        ),
+                    }));
+                };
+                let jdk =
+                    resolve_requirement(&context, &requirement, self.distribution.as_deref())?;
+                debug!("Resolved {} from {} to JDK {}", requirement, source, jdk);
+                jdk
+            }
+        };
+        if self.if_missing {
+            if JDK_MANAGER.is_installed(&jdk) {
+                debug!("JDK {} is installed, not checking anything online", jdk);
+            } else if let Some(distribution) = &self.distribution {
+                validate_distributions(std::slice::from_ref(distribution))?;
+            }
+        }
+        if let Some(manifest) = &self.from_manifest {
+            install_from_manifest(&context, &jdk, manifest)?;
+        }
+        if self.temp {
+            return self.use_temp(&context, &jdk);
+        }
+        if self.dry_run {
+            return self.dry_run(&context, &jdk);
+        }
+        if let Some(java_home) = conflicting_java_home(&context.config).filter(|_| !self.force) {
+            warn!(
+                "{}",
+                java_home_conflict_message(&context.config, &java_home)
+            );
+        }
+        // Only a JDK that's about to be installed can become the default on its own, so the store
+        // is only listed then.
+        let installed_before = if JDK_MANAGER.is_installed(&jdk) {
+            None
+        } else {
+            Some(
+                JDK_MANAGER
+                    .get_installed_jdks()
+                    .change_context(JpreError::Unexpected)
+                    .attach_printable("Failed to get installed JDKs")?,
+            )
         };
-        set_context_path_to_java_home(&context, &jdk)?;
+        set_context_path_to_java_home(&context, &jdk, self.distribution.as_deref())?;
+        journal::record(JournalEvent::Used { jdk: jdk.clone() });
 
         eprintln!(
             "Using JDK {}",
             jdk.if_supports_color(Stream::Stderr, |s| s.color(jdk_color()))
         );
+        if self.remember {
+            Self::remember(&context, &jdk)?;
+        }
+        let global = self.global
+            || match &installed_before {
+                Some(installed) => self.should_auto_default(&context, &jdk, installed)?,
+                None => false,
+            };
+        if global {
+            context
+                .config
+                .set_default_jdk(&jdk)
+                .change_context(JpreError::Unexpected)
+                .attach_printable("Failed to save config")?;
+            eprintln!(
+                "Default JDK set to '{}'",
+                jdk.if_supports_color(Stream::Stderr, |s| s.color(jdk_color()))
+            );
+        }
         Ok(())
     }
 }
+
+impl UseJdk {
+    /// Whether this uses a temporary context, which may run a command for any amount of time.
+    pub fn is_temp(&self) -> bool {
+        self.temp
+    }
+
+    /// Report the package `use` would download for `jdk`, if it's not installed.
+    fn dry_run(&self, context: &Context, jdk: &VersionKey) -> ESResult<(), JpreError> {
+        let colored_jdk = jdk.if_supports_color(Stream::Stderr, |s| s.color(jdk_color()));
+        if JDK_MANAGER.is_installed(jdk) {
+            eprintln!("JDK {} is installed, nothing to download", colored_jdk);
+            return Ok(());
+        }
+        let (list_info, _) = FOOJAY_API
+            .get_latest_package_info_using_priority(
+                &context.config,
+                context.platform()?,
+                jdk,
+                self.distribution.as_deref(),
+            )
+            .change_context(JpreError::Unexpected)
+            .attach_printable_lazy(|| format!("Failed to get package info for {}", jdk))?;
+        eprintln!(
+            "Would download JDK {} ({}) from {}, {}",
+            colored_jdk,
+            list_info.java_version,
+            list_info.distribution,
+            if list_info.size > 0 {
+                format_bytes(list_info.size)
+            } else {
+                "size unknown".to_string()
+            }
+        );
+        Ok(())
+    }
+
+    fn remember(context: &Context, jdk: &VersionKey) -> ESResult<(), JpreError> {
+        remembered_jdk::remember(&context.config, jdk)
+            .change_context(JpreError::Unexpected)
+            .attach_printable("Failed to remember the JDK of this context")?;
+        eprintln!("Remembering JDK {} for this context", jdk);
+        Ok(())
+    }
+
+    /// Whether the `auto_default` policy makes `jdk`, which was just installed, the default.
+    fn should_auto_default(
+        &self,
+        context: &Context,
+        jdk: &VersionKey,
+        installed_before: &[VersionKey],
+    ) -> ESResult<bool, JpreError> {
+        if context.config.default_auto_latest_lts {
+            return Ok(false);
+        }
+        let default = context.config.default_jdk.as_ref();
+        match context.config.auto_default {
+            AutoDefault::Never => Ok(false),
+            AutoDefault::FirstInstall => {
+                if default.is_some() || !installed_before.is_empty() {
+                    return Ok(false);
+                }
+                Ok(self.yes
+                    || confirm(format!(
+                        "No default JDK is set. Make JDK {} the default?",
+                        jdk.if_supports_color(Stream::Stderr, |s| s.color(jdk_color()))
+                    )))
+            }
+            AutoDefault::AlwaysLatest => Ok(default.is_none_or(|default| jdk > default)),
+        }
+    }
+
+    fn use_temp(self, context: &Context, jdk: &VersionKey) -> ESResult<(), JpreError> {
+        if let Err(e) = temp_context::gc() {
+            warn!("Could not remove expired temporary contexts: {:?}", e);
+        }
+        let jdk_path = JDK_MANAGER
+            .get_jdk_path(
+                &context.config,
+                &context.platform,
+                jdk,
+                self.distribution.as_deref(),
+            )
+            .map_err(install_error)
+            .attach_printable_lazy(|| format!("Failed to get path for JDK {}", jdk))?;
+        let ttl = Duration::from_secs(self.ttl * 60);
+        let Some((program, args)) = self.command.split_first() else {
+            // Nothing to wait for, so the context lives as long as the calling shell.
+            let link = temp_context::create(&jdk_path, ttl, std::os::unix::process::parent_id())
+                .change_context(JpreError::Unexpected)
+                .attach_printable("Failed to create temporary context")?;
+            eprintln!(
+                "Using JDK {} in a temporary context",
+                jdk.if_supports_color(Stream::Stderr, |s| s.color(jdk_color()))
+            );
+            println!("{}", link.display());
+            return Ok(());
+        };
+
+        let link = temp_context::create(&jdk_path, ttl, std::process::id())
+            .change_context(JpreError::Unexpected)
+            .attach_printable("Failed to create temporary context")?;
+        let mut command = std::process::Command::new(program);
+        command.args(args);
+        let code = run_in_context(command, &link, || temp_context::remove(&link))?;
+        // Pass on the exit code, so `jpre use --temp` can stand in for the command in scripts.
+        std::process::exit(code);
+    }
+}
+
+/// Run `command` with `JAVA_HOME` set to the context `link` and its `bin` first on `PATH`, then
+/// `remove` the context. Returns the exit code of the command, or 128 plus the signal that
+/// killed it.
+pub(super) fn run_in_context(
+    mut command: std::process::Command,
+    link: &Path,
+    remove: impl FnOnce(),
+) -> ESResult<i32, JpreError> {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let path = match std::env::join_paths(
+        std::iter::once(link.join("bin")).chain(std::env::split_paths(&path)),
+    ) {
+        Ok(path) => path,
+        Err(e) => {
+            remove();
+            return Err(e)
+                .change_context(JpreError::Unexpected)
+                .attach_printable("Failed to build PATH");
+        }
+    };
+    // Ctrl-C goes to the command too, wait for it so the context is still removed.
+    let interruptible = jobs::interruptible();
+    let status = command.env("JAVA_HOME", link).env("PATH", path).status();
+    drop(interruptible);
+    remove();
+    let status = status.map_err(|e| {
+        Report::new(JpreError::UserError)
+            .attach(UserMessage {
+                message: format!(
+                    "Failed to run {}: {}",
+                    command.get_program().to_string_lossy(),
+                    e
+                ),
+            })
+            .attach_printable(e)
+    })?;
+    Ok(status
+        .code()
+        .or_else(|| status.signal().map(|s| 128 + s))
+        .unwrap_or(1))
+}
@@ -0,0 +1,75 @@
+use crate::command::{Context, JpreCommand};
+use crate::error::{ESResult, JpreError, UserMessage};
+use crate::java_version::key::VersionKey;
+use crate::jdk_manager::JDK_MANAGER;
+use crate::tree_manifest::{self, TreeManifest};
+use crate::tui::jdk_color;
+use clap::Args;
+use error_stack::{Report, ResultExt};
+use owo_colors::{OwoColorize, Stream};
+
+/// Check an installed JDK against the manifest of its files recorded at install time, to find
+/// files that were modified, removed or added since. Needs `tree_manifest = true` in the config
+/// when the JDK was installed.
+#[derive(Debug, Args)]
+pub struct Verify {
+    /// The JDK to check.
+    jdk: VersionKey,
+    /// Re-hash every file, instead of only comparing sizes and links. Slower, but also finds
+    /// corruption and edits that keep the size.
+    #[clap(long)]
+    deep: bool,
+}
+
+impl JpreCommand for Verify {
+    fn run(self, _context: Context) -> ESResult<(), JpreError> {
+        if !JDK_MANAGER.is_installed(&self.jdk) {
+            return Err(Report::new(JpreError::UserError).attach(UserMessage {
+                message: format!("JDK {} is not installed", self.jdk),
+            }));
+        }
+        let jdk_home = JDK_MANAGER.get_jdk_install_path(&self.jdk);
+        let has_manifest = TreeManifest::read(&jdk_home)
+            .change_context(JpreError::Unexpected)
+            .attach_printable_lazy(|| format!("Failed to read manifest of JDK {}", self.jdk))?
+            .is_some();
+        if !has_manifest {
+            return Err(Report::new(JpreError::UserError).attach(UserMessage {
+                message: format!(
+                    "No manifest was recorded for JDK {}. Set 'tree_manifest = true' in the \
+                     config and reinstall it",
+                    self.jdk
+                ),
+            }));
+        }
+        let differences = tree_manifest::verify(&jdk_home, self.deep)
+            .change_context(JpreError::Unexpected)
+            .attach_printable_lazy(|| format!("Failed to verify JDK {}", self.jdk))?;
+        let jdk = self
+            .jdk
+            .if_supports_color(Stream::Stderr, |s| s.color(jdk_color()));
+        if differences.is_empty() {
+            eprintln!(
+                "JDK {} matches its manifest{}",
+                jdk,
+                if self.deep { "" } else { " (sizes only)" }
+            );
+            return Ok(());
+        }
+        for difference in &differences {
+            eprintln!(
+                "  {}",
+                difference.if_supports_color(Stream::Stderr, |s| s.yellow())
+            );
+        }
+        Err(Report::new(JpreError::UserError).attach(UserMessage {
+            message: format!(
+                "JDK {} differs from its manifest in {} file(s), reinstall it with `jpre remove {} && jpre install {}`",
+                self.jdk,
+                differences.len(),
+                self.jdk,
+                self.jdk
+            ),
+        }))
+    }
+}
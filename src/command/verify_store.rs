@@ -0,0 +1,88 @@
+use crate::command::{Context, JpreCommand};
+use crate::error::{ESResult, JpreError, UserMessage};
+use crate::jdk_manager::{jdk_store_path, JDK_MANAGER};
+use crate::store_check::check_store;
+use clap::Args;
+use error_stack::{Report, ResultExt};
+use owo_colors::{OwoColorize, Stream};
+use tracing::error;
+
+/// Check the whole JDK store for broken or leftover entries, and show how to repair them.
+#[derive(Debug, Args)]
+pub struct VerifyStore {
+    /// Carry out the repairs.
+    #[clap(long)]
+    fix: bool,
+}
+
+impl JpreCommand for VerifyStore {
+    fn run(self, _context: Context) -> ESResult<(), JpreError> {
+        let store = jdk_store_path();
+        // Repairs move JDKs around, so nothing may be installing meanwhile.
+        let _lock = if self.fix && store.exists() {
+            JDK_MANAGER
+                .prepare_store_for_writing()
+                .and_then(|()| JDK_MANAGER.lock_store())
+                .map(Some)
+                .change_context(JpreError::Unexpected)?
+        } else {
+            None
+        };
+        let findings = check_store(store)
+            .change_context(JpreError::Unexpected)
+            .attach_printable("Failed to check JDK store")?;
+        if findings.is_empty() {
+            eprintln!("JDK store at {} is consistent", store.display());
+            return Ok(());
+        }
+
+        let mut unrepaired = 0;
+        for finding in &findings {
+            eprintln!(
+                "{}: {}",
+                finding
+                    .path
+                    .display()
+                    .if_supports_color(Stream::Stderr, |s| s.cyan()),
+                finding.problem
+            );
+            let Some(repair) = &finding.repair else {
+                eprintln!("  No automatic repair, check it by hand");
+                unrepaired += 1;
+                continue;
+            };
+            if !self.fix {
+                eprintln!("  Repair: {}", repair);
+                unrepaired += 1;
+                continue;
+            }
+            match finding.apply_repair(store) {
+                Ok(()) => eprintln!(
+                    "  {}",
+                    format!("Repaired: {}", repair)
+                        .if_supports_color(Stream::Stderr, |s| s.green())
+                ),
+                Err(e) => {
+                    error!("  Repair failed: {:?}", e);
+                    unrepaired += 1;
+                }
+            }
+        }
+        if self.fix {
+            JDK_MANAGER.update_inventory();
+        }
+        if unrepaired > 0 {
+            return Err(Report::new(JpreError::UserError).attach(UserMessage {
+                message: if self.fix {
+                    format!("{} problem(s) in the JDK store remain", unrepaired)
+                } else {
+                    format!(
+                        "Found {} problem(s) in the JDK store, run with --fix to repair them",
+                        unrepaired
+                    )
+                },
+            }));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+use crate::config::{JpreConfig, PROJECT_DIRS};
+use crate::error::ESResult;
+use crate::foojay::FOOJAY_API;
+use crate::java_version::key::VersionKey;
+use derive_more::Display;
+use error_stack::{Context, ResultExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::time::{Duration, SystemTime};
+use tracing::debug;
+
+#[derive(Debug, Display)]
+pub struct CompletionIndexError;
+
+impl Context for CompletionIndexError {}
+
+/// The index is refreshed at most this often.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+static COMPLETION_INDEX_PATH: LazyLock<PathBuf> =
+    LazyLock::new(|| PROJECT_DIRS.cache_dir().join("completion-index.json"));
+
+/// The version keys available per distribution, for shell completion without network access.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct CompletionIndex {
+    /// Seconds since the Unix epoch.
+    refreshed_at: u64,
+    distributions: BTreeMap<String, BTreeSet<VersionKey>>,
+}
+
+impl CompletionIndex {
+    pub fn read() -> Option<CompletionIndex> {
+        let contents = std::fs::read_to_string(&*COMPLETION_INDEX_PATH).ok()?;
+        serde_json::from_str(&contents)
+            .inspect_err(|e| debug!("Ignoring invalid completion index: {}", e))
+            .ok()
+    }
+
+    fn write(&self) -> ESResult<(), CompletionIndexError> {
+        let contents = serde_json::to_string(self).change_context(CompletionIndexError)?;
+        let temp = tempfile::NamedTempFile::new_in(PROJECT_DIRS.cache_dir())
+            .change_context(CompletionIndexError)
+            .attach_printable("Could not create temporary file for completion index")?;
+        std::fs::write(temp.path(), contents)
+            .change_context(CompletionIndexError)
+            .attach_printable_lazy(|| format!("Could not write {:?}", temp.path()))?;
+        temp.persist(&*COMPLETION_INDEX_PATH)
+            .change_context(CompletionIndexError)
+            .attach_printable_lazy(|| format!("Could not write {:?}", *COMPLETION_INDEX_PATH))?;
+        Ok(())
+    }
+
+    /// Whether the index is too old or doesn't cover all of `distributions`.
+    fn is_stale(&self, now: u64, distributions: &[String]) -> bool {
+        now.saturating_sub(self.refreshed_at) >= REFRESH_INTERVAL.as_secs()
+            || distributions
+                .iter()
+                .any(|d| !self.distributions.contains_key(d))
+    }
+
+    /// Completion candidates starting with `prefix`: `default`, then the installed keys, then the
+    /// known remote keys.
+    pub fn candidates(&self, installed: &[VersionKey], prefix: &str) -> Vec<String> {
+        let mut seen = BTreeSet::new();
+        let remote = self
+            .distributions
+            .values()
+            .flatten()
+            .collect::<BTreeSet<_>>();
+        std::iter::once("default".to_string())
+            .chain(installed.iter().map(VersionKey::to_string))
+            .chain(remote.into_iter().map(VersionKey::to_string))
+            .filter(|c| c.starts_with(prefix) && seen.insert(c.clone()))
+            .collect()
+    }
+}
+
+/// Refresh the index from Foojay if it's older than a day. Meant to be called by commands that
+/// use the network anyway, failures are only logged.
+pub fn refresh_if_stale(config: &JpreConfig) {
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let mut index = CompletionIndex::read().unwrap_or_default();
+    if !index.is_stale(now, &config.distributions) {
+        return;
+    }
+    debug!("Refreshing completion index");
+    index.distributions.clear();
+    for distribution in &config.distributions {
+        match FOOJAY_API.list_dist_version_keys(distribution) {
+            Ok(keys) => {
+                index
+                    .distributions
+                    .insert(distribution.clone(), keys.into_iter().collect());
+            }
+            Err(e) => {
+                debug!("Not refreshing completion index: {:?}", e);
+                return;
+            }
+        }
+    }
+    index.refreshed_at = now;
+    if let Err(e) = index.write() {
+        debug!("Could not write completion index: {:?}", e);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    fn keys(keys: &[&str]) -> BTreeSet<VersionKey> {
+        keys.iter()
+            .map(|k| VersionKey::from_str(k).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_candidates() {
+        let index = CompletionIndex {
+            refreshed_at: 0,
+            distributions: BTreeMap::from([
+                ("temurin".to_string(), keys(&["11", "17", "21", "23-ea"])),
+                ("zulu".to_string(), keys(&["8", "17"])),
+            ]),
+        };
+        let installed = keys(&["21", "22"]).into_iter().collect::<Vec<_>>();
+        assert_eq!(
+            vec!["default", "21", "22", "8", "11", "17", "23-ea"],
+            index.candidates(&installed, "")
+        );
+        assert_eq!(vec!["21", "22", "23-ea"], index.candidates(&installed, "2"));
+    }
+
+    #[test]
+    fn test_is_stale() {
+        let index = CompletionIndex {
+            refreshed_at: 1000,
+            distributions: BTreeMap::from([("temurin".to_string(), keys(&["21"]))]),
+        };
+        let temurin = ["temurin".to_string()];
+        assert!(!index.is_stale(1000 + 60, &temurin));
+        assert!(index.is_stale(1000 + 24 * 60 * 60, &temurin));
+        assert!(index.is_stale(1000, &["zulu".to_string()]));
+    }
+}
@@ -1,25 +1,123 @@
+use crate::context_id::{default_context_id_strategies, ContextIdStrategy};
 use crate::error::{ESResult, JpreError, UserMessage};
 use crate::java_version::key::VersionKey;
-use crate::java_version::PreRelease;
+use crate::java_version::range::JdkRequirement;
+use crate::java_version::{JavaVersion, PreRelease};
 use directories::ProjectDirs;
 use error_stack::ResultExt;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use std::sync::LazyLock;
-use tracing::{debug, trace};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, OnceLock};
+use toml_edit::{value, Array, DocumentMut, Item, Value};
+use tracing::{debug, trace, warn};
+
+mod validation;
 
 pub static PROJECT_DIRS: LazyLock<ProjectDirs> = LazyLock::new(|| {
     ProjectDirs::from("net", "octyl", "jpre").expect("Could not determine project directories")
 });
 
-static CONFIG_PATH: LazyLock<PathBuf> =
-    LazyLock::new(|| PROJECT_DIRS.preference_dir().join("config.toml"));
+static CONFIG_PATH_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Use the config file at `path` instead of the default one, from `--config` or `JPRE_CONFIG`.
+/// Must be called before the config is first loaded, later calls are ignored.
+pub fn configure_config_path(path: PathBuf) {
+    if let Err(path) = CONFIG_PATH_OVERRIDE.set(path) {
+        warn!("Config path was already configured, ignoring {:?}", path);
+    }
+}
+
+/// Whether an alternate config file is used. Its state is kept apart from the default config's,
+/// and it must already exist.
+pub fn is_alternate_config() -> bool {
+    CONFIG_PATH_OVERRIDE.get().is_some()
+}
+
+static PROFILE: OnceLock<String> = OnceLock::new();
+
+/// Merge the `[profiles.<name>]` table over the config, from `--profile` or `JPRE_PROFILE`. Must
+/// be called before the config is first loaded, later calls are ignored.
+pub fn configure_profile(name: String) {
+    if let Err(name) = PROFILE.set(name) {
+        warn!("Profile was already configured, ignoring {:?}", name);
+    }
+}
+
+pub fn active_profile() -> Option<&'static str> {
+    PROFILE.get().map(String::as_str)
+}
+
+/// A machine-wide config the user's config is layered over, for admins of shared hosts.
+const SYSTEM_CONFIG_PATH: &str = "/etc/jpre/config.toml";
+
+/// The key of the system config listing the keys users can't override.
+const ENFORCED_KEY: &str = "enforced";
+
+static SYSTEM_CONFIG: OnceLock<Option<toml::Table>> = OnceLock::new();
+
+/// Whether a system config is layered under the user's config. Only known once the config is
+/// loaded.
+pub fn has_system_config() -> bool {
+    system_config().is_some()
+}
+
+fn system_config() -> Option<&'static toml::Table> {
+    SYSTEM_CONFIG.get().and_then(Option::as_ref)
+}
+
+fn load_system_config() -> ESResult<(), JpreError> {
+    if SYSTEM_CONFIG.get().is_none() {
+        let system = read_system_config(Path::new(SYSTEM_CONFIG_PATH))?;
+        SYSTEM_CONFIG.get_or_init(|| system);
+    }
+    Ok(())
+}
+
+/// Read the system config, if there is one. It must be a valid config on its own.
+fn read_system_config(path: &Path) -> ESResult<Option<toml::Table>, JpreError> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(e)
+                .change_context(JpreError::Unexpected)
+                .attach_printable_lazy(|| format!("Could not read system config at {:?}", path))
+        }
+    };
+    let table = toml::from_str::<toml::Table>(&contents)
+        .and_then(|mut table| {
+            let enforced = table.remove(ENFORCED_KEY);
+            if enforced.as_ref().is_some_and(|e| {
+                !e.as_array()
+                    .is_some_and(|keys| keys.iter().all(toml::Value::is_str))
+            }) {
+                return Err(serde::de::Error::custom(
+                    "'enforced' must be a list of keys, e.g. [\"distributions\"]",
+                ));
+            }
+            toml::Value::Table(table.clone()).try_into::<JpreConfig>()?;
+            table.extend(enforced.map(|e| (ENFORCED_KEY.to_string(), e)));
+            Ok(table)
+        })
+        .map_err(|e| validation::config_error_report(&e, &contents, path))?;
+    Ok(Some(table))
+}
+
+static CONFIG_PATH: LazyLock<PathBuf> = LazyLock::new(|| match CONFIG_PATH_OVERRIDE.get() {
+    Some(path) => path.clone(),
+    None => PROJECT_DIRS.preference_dir().join("config.toml"),
+});
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct JpreConfig {
     /// The default JDK to use in a new context.
     #[serde(default)]
     pub default_jdk: Option<VersionKey>,
+    /// Use the latest installed LTS JDK as the default instead of `default_jdk`, which is only
+    /// used while no LTS JDK is installed.
+    #[serde(default)]
+    pub default_auto_latest_lts: bool,
     /// The legacy distribution option.
     #[serde(default)]
     distribution: Option<String>,
@@ -34,16 +132,148 @@ pub struct JpreConfig {
     /// mapped.
     #[serde(default)]
     pub forced_os: Option<String>,
+    /// Whether to remove the `com.apple.quarantine` attribute from newly installed JDKs. Only
+    /// applies on macOS.
+    #[serde(default = "default_true")]
+    pub clear_quarantine: bool,
+    /// Whether to run `codesign --verify` on newly installed JDKs. Only applies on macOS.
+    #[serde(default)]
+    pub verify_codesign: bool,
+    /// Whether to keep downloaded JDK archives, so re-installing the same package doesn't need to
+    /// download it again.
+    #[serde(default)]
+    pub keep_downloads: bool,
+    /// How many seconds an API call may take in total.
+    #[serde(default = "default_timeout_secs")]
+    pub metadata_timeout_secs: u64,
+    /// How many seconds a JDK download may go without receiving data.
+    #[serde(default = "default_timeout_secs")]
+    pub download_timeout_secs: u64,
+    /// Whether to connect over IPv4 before trying IPv6, for networks where IPv6 is broken.
+    #[serde(default)]
+    pub prefer_ipv4: bool,
+    /// Whether to run `gu install native-image` for GraalVM builds that don't include it.
+    #[serde(default)]
+    pub install_native_image: bool,
+    /// How to identify the current context, in order of priority. The first strategy that applies
+    /// is used. Options are `env` (`JPRE_CONTEXT_ID`), `tmux-pane`, `term-session`, `ssh` and
+    /// `parent-pid`.
+    #[serde(default = "default_context_id_strategies")]
+    pub context_id_strategies: Vec<ContextIdStrategy>,
+    /// How many ancestor processes the `parent-pid` strategy looks through to find an interactive
+    /// shell.
+    #[serde(default = "default_parent_pid_max_depth")]
+    pub parent_pid_max_depth: u32,
+    /// Whether to clean up old download artifacts automatically on startup.
+    #[serde(default = "default_true")]
+    pub auto_clean_downloads: bool,
+    /// Download artifacts older than this many days are removed by cleanup.
+    #[serde(default = "default_downloads_max_age_days")]
+    pub downloads_max_age_days: Option<u64>,
+    /// The oldest download artifacts are removed by cleanup until the rest fit in this many MiB.
+    #[serde(default)]
+    pub downloads_max_size_mb: Option<u64>,
+    /// How many versions of a JDK replaced by updates to keep in the store. Older ones, and the
+    /// cached archives of any that aren't kept, are removed after each update.
+    #[serde(default)]
+    pub keep_previous_versions: usize,
+    /// Install JDKs into the system-wide store shared by all users, instead of the user's cache
+    /// directory. Same as always passing `--system`.
+    #[serde(default)]
+    pub system_store: bool,
+    /// Whether `use` sets the JDK it installs as the default.
+    #[serde(default)]
+    pub auto_default: AutoDefault,
+    /// Whether to record installs and uses in a local journal, summarized by `jpre stats`.
+    #[serde(default)]
+    pub usage_journal: bool,
+    /// Whether slow installs and updates show a desktop notification when they finish.
+    #[serde(default)]
+    pub notifications: bool,
+    /// Whether to mention it after commands when the active JDK has an update. Checked at most
+    /// once a day, in the background.
+    #[serde(default)]
+    pub update_notices: bool,
+    /// Whether to also verify downloads against the package's checksum file, when Foojay lists
+    /// one besides its own checksum.
+    #[serde(default)]
+    pub verify_all_checksums: bool,
+    /// Whether to record the SHA-256 of every file of newly installed JDKs, so `jpre verify --deep`
+    /// can tell when one was modified or corrupted.
+    #[serde(default)]
+    pub tree_manifest: bool,
+    /// The JDK `use` picks when no version is given and there's no pin file, a version key or a
+    /// range like `>=17, <22`. Mostly useful in a project's alternate config.
+    #[serde(default)]
+    pub required_java: Option<JdkRequirement>,
+    /// Distributions whose license was accepted with `--accept-license`. Packages Foojay doesn't
+    /// mark as free to use in production are only downloaded from these.
+    #[serde(default)]
+    pub accepted_licenses: Vec<String>,
+    /// If set, JDKs are only installed from these distributions. `/etc/jpre/policy.toml` can set
+    /// this and `blocked_distributions` too, and the config can't allow what it doesn't.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_distributions: Option<Vec<String>>,
+    /// Distributions JDKs are never installed from.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocked_distributions: Vec<String>,
+    /// Exact versions to install for version keys, with their expected checksums. Installs fail
+    /// if Foojay serves anything else.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub pins: BTreeMap<VersionKey, JdkPin>,
+    /// Named sets of options merged over the rest of the config when selected with `--profile` or
+    /// `JPRE_PROFILE`. Tables like `pins` are merged key by key, other values are replaced.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, toml::Table>,
+}
+
+/// When `use` makes a JDK it installs the default.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AutoDefault {
+    /// Offer to make the first installed JDK the default, if no default is set.
+    #[default]
+    FirstInstall,
+    Never,
+    /// Make every newly installed JDK the default if it's newer than the current default.
+    AlwaysLatest,
+}
+
+/// A version key pinned to an exact build.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct JdkPin {
+    pub version: JavaVersion,
+    /// The expected SHA-256 of the archive, in hex.
+    pub sha256: String,
+    /// The distribution to install from, instead of the configured ones.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub distribution: Option<String>,
+}
+
+impl JdkPin {
+    fn is_valid_sha256(&self) -> bool {
+        self.sha256.len() == 64 && self.sha256.chars().all(|c| c.is_ascii_hexdigit())
+    }
+}
+
+pub fn config_path() -> &'static Path {
+    &CONFIG_PATH
 }
 
 impl JpreConfig {
     pub(super) fn load() -> ESResult<JpreConfig, JpreError> {
+        load_system_config()?;
+        if is_alternate_config() && !CONFIG_PATH.is_file() {
+            return Err(JpreError::UserError).attach(UserMessage {
+                message: format!("Config file {} does not exist", CONFIG_PATH.display()),
+            });
+        }
         std::fs::create_dir_all(CONFIG_PATH.parent().unwrap())
             .change_context(JpreError::Unexpected)
             .attach_printable_lazy(|| {
                 format!(
                     "Could not create config directory at {:?}",
-                    PROJECT_DIRS.config_dir()
+                    CONFIG_PATH.parent().unwrap()
                 )
             })?;
         std::fs::OpenOptions::new()
@@ -59,36 +289,32 @@ impl JpreConfig {
             .attach_printable_lazy(|| {
                 format!("Could not read config file at {:?}", *CONFIG_PATH)
             })?;
-        let config = toml::from_str::<JpreConfig>(&contents);
+        let config = Self::parse(&contents);
         match config {
-            Ok(mut config) => {
-                if let Some(distribution) = config.distribution {
-                    config.distributions = vec![distribution];
-                    config.distribution = None;
-                }
+            Ok(config) => {
                 if config.distributions.is_empty() {
                     return Err(JpreError::UserError).attach(UserMessage {
                         message: "No distributions set in config".to_string(),
                     });
                 }
+                if let Some((jdk, _)) = config.pins.iter().find(|(_, pin)| !pin.is_valid_sha256()) {
+                    return Err(JpreError::UserError).attach(UserMessage {
+                        message: format!(
+                            "Pin for JDK {} has an invalid sha256, expected 64 hex digits",
+                            jdk
+                        ),
+                    });
+                }
                 Ok(config)
             }
             Err(e) => {
                 // Try to load the old config format.
                 let Ok(old_config) = toml::from_str::<toml::Table>(&contents) else {
-                    return Err(e)
-                        .change_context(JpreError::Unexpected)
-                        .attach_printable_lazy(|| {
-                            format!("Could not parse config file at {:?}", *CONFIG_PATH)
-                        });
+                    return Err(validation::config_error_report(&e, &contents, &CONFIG_PATH));
                 };
                 if let Some(toml::Value::Integer(major)) = old_config.get("default_jdk") {
                     if old_config.keys().len() != 1 {
-                        return Err(e)
-                            .change_context(JpreError::Unexpected)
-                            .attach_printable_lazy(|| {
-                                format!("Could not parse config file at {:?}", *CONFIG_PATH)
-                            });
+                        return Err(validation::config_error_report(&e, &contents, &CONFIG_PATH));
                     }
                     // jpre 0.2 config format
                     let new_config = JpreConfig {
@@ -96,38 +322,484 @@ impl JpreConfig {
                             major: *major as u32,
                             pre_release: PreRelease::None,
                         }),
+                        default_auto_latest_lts: false,
                         distribution: None,
                         distributions: default_distribution(),
                         forced_architecture: None,
                         forced_os: None,
+                        clear_quarantine: true,
+                        verify_codesign: false,
+                        keep_downloads: false,
+                        metadata_timeout_secs: default_timeout_secs(),
+                        download_timeout_secs: default_timeout_secs(),
+                        prefer_ipv4: false,
+                        install_native_image: false,
+                        context_id_strategies: default_context_id_strategies(),
+                        parent_pid_max_depth: default_parent_pid_max_depth(),
+                        auto_clean_downloads: true,
+                        downloads_max_age_days: default_downloads_max_age_days(),
+                        downloads_max_size_mb: None,
+                        keep_previous_versions: 0,
+                        system_store: false,
+                        auto_default: AutoDefault::default(),
+                        usage_journal: false,
+                        notifications: false,
+                        update_notices: false,
+                        verify_all_checksums: false,
+                        tree_manifest: false,
+                        required_java: None,
+                        accepted_licenses: Vec::new(),
+                        allowed_distributions: None,
+                        blocked_distributions: Vec::new(),
+                        pins: BTreeMap::new(),
+                        profiles: BTreeMap::new(),
                     };
                     new_config.save()?;
                     return Ok(new_config);
                 }
-                Err(e)
-                    .change_context(JpreError::Unexpected)
-                    .attach_printable_lazy(|| {
-                        format!("Could not parse config file at {:?}", *CONFIG_PATH)
+                Err(validation::config_error_report(&e, &contents, &CONFIG_PATH))
+            }
+        }
+    }
+
+    /// Load the config without creating or migrating anything, for commands that promise not to
+    /// write. A missing default config is an empty one.
+    pub fn load_read_only() -> ESResult<JpreConfig, JpreError> {
+        load_system_config()?;
+        let contents = match std::fs::read_to_string(&*CONFIG_PATH) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && !is_alternate_config() => {
+                String::new()
+            }
+            Err(e) => {
+                return Err(e)
+                    .change_context(JpreError::UserError)
+                    .attach(UserMessage {
+                        message: format!("Could not read config file {}", CONFIG_PATH.display()),
                     })
             }
+        };
+        Self::parse(&contents)
+            .map_err(|e| validation::config_error_report(&e, &contents, &CONFIG_PATH))
+    }
+
+    fn parse(contents: &str) -> Result<JpreConfig, toml::de::Error> {
+        // Parsed on its own first, so errors point into the file.
+        let mut config = toml::from_str::<JpreConfig>(contents)?;
+        if active_profile().is_some() || system_config().is_some() {
+            let mut table = match active_profile() {
+                Some(profile) => Self::profile_table(contents, profile)?,
+                None => toml::from_str::<toml::Table>(contents)?,
+            };
+            if let Some(system) = system_config() {
+                table = Self::over_system(system, table);
+            }
+            config = toml::Value::Table(table).try_into()?;
+        }
+        if let Some(distribution) = config.distribution.take() {
+            config.distributions = vec![distribution];
+        }
+        Ok(config)
+    }
+
+    /// `user`, the options set in the user's config, layered over the system config. Keys the
+    /// system config lists in `enforced` can't be overridden.
+    fn over_system(system: &toml::Table, mut user: toml::Table) -> toml::Table {
+        let mut layered = system.clone();
+        let enforced = layered
+            .remove(ENFORCED_KEY)
+            .and_then(|e| e.as_array().cloned())
+            .unwrap_or_default();
+        // The legacy `distribution` of either one replaces its `distributions`.
+        for table in [&mut layered, &mut user] {
+            if let Some(distribution) = table.remove("distribution") {
+                table.insert(
+                    "distributions".to_string(),
+                    toml::Value::Array(vec![distribution]),
+                );
+            }
+        }
+        for key in enforced.iter().filter_map(toml::Value::as_str) {
+            if let Some(value) = user.remove(key) {
+                if system.get(key) != Some(&value) {
+                    warn!(
+                        "'{}' is enforced by {}, ignoring it in your config",
+                        key, SYSTEM_CONFIG_PATH
+                    );
+                }
+            }
+        }
+        merge_table(&mut layered, user);
+        layered
+    }
+
+    /// The config in `contents` with the options of `profile` merged over it.
+    #[cfg(test)]
+    fn with_profile(contents: &str, profile: &str) -> Result<JpreConfig, toml::de::Error> {
+        toml::Value::Table(Self::profile_table(contents, profile)?).try_into()
+    }
+
+    /// The options in `contents` with those of `profile` merged over them.
+    fn profile_table(contents: &str, profile: &str) -> Result<toml::Table, toml::de::Error> {
+        let mut table = toml::from_str::<toml::Table>(contents)?;
+        let Some(toml::Value::Table(overlay)) = table
+            .get("profiles")
+            .and_then(|profiles| profiles.get(profile))
+            .cloned()
+        else {
+            return Err(serde::de::Error::custom(format!(
+                "no profile named '{}', add a [profiles.{}] table",
+                profile, profile
+            )));
+        };
+        if overlay.contains_key("profiles") {
+            return Err(serde::de::Error::custom(format!(
+                "profile '{}' can't contain other profiles",
+                profile
+            )));
+        }
+        // Whichever of `distribution` and `distributions` the profile sets must win over the other
+        // in the base config.
+        if overlay.contains_key("distribution") {
+            table.remove("distributions");
+        }
+        if overlay.contains_key("distributions") {
+            table.remove("distribution");
         }
+        merge_table(&mut table, overlay);
+        Ok(table)
     }
 
+    /// Write the config, keeping the comments and formatting of the existing file. Only values
+    /// that changed are replaced, and options at their default are only written if the file
+    /// already has them, so a system config can still set them.
     pub fn save(&self) -> ESResult<(), JpreError> {
         let contents = toml::to_string(self)
             .change_context(JpreError::Unexpected)
             .attach_printable("Could not serialize config to TOML")?;
+        let mut new = contents
+            .parse::<DocumentMut>()
+            .change_context(JpreError::Unexpected)
+            .attach_printable_lazy(|| {
+                format!("Serialized config is not valid TOML:\n{}", contents)
+            })?;
+        let defaults = toml::from_str::<JpreConfig>("")
+            .and_then(|defaults| toml::Table::try_from(defaults).map_err(serde::de::Error::custom))
+            .change_context(JpreError::Unexpected)
+            .attach_printable("Could not serialize default config")?;
+        Self::edit_document(|doc| {
+            remove_unset_defaults(doc, &mut new, &defaults);
+            merge_document(doc, &new)
+        })?;
+        Ok(())
+    }
+
+    /// Edit the config file in place, keeping comments and formatting. This config is updated to
+    /// the result. Fails without writing anything if the edited document isn't a valid config.
+    pub fn edit_config(&mut self, edit: impl FnOnce(&mut DocumentMut)) -> ESResult<(), JpreError> {
+        *self = Self::edit_document(edit)?;
+        Ok(())
+    }
+
+    /// Set a top-level key to `value`.
+    pub fn set_key(&mut self, key: &str, value: impl Into<Value>) -> ESResult<(), JpreError> {
+        let value = value.into();
+        self.edit_config(|doc| set_item_keeping_decor(doc, key, Item::Value(value)))
+    }
+
+    /// Set a fixed default JDK, which also stops following the latest LTS.
+    pub fn set_default_jdk(&mut self, jdk: &VersionKey) -> ESResult<(), JpreError> {
+        self.edit_config(|doc| {
+            set_item_keeping_decor(doc, "default_jdk", value(jdk.to_string()));
+            set_item_keeping_decor(doc, "default_auto_latest_lts", value(false));
+        })
+    }
+
+    /// Add a distribution to the end of the priority list, if it's not in the list yet.
+    pub fn add_distribution(&mut self, distribution: &str) -> ESResult<(), JpreError> {
+        self.edit_config(|doc| {
+            let distributions = distributions_array(doc);
+            if !distributions
+                .iter()
+                .any(|d| d.as_str() == Some(distribution))
+            {
+                distributions.push(distribution);
+            }
+        })
+    }
+
+    /// Record that the license of a distribution was accepted.
+    pub fn accept_license(&mut self, distribution: &str) -> ESResult<(), JpreError> {
+        self.edit_config(|doc| {
+            if !doc.get("accepted_licenses").is_some_and(Item::is_array) {
+                doc["accepted_licenses"] = value(Array::new());
+            }
+            let accepted = doc["accepted_licenses"].as_array_mut().unwrap();
+            if !accepted.iter().any(|d| d.as_str() == Some(distribution)) {
+                accepted.push(distribution);
+            }
+        })
+    }
+
+    pub fn remove_distribution(&mut self, distribution: &str) -> ESResult<(), JpreError> {
+        self.edit_config(|doc| {
+            distributions_array(doc).retain(|d| d.as_str() != Some(distribution));
+        })
+    }
+
+    fn edit_document(edit: impl FnOnce(&mut DocumentMut)) -> ESResult<JpreConfig, JpreError> {
+        let contents = match std::fs::read_to_string(&*CONFIG_PATH) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                return Err(e)
+                    .change_context(JpreError::Unexpected)
+                    .attach_printable_lazy(|| {
+                        format!("Could not read config file at {:?}", *CONFIG_PATH)
+                    })
+            }
+        };
+        let mut doc = contents
+            .parse::<DocumentMut>()
+            .change_context(JpreError::Unexpected)
+            .attach_printable_lazy(|| {
+                format!("Could not parse config file at {:?}", *CONFIG_PATH)
+            })?;
+        edit(&mut doc);
+        let edited = doc.to_string();
+        if let Some(profile) = active_profile() {
+            warn_profile_overrides(profile, &contents, &edited);
+        }
+        let config = Self::parse(&edited)
+            .change_context(JpreError::Unexpected)
+            .attach_printable("Edited config is invalid")
+            .attach_printable_lazy(|| format!("Edited config:\n{}", edited))?;
+        if edited == contents {
+            return Ok(config);
+        }
         debug!("Writing config to {:?}", *CONFIG_PATH);
-        trace!("Config: {}", contents);
-        std::fs::write(&*CONFIG_PATH, contents)
+        trace!("Config: {}", edited);
+        std::fs::write(&*CONFIG_PATH, edited)
             .change_context(JpreError::Unexpected)
             .attach_printable_lazy(|| {
                 format!("Could not write config file to {:?}", *CONFIG_PATH)
             })?;
-        Ok(())
+        Ok(config)
+    }
+}
+
+/// Merge `overlay` into `base`, recursing into tables both have and replacing everything else.
+fn merge_table(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => {
+                merge_table(base, overlay)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Edits go to the base config, warn when the active profile hides what changed.
+fn warn_profile_overrides(profile: &str, old: &str, new: &str) {
+    let old = toml::from_str::<toml::Table>(old).unwrap_or_default();
+    let new = toml::from_str::<toml::Table>(new).unwrap_or_default();
+    let Some(toml::Value::Table(overlay)) = new.get("profiles").and_then(|p| p.get(profile)) else {
+        return;
+    };
+    for key in overlay.keys() {
+        if key != "profiles" && old.get(key) != new.get(key) {
+            warn!(
+                "Changed '{}' in the base config, but profile '{}' overrides it",
+                key, profile
+            );
+        }
+    }
+}
+
+/// Drop the options of `new` that are at their default in `defaults` and not in `doc` yet.
+fn remove_unset_defaults(doc: &DocumentMut, new: &mut DocumentMut, defaults: &toml::Table) {
+    let new_values = toml::from_str::<toml::Table>(&new.to_string()).unwrap_or_default();
+    let unset = new_values
+        .iter()
+        .filter(|(key, value)| !doc.contains_key(key) && defaults.get(*key) == Some(*value))
+        .map(|(key, _)| key.clone())
+        .collect::<Vec<_>>();
+    for key in unset {
+        new.remove(&key);
+    }
+}
+
+/// Make `doc` have the same values as `new`, only touching the keys that changed.
+fn merge_document(doc: &mut DocumentMut, new: &DocumentMut) {
+    let old_values = toml::from_str::<toml::Table>(&doc.to_string()).unwrap_or_default();
+    let new_values = toml::from_str::<toml::Table>(&new.to_string()).unwrap_or_default();
+    // Keys we don't write anymore, e.g. the legacy `distribution` or unset options.
+    let removed = doc
+        .iter()
+        .map(|(key, _)| key.to_string())
+        .filter(|key| !new_values.contains_key(key))
+        .collect::<Vec<_>>();
+    for key in removed {
+        doc.remove(&key);
+    }
+    for (key, item) in new.iter() {
+        if old_values.get(key) != new_values.get(key) {
+            set_item_keeping_decor(doc, key, item.clone());
+        }
     }
 }
 
+/// Replace `doc[key]`, keeping the comments around the old value.
+fn set_item_keeping_decor(doc: &mut DocumentMut, key: &str, mut item: Item) {
+    if let (Some(old), Some(new)) = (doc.get(key).and_then(Item::as_value), item.as_value_mut()) {
+        *new.decor_mut() = old.decor().clone();
+    }
+    doc[key] = item;
+}
+
+/// The `distributions` array, created if missing or not an array.
+fn distributions_array(doc: &mut DocumentMut) -> &mut Array {
+    if !doc.get("distributions").is_some_and(Item::is_array) {
+        doc["distributions"] = value(Array::from_iter(default_distribution()));
+    }
+    doc["distributions"].as_array_mut().unwrap()
+}
+
 fn default_distribution() -> Vec<String> {
     vec!["temurin".to_string()]
 }
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_timeout_secs() -> u64 {
+    30
+}
+
+fn default_parent_pid_max_depth() -> u32 {
+    8
+}
+
+fn default_downloads_max_age_days() -> Option<u64> {
+    Some(30)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_merge_document_keeps_comments() {
+        let mut doc = "# My JDK\ndefault_jdk = \"17\" # LTS\n\n# Vendors\ndistributions = [\"zulu\", \"temurin\"]\ndistribution = \"zulu\"\n"
+            .parse::<DocumentMut>()
+            .unwrap();
+        let new =
+            "default_jdk = \"21\"\ndistributions = [\"zulu\",\"temurin\"]\nkeep_downloads = true\n"
+                .parse::<DocumentMut>()
+                .unwrap();
+        merge_document(&mut doc, &new);
+        assert_eq!(
+            "# My JDK\ndefault_jdk = \"21\" # LTS\n\n# Vendors\ndistributions = [\"zulu\", \"temurin\"]\nkeep_downloads = true\n",
+            doc.to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_pins() {
+        let config = JpreConfig::parse(
+            "[pins]\n\"17\" = { version = \"17.0.10+7\", sha256 = \"a1b2\" }\n\"21-crac\" = { version = \"21.0.4+7\", sha256 = \"c3d4\", distribution = \"zulu\" }\n",
+        )
+        .unwrap();
+        let pin = &config.pins[&VersionKey::from_str("17").unwrap()];
+        assert_eq!("17.0.10+7", pin.version.to_string());
+        assert_eq!(None, pin.distribution);
+        assert!(!pin.is_valid_sha256());
+        let pin = &config.pins[&VersionKey::from_str("21-crac").unwrap()];
+        assert_eq!(Some("zulu"), pin.distribution.as_deref());
+    }
+
+    #[test]
+    fn test_with_profile() {
+        let contents = "default_jdk = \"17\"\ndistributions = [\"temurin\"]\n\n\
+            [pins]\n\"17\" = { version = \"17.0.10+7\", sha256 = \"a1b2\" }\n\n\
+            [profiles.work]\ndistributions = [\"zulu\"]\n\n\
+            [profiles.work.pins]\n\"21\" = { version = \"21.0.4+7\", sha256 = \"c3d4\" }\n\n\
+            [profiles.oss]\ndistribution = \"corretto\"\n";
+        let work = JpreConfig::with_profile(contents, "work").unwrap();
+        assert_eq!(vec!["zulu".to_string()], work.distributions);
+        assert_eq!(Some(VersionKey::from_str("17").unwrap()), work.default_jdk);
+        // Tables are merged, so the base pins are kept.
+        assert_eq!(2, work.pins.len());
+
+        let oss = JpreConfig::with_profile(contents, "oss").unwrap();
+        assert_eq!(Some("corretto"), oss.distribution.as_deref());
+
+        assert!(JpreConfig::with_profile(contents, "missing").is_err());
+    }
+
+    #[test]
+    fn test_over_system() {
+        let system = toml::from_str::<toml::Table>(
+            "distributions = [\"corretto\"]\nkeep_downloads = true\nmetadata_timeout_secs = 60\n\
+             enforced = [\"metadata_timeout_secs\"]\n",
+        )
+        .unwrap();
+        let layered = |user: &str| -> JpreConfig {
+            let user = toml::from_str::<toml::Table>(user).unwrap();
+            toml::Value::Table(JpreConfig::over_system(&system, user))
+                .try_into()
+                .unwrap()
+        };
+        let config =
+            layered("default_jdk = \"21\"\nkeep_downloads = false\nmetadata_timeout_secs = 10\n");
+        // Set explicitly, jpre's default overrides the system config too.
+        assert!(!config.keep_downloads);
+        assert_eq!(60, config.metadata_timeout_secs);
+        assert_eq!(vec!["corretto".to_string()], config.distributions);
+        assert_eq!(
+            Some(VersionKey::from_str("21").unwrap()),
+            config.default_jdk
+        );
+
+        let config = layered("distribution = \"zulu\"\n");
+        assert_eq!(vec!["zulu".to_string()], config.distributions);
+        assert!(config.keep_downloads);
+    }
+
+    #[test]
+    fn test_save_skips_unset_defaults() {
+        let defaults = toml::Table::try_from(toml::from_str::<JpreConfig>("").unwrap()).unwrap();
+        let doc = "keep_downloads = false\n".parse::<DocumentMut>().unwrap();
+        let mut new = "keep_downloads = false\nclear_quarantine = true\ndefault_jdk = \"21\"\n"
+            .parse::<DocumentMut>()
+            .unwrap();
+        remove_unset_defaults(&doc, &mut new, &defaults);
+        assert_eq!(
+            "keep_downloads = false\ndefault_jdk = \"21\"\n",
+            new.to_string()
+        );
+    }
+
+    #[test]
+    fn test_read_system_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        assert!(read_system_config(&path).unwrap().is_none());
+        std::fs::write(
+            &path,
+            "keep_downloads = true\nenforced = [\"keep_downloads\"]\n",
+        )
+        .unwrap();
+        let system = read_system_config(&path).unwrap().unwrap();
+        assert!(system.contains_key(ENFORCED_KEY));
+        std::fs::write(&path, "enforced = \"keep_downloads\"\n").unwrap();
+        assert!(read_system_config(&path).is_err());
+        std::fs::write(&path, "keep_downloads = 3\n").unwrap();
+        assert!(read_system_config(&path).is_err());
+    }
+}
@@ -0,0 +1,195 @@
+use crate::error::{JpreError, UserMessage};
+use error_stack::Report;
+use std::path::Path;
+
+/// Keys we know the type of, with a description of what's expected.
+const KNOWN_KEYS: &[(&str, &str)] = &[
+    ("default_jdk", "a version key, e.g. \"21\" or \"23-ea\""),
+    ("default_auto_latest_lts", "true or false"),
+    (
+        "distributions",
+        "a list of distribution names, e.g. [\"temurin\"]",
+    ),
+    (
+        "forced_architecture",
+        "a Foojay architecture name, e.g. \"aarch64\"",
+    ),
+    (
+        "forced_os",
+        "a Foojay operating system name, e.g. \"linux\"",
+    ),
+    ("clear_quarantine", "true or false"),
+    ("verify_codesign", "true or false"),
+    ("keep_downloads", "true or false"),
+    ("metadata_timeout_secs", "a number of seconds"),
+    ("download_timeout_secs", "a number of seconds"),
+    ("prefer_ipv4", "true or false"),
+    ("install_native_image", "true or false"),
+    (
+        "context_id_strategies",
+        "a list of strategies, e.g. [\"env\", \"tmux-pane\", \"parent-pid\"]",
+    ),
+    ("parent_pid_max_depth", "a number of processes"),
+    ("auto_clean_downloads", "true or false"),
+    ("downloads_max_age_days", "a number of days"),
+    ("downloads_max_size_mb", "a number of MiB"),
+    ("keep_previous_versions", "a number of versions"),
+    ("system_store", "true or false"),
+    (
+        "auto_default",
+        "\"first-install\", \"never\" or \"always-latest\"",
+    ),
+    ("usage_journal", "true or false"),
+    ("notifications", "true or false"),
+    ("update_notices", "true or false"),
+    ("verify_all_checksums", "true or false"),
+    ("tree_manifest", "true or false"),
+    (
+        "required_java",
+        "a version key or range, e.g. \"21\" or \">=17, <22\"",
+    ),
+    (
+        "accepted_licenses",
+        "a list of distribution names, e.g. [\"oracle\"]",
+    ),
+    (
+        "allowed_distributions",
+        "a list of distribution names, e.g. [\"temurin\", \"corretto\"]",
+    ),
+    (
+        "blocked_distributions",
+        "a list of distribution names, e.g. [\"oracle\"]",
+    ),
+    (
+        "pins",
+        "a table of version keys to pins, e.g. \"17\" = { version = \"17.0.10+7\", sha256 = \"...\" }",
+    ),
+    (
+        "profiles",
+        "a table of profile names to options, e.g. [profiles.work] distributions = [\"zulu\"]",
+    ),
+];
+
+/// Turn a config parsing error into a report that points at the offending part of the file.
+pub(super) fn config_error_report(
+    error: &toml::de::Error,
+    contents: &str,
+    path: &Path,
+) -> Report<JpreError> {
+    let mut report = Report::new(JpreError::UserError);
+    for message in describe_error(error, contents, path) {
+        report = report.attach(UserMessage { message });
+    }
+    report
+}
+
+fn describe_error(error: &toml::de::Error, contents: &str, path: &Path) -> Vec<String> {
+    let Some(span) = error.span() else {
+        return vec![
+            format!("Invalid config file {}:", path.display()),
+            error.message().to_string(),
+        ];
+    };
+    let start = span.start.min(contents.len());
+    let line_start = contents[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = contents[start..]
+        .find('\n')
+        .map_or(contents.len(), |i| start + i);
+    let line = contents[line_start..line_end].trim_end_matches('\r');
+    let line_number = contents[..line_start].matches('\n').count() + 1;
+    let column = contents[line_start..start].chars().count() + 1;
+    let width = contents[start..span.end.clamp(start, line_end)]
+        .chars()
+        .count()
+        .max(1);
+    let gutter = line_number.to_string().len();
+
+    let mut messages = vec![
+        format!(
+            "Invalid config file {}:{}:{}:",
+            path.display(),
+            line_number,
+            column
+        ),
+        format!("{:>gutter$} | {}", line_number, line),
+        format!(
+            "{:>gutter$} | {}{}",
+            "",
+            " ".repeat(column - 1),
+            "^".repeat(width)
+        ),
+        error.message().trim().to_string(),
+    ];
+    let key = line
+        .split_once('=')
+        .map(|(key, _)| key.trim().trim_matches('"'))
+        .filter(|key| !key.is_empty());
+    if let Some((key, expected)) =
+        key.and_then(|k| KNOWN_KEYS.iter().find(|(known, _)| *known == k))
+    {
+        messages.push(format!("'{}' should be {}", key, expected));
+        if let Some(hint) = hint(key, line) {
+            messages.push(format!("Hint: {}", hint));
+        }
+    }
+    messages
+}
+
+/// Suggestions for mistakes that are easy to make.
+fn hint(key: &str, line: &str) -> Option<String> {
+    let value = line.split_once('=')?.1.trim();
+    match key {
+        "distributions" | "context_id_strategies" if value.starts_with('"') => Some(format!(
+            "use a list, even for one entry: {} = [{}]",
+            key, value
+        )),
+        "default_jdk" if value.contains('.') => Some(
+            "use only the major version (plus e.g. '-ea'), jpre picks the latest update"
+                .to_string(),
+        ),
+        _ if value == "\"true\"" || value == "\"false\"" => Some(format!(
+            "booleans aren't quoted: {} = {}",
+            key,
+            value.trim_matches('"')
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::JpreConfig;
+
+    fn describe(contents: &str) -> Vec<String> {
+        let error = toml::from_str::<JpreConfig>(contents).unwrap_err();
+        describe_error(&error, contents, Path::new("config.toml"))
+    }
+
+    #[test]
+    fn test_string_instead_of_list() {
+        let messages = describe("keep_downloads = true\ndistributions = \"zulu\"\n");
+        assert_eq!("Invalid config file config.toml:2:17:", messages[0]);
+        assert_eq!("2 | distributions = \"zulu\"", messages[1]);
+        assert_eq!("  |                 ^^^^^^", messages[2]);
+        assert!(messages
+            .iter()
+            .any(|m| m == "Hint: use a list, even for one entry: distributions = [\"zulu\"]"));
+    }
+
+    #[test]
+    fn test_syntax_error() {
+        let messages = describe("distributions = [\"zulu\" \"temurin\"]\nkeep_downloads = true\n");
+        assert!(messages[0].starts_with("Invalid config file config.toml:1:"));
+        assert_eq!("1 | distributions = [\"zulu\" \"temurin\"]", messages[1]);
+    }
+
+    #[test]
+    fn test_quoted_boolean() {
+        let messages = describe("keep_downloads = \"true\"\n");
+        assert_eq!(
+            Some("Hint: booleans aren't quoted: keep_downloads = true"),
+            messages.last().map(String::as_str)
+        );
+    }
+}
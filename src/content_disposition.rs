@@ -0,0 +1,244 @@
+use crate::error::ESResult;
+use derive_more::Display;
+use error_stack::{Context, Report};
+
+#[derive(Debug, Display)]
+pub enum ContentDispositionParseError {
+    #[display("Malformed Content-Disposition header")]
+    Malformed,
+    #[display("Unsupported Content-Disposition parameter")]
+    Unsupported,
+}
+
+impl Context for ContentDispositionParseError {}
+
+/// A parsed `Content-Disposition` header. Only the parts we care about are kept.
+#[derive(Debug, Eq, PartialEq)]
+pub struct ContentDisposition {
+    pub disposition_type: String,
+    pub filename: Option<String>,
+}
+
+impl ContentDisposition {
+    /// Parse a header value as described in https://www.rfc-editor.org/rfc/rfc6266.
+    pub fn parse(header: &str) -> ESResult<ContentDisposition, ContentDispositionParseError> {
+        let mut parser = Parser {
+            rest: header.trim(),
+        };
+        let disposition_type = parser.token()?.to_ascii_lowercase();
+        let mut filename = None;
+        let mut ext_filename = None;
+        loop {
+            parser.skip_whitespace();
+            if parser.rest.is_empty() {
+                break;
+            }
+            parser.expect(';')?;
+            parser.skip_whitespace();
+            if parser.rest.is_empty() {
+                // Trailing semicolon, lenient like most browsers.
+                break;
+            }
+            let name = parser.token()?.to_ascii_lowercase();
+            parser.skip_whitespace();
+            parser.expect('=')?;
+            parser.skip_whitespace();
+            let value = parser.value()?;
+            match name.as_str() {
+                "filename" => filename = Some(value),
+                "filename*" => ext_filename = Some(decode_ext_value(&value)?),
+                _ => {}
+            }
+        }
+        Ok(ContentDisposition {
+            disposition_type,
+            // The extended form is preferred, plain `filename` is the fallback for old clients.
+            filename: ext_filename.or(filename),
+        })
+    }
+}
+
+struct Parser<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_whitespace(&mut self) {
+        self.rest = self.rest.trim_start();
+    }
+
+    fn expect(&mut self, c: char) -> ESResult<(), ContentDispositionParseError> {
+        match self.rest.strip_prefix(c) {
+            Some(rest) => {
+                self.rest = rest;
+                Ok(())
+            }
+            None => Err(Report::new(ContentDispositionParseError::Malformed)
+                .attach_printable(format!("Expected '{}' at '{}'", c, self.rest))),
+        }
+    }
+
+    fn token(&mut self) -> ESResult<&'a str, ContentDispositionParseError> {
+        let end = self
+            .rest
+            .find(|c: char| !is_token_char(c))
+            .unwrap_or(self.rest.len());
+        if end == 0 {
+            return Err(Report::new(ContentDispositionParseError::Malformed)
+                .attach_printable(format!("Expected token at '{}'", self.rest)));
+        }
+        let (token, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        Ok(token)
+    }
+
+    fn value(&mut self) -> ESResult<String, ContentDispositionParseError> {
+        if self.rest.starts_with('"') {
+            self.quoted_string()
+        } else {
+            self.token().map(str::to_string)
+        }
+    }
+
+    fn quoted_string(&mut self) -> ESResult<String, ContentDispositionParseError> {
+        self.expect('"')?;
+        let mut result = String::new();
+        let mut chars = self.rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.rest = &self.rest[i + 1..];
+                    return Ok(result);
+                }
+                '\\' => match chars.next() {
+                    Some((_, escaped)) => result.push(escaped),
+                    None => break,
+                },
+                _ => result.push(c),
+            }
+        }
+        Err(Report::new(ContentDispositionParseError::Malformed)
+            .attach_printable("Unterminated quoted string"))
+    }
+}
+
+/// Decode an `ext-value` from https://www.rfc-editor.org/rfc/rfc5987#section-3.2, i.e.
+/// `charset'[language]'percent-encoded-value`.
+fn decode_ext_value(value: &str) -> ESResult<String, ContentDispositionParseError> {
+    let mut parts = value.splitn(3, '\'');
+    let (Some(charset), Some(_language), Some(encoded)) =
+        (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(Report::new(ContentDispositionParseError::Malformed)
+            .attach_printable(format!("Extended value is missing charset: {}", value)));
+    };
+    let bytes = percent_decode(encoded)?;
+    match charset.to_ascii_lowercase().as_str() {
+        "utf-8" => String::from_utf8(bytes).map_err(|_| {
+            Report::new(ContentDispositionParseError::Malformed)
+                .attach_printable(format!("Extended value is not valid UTF-8: {}", value))
+        }),
+        // Every byte maps directly to the code point of the same value.
+        "iso-8859-1" => Ok(bytes.into_iter().map(char::from).collect()),
+        _ => Err(Report::new(ContentDispositionParseError::Unsupported)
+            .attach_printable(format!("Unsupported charset: {}", charset))),
+    }
+}
+
+fn percent_decode(value: &str) -> ESResult<Vec<u8>, ContentDispositionParseError> {
+    let mut result = Vec::with_capacity(value.len());
+    let mut bytes = value.bytes();
+    while let Some(b) = bytes.next() {
+        if b != b'%' {
+            result.push(b);
+            continue;
+        }
+        let hex = [bytes.next(), bytes.next()];
+        let decoded = match hex {
+            [Some(hi), Some(lo)] => std::str::from_utf8(&[hi, lo])
+                .ok()
+                .and_then(|h| u8::from_str_radix(h, 16).ok()),
+            _ => None,
+        };
+        let Some(decoded) = decoded else {
+            return Err(Report::new(ContentDispositionParseError::Malformed)
+                .attach_printable(format!("Invalid percent-encoding in {}", value)));
+        };
+        result.push(decoded);
+    }
+    Ok(result)
+}
+
+/// `token` characters from https://www.rfc-editor.org/rfc/rfc9110#name-tokens.
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn filename(header: &str) -> Option<String> {
+        ContentDisposition::parse(header).unwrap().filename
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            ContentDisposition {
+                disposition_type: "attachment".to_string(),
+                filename: None,
+            },
+            ContentDisposition::parse("Attachment").unwrap()
+        );
+        assert_eq!(
+            Some("jdk.tar.gz".to_string()),
+            filename("attachment; filename=jdk.tar.gz")
+        );
+        assert_eq!(
+            Some("OpenJDK 17.tar.gz".to_string()),
+            filename("attachment; FILENAME=\"OpenJDK 17.tar.gz\"")
+        );
+        assert_eq!(
+            Some("a\"b.zip".to_string()),
+            filename("attachment; size=12; filename=\"a\\\"b.zip\";")
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(ContentDisposition::parse("").is_err());
+        assert!(ContentDisposition::parse("attachment; filename").is_err());
+        assert!(ContentDisposition::parse("attachment; filename=\"unterminated").is_err());
+        assert!(matches!(
+            ContentDisposition::parse("attachment; filename*=KOI8-R''a.zip")
+                .unwrap_err()
+                .current_context(),
+            ContentDispositionParseError::Unsupported
+        ));
+        assert!(ContentDisposition::parse("attachment; filename*=a.zip").is_err());
+        assert!(ContentDisposition::parse("attachment; filename*=UTF-8''a%2.zip").is_err());
+        assert!(ContentDisposition::parse("attachment; filename*=UTF-8''%FF.zip").is_err());
+    }
+
+    #[test]
+    fn test_parse_ext_value() {
+        assert_eq!(
+            Some("jdk 17.zip".to_string()),
+            filename("attachment; filename*=UTF-8''jdk%2017.zip")
+        );
+        assert_eq!(
+            Some("€ rates.tar.gz".to_string()),
+            filename("attachment; filename*=utf-8'en'%E2%82%AC%20rates.tar.gz")
+        );
+        assert_eq!(
+            Some("£.zip".to_string()),
+            filename("attachment; filename*=iso-8859-1''%A3.zip")
+        );
+        // Extended form wins regardless of order.
+        assert_eq!(
+            Some("ext.zip".to_string()),
+            filename("attachment; filename*=UTF-8''ext.zip; filename=\"plain.zip\"")
+        );
+    }
+}
@@ -1,35 +1,307 @@
-use crate::config::PROJECT_DIRS;
+use crate::config::{config_path, is_alternate_config, JpreConfig, PROJECT_DIRS};
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::borrow::Cow;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
-use sysinfo::{get_current_pid, ProcessRefreshKind, RefreshKind, System};
-
-static JPRE_CONTEXT_ID: LazyLock<Option<String>> =
-    LazyLock::new(|| std::env::var("JPRE_CONTEXT_ID").ok());
+use sysinfo::{
+    get_current_pid, Pid, ProcessRefreshKind, ProcessesToUpdate, RefreshKind, System, UpdateKind,
+};
+use tracing::debug;
 
 static SYSTEM_PROCESSES_PID_ONLY: LazyLock<System> = LazyLock::new(|| {
     System::new_with_specifics(RefreshKind::new().with_processes(ProcessRefreshKind::new()))
 });
 
-pub fn get_context_id() -> String {
-    if let Some(context_id) = &*JPRE_CONTEXT_ID {
-        return context_id.clone();
-    }
-    let process = SYSTEM_PROCESSES_PID_ONLY
-        .process(get_current_pid().unwrap())
-        .expect("Could not find current process in system processes");
-    process
-        .parent()
-        .expect("Could not find parent process")
-        .as_u32()
+/// A way to identify the context (usually a terminal) that jpre is running in.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize, Serialize, Display)]
+#[serde(rename_all = "kebab-case")]
+pub enum ContextIdStrategy {
+    /// The `JPRE_CONTEXT_ID` environment variable.
+    #[display("env")]
+    Env,
+    /// The tmux pane, from `TMUX_PANE`.
+    #[display("tmux-pane")]
+    TmuxPane,
+    /// The terminal session, from `TERM_SESSION_ID` (set by e.g. macOS Terminal and iTerm2).
+    #[display("term-session")]
+    TermSession,
+    /// The SSH connection, from `SSH_CONNECTION`.
+    #[display("ssh")]
+    Ssh,
+    /// The PID of the nearest interactive shell among the ancestors, so `sudo` and `bash -c`
+    /// don't change the context. Falls back to the parent process.
+    #[display("parent-pid")]
+    ParentPid,
+}
+
+impl ContextIdStrategy {
+    fn resolve(self, config: &JpreConfig) -> Option<String> {
+        let env = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        match self {
+            ContextIdStrategy::Env => env("JPRE_CONTEXT_ID").map(|id| sanitize(&id)),
+            // Pane IDs are only unique per tmux server, so include the socket from `TMUX` too.
+            ContextIdStrategy::TmuxPane => {
+                let pane = env("TMUX_PANE")?;
+                let socket = env("TMUX").and_then(|t| t.split(',').next().map(str::to_string));
+                Some(sanitize(&format!(
+                    "tmux-{}-{}",
+                    socket.unwrap_or_default(),
+                    pane
+                )))
+            }
+            ContextIdStrategy::TermSession => {
+                env("TERM_SESSION_ID").map(|id| sanitize(&format!("term-{}", id)))
+            }
+            ContextIdStrategy::Ssh => {
+                env("SSH_CONNECTION").map(|id| sanitize(&format!("ssh-{}", id)))
+            }
+            ContextIdStrategy::ParentPid => Some(shell_pid(config).to_string()),
+        }
+    }
+}
+
+pub fn default_context_id_strategies() -> Vec<ContextIdStrategy> {
+    vec![ContextIdStrategy::Env, ContextIdStrategy::ParentPid]
+}
+
+/// Find the context ID using the first strategy in the config that applies. Falls back to the
+/// parent PID if none do.
+pub fn resolve_context_id(config: &JpreConfig) -> (ContextIdStrategy, String) {
+    config
+        .context_id_strategies
+        .iter()
+        .find_map(|&strategy| {
+            strategy
+                .resolve(config)
+                .filter(|id| !id.is_empty())
+                .map(|id| (strategy, id))
+        })
+        .unwrap_or_else(|| (ContextIdStrategy::ParentPid, shell_pid(config).to_string()))
+}
+
+/// Whether setting `JPRE_CONTEXT_ID` names the context. It doesn't when the `env` strategy is off,
+/// or a strategy before it applies.
+pub fn context_id_env_applies(config: &JpreConfig) -> bool {
+    for &strategy in &config.context_id_strategies {
+        if strategy == ContextIdStrategy::Env {
+            return true;
+        }
+        if strategy.resolve(config).is_some_and(|id| !id.is_empty()) {
+            return false;
+        }
+    }
+    false
+}
+
+pub fn get_context_id(config: &JpreConfig) -> String {
+    resolve_context_id(config).1
+}
+
+/// Make `id` safe to use as a file name.
+fn sanitize(id: &str) -> String {
+    id.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>()
+        .trim_start_matches('.')
         .to_string()
 }
 
-pub fn get_context_path() -> PathBuf {
+/// The PID of the nearest interactive shell among the first `parent_pid_max_depth` ancestors, or
+/// of the parent process if there is none.
+fn shell_pid(config: &JpreConfig) -> u32 {
+    let parent = parent_pid();
+    if !sysinfo::IS_SUPPORTED_SYSTEM {
+        return parent;
+    }
+    let mut system = System::new();
+    let mut pid = Pid::from_u32(parent);
+    for _ in 0..config.parent_pid_max_depth {
+        system.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&[pid]),
+            false,
+            ProcessRefreshKind::new().with_cmd(UpdateKind::OnlyIfNotSet),
+        );
+        let Some(process) = system.process(pid) else {
+            break;
+        };
+        if is_interactive_shell(process.name(), process.cmd()) {
+            debug!("Using shell {:?} ({}) as the context", process.name(), pid);
+            return pid.as_u32();
+        }
+        match process.parent() {
+            Some(next) if next.as_u32() > 1 => pid = next,
+            _ => break,
+        }
+    }
+    debug!("No interactive shell among the ancestors, using the parent process");
+    parent
+}
+
+const SHELL_NAMES: &[&str] = &[
+    "bash", "zsh", "fish", "sh", "dash", "ksh", "mksh", "tcsh", "csh", "nu", "elvish", "xonsh",
+    "pwsh",
+];
+
+/// Whether a process looks like an interactive shell: a known shell that isn't running a script
+/// or a `-c` command.
+fn is_interactive_shell(name: &OsStr, cmd: &[OsString]) -> bool {
+    let name = name.to_string_lossy();
+    // Login shells are started as e.g. `-bash`.
+    if !SHELL_NAMES.contains(&name.trim_start_matches('-')) {
+        return false;
+    }
+    cmd.iter().skip(1).all(|arg| {
+        let arg = arg.to_string_lossy();
+        arg.starts_with('-') && arg != "-c"
+    })
+}
+
+/// The PID of the process that started us.
+fn parent_pid() -> u32 {
+    if sysinfo::IS_SUPPORTED_SYSTEM {
+        let parent = get_current_pid().ok().and_then(|pid| {
+            SYSTEM_PROCESSES_PID_ONLY
+                .process(pid)
+                .and_then(|p| p.parent())
+        });
+        if let Some(parent) = parent {
+            return parent.as_u32();
+        }
+        debug!("sysinfo could not find the parent process, falling back to getppid");
+    }
+    std::os::unix::process::parent_id()
+}
+
+/// Whether a process with this PID exists.
+pub fn is_process_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // SAFETY: signal 0 only checks whether the process exists and may be signalled.
+    if unsafe { libc::kill(pid, 0) } == 0 {
+        return true;
+    }
+    // The process exists, but belongs to someone else.
+    std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Directory name in the state dir for the context symlinks.
+pub const CONTEXT_LINKS_DIR_NAME: &str = "java-home-by-pid";
+
+/// The state dir shared by every config, which [state_dir] is in.
+pub fn base_state_dir() -> Cow<'static, Path> {
     PROJECT_DIRS
         .state_dir()
         .map(Cow::Borrowed)
         .unwrap_or_else(|| Cow::Owned(PROJECT_DIRS.cache_dir().join("state")))
-        .join("java-home-by-pid")
-        .join(get_context_id())
+}
+
+/// The state dir for the config in use. An alternate config gets its own, so its contexts don't
+/// collide with those of the default config.
+pub fn state_dir() -> Cow<'static, Path> {
+    if !is_alternate_config() {
+        return base_state_dir();
+    }
+    let digest = Sha256::digest(config_path().as_os_str().as_encoded_bytes());
+    Cow::Owned(
+        base_state_dir()
+            .join("configs")
+            .join(&hex::encode(digest)[..16]),
+    )
+}
+
+/// How many contexts of any config link to each JDK, by its path. Contexts named after a process
+/// that exited are left out, others can't be told apart from live ones and count.
+pub fn contexts_by_jdk() -> HashMap<PathBuf, usize> {
+    let base = base_state_dir();
+    let alternate_configs = std::fs::read_dir(base.join("configs"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path().join(CONTEXT_LINKS_DIR_NAME));
+    let mut contexts = HashMap::new();
+    for dir in std::iter::once(base.join(CONTEXT_LINKS_DIR_NAME)).chain(alternate_configs) {
+        for entry in std::fs::read_dir(&dir).into_iter().flatten().flatten() {
+            let exited = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.parse::<u32>().ok())
+                .is_some_and(|pid| !is_process_alive(pid));
+            if exited {
+                continue;
+            }
+            match std::fs::read_link(entry.path()) {
+                Ok(target) if target.exists() => *contexts.entry(target).or_default() += 1,
+                _ => {}
+            }
+        }
+    }
+    contexts
+}
+
+/// `3 shells`, for a number of contexts.
+pub fn format_context_count(count: usize) -> String {
+    format!("{} shell{}", count, if count == 1 { "" } else { "s" })
+}
+
+pub fn get_context_path(config: &JpreConfig) -> PathBuf {
+    context_path_for_id(&get_context_id(config))
+}
+
+/// The context link of another context, by its ID.
+pub fn context_path_for_id(context_id: &str) -> PathBuf {
+    state_dir().join(CONTEXT_LINKS_DIR_NAME).join(context_id)
+}
+
+/// Where `use --remember` keeps the JDK chosen for each context.
+pub fn remembered_jdks_dir() -> PathBuf {
+    state_dir().join("remembered")
+}
+
+/// Where `use --remember` keeps the JDK chosen for the current context.
+pub fn get_remembered_jdk_path(config: &JpreConfig) -> PathBuf {
+    remembered_jdks_dir().join(get_context_id(config))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!("12345", sanitize("12345"));
+        assert_eq!(
+            "tmux-_tmp_tmux-1000_default-_3",
+            sanitize("tmux-/tmp/tmux-1000/default-%3")
+        );
+        assert_eq!("term-w0t0p0_ABC-123", sanitize("term-w0t0p0:ABC-123"));
+        assert_eq!("_", sanitize("../"));
+    }
+
+    #[test]
+    fn test_is_interactive_shell() {
+        let check = |name: &str, cmd: &[&str]| {
+            is_interactive_shell(
+                OsStr::new(name),
+                &cmd.iter().map(OsString::from).collect::<Vec<_>>(),
+            )
+        };
+        assert!(check("bash", &["bash"]));
+        assert!(check("-zsh", &["-zsh"]));
+        assert!(check("fish", &["fish", "--login"]));
+        assert!(!check("bash", &["bash", "-c", "jpre use 17"]));
+        assert!(!check("bash", &["bash", "build.sh"]));
+        assert!(!check("sudo", &["sudo", "jpre", "use", "17"]));
+        assert!(!check("java", &["java"]));
+    }
 }
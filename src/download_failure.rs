@@ -0,0 +1,140 @@
+use crate::tui::format_duration;
+use std::time::Duration;
+use url::Url;
+
+/// The longest `Retry-After` a download waits for. Longer ones fail the download instead.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Why a download request failed, to tell the user what to do about it and whether retrying can
+/// help.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadFailure {
+    /// The package is gone, usually because the version was pulled upstream.
+    NotFound,
+    /// The package needs a license to be accepted or a login, like some Oracle builds.
+    Forbidden,
+    /// Too many requests, with how long the server asked to wait if it did.
+    RateLimited(Option<Duration>),
+    ServerError(u16),
+    OtherStatus(u16),
+    /// There was no response, e.g. the connection broke.
+    Transport,
+}
+
+impl DownloadFailure {
+    pub fn of(error: &ureq::Error) -> Self {
+        match error {
+            ureq::Error::Status(status, response) => {
+                Self::from_status(*status, response.header("Retry-After"))
+            }
+            ureq::Error::Transport(_) => DownloadFailure::Transport,
+        }
+    }
+
+    fn from_status(status: u16, retry_after: Option<&str>) -> Self {
+        match status {
+            404 | 410 => DownloadFailure::NotFound,
+            401 | 403 | 451 => DownloadFailure::Forbidden,
+            // Only the delay form, HTTP dates are rare for downloads.
+            429 => DownloadFailure::RateLimited(
+                retry_after
+                    .and_then(|s| s.trim().parse().ok())
+                    .map(Duration::from_secs),
+            ),
+            500..=599 => DownloadFailure::ServerError(status),
+            _ => DownloadFailure::OtherStatus(status),
+        }
+    }
+
+    /// How long to wait before retrying, given the usual `delay`. `None` if retrying won't help.
+    pub fn retry_delay(self, delay: Duration) -> Option<Duration> {
+        match self {
+            DownloadFailure::NotFound
+            | DownloadFailure::Forbidden
+            | DownloadFailure::OtherStatus(_) => None,
+            DownloadFailure::RateLimited(Some(wait)) if wait > MAX_RETRY_AFTER => None,
+            DownloadFailure::RateLimited(Some(wait)) => Some(wait),
+            // Without a hint, back off more than for other failures.
+            DownloadFailure::RateLimited(None) => Some(delay * 5),
+            DownloadFailure::ServerError(_) | DownloadFailure::Transport => Some(delay),
+        }
+    }
+
+    /// What to tell the user when the download of `url` finally fails this way.
+    pub fn user_message(self, url: &Url) -> Option<String> {
+        let host = url.host_str().unwrap_or("The server");
+        match self {
+            DownloadFailure::NotFound => Some(format!(
+                "The JDK package is gone from {}, the version may have been pulled upstream. Try \
+                 again later, or another distribution with --distribution",
+                url
+            )),
+            DownloadFailure::Forbidden => Some(format!(
+                "{} refused the download, the package may need a license to be accepted or a \
+                 login. Try another distribution with --distribution",
+                host
+            )),
+            DownloadFailure::RateLimited(wait) => Some(format!(
+                "{} is rate limiting downloads, try again {}",
+                host,
+                wait.map_or_else(
+                    || "later".to_string(),
+                    |wait| format!("in {}", format_duration(wait))
+                )
+            )),
+            DownloadFailure::ServerError(status) => Some(format!(
+                "{} failed to serve the download (HTTP {}), try again later",
+                host, status
+            )),
+            DownloadFailure::OtherStatus(_) | DownloadFailure::Transport => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_classify_status() {
+        assert_eq!(
+            DownloadFailure::NotFound,
+            DownloadFailure::from_status(404, None)
+        );
+        assert_eq!(
+            DownloadFailure::Forbidden,
+            DownloadFailure::from_status(403, None)
+        );
+        assert_eq!(
+            DownloadFailure::RateLimited(Some(Duration::from_secs(30))),
+            DownloadFailure::from_status(429, Some("30"))
+        );
+        assert_eq!(
+            DownloadFailure::RateLimited(None),
+            DownloadFailure::from_status(429, Some("Wed, 21 Oct 2015 07:28:00 GMT"))
+        );
+        assert_eq!(
+            DownloadFailure::ServerError(503),
+            DownloadFailure::from_status(503, None)
+        );
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let delay = Duration::from_secs(2);
+        assert_eq!(None, DownloadFailure::NotFound.retry_delay(delay));
+        assert_eq!(None, DownloadFailure::Forbidden.retry_delay(delay));
+        assert_eq!(
+            Some(delay),
+            DownloadFailure::ServerError(502).retry_delay(delay)
+        );
+        assert_eq!(
+            Some(Duration::from_secs(10)),
+            DownloadFailure::RateLimited(None).retry_delay(delay)
+        );
+        assert_eq!(
+            None,
+            DownloadFailure::RateLimited(Some(Duration::from_secs(3600))).retry_delay(delay)
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use crate::foojay::{FoojayMajorVersion, TermOfSupport};
+use crate::java_version::key::VersionKey;
+
+/// Whether a JDK still gets updates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SupportStatus {
+    Maintained,
+    /// Past end-of-life. `interim` is set for non-LTS releases, which only get updates until the
+    /// next release.
+    EndOfLife {
+        interim: bool,
+    },
+    /// Foojay doesn't know the major version.
+    Unknown,
+}
+
+impl SupportStatus {
+    /// The support status of `jdk`, according to Foojay's major versions.
+    pub fn of(majors: &[FoojayMajorVersion], jdk: &VersionKey) -> SupportStatus {
+        let Some(major) = majors.iter().find(|m| m.major_version == jdk.major) else {
+            return SupportStatus::Unknown;
+        };
+        if major.maintained {
+            SupportStatus::Maintained
+        } else {
+            SupportStatus::EndOfLife {
+                interim: major.term_of_support != TermOfSupport::Lts,
+            }
+        }
+    }
+
+    pub fn is_eol(&self) -> bool {
+        matches!(self, SupportStatus::EndOfLife { .. })
+    }
+}
+
+impl std::fmt::Display for SupportStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SupportStatus::Maintained => write!(f, "maintained"),
+            SupportStatus::EndOfLife { interim: false } => write!(f, "end-of-life"),
+            SupportStatus::EndOfLife { interim: true } => {
+                write!(f, "end-of-life, non-LTS release")
+            }
+            SupportStatus::Unknown => write!(f, "unknown support status"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_support_status() {
+        let majors: Vec<FoojayMajorVersion> = serde_json::from_str(
+            r#"[
+                {"major_version": 22, "term_of_support": "STS", "maintained": false},
+                {"major_version": 21, "term_of_support": "LTS", "maintained": true},
+                {"major_version": 7, "term_of_support": "LTS", "maintained": false}
+            ]"#,
+        )
+        .unwrap();
+        let status = |key: &str| SupportStatus::of(&majors, &VersionKey::from_str(key).unwrap());
+        assert_eq!(SupportStatus::Maintained, status("21"));
+        assert_eq!(SupportStatus::EndOfLife { interim: true }, status("22"));
+        assert_eq!(SupportStatus::EndOfLife { interim: false }, status("7"));
+        assert_eq!(SupportStatus::Unknown, status("99-ea"));
+    }
+}
@@ -1,18 +1,30 @@
-use crate::config::JpreConfig;
-use crate::error::ESResult;
-use crate::http_client::new_http_client;
+use crate::checksum_verifier::{self, Checksum};
+use crate::config::{JdkPin, JpreConfig};
+use crate::error::{ESResult, UserMessage};
+use crate::http_cache::{get_cached, get_cached_only, CachedResponse};
+use crate::http_client::HTTP_CLIENT;
 use crate::java_version::key::VersionKey;
-use crate::java_version::{JavaVersion, PreRelease};
+use crate::java_version::{JavaVersion, OptComparison};
+use crate::platform::Platform;
+use crate::policy;
 use derive_more::Display;
 use error_stack::{Context, Report, ResultExt};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
-use std::collections::HashSet;
-use std::sync::LazyLock;
-use tracing::debug;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::{Arc, LazyLock, Mutex};
+use tracing::{debug, warn};
 use url::Url;
 
-const FOOJAY_BASE_URL: &str = "https://api.foojay.io/disco/v3.0";
+mod checksum_file;
+mod schema;
+
+pub use schema::configure_strict_api;
+use schema::{ApiRecord, FoojayResult};
+
+pub const FOOJAY_BASE_URL: &str = "https://api.foojay.io/disco/v3.0";
 
 #[derive(Debug, Display)]
 pub enum FoojayDiscoApiError {
@@ -20,59 +32,62 @@ pub enum FoojayDiscoApiError {
     Api,
     #[display("Invalid distribution")]
     InvalidDistribution,
+    #[display("Pinned checksum mismatch")]
+    PinMismatch,
+    #[display("No package available")]
+    NoPackage,
 }
 
 impl Context for FoojayDiscoApiError {}
 
 pub static FOOJAY_API: LazyLock<FoojayDiscoApi> = LazyLock::new(FoojayDiscoApi::new);
 
-fn detected_foojay_arch() -> &'static str {
-    match std::env::consts::ARCH {
-        "x86" => "x86",
-        "x86_64" => "x64",
-        "aarch64" => "arm64",
-        _ => panic!(
-            "Unsupported architecture: {}, try setting forced_architecture in the config",
-            std::env::consts::ARCH
-        ),
-    }
-}
-
-fn detected_foojay_os() -> &'static str {
-    match std::env::consts::OS {
-        "macos" => "macos",
-        "linux" => {
-            if cfg!(target_env = "musl") {
-                "linux-musl"
-            } else {
-                "linux"
-            }
-        }
-        _ => panic!(
-            "Unsupported OS: {}, try setting forced_os in the config",
-            std::env::consts::OS
-        ),
-    }
-}
+/// Past this many queries in one run, we warn that a command is hitting Foojay harder than it
+/// should.
+const SOFT_QUERY_LIMIT: usize = 200;
 
 pub struct FoojayDiscoApi {
     client: ureq::Agent,
+    /// Successful responses of this run, so identical queries, e.g. from checking and then
+    /// downloading the same JDK, are only made once. Concurrent identical queries wait for the
+    /// first one.
+    responses: Mutex<HashMap<Url, Arc<Mutex<Option<CachedResponse>>>>>,
+    queries: AtomicUsize,
 }
 
 impl FoojayDiscoApi {
     pub fn new() -> Self {
         Self {
-            client: new_http_client(),
+            client: HTTP_CLIENT.clone(),
+            responses: Mutex::default(),
+            queries: AtomicUsize::new(0),
         }
     }
 
     /// List all distributions, including synonyms.
     pub fn list_distributions(
         &self,
+    ) -> ESResult<Vec<FoojayDistributionListInfo>, FoojayDiscoApiError> {
+        self.list_distributions_with(false)
+    }
+
+    /// List all distributions, including synonyms and the versions each one offers.
+    pub fn list_distributions_with_versions(
+        &self,
+    ) -> ESResult<Vec<FoojayDistributionListInfo>, FoojayDiscoApiError> {
+        self.list_distributions_with(true)
+    }
+
+    fn list_distributions_with(
+        &self,
+        include_versions: bool,
     ) -> ESResult<Vec<FoojayDistributionListInfo>, FoojayDiscoApiError> {
         let url = Url::parse_with_params(
             &format!("{}/distributions", FOOJAY_BASE_URL),
-            &[("include_versions", "false"), ("include_synonyms", "true")],
+            &[
+                ("include_versions", include_versions.to_string().as_str()),
+                ("include_synonyms", "true"),
+            ],
         )
         .unwrap();
         Ok(self
@@ -99,15 +114,37 @@ impl FoojayDiscoApi {
             .collect())
     }
 
+    /// List all major versions with their support status.
+    pub fn list_major_versions(&self) -> ESResult<Vec<FoojayMajorVersion>, FoojayDiscoApiError> {
+        self.call_foojay_api(major_versions_url())
+    }
+
+    /// The major versions of the last [Self::list_major_versions] response that was cached,
+    /// however old, without asking Foojay. `None` if there's none.
+    pub fn cached_major_versions(&self) -> Option<Vec<FoojayMajorVersion>> {
+        let response = get_cached_only(&major_versions_url())?;
+        if !matches!(response.status, 200..=299) {
+            return None;
+        }
+        FoojayResult::parse(&response.body).ok()?.records().ok()
+    }
+
+    /// Get the latest package from the first configured distribution that has one. If
+    /// `distribution` is given, only that distribution is used instead. A JDK pinned in the config
+    /// always gets its pinned package. Configured distributions the policy doesn't allow are
+    /// skipped.
     pub fn get_latest_package_info_using_priority(
         &self,
         config: &JpreConfig,
+        platform: &Platform,
         jdk: &VersionKey,
+        distribution: Option<&str>,
     ) -> ESResult<(FoojayPackageListInfo, FoojayPackageInfo), FoojayDiscoApiError> {
-        let mut iter = config
-            .distributions
+        let pin = config.pins.get(jdk);
+        let distributions = candidate_distributions(config, jdk, distribution)?;
+        let mut iter = distributions
             .iter()
-            .map(|dist| self.get_latest_package_info(config, dist, jdk));
+            .map(|&dist| self.get_package_info(config, platform, dist, jdk));
         let first = iter.next().expect("always at least one distribution");
         if let Ok((list_info, info)) = first {
             return Ok((list_info, info));
@@ -119,100 +156,338 @@ impl FoojayDiscoApi {
                 Err(e) => errors.push(e),
             }
         }
-        let mut report = Report::new(FoojayDiscoApiError::Api)
-            .attach_printable("Failed to get latest package info");
+        // If no distribution has a package, the platform may be wrong or unsupported by Foojay.
+        let mut report = if errors
+            .iter()
+            .all(|e| matches!(e.current_context(), FoojayDiscoApiError::NoPackage))
+        {
+            let missing = format!(
+                "No package of JDK {} for {} in {}",
+                jdk,
+                platform,
+                distributions.join(", ")
+            );
+            let offering = self
+                .distributions_offering(config, platform, jdk, pin)
+                .inspect_err(|e| debug!("Failed to find other distributions: {:?}", e))
+                .unwrap_or_default();
+            let message = if offering.is_empty() {
+                format!(
+                    "{}. If that's not your platform, set forced_os or forced_architecture in \
+                    the config, or pass --os or --arch",
+                    missing
+                )
+            } else {
+                format!(
+                    "{}. JDK {} for {} is available from: {}",
+                    missing,
+                    jdk,
+                    platform.arch,
+                    offering.join(", ")
+                )
+            };
+            Report::new(FoojayDiscoApiError::NoPackage).attach(UserMessage { message })
+        } else {
+            Report::new(FoojayDiscoApiError::Api)
+                .attach_printable("Failed to get latest package info")
+        };
         for error in errors {
             report.extend_one(error);
         }
         Err(report)
     }
 
-    pub fn get_latest_package_info(
+    /// The package of `jdk` from `distribution`: the pinned one if `jdk` is pinned in the config,
+    /// else the latest.
+    fn get_package_info(
         &self,
         config: &JpreConfig,
+        platform: &Platform,
         distribution: &str,
         jdk: &VersionKey,
     ) -> ESResult<(FoojayPackageListInfo, FoojayPackageInfo), FoojayDiscoApiError> {
-        let arch = config
-            .forced_architecture
-            .clone()
-            .unwrap_or_else(|| detected_foojay_arch().to_string());
-        let os = config
-            .forced_os
-            .clone()
-            .unwrap_or_else(|| detected_foojay_os().to_string());
-        let url = Url::parse_with_params(
-            &format!("{}/packages", FOOJAY_BASE_URL),
-            &[
-                // We don't want to handle JREs yet.
-                ("package_type", "jdk".to_string()),
-                // JavaFX can be nice to have bundled.
-                ("with_javafx_if_available", "true".to_string()),
-                // We need to be able to download it.
-                ("directly_downloadable", "true".to_string()),
-                ("jdk_version", jdk.major.to_string()),
-                (
-                    "release_status",
-                    match &jdk.pre_release {
-                        PreRelease::None => "ga".to_string(),
-                        PreRelease::Numeric(v) => v.to_string(),
-                        PreRelease::Other(v) => v.clone(),
-                    },
-                ),
-                ("distribution", distribution.to_string()),
-                ("operating_system", os),
-                ("architecture", arch),
-            ],
-        )
-        .unwrap();
-        self.call_foojay_api::<FoojayPackageListInfo>(url)?
+        match config.pins.get(jdk) {
+            Some(pin) => self.get_pinned_package_info(platform, distribution, jdk, pin),
+            None => self.get_latest_package_info(config, platform, distribution, jdk),
+        }
+    }
+
+    /// The distributions the policy allows with a package of `jdk` for this platform that could
+    /// be installed, i.e. one that [Self::get_latest_package_info_using_priority] would pick. A
+    /// single query finds the distributions with any package, which are then checked like the
+    /// configured ones.
+    fn distributions_offering(
+        &self,
+        config: &JpreConfig,
+        platform: &Platform,
+        jdk: &VersionKey,
+        pin: Option<&JdkPin>,
+    ) -> ESResult<Vec<String>, FoojayDiscoApiError> {
+        let url = packages_url(platform, None, jdk, pin.map(|p| &p.version))?;
+        let mut distributions = self
+            .call_foojay_api::<FoojayPackageListInfo>(url)?
             .into_iter()
+            .filter(|p| {
+                (pin.is_some() || p.latest_build_available)
+                    && !matches!(p.archive_type, ArchiveType::Unknown(_))
+            })
+            .map(|p| p.distribution)
+            .collect::<Vec<_>>();
+        distributions.sort();
+        distributions.dedup();
+        let policy = policy::current();
+        distributions.retain(|dist| {
+            policy.check_distribution(dist).is_ok()
+                && self
+                    .get_package_info(config, platform, dist, jdk)
+                    .inspect_err(|e| debug!("Not offering distribution {}: {:?}", dist, e))
+                    .is_ok()
+        });
+        Ok(distributions)
+    }
+
+    pub fn get_latest_package_info(
+        &self,
+        config: &JpreConfig,
+        platform: &Platform,
+        distribution: &str,
+        jdk: &VersionKey,
+    ) -> ESResult<(FoojayPackageListInfo, FoojayPackageInfo), FoojayDiscoApiError> {
+        self.latest_package_candidates(platform, distribution, jdk)?
             .find_map(|p| -> Option<ESResult<_, FoojayDiscoApiError>> {
-                if !p.latest_build_available {
-                    return None;
-                }
-                if let ArchiveType::Unknown(archive_type) = &p.archive_type {
-                    debug!("Unknown archive type: {}", archive_type);
-                    return None;
-                }
                 self.call_foojay_api_single(p.links.pkg_info_uri.clone())
                     .map(|mut info: FoojayPackageInfo| {
                         if matches!(info.checksum_type, ChecksumType::Unknown(ref ct) if ct.is_empty()) {
                             try_fill_checksum(&mut info);
+                        } else if config.verify_all_checksums {
+                            add_checksum_file(&mut info);
                         }
-                        if let ChecksumType::Unknown(checksum_type) = &info.checksum_type {
-                            debug!("Unknown checksum type: {}", checksum_type);
-                            None
-                        } else {
-                            Some((p, info))
+                        // Bad checksum metadata skips to the next candidate, rather than failing
+                        // the download.
+                        match checksum_verifier::validate(&info.checksums()) {
+                            Ok(()) => Some((p, info)),
+                            Err(e) => {
+                                debug!(
+                                    "Skipping package {} with unusable checksum: {:?}",
+                                    info.filename(),
+                                    e
+                                );
+                                None
+                            }
                         }
                     })
                     .transpose()
             })
             .ok_or_else(|| {
-                Report::new(FoojayDiscoApiError::Api).attach_printable(format!(
+                Report::new(FoojayDiscoApiError::NoPackage).attach_printable(format!(
                     "No latest package available for JDK {} in distribution {}",
                     jdk, distribution
                 ))
             })?
     }
 
-    fn call_foojay_api<T: for<'a> Deserialize<'a>>(
+    /// Get the package of a pinned JDK. The pinned checksum is used to verify the download, and
+    /// it's an error if Foojay lists a different one.
+    pub fn get_pinned_package_info(
+        &self,
+        platform: &Platform,
+        distribution: &str,
+        jdk: &VersionKey,
+        pin: &JdkPin,
+    ) -> ESResult<(FoojayPackageListInfo, FoojayPackageInfo), FoojayDiscoApiError> {
+        let url = packages_url(platform, Some(distribution), jdk, Some(&pin.version))?;
+        let candidates = self
+            .call_foojay_api::<FoojayPackageListInfo>(url)?
+            .into_iter()
+            .filter_map(|mut p| {
+                if let ArchiveType::Unknown(archive_type) = &p.archive_type {
+                    debug!("Unknown archive type: {}", archive_type);
+                    return None;
+                }
+                p.java_version = p.java_version.normalize_for_distribution(&p.distribution);
+                (p.java_version
+                    .compare_with(&pin.version, OptComparison::Natural)
+                    == Ordering::Equal)
+                    .then_some(p)
+            });
+        let mut unchecked = None;
+        let mut mismatched = Vec::new();
+        for p in candidates {
+            let info: FoojayPackageInfo =
+                self.call_foojay_api_single(p.links.pkg_info_uri.clone())?;
+            match info.checksum_type {
+                ChecksumType::Sha256 if info.checksum.eq_ignore_ascii_case(&pin.sha256) => {
+                    return Ok((p, info));
+                }
+                ChecksumType::Sha256 if !info.checksum.is_empty() => mismatched.push(info.checksum),
+                // Foojay doesn't know the checksum, the download is still verified against the pin.
+                _ => {
+                    if unchecked.is_none() {
+                        unchecked = Some((p, info));
+                    }
+                }
+            }
+        }
+        if let Some((p, mut info)) = unchecked {
+            info.checksum = pin.sha256.to_ascii_lowercase();
+            info.checksum_type = ChecksumType::Sha256;
+            return Ok((p, info));
+        }
+        if !mismatched.is_empty() {
+            return Err(
+                Report::new(FoojayDiscoApiError::PinMismatch).attach(UserMessage {
+                    message: format!(
+                        "JDK {} is pinned to {} with sha256 {}, but {} serves {}",
+                        jdk,
+                        pin.version,
+                        pin.sha256,
+                        distribution,
+                        mismatched.join(", ")
+                    ),
+                }),
+            );
+        }
+        Err(
+            Report::new(FoojayDiscoApiError::NoPackage).attach_printable(format!(
+                "Pinned version {} of JDK {} not available in distribution {}",
+                pin.version, jdk, distribution
+            )),
+        )
+    }
+
+    /// Get the latest package of a JDK without requesting its package info. Returns `None` if the
+    /// distribution doesn't have a usable package.
+    pub fn get_latest_package_list_info(
+        &self,
+        platform: &Platform,
+        distribution: &str,
+        jdk: &VersionKey,
+    ) -> ESResult<Option<FoojayPackageListInfo>, FoojayDiscoApiError> {
+        Ok(self
+            .latest_package_candidates(platform, distribution, jdk)?
+            .next())
+    }
+
+    /// The packages for the latest build of a JDK that we know how to install.
+    fn latest_package_candidates(
+        &self,
+        platform: &Platform,
+        distribution: &str,
+        jdk: &VersionKey,
+    ) -> ESResult<impl Iterator<Item = FoojayPackageListInfo>, FoojayDiscoApiError> {
+        let url = packages_url(platform, Some(distribution), jdk, None)?;
+        Ok(self
+            .call_foojay_api::<FoojayPackageListInfo>(url)?
+            .into_iter()
+            .filter_map(|mut p| {
+                if !p.latest_build_available {
+                    return None;
+                }
+                if let ArchiveType::Unknown(archive_type) = &p.archive_type {
+                    debug!("Unknown archive type: {}", archive_type);
+                    return None;
+                }
+                p.java_version = p.java_version.normalize_for_distribution(&p.distribution);
+                Some(p)
+            }))
+    }
+
+    /// Run the same package queries as [Self::get_latest_package_info_using_priority], returning
+    /// for every distribution it would try the raw responses of the package list and the package
+    /// info of the package it would pick, if any. `selected` marks the distribution it would
+    /// install from.
+    pub fn dump_package_queries(
         &self,
-        url: Url,
-    ) -> ESResult<Vec<T>, FoojayDiscoApiError> {
-        let response = self
-            .client
-            .get(url.as_str())
-            .call()
-            .change_context(FoojayDiscoApiError::Api)?;
-        let status_code = response.status();
-        let data: FoojayResult<T> = response
-            .into_json()
-            .change_context(FoojayDiscoApiError::Api)?;
+        config: &JpreConfig,
+        platform: &Platform,
+        jdk: &VersionKey,
+    ) -> ESResult<serde_json::Value, FoojayDiscoApiError> {
+        let pin = config.pins.get(jdk);
+        let mut selected = false;
+        let mut dump = serde_json::Map::new();
+        for distribution in candidate_distributions(config, jdk, None)? {
+            let url = packages_url(platform, Some(distribution), jdk, pin.map(|p| &p.version))?;
+            let packages = self
+                .get_raw(&url)
+                .attach_printable_lazy(|| format!("Distribution: {}", distribution))?;
+            let (package_info, error) =
+                match self.get_package_info(config, platform, distribution, jdk) {
+                    Ok((list_info, _)) => {
+                        (Some(self.get_raw(&list_info.links.pkg_info_uri)?), None)
+                    }
+                    Err(e) => (None, Some(format!("{:?}", e))),
+                };
+            let is_selected = package_info.is_some() && !selected;
+            selected |= is_selected;
+            dump.insert(
+                distribution.to_string(),
+                serde_json::json!({
+                    "packages_url": url.as_str(),
+                    "packages": packages,
+                    "package_info": package_info,
+                    "selected": is_selected,
+                    "error": error,
+                }),
+            );
+        }
+        Ok(serde_json::Value::Object(dump))
+    }
+
+    /// GET `url` like the other queries, returning the JSON as it is.
+    fn get_raw(&self, url: &Url) -> ESResult<serde_json::Value, FoojayDiscoApiError> {
+        let response = self.get_memoized(url)?;
+        serde_json::from_str(&response.body)
+            .change_context(FoojayDiscoApiError::Api)
+            .attach_printable_lazy(|| format!("URL: {}", url))
+    }
+
+    /// GET `url`, at most once per run if it succeeds.
+    fn get_memoized(&self, url: &Url) -> ESResult<CachedResponse, FoojayDiscoApiError> {
+        self.get_memoized_with(url, || {
+            get_cached(&self.client, url).change_context(FoojayDiscoApiError::Api)
+        })
+    }
+
+    fn get_memoized_with(
+        &self,
+        url: &Url,
+        fetch: impl FnOnce() -> ESResult<CachedResponse, FoojayDiscoApiError>,
+    ) -> ESResult<CachedResponse, FoojayDiscoApiError> {
+        let slot = self
+            .responses
+            .lock()
+            .unwrap()
+            .entry(url.clone())
+            .or_default()
+            .clone();
+        let mut slot = slot.lock().unwrap();
+        if let Some(response) = &*slot {
+            debug!("Re-using response of this run for {}", url);
+            return Ok(response.clone());
+        }
+        if self.queries.fetch_add(1, AtomicOrdering::Relaxed) + 1 == SOFT_QUERY_LIMIT {
+            warn!(
+                "Made {} Foojay queries in this run, which is more than expected",
+                SOFT_QUERY_LIMIT
+            );
+        }
+        let response = fetch()?;
+        if matches!(response.status, 200..=299) {
+            *slot = Some(response.clone());
+        }
+        Ok(response)
+    }
+
+    fn call_foojay_api<T: ApiRecord>(&self, url: Url) -> ESResult<Vec<T>, FoojayDiscoApiError> {
+        let response = self.get_memoized(&url)?;
+        let status_code = response.status;
+        let data = FoojayResult::parse(&response.body)
+            .attach_printable_lazy(|| format!("URL: {}", url))?;
 
         match status_code {
-            200..=299 => Ok(data.result),
+            200..=299 => data
+                .records()
+                .attach_printable_lazy(|| format!("URL: {}", url)),
             _ => match data.message.as_str() {
                 "Requested distribution not found" => {
                     Err(Report::new(FoojayDiscoApiError::InvalidDistribution))
@@ -224,48 +499,181 @@ impl FoojayDiscoApi {
         }
     }
 
-    fn call_foojay_api_single<T: for<'a> Deserialize<'a>>(
-        &self,
-        url: Url,
-    ) -> ESResult<T, FoojayDiscoApiError> {
-        let result: Vec<T> = self.call_foojay_api(url)?;
-        assert_eq!(result.len(), 1, "Expected exactly one result");
+    fn call_foojay_api_single<T: ApiRecord>(&self, url: Url) -> ESResult<T, FoojayDiscoApiError> {
+        let result: Vec<T> = self.call_foojay_api(url.clone())?;
+        if result.len() != 1 {
+            return Err(
+                Report::new(FoojayDiscoApiError::Api).attach_printable(format!(
+                    "Expected exactly one result, got {} from {}",
+                    result.len(),
+                    url
+                )),
+            );
+        }
         Ok(result.into_iter().next().unwrap())
     }
 }
 
-/// Attempt to fill in the missing checksum data using known checksum URL patterns.
-fn try_fill_checksum(info: &mut FoojayPackageInfo) {
-    for suffix in &["sha256", "sha256.text"] {
-        let url = format!("{}.{}", info.direct_download_uri, suffix);
-        let Ok(response) = ureq::get(&url).call() else {
-            continue;
-        };
-        if !matches!(response.status(), 200..=299) {
-            continue;
+/// The distributions to get `jdk` from, in order: `distribution` if given, else the one it's
+/// pinned to, else the configured ones. Those the policy doesn't allow are left out, it's an
+/// error if that leaves none.
+fn candidate_distributions<'a>(
+    config: &'a JpreConfig,
+    jdk: &VersionKey,
+    distribution: Option<&'a str>,
+) -> ESResult<Vec<&'a str>, FoojayDiscoApiError> {
+    let pin = config.pins.get(jdk);
+    let policy = policy::current();
+    match distribution.or(pin.and_then(|p| p.distribution.as_deref())) {
+        Some(distribution) => {
+            policy
+                .check_distribution(distribution)
+                .change_context(FoojayDiscoApiError::InvalidDistribution)?;
+            Ok(vec![distribution])
         }
-        let Ok(checksum) = response.into_string() else {
-            continue;
-        };
-        let checksum = checksum.trim();
-        if checksum.len() == 64 {
-            info.checksum = checksum.to_string();
-            info.checksum_type = ChecksumType::Sha256;
-            return;
+        None => {
+            let allowed = config
+                .distributions
+                .iter()
+                .map(String::as_str)
+                .filter(|d| policy.check_distribution(d).is_ok())
+                .collect::<Vec<_>>();
+            if allowed.is_empty() {
+                // Explain why the first one isn't allowed.
+                policy
+                    .check_distribution(&config.distributions[0])
+                    .change_context(FoojayDiscoApiError::InvalidDistribution)?;
+            }
+            Ok(allowed)
         }
     }
 }
 
-#[derive(Debug, Deserialize)]
-struct FoojayResult<T> {
-    message: String,
-    result: Vec<T>,
+fn major_versions_url() -> Url {
+    Url::parse_with_params(
+        &format!("{}/major_versions", FOOJAY_BASE_URL),
+        &[
+            ("ea", "true"),
+            ("ga", "true"),
+            ("include_versions", "false"),
+        ],
+    )
+    .unwrap()
+}
+
+/// The package list query for `jdk`, or for the exact `version` of it if given. Without a
+/// `distribution`, all distributions are queried.
+fn packages_url(
+    platform: &Platform,
+    distribution: Option<&str>,
+    jdk: &VersionKey,
+    version: Option<&JavaVersion>,
+) -> ESResult<Url, FoojayDiscoApiError> {
+    let mut params = vec![
+        // We don't want to handle JREs yet.
+        ("package_type", "jdk".to_string()),
+        // JavaFX can be nice to have bundled.
+        ("with_javafx_if_available", "true".to_string()),
+        // We need to be able to download it.
+        ("directly_downloadable", "true".to_string()),
+        match version {
+            Some(version) => ("version", version.to_string()),
+            None => ("jdk_version", jdk.major.to_string()),
+        },
+        ("release_status", jdk.release_status()),
+    ];
+    if let Some(distribution) = distribution {
+        params.push(("distribution", distribution.to_string()));
+    }
+    params.push(("operating_system", platform.foojay_os()));
+    params.push(("architecture", platform.arch.clone()));
+    // Special builds such as CRaC are selected by feature, not by release status.
+    if let Some(feature) = jdk.feature() {
+        params.push(("feature", feature.to_string()));
+    }
+    Ok(Url::parse_with_params(&format!("{}/packages", FOOJAY_BASE_URL), &params).unwrap())
+}
+
+/// Attempt to fill in the missing checksum data, from the package's checksum file if it has one,
+/// or else from known checksum URL patterns.
+fn try_fill_checksum(info: &mut FoojayPackageInfo) {
+    let guessed = ["sha256", "sha256.text"]
+        .iter()
+        .map(|suffix| format!("{}.{}", info.direct_download_uri, suffix));
+    let urls = info
+        .checksum_uri()
+        .map(str::to_string)
+        .into_iter()
+        .chain(guessed)
+        .collect::<Vec<_>>();
+    if let Some(checksum) = urls
+        .iter()
+        .find_map(|url| fetch_checksum_file(url, info.filename()))
+    {
+        info.checksum = checksum;
+        info.checksum_type = ChecksumType::Sha256;
+    }
+}
+
+/// Also verify the download against the package's checksum file, if it has one that differs from
+/// the checksum Foojay lists.
+fn add_checksum_file(info: &mut FoojayPackageInfo) {
+    let Some(url) = info.checksum_uri() else {
+        return;
+    };
+    let Some(checksum) = fetch_checksum_file(url, info.filename()) else {
+        return;
+    };
+    if matches!(info.checksum_type, ChecksumType::Sha256)
+        && info.checksum.eq_ignore_ascii_case(&checksum)
+    {
+        return;
+    }
+    info.extra_checksums.push(Checksum {
+        checksum_type: ChecksumType::Sha256,
+        value: checksum,
+    });
+}
+
+/// The SHA-256 checksum of `filename` in the checksum file at `url`.
+fn fetch_checksum_file(url: &str, filename: &str) -> Option<String> {
+    let response = HTTP_CLIENT.get(url).call().ok()?;
+    if !matches!(response.status(), 200..=299) {
+        return None;
+    }
+    let contents = response.into_string().ok()?;
+    let checksum = checksum_file::find_sha256(&contents, filename);
+    match &checksum {
+        Some(_) => debug!("Found checksum of {} in {}", filename, url),
+        None => debug!("No checksum for {} in {}", filename, url),
+    }
+    checksum
 }
 
 #[derive(Debug, Deserialize)]
 pub struct FoojayDistributionListInfo {
     pub name: String,
+    #[serde(default)]
     pub synonyms: Vec<String>,
+    /// Only filled in by [FoojayDiscoApi::list_distributions_with_versions].
+    #[serde(default)]
+    pub versions: Vec<String>,
+}
+
+impl FoojayDistributionListInfo {
+    /// The major versions this distribution offers, oldest first. Versions that can't be parsed
+    /// are left out.
+    pub fn major_versions(&self) -> Vec<u32> {
+        let mut majors = self
+            .versions
+            .iter()
+            .filter_map(|v| JavaVersion::from_str(v).ok())
+            .map(|v| v.major())
+            .collect::<Vec<_>>();
+        majors.sort_unstable();
+        majors.dedup();
+        majors
+    }
 }
 
 impl PartialEq for FoojayDistributionListInfo {
@@ -293,18 +701,61 @@ struct FoojayDistributionInfo {
     versions: Vec<JavaVersion>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct FoojayMajorVersion {
+    pub major_version: u32,
+    pub term_of_support: TermOfSupport,
+    /// Whether the major version still gets updates.
+    pub maintained: bool,
+    /// Whether there are only early access builds, i.e. the version isn't released yet.
+    #[serde(default)]
+    pub early_access_only: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub enum TermOfSupport {
+    #[serde(rename = "LTS")]
+    Lts,
+    #[serde(rename = "MTS")]
+    Mts,
+    #[serde(rename = "STS")]
+    Sts,
+    #[serde(untagged)]
+    Unknown(String),
+}
+
 #[derive(Debug, Deserialize)]
 pub struct FoojayPackageListInfo {
     pub archive_type: ArchiveType,
+    pub distribution: String,
     pub java_version: JavaVersion,
+    /// The vendor's own version, e.g. `21.0.2.13.1` for Corretto.
+    #[serde(default)]
+    pub distribution_version: Option<String>,
+    /// Link to the vendor's release notes, when Foojay knows it.
+    #[serde(default)]
+    pub release_notes_uri: Option<String>,
     pub latest_build_available: bool,
     pub links: FoojayPackageLinks,
+    /// Size of the archive in bytes, 0 if unknown.
+    #[serde(default)]
+    pub size: u64,
+    /// Whether the license allows using the JDK in production without accepting it first, as
+    /// with Oracle's OTN license.
+    #[serde(default = "default_free_use")]
+    pub free_use_in_production: bool,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+fn default_free_use() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum ArchiveType {
     #[serde(rename = "tar.gz")]
     TarGz,
+    #[serde(rename = "tar.zst")]
+    TarZst,
     #[serde(rename = "zip")]
     Zip,
     #[serde(untagged)]
@@ -318,15 +769,148 @@ pub struct FoojayPackageLinks {
 
 #[derive(Debug, Deserialize)]
 pub struct FoojayPackageInfo {
+    #[serde(default)]
+    filename: String,
     pub direct_download_uri: Url,
+    /// The vendor's download page, empty if Foojay doesn't know it.
+    #[serde(default)]
+    download_site_uri: String,
+    /// Empty if the package has no signature.
+    #[serde(default)]
+    signature_uri: String,
+    /// Empty if the package has no checksum file.
+    #[serde(default)]
+    checksum_uri: String,
     pub checksum: String,
     pub checksum_type: ChecksumType,
+    /// Checksums from other sources, which the download is verified against as well.
+    #[serde(skip)]
+    pub extra_checksums: Vec<Checksum>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+impl FoojayPackageInfo {
+    /// A package Foojay doesn't know about, downloaded from `uri` and checked against `sha256`.
+    pub fn direct(uri: Url, sha256: String) -> Self {
+        FoojayPackageInfo {
+            filename: String::new(),
+            direct_download_uri: uri,
+            download_site_uri: String::new(),
+            signature_uri: String::new(),
+            checksum_uri: String::new(),
+            checksum: sha256,
+            checksum_type: ChecksumType::Sha256,
+            extra_checksums: Vec::new(),
+        }
+    }
+
+    /// All checksums the download must match.
+    pub fn checksums(&self) -> Vec<Checksum> {
+        std::iter::once(Checksum {
+            checksum_type: self.checksum_type.clone(),
+            value: self.checksum.clone(),
+        })
+        .chain(self.extra_checksums.iter().cloned())
+        .collect()
+    }
+
+    /// The archive's file name, from the download URL if Foojay doesn't list it.
+    pub fn filename(&self) -> &str {
+        if !self.filename.is_empty() {
+            return &self.filename;
+        }
+        self.direct_download_uri
+            .path_segments()
+            .and_then(|mut s| s.next_back())
+            .unwrap_or_default()
+    }
+
+    pub fn signature_uri(&self) -> Option<&str> {
+        Some(self.signature_uri.as_str()).filter(|u| !u.is_empty())
+    }
+
+    pub fn checksum_uri(&self) -> Option<&str> {
+        Some(self.checksum_uri.as_str()).filter(|u| !u.is_empty())
+    }
+
+    /// Where to read the license of a package from `distribution`: the vendor's download page, or
+    /// the license of a distribution we know.
+    pub fn license_uri(&self, distribution: &str) -> Option<&str> {
+        Some(self.download_site_uri.as_str())
+            .filter(|u| !u.is_empty())
+            .or_else(|| {
+                KNOWN_LICENSES
+                    .iter()
+                    .find(|(d, _)| *d == distribution)
+                    .map(|(_, uri)| *uri)
+            })
+    }
+}
+
+/// Licenses of distributions with packages that aren't free to use in production.
+const KNOWN_LICENSES: &[(&str, &str)] = &[(
+    "oracle",
+    "https://www.oracle.com/downloads/licenses/javase-license1.html",
+)];
+
+#[derive(Debug, Clone, Deserialize, Display)]
 pub enum ChecksumType {
     #[serde(rename = "sha256")]
+    #[display("sha256")]
     Sha256,
+    /// Any other algorithm, see [checksum_verifier::validate] for whether it can be verified.
+    /// Empty if Foojay doesn't know the checksum.
     #[serde(untagged)]
+    #[display("{_0}")]
     Unknown(String),
 }
+
+impl ChecksumType {
+    /// The Foojay name of the algorithm.
+    pub fn name(&self) -> &str {
+        match self {
+            ChecksumType::Sha256 => "sha256",
+            ChecksumType::Unknown(name) => name,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_archive_type() {
+        let types: Vec<ArchiveType> =
+            serde_json::from_str(r#"["tar.gz", "tar.zst", "zip", "msi"]"#).unwrap();
+        assert!(matches!(types[0], ArchiveType::TarGz));
+        assert!(matches!(types[1], ArchiveType::TarZst));
+        assert!(matches!(types[2], ArchiveType::Zip));
+        assert!(matches!(&types[3], ArchiveType::Unknown(t) if t == "msi"));
+    }
+
+    #[test]
+    fn test_get_memoized() {
+        let api = FoojayDiscoApi::new();
+        let url = major_versions_url();
+        let fetches = AtomicUsize::new(0);
+        let fetch = |status| {
+            fetches.fetch_add(1, AtomicOrdering::Relaxed);
+            Ok(CachedResponse {
+                status,
+                body: "{}".to_string(),
+            })
+        };
+        // Failures aren't kept, the next query tries again.
+        api.get_memoized_with(&url, || fetch(500)).unwrap();
+        for _ in 0..3 {
+            let response = api.get_memoized_with(&url, || fetch(200)).unwrap();
+            assert_eq!(response.status, 200);
+        }
+        assert_eq!(fetches.load(AtomicOrdering::Relaxed), 2);
+        assert_eq!(api.queries.load(AtomicOrdering::Relaxed), 2);
+
+        let other = Url::parse(&format!("{}/distributions", FOOJAY_BASE_URL)).unwrap();
+        api.get_memoized_with(&other, || fetch(200)).unwrap();
+        assert_eq!(fetches.load(AtomicOrdering::Relaxed), 3);
+    }
+}
@@ -0,0 +1,65 @@
+/// Find the SHA-256 checksum of `filename` in a checksum file. Handles a bare checksum, the
+/// `sha256sum` format (`<checksum>  <file>`, possibly listing several files) and the BSD format
+/// (`SHA256 (<file>) = <checksum>`).
+pub(super) fn find_sha256(contents: &str, filename: &str) -> Option<String> {
+    let is_sha256 = |s: &str| s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit());
+    let mut entries = Vec::new();
+    for line in contents.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if let Some(rest) = line.strip_prefix("SHA256 (") {
+            if let Some((name, checksum)) = rest.split_once(") = ") {
+                entries.push((Some(name), checksum.trim()));
+            }
+            continue;
+        }
+        match line.split_once(char::is_whitespace) {
+            // `*` marks binary mode in `sha256sum` output.
+            Some((checksum, name)) => {
+                entries.push((Some(name.trim().trim_start_matches('*')), checksum))
+            }
+            None => entries.push((None, line)),
+        }
+    }
+    let matches_file = |name: &str| name.rsplit('/').next() == Some(filename);
+    let checksum = match entries.as_slice() {
+        [(None, checksum)] => Some(*checksum),
+        _ => entries
+            .iter()
+            .find(|(name, _)| name.is_some_and(matches_file))
+            .map(|(_, checksum)| *checksum),
+    }?;
+    is_sha256(checksum).then(|| checksum.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SUM: &str = "454bebb2c9fe48d981341461ffb6bf1017c7b7c6e15c6b0c29b959194ba3aaa5";
+    const OTHER: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+    #[test]
+    fn test_find_sha256() {
+        let file = "jdk.tar.gz";
+        assert_eq!(
+            Some(SUM.to_string()),
+            find_sha256(&format!("{}\n", SUM), file)
+        );
+        assert_eq!(
+            Some(SUM.to_string()),
+            find_sha256(&format!("{}  jdk.tar.gz\n", SUM), file)
+        );
+        assert_eq!(
+            Some(SUM.to_string()),
+            find_sha256(
+                &format!("{}  jre.tar.gz\n{} *dist/jdk.tar.gz\n", OTHER, SUM),
+                file
+            )
+        );
+        assert_eq!(
+            Some(SUM.to_string()),
+            find_sha256(&format!("SHA256 (jdk.tar.gz) = {}\n", SUM), file)
+        );
+        assert_eq!(None, find_sha256(&format!("{}  jre.tar.gz\n", OTHER), file));
+        assert_eq!(None, find_sha256("not a checksum\n", file));
+    }
+}
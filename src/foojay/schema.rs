@@ -0,0 +1,243 @@
+use super::{
+    FoojayDiscoApiError, FoojayDistributionInfo, FoojayDistributionListInfo, FoojayMajorVersion,
+    FoojayPackageInfo, FoojayPackageListInfo,
+};
+use crate::error::ESResult;
+use error_stack::{Report, ResultExt};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::sync::OnceLock;
+use tracing::debug;
+
+static STRICT: OnceLock<bool> = OnceLock::new();
+
+/// Fail on API responses that don't match the recorded schema, instead of tolerating them. For
+/// debugging changes to the Foojay API.
+pub fn configure_strict_api(strict: bool) {
+    let _ = STRICT.set(strict);
+}
+
+fn is_strict() -> bool {
+    STRICT.get().copied().unwrap_or(false)
+}
+
+/// A result type of the Foojay API, with the top-level fields Foojay was recorded sending.
+/// Fields outside this list are new, and are logged so changes to the API get noticed.
+pub(super) trait ApiRecord: DeserializeOwned {
+    const FIELDS: &'static [&'static str];
+}
+
+impl ApiRecord for FoojayDistributionListInfo {
+    const FIELDS: &'static [&'static str] = &[
+        "name",
+        "api_parameter",
+        "maintained",
+        "available",
+        "build_of_openjdk",
+        "build_of_graalvm",
+        "official_uri",
+        "synonyms",
+        "versions",
+    ];
+}
+
+impl ApiRecord for FoojayDistributionInfo {
+    const FIELDS: &'static [&'static str] = FoojayDistributionListInfo::FIELDS;
+}
+
+impl ApiRecord for FoojayMajorVersion {
+    const FIELDS: &'static [&'static str] = &[
+        "major_version",
+        "term_of_support",
+        "maintained",
+        "early_access_only",
+        "release_status",
+        "versions",
+    ];
+}
+
+impl ApiRecord for FoojayPackageListInfo {
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "archive_type",
+        "distribution",
+        "major_version",
+        "java_version",
+        "distribution_version",
+        "jdk_version",
+        "latest_build_available",
+        "release_status",
+        "term_of_support",
+        "operating_system",
+        "lib_c_type",
+        "architecture",
+        "fpu",
+        "package_type",
+        "javafx_bundled",
+        "directly_downloadable",
+        "filename",
+        "links",
+        "free_use_in_production",
+        "tck_tested",
+        "tck_cert_uri",
+        "aqavit_certified",
+        "aqavit_cert_uri",
+        "size",
+        "feature",
+        "release_notes_uri",
+    ];
+}
+
+impl ApiRecord for FoojayPackageInfo {
+    const FIELDS: &'static [&'static str] = &[
+        "filename",
+        "direct_download_uri",
+        "download_site_uri",
+        "signature_uri",
+        "checksum_uri",
+        "checksum",
+        "checksum_type",
+    ];
+}
+
+/// The envelope of every Foojay response.
+#[derive(Debug, Deserialize)]
+pub(super) struct FoojayResult {
+    #[serde(default)]
+    pub message: String,
+    #[serde(default)]
+    result: Vec<serde_json::Value>,
+}
+
+impl FoojayResult {
+    pub fn parse(body: &str) -> ESResult<FoojayResult, FoojayDiscoApiError> {
+        serde_json::from_str(body).change_context(FoojayDiscoApiError::Api)
+    }
+
+    /// The results that could be read as `T`. Results that can't are skipped, unless the API is
+    /// strict.
+    pub fn records<T: ApiRecord>(self) -> ESResult<Vec<T>, FoojayDiscoApiError> {
+        let mut records = Vec::with_capacity(self.result.len());
+        for value in self.result {
+            check_fields::<T>(&value)?;
+            match serde_json::from_value::<T>(value) {
+                Ok(record) => records.push(record),
+                Err(e) if is_strict() => {
+                    return Err(Report::new(e).change_context(FoojayDiscoApiError::Api));
+                }
+                Err(e) => debug!("Skipping unreadable {}: {}", std::any::type_name::<T>(), e),
+            }
+        }
+        Ok(records)
+    }
+}
+
+/// Log fields of `value` that aren't in the recorded schema of `T`, or fail on them if the API
+/// is strict.
+fn check_fields<T: ApiRecord>(value: &serde_json::Value) -> ESResult<(), FoojayDiscoApiError> {
+    let Some(object) = value.as_object() else {
+        return Ok(());
+    };
+    let unknown = object
+        .keys()
+        .filter(|k| !T::FIELDS.contains(&k.as_str()))
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    if unknown.is_empty() {
+        return Ok(());
+    }
+    if is_strict() {
+        return Err(
+            Report::new(FoojayDiscoApiError::Api).attach_printable(format!(
+                "Unknown fields in {}: {}",
+                std::any::type_name::<T>(),
+                unknown.join(", ")
+            )),
+        );
+    }
+    debug!(
+        "Ignoring unknown fields in {}: {}",
+        std::any::type_name::<T>(),
+        unknown.join(", ")
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::foojay::{ArchiveType, ChecksumType, TermOfSupport};
+
+    /// Every result of `fixture` has only recorded fields, and reads as `T`.
+    fn contract<T: ApiRecord>(fixture: &str) -> Vec<T> {
+        let result = FoojayResult::parse(fixture).unwrap();
+        for value in &result.result {
+            let unknown = value
+                .as_object()
+                .unwrap()
+                .keys()
+                .filter(|k| !T::FIELDS.contains(&k.as_str()))
+                .collect::<Vec<_>>();
+            assert!(unknown.is_empty(), "unrecorded fields {:?}", unknown);
+        }
+        let count = result.result.len();
+        let records = result.records::<T>().unwrap();
+        assert_eq!(count, records.len());
+        records
+    }
+
+    #[test]
+    fn test_distributions_contract() {
+        let distributions =
+            contract::<FoojayDistributionListInfo>(include_str!("fixtures/distributions.json"));
+        assert_eq!("Temurin", distributions[0].name);
+        assert_eq!(vec!["adoptium", "temurin"], distributions[0].synonyms);
+    }
+
+    #[test]
+    fn test_distribution_contract() {
+        let distribution =
+            contract::<FoojayDistributionInfo>(include_str!("fixtures/distribution.json"));
+        assert_eq!(3, distribution[0].versions.len());
+    }
+
+    #[test]
+    fn test_major_versions_contract() {
+        let majors = contract::<FoojayMajorVersion>(include_str!("fixtures/major_versions.json"));
+        assert_eq!(23, majors[0].major_version);
+        assert_eq!(TermOfSupport::Lts, majors[2].term_of_support);
+        assert!(!majors[0].early_access_only);
+    }
+
+    #[test]
+    fn test_packages_contract() {
+        let packages = contract::<FoojayPackageListInfo>(include_str!("fixtures/packages.json"));
+        assert!(matches!(packages[0].archive_type, ArchiveType::TarGz));
+        assert_eq!("21.0.2+13", packages[0].java_version.to_string());
+        assert!(packages[0].latest_build_available);
+    }
+
+    #[test]
+    fn test_package_info_contract() {
+        let info = contract::<FoojayPackageInfo>(include_str!("fixtures/package_info.json"));
+        assert!(matches!(info[0].checksum_type, ChecksumType::Sha256));
+        assert_eq!(
+            "OpenJDK21U-jdk_x64_linux_hotspot_21.0.2_13.tar.gz",
+            info[0].filename()
+        );
+        assert!(info[0].checksum_uri().unwrap().ends_with(".sha256.txt"));
+    }
+
+    #[test]
+    fn test_tolerates_changes() {
+        let result = FoojayResult::parse(
+            r#"{"result": [
+                {"major_version": 21, "term_of_support": "LTS", "maintained": true, "new_field": 1},
+                {"major_version": "twenty-two"}
+            ]}"#,
+        )
+        .unwrap();
+        let majors = result.records::<FoojayMajorVersion>().unwrap();
+        assert_eq!(1, majors.len());
+    }
+}
@@ -0,0 +1,336 @@
+use std::ffi::CString;
+use std::io::ErrorKind;
+use std::mem::MaybeUninit;
+use std::os::fd::AsRawFd;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Component, Path, PathBuf};
+use tracing::debug;
+
+/// Bytes available to unprivileged users on the filesystem containing `path`.
+pub fn available_space(path: &Path) -> std::io::Result<u64> {
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `c_path` is a valid C string, and `stat` is only read if the call succeeded.
+    let stat = unsafe {
+        if libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        stat.assume_init()
+    };
+    #[allow(clippy::useless_conversion)] // The field types differ between platforms.
+    Ok(u64::from(stat.f_bavail) * u64::from(stat.f_frsize))
+}
+
+/// Recursively copy `from` to `to`, which must not exist yet. Symlinks are copied as symlinks and
+/// permissions are kept. Files are cloned where the filesystem supports it, see [FileCopier].
+pub fn copy_dir_all(from: &Path, to: &Path) -> std::io::Result<()> {
+    let mut copier = FileCopier::default();
+    copier.copy_dir_all(from, to)?;
+    debug!(
+        "Copied {:?} to {:?}: {} file(s) cloned, {} copied",
+        from, to, copier.cloned, copier.copied
+    );
+    Ok(())
+}
+
+/// Copies files by cloning them on filesystems that can (btrfs, XFS, APFS), so copies share their
+/// data with the original until either changes. Falls back to a regular copy, which still uses
+/// `copy_file_range` on Linux, once cloning turns out to be unsupported.
+#[derive(Debug)]
+struct FileCopier {
+    /// Cleared on the first clone that fails for lack of support, which applies to the rest of
+    /// the files too.
+    try_clone: bool,
+    cloned: u64,
+    copied: u64,
+}
+
+impl Default for FileCopier {
+    fn default() -> Self {
+        Self {
+            try_clone: true,
+            cloned: 0,
+            copied: 0,
+        }
+    }
+}
+
+impl FileCopier {
+    fn copy_dir_all(&mut self, from: &Path, to: &Path) -> std::io::Result<()> {
+        std::fs::create_dir(to)?;
+        std::fs::set_permissions(to, std::fs::metadata(from)?.permissions())?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            let target = to.join(entry.file_name());
+            let file_type = entry.file_type()?;
+            if file_type.is_symlink() {
+                std::os::unix::fs::symlink(std::fs::read_link(entry.path())?, &target)?;
+            } else if file_type.is_dir() {
+                self.copy_dir_all(&entry.path(), &target)?;
+            } else {
+                self.copy_file(&entry.path(), &target)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Copy `from` to `to`, which must not exist yet, keeping its permissions.
+    fn copy_file(&mut self, from: &Path, to: &Path) -> std::io::Result<()> {
+        if self.try_clone {
+            match clone_file(from, to) {
+                Ok(()) => {
+                    self.cloned += 1;
+                    return Ok(());
+                }
+                Err(e) if is_clone_unsupported(&e) => {
+                    debug!("Cloning files isn't supported here, copying instead: {}", e);
+                    self.try_clone = false;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        std::fs::copy(from, to)?;
+        self.copied += 1;
+        Ok(())
+    }
+}
+
+/// Whether a clone failed because the filesystems can't, rather than for the file itself.
+fn is_clone_unsupported(error: &std::io::Error) -> bool {
+    error.kind() == ErrorKind::Unsupported
+        || matches!(
+            error.raw_os_error(),
+            Some(libc::EOPNOTSUPP | libc::ENOTTY | libc::EXDEV | libc::EINVAL | libc::ENOSYS)
+        )
+}
+
+#[cfg(target_os = "linux")]
+fn clone_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    let source = std::fs::File::open(from)?;
+    let target = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(to)?;
+    // SAFETY: both are open file descriptors, FICLONE only reads the source one.
+    if unsafe { libc::ioctl(target.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) } != 0 {
+        let error = std::io::Error::last_os_error();
+        drop(target);
+        let _ = std::fs::remove_file(to);
+        return Err(error);
+    }
+    target.set_permissions(source.metadata()?.permissions())
+}
+
+#[cfg(target_os = "macos")]
+fn clone_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    let from = CString::new(from.as_os_str().as_bytes())?;
+    let to = CString::new(to.as_os_str().as_bytes())?;
+    // SAFETY: both are valid C strings. clonefile keeps the permissions.
+    if unsafe { libc::clonefile(from.as_ptr(), to.as_ptr(), 0) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn clone_file(_from: &Path, _to: &Path) -> std::io::Result<()> {
+    Err(ErrorKind::Unsupported.into())
+}
+
+/// Move a file or directory, falling back to copy and delete when `from` and `to` are on different
+/// filesystems.
+pub fn move_path(from: &Path, to: &Path) -> std::io::Result<()> {
+    match std::fs::rename(from, to) {
+        Err(e) if e.raw_os_error() == Some(libc::EXDEV) => {}
+        result => return result,
+    }
+    let metadata = std::fs::symlink_metadata(from)?;
+    let copied = if metadata.is_dir() {
+        copy_dir_all(from, to)
+    } else {
+        std::fs::copy(from, to).map(|_| ())
+    };
+    if let Err(e) = copied {
+        // Don't leave a partial copy behind, `from` is still complete.
+        let _ = if metadata.is_dir() {
+            std::fs::remove_dir_all(to)
+        } else {
+            std::fs::remove_file(to)
+        };
+        return Err(e);
+    }
+    if metadata.is_dir() {
+        std::fs::remove_dir_all(from)
+    } else {
+        std::fs::remove_file(from)
+    }
+}
+
+/// Whether this user may create or change entries in `path`, or if it doesn't exist yet, in its
+/// nearest existing parent.
+pub fn is_writable(path: &Path) -> bool {
+    let Some(existing) = path.ancestors().find(|p| p.exists()) else {
+        return false;
+    };
+    let Ok(c_path) = CString::new(existing.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // SAFETY: `c_path` is a valid C string.
+    unsafe { libc::access(c_path.as_ptr(), libc::W_OK) == 0 }
+}
+
+/// Take an exclusive advisory lock on the file at `path`, creating it if needed. The lock is held
+/// until the returned file is dropped. Without `wait`, returns `None` if someone else holds it.
+/// Locking doesn't need write access, so a lock file created by another user works too.
+pub fn lock_exclusive(path: &Path, wait: bool) -> std::io::Result<Option<std::fs::File>> {
+    let file = match std::fs::File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+    {
+        Err(e) if e.kind() == ErrorKind::PermissionDenied => std::fs::File::open(path)?,
+        file => file?,
+    };
+    let operation = if wait {
+        libc::LOCK_EX
+    } else {
+        libc::LOCK_EX | libc::LOCK_NB
+    };
+    // SAFETY: `file` is open for as long as the call.
+    if unsafe { libc::flock(file.as_raw_fd(), operation) } != 0 {
+        let error = std::io::Error::last_os_error();
+        if error.kind() == ErrorKind::WouldBlock {
+            return Ok(None);
+        }
+        return Err(error);
+    }
+    Ok(Some(file))
+}
+
+/// `path` with the symlinks in its parents resolved, if that's still inside `root`. The last
+/// component isn't resolved, so renaming or removing the result never touches a symlink's target.
+/// Guards against a shared store being redirected elsewhere by symlinks other users control.
+pub fn resolve_within(root: &Path, path: &Path) -> std::io::Result<PathBuf> {
+    let (Some(parent), Some(Component::Normal(name))) =
+        (path.parent(), path.components().next_back())
+    else {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("{:?} doesn't name an entry of a directory", path),
+        ));
+    };
+    let root = root.canonicalize()?;
+    let parent = parent.canonicalize()?;
+    if !parent.starts_with(&root) {
+        return Err(std::io::Error::new(
+            ErrorKind::PermissionDenied,
+            format!("{:?} leads out of {:?} through a symlink", path, root),
+        ));
+    }
+    Ok(parent.join(name))
+}
+
+/// Remove the directory `path` inside `root` and everything in it, see [resolve_within].
+///
+/// `remove_dir_all` removes a symlink at `path` instead of following it, and never follows the
+/// symlinks below it either. The parents are only checked once though: one swapped for a symlink
+/// after [resolve_within] returned still redirects the removal. jpre only removes and renames
+/// direct entries of its own directories, whose parent is the root itself, and relies on the
+/// store lock and on only trusted users being able to write to the root for the rest.
+pub fn remove_dir_all_within(root: &Path, path: &Path) -> std::io::Result<()> {
+    std::fs::remove_dir_all(resolve_within(root, path)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::Permissions;
+    use std::os::unix::fs::{symlink, PermissionsExt};
+
+    #[test]
+    fn test_remove_dir_all_within() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("store");
+        let outside = dir.path().join("outside");
+        std::fs::create_dir_all(root.join("21/bin")).unwrap();
+        std::fs::create_dir_all(outside.join("21")).unwrap();
+
+        remove_dir_all_within(&root, &root.join("21")).unwrap();
+        assert!(!root.join("21").exists());
+
+        // A symlinked entry is removed, its target is kept.
+        symlink(outside.join("21"), root.join("17")).unwrap();
+        remove_dir_all_within(&root, &root.join("17")).unwrap();
+        assert!(root.join("17").symlink_metadata().is_err());
+        assert!(outside.join("21").exists());
+
+        // Symlinks in the parents must not lead out of the root.
+        symlink(&outside, root.join("nested")).unwrap();
+        let error = remove_dir_all_within(&root, &root.join("nested/21")).unwrap_err();
+        assert_eq!(ErrorKind::PermissionDenied, error.kind());
+        assert!(outside.join("21").exists());
+
+        let error = remove_dir_all_within(&root, &root.join("..")).unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+        assert!(remove_dir_all_within(&root, &root.join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_copy_dir_all() {
+        let dir = tempfile::tempdir().unwrap();
+        let from = dir.path().join("from");
+        std::fs::create_dir_all(from.join("bin")).unwrap();
+        std::fs::write(from.join("bin/java"), "java").unwrap();
+        std::fs::set_permissions(from.join("bin/java"), Permissions::from_mode(0o755)).unwrap();
+        symlink("bin/java", from.join("java")).unwrap();
+
+        // Cloned or copied, depending on the filesystem, the result is the same.
+        let to = dir.path().join("to");
+        copy_dir_all(&from, &to).unwrap();
+        assert_eq!(
+            "java",
+            std::fs::read_to_string(to.join("bin/java")).unwrap()
+        );
+        let mode = std::fs::metadata(to.join("bin/java"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(0o755, mode & 0o777);
+        assert_eq!(
+            Path::new("bin/java"),
+            std::fs::read_link(to.join("java")).unwrap()
+        );
+        // The copy is independent of the original.
+        std::fs::write(to.join("bin/java"), "changed").unwrap();
+        assert_eq!(
+            "java",
+            std::fs::read_to_string(from.join("bin/java")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_lock_exclusive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".lock");
+        let lock = lock_exclusive(&path, false).unwrap();
+        assert!(lock.is_some());
+        assert!(lock_exclusive(&path, false).unwrap().is_none());
+        drop(lock);
+        assert!(lock_exclusive(&path, false).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_resolve_within_follows_root_symlink() {
+        let dir = tempfile::tempdir().unwrap();
+        let real = dir.path().join("real");
+        std::fs::create_dir_all(real.join("21")).unwrap();
+        // A store moved to another disk and linked back is fine.
+        let linked = dir.path().join("linked");
+        symlink(&real, &linked).unwrap();
+        assert_eq!(
+            real.canonicalize().unwrap().join("21"),
+            resolve_within(&linked, &linked.join("21")).unwrap()
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use crate::config::PROJECT_DIRS;
+use crate::error::ESResult;
+use crate::http_fixtures;
+use derive_more::Display;
+use error_stack::{Context, ResultExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+use url::Url;
+
+/// How long a cached response is used without asking the server again.
+const FRESH_FOR: Duration = Duration::from_secs(10 * 60);
+
+static HTTP_CACHE_PATH: LazyLock<PathBuf> = LazyLock::new(|| PROJECT_DIRS.cache_dir().join("http"));
+
+#[derive(Debug, Display)]
+#[display("HTTP request failed")]
+pub struct HttpCacheError;
+
+impl Context for HttpCacheError {}
+
+#[derive(Clone)]
+pub struct CachedResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct CacheEntry {
+    url: String,
+    etag: Option<String>,
+    fetched_at: u64,
+    body: String,
+}
+
+/// GET `url`, re-using a recent cached response or revalidating an older one with its `ETag`.
+/// Only successful responses are cached, and failing to use the cache is never fatal. When
+/// replaying HTTP fixtures, the fixture is used instead of the cache and the network.
+pub fn get_cached(agent: &ureq::Agent, url: &Url) -> ESResult<CachedResponse, HttpCacheError> {
+    if let Some(replayed) = http_fixtures::replay_text(url) {
+        let (status, body) = replayed.change_context(HttpCacheError)?;
+        return Ok(CachedResponse { status, body });
+    }
+    let response = get_from_cache_or_network(agent, url)?;
+    http_fixtures::record_text(url, response.status, &response.body);
+    Ok(response)
+}
+
+/// The cached response for `url`, however old, without asking the server. When replaying HTTP
+/// fixtures, the fixture is used instead.
+pub fn get_cached_only(url: &Url) -> Option<CachedResponse> {
+    if let Some(replayed) = http_fixtures::replay_text(url) {
+        let (status, body) = replayed.ok()?;
+        return Some(CachedResponse { status, body });
+    }
+    read_entry(&entry_path(url), url).map(|entry| CachedResponse {
+        status: 200,
+        body: entry.body,
+    })
+}
+
+fn entry_path(url: &Url) -> PathBuf {
+    HTTP_CACHE_PATH.join(format!(
+        "{}.json",
+        hex::encode(Sha256::digest(url.as_str()))
+    ))
+}
+
+fn read_entry(path: &Path, url: &Url) -> Option<CacheEntry> {
+    std::fs::read(path)
+        .ok()
+        .and_then(|b| serde_json::from_slice::<CacheEntry>(&b).ok())
+        .filter(|e| e.url == url.as_str())
+}
+
+fn get_from_cache_or_network(
+    agent: &ureq::Agent,
+    url: &Url,
+) -> ESResult<CachedResponse, HttpCacheError> {
+    let entry_path = entry_path(url);
+    let entry = read_entry(&entry_path, url);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    if let Some(entry) = &entry {
+        if now.saturating_sub(entry.fetched_at) < FRESH_FOR.as_secs() {
+            debug!("Using cached response for {}", url);
+            return Ok(CachedResponse {
+                status: 200,
+                body: entry.body.clone(),
+            });
+        }
+    }
+
+    let mut request = agent.get(url.as_str());
+    if let Some(etag) = entry.as_ref().and_then(|e| e.etag.as_deref()) {
+        request = request.set("If-None-Match", etag);
+    }
+    let response = request
+        .call()
+        .change_context(HttpCacheError)
+        .attach_printable_lazy(|| format!("URL: {}", url))?;
+    let status = response.status();
+
+    let new_entry = match (status, entry) {
+        (304, Some(entry)) => {
+            debug!("Cached response for {} is still valid", url);
+            CacheEntry {
+                fetched_at: now,
+                ..entry
+            }
+        }
+        (200..=299, _) => CacheEntry {
+            url: url.to_string(),
+            etag: response.header("ETag").map(str::to_string),
+            fetched_at: now,
+            body: response
+                .into_string()
+                .change_context(HttpCacheError)
+                .attach_printable_lazy(|| format!("Could not read response from {}", url))?,
+        },
+        _ => {
+            return Ok(CachedResponse {
+                status,
+                body: response
+                    .into_string()
+                    .change_context(HttpCacheError)
+                    .attach_printable_lazy(|| format!("Could not read response from {}", url))?,
+            })
+        }
+    };
+    if let Err(e) = write_entry(&entry_path, &new_entry) {
+        warn!("Could not cache response for {}: {:?}", url, e);
+    }
+    Ok(CachedResponse {
+        status: 200,
+        body: new_entry.body,
+    })
+}
+
+fn write_entry(path: &Path, entry: &CacheEntry) -> ESResult<(), HttpCacheError> {
+    std::fs::create_dir_all(&*HTTP_CACHE_PATH)
+        .change_context(HttpCacheError)
+        .attach_printable_lazy(|| format!("Could not create {:?}", *HTTP_CACHE_PATH))?;
+    // Write to a temporary file first, concurrent readers should never see a partial entry.
+    let mut temp = tempfile::NamedTempFile::new_in(&*HTTP_CACHE_PATH)
+        .change_context(HttpCacheError)
+        .attach_printable("Could not create temporary cache file")?;
+    serde_json::to_writer(&mut temp, entry)
+        .change_context(HttpCacheError)
+        .attach_printable("Could not serialize cache entry")?;
+    temp.flush()
+        .change_context(HttpCacheError)
+        .attach_printable("Could not write cache entry")?;
+    temp.persist(path)
+        .change_context(HttpCacheError)
+        .attach_printable_lazy(|| format!("Could not move cache entry to {:?}", path))?;
+    Ok(())
+}
@@ -1,8 +1,148 @@
-pub fn new_http_client() -> ureq::Agent {
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::{LazyLock, OnceLock};
+use std::time::{Duration, Instant};
+
+/// How many idle connections to keep per host, enough for concurrent API queries.
+const MAX_IDLE_CONNECTIONS_PER_HOST: usize = 8;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+static TIMEOUTS: OnceLock<HttpTimeouts> = OnceLock::new();
+static PREFER_IPV4: OnceLock<bool> = OnceLock::new();
+
+/// The agent for API calls and other small requests. Clones share the connection pool, so
+/// connections to the same host are reused within a command. ureq only speaks HTTP/1.1, so reuse is
+/// what we can get.
+pub static HTTP_CLIENT: LazyLock<ureq::Agent> = LazyLock::new(|| {
+    let timeouts = timeouts();
+    new_http_client()
+        .timeout_connect(CONNECT_TIMEOUT.min(timeouts.metadata))
+        .timeout(timeouts.metadata)
+        .build()
+});
+
+/// The agent for JDK downloads. These can take long on slow links, so only stalls time out.
+pub static DOWNLOAD_CLIENT: LazyLock<ureq::Agent> = LazyLock::new(|| {
+    let timeouts = timeouts();
+    new_http_client()
+        .timeout_connect(CONNECT_TIMEOUT.min(timeouts.download_read))
+        .timeout_read(timeouts.download_read)
+        .timeout_write(timeouts.download_read)
+        .build()
+});
+
+#[derive(Debug, Clone, Copy)]
+pub struct HttpTimeouts {
+    /// Total time for an API call.
+    pub metadata: Duration,
+    /// Time a download may go without receiving data.
+    pub download_read: Duration,
+}
+
+impl Default for HttpTimeouts {
+    fn default() -> Self {
+        Self {
+            metadata: Duration::from_secs(30),
+            download_read: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Set the timeouts of the agents. Must be called before they're first used, later calls are
+/// ignored.
+pub fn configure_timeouts(timeouts: HttpTimeouts) {
+    if TIMEOUTS.set(timeouts).is_err() {
+        tracing::warn!(
+            "HTTP timeouts were already configured, ignoring {:?}",
+            timeouts
+        );
+    }
+}
+
+fn timeouts() -> HttpTimeouts {
+    *TIMEOUTS.get_or_init(HttpTimeouts::default)
+}
+
+/// Connect over IPv4 before trying IPv6. Must be called before the agents are first used.
+pub fn configure_prefer_ipv4(prefer_ipv4: bool) {
+    if PREFER_IPV4.set(prefer_ipv4).is_err() {
+        tracing::warn!(
+            "IPv4 preference was already configured, ignoring {}",
+            prefer_ipv4
+        );
+    }
+}
+
+fn prefer_ipv4() -> bool {
+    *PREFER_IPV4.get_or_init(|| false)
+}
+
+/// Resolves like the system does, but orders the addresses so a broken address family can't use
+/// up the connect timeout. ureq tries them in order, giving each half of the time that's left.
+struct AddressOrderResolver;
+
+impl ureq::Resolver for AddressOrderResolver {
+    fn resolve(&self, netloc: &str) -> std::io::Result<Vec<SocketAddr>> {
+        let addrs = netloc.to_socket_addrs()?.collect();
+        Ok(order_addresses(addrs, prefer_ipv4()))
+    }
+}
+
+/// With `prefer_ipv4`, all IPv4 addresses go first. Otherwise the families alternate, starting
+/// with the resolver's favourite like Happy Eyeballs does, so a family that can't connect only
+/// costs one attempt.
+fn order_addresses(addrs: Vec<SocketAddr>, prefer_ipv4: bool) -> Vec<SocketAddr> {
+    let (ipv4, ipv6): (Vec<_>, Vec<_>) = addrs.iter().copied().partition(|addr| addr.is_ipv4());
+    if prefer_ipv4 {
+        return ipv4.into_iter().chain(ipv6).collect();
+    }
+    let (mut first, mut second) = if addrs.first().is_some_and(SocketAddr::is_ipv4) {
+        (ipv4.into_iter(), ipv6.into_iter())
+    } else {
+        (ipv6.into_iter(), ipv4.into_iter())
+    };
+    let mut ordered = Vec::with_capacity(addrs.len());
+    loop {
+        match (first.next(), second.next()) {
+            (None, None) => return ordered,
+            (a, b) => ordered.extend(a.into_iter().chain(b)),
+        }
+    }
+}
+
+/// How connecting to a host over one address family went.
+#[derive(Debug)]
+pub enum ConnectProbe {
+    /// The host has no address of this family.
+    NoAddress,
+    Connected(Duration),
+    Failed(std::io::Error),
+}
+
+/// Connect to `host` over IPv4 and IPv6 at the same time, to tell whether one of them is broken.
+pub fn probe_address_families(
+    host: &str,
+    port: u16,
+) -> std::io::Result<(ConnectProbe, ConnectProbe)> {
+    let addrs = (host, port).to_socket_addrs()?.collect::<Vec<_>>();
+    let probe = |ipv4: bool| match addrs.iter().find(|addr| addr.is_ipv4() == ipv4) {
+        None => ConnectProbe::NoAddress,
+        Some(addr) => {
+            let start = Instant::now();
+            match TcpStream::connect_timeout(addr, CONNECT_TIMEOUT) {
+                Ok(_) => ConnectProbe::Connected(start.elapsed()),
+                Err(e) => ConnectProbe::Failed(e),
+            }
+        }
+    };
+    Ok(std::thread::scope(|scope| {
+        let ipv6 = scope.spawn(|| probe(false));
+        (probe(true), ipv6.join().unwrap())
+    }))
+}
+
+fn new_http_client() -> ureq::AgentBuilder {
     ureq::AgentBuilder::new()
-        .timeout_connect(std::time::Duration::from_secs(5))
-        .timeout_read(std::time::Duration::from_secs(30))
-        .timeout_write(std::time::Duration::from_secs(30))
+        .max_idle_connections_per_host(MAX_IDLE_CONNECTIONS_PER_HOST)
         .user_agent(concat!(
             env!("CARGO_PKG_NAME"),
             "/",
@@ -12,5 +152,50 @@ pub fn new_http_client() -> ureq::Agent {
             ")",
         ))
         .https_only(true)
-        .build()
+        .resolver(AddressOrderResolver)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn addrs(addrs: &[&str]) -> Vec<SocketAddr> {
+        addrs.iter().map(|addr| addr.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_order_addresses() {
+        let resolved = addrs(&[
+            "[::1]:443",
+            "[::2]:443",
+            "[::3]:443",
+            "1.0.0.1:443",
+            "1.0.0.2:443",
+        ]);
+        assert_eq!(
+            addrs(&[
+                "[::1]:443",
+                "1.0.0.1:443",
+                "[::2]:443",
+                "1.0.0.2:443",
+                "[::3]:443"
+            ]),
+            order_addresses(resolved.clone(), false)
+        );
+        assert_eq!(
+            addrs(&[
+                "1.0.0.1:443",
+                "1.0.0.2:443",
+                "[::1]:443",
+                "[::2]:443",
+                "[::3]:443"
+            ]),
+            order_addresses(resolved, true)
+        );
+        let resolved = addrs(&["1.0.0.1:443", "1.0.0.2:443", "[::1]:443"]);
+        assert_eq!(
+            addrs(&["1.0.0.1:443", "[::1]:443", "1.0.0.2:443"]),
+            order_addresses(resolved, false)
+        );
+    }
 }
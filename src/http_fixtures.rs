@@ -0,0 +1,126 @@
+use crate::error::ESResult;
+use derive_more::Display;
+use error_stack::{Context, Report, ResultExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use tracing::{debug, warn};
+use url::Url;
+
+#[derive(Debug, Display)]
+#[display("HTTP fixture error")]
+pub struct HttpFixtureError;
+
+impl Context for HttpFixtureError {}
+
+/// Responses are saved to this directory as they come in.
+const RECORD_ENV: &str = "JPRE_RECORD_FIXTURES";
+/// Responses are served from this directory instead of the network. Requests without a fixture
+/// fail, so replays never depend on the network.
+const REPLAY_ENV: &str = "JPRE_REPLAY_FIXTURES";
+
+enum FixtureMode {
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+static MODE: LazyLock<Option<FixtureMode>> = LazyLock::new(|| {
+    let env = |name| std::env::var_os(name).filter(|v| !v.is_empty());
+    if let Some(dir) = env(REPLAY_ENV) {
+        debug!("Replaying HTTP fixtures from {:?}", dir);
+        Some(FixtureMode::Replay(dir.into()))
+    } else {
+        env(RECORD_ENV).map(|dir| {
+            debug!("Recording HTTP fixtures to {:?}", dir);
+            FixtureMode::Record(dir.into())
+        })
+    }
+});
+
+/// A recorded text response.
+#[derive(Debug, Deserialize, Serialize)]
+struct TextFixture {
+    url: String,
+    status: u16,
+    body: String,
+}
+
+/// The fixture files of `url` in `dir`, named after the hash of the URL like the HTTP cache.
+fn fixture_path(dir: &Path, url: &Url, extension: &str) -> PathBuf {
+    dir.join(format!(
+        "{}.{}",
+        hex::encode(Sha256::digest(url.as_str())),
+        extension
+    ))
+}
+
+fn missing_fixture(dir: &Path, url: &Url) -> Report<HttpFixtureError> {
+    Report::new(HttpFixtureError).attach_printable(format!(
+        "No fixture for {} in {:?}, record one with {}",
+        url, dir, RECORD_ENV
+    ))
+}
+
+/// The recorded status and body of `url`, if replaying.
+pub fn replay_text(url: &Url) -> Option<ESResult<(u16, String), HttpFixtureError>> {
+    let Some(FixtureMode::Replay(dir)) = &*MODE else {
+        return None;
+    };
+    let path = fixture_path(dir, url, "json");
+    Some(
+        std::fs::read(&path)
+            .map_err(|_| missing_fixture(dir, url))
+            .and_then(|contents| {
+                serde_json::from_slice::<TextFixture>(&contents)
+                    .change_context(HttpFixtureError)
+                    .attach_printable_lazy(|| format!("Invalid fixture {:?}", path))
+            })
+            .map(|fixture| (fixture.status, fixture.body)),
+    )
+}
+
+/// Save the response of `url`, if recording.
+pub fn record_text(url: &Url, status: u16, body: &str) {
+    let Some(FixtureMode::Record(dir)) = &*MODE else {
+        return;
+    };
+    let fixture = TextFixture {
+        url: url.to_string(),
+        status,
+        body: body.to_string(),
+    };
+    let path = fixture_path(dir, url, "json");
+    let result = std::fs::create_dir_all(dir).and_then(|_| {
+        std::fs::write(
+            &path,
+            serde_json::to_vec_pretty(&fixture).expect("fixtures serialize"),
+        )
+    });
+    if let Err(e) = result {
+        warn!("Could not record fixture for {} to {:?}: {}", url, path, e);
+    }
+}
+
+/// The recorded download of `url`, if replaying.
+pub fn replay_download(url: &Url) -> Option<ESResult<File, HttpFixtureError>> {
+    let Some(FixtureMode::Replay(dir)) = &*MODE else {
+        return None;
+    };
+    Some(File::open(fixture_path(dir, url, "download")).map_err(|_| missing_fixture(dir, url)))
+}
+
+/// Save the download of `url`, already written to `path`, if recording.
+pub fn record_download(url: &Url, path: &Path) {
+    let Some(FixtureMode::Record(dir)) = &*MODE else {
+        return;
+    };
+    let fixture = fixture_path(dir, url, "download");
+    if let Err(e) = std::fs::create_dir_all(dir).and_then(|_| std::fs::copy(path, &fixture)) {
+        warn!(
+            "Could not record fixture for {} to {:?}: {}",
+            url, fixture, e
+        );
+    }
+}
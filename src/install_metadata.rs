@@ -0,0 +1,151 @@
+use crate::error::ESResult;
+use crate::java_version::JavaVersion;
+use crate::tui::{format_bytes, format_duration};
+use derive_more::Display;
+use error_stack::{Context, ResultExt};
+use serde::{Deserialize, Serialize};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Debug, Display)]
+pub struct InstallMetadataError;
+
+impl Context for InstallMetadataError {}
+
+const INSTALL_METADATA_FILE_NAME: &str = ".jpre_install.json";
+
+/// What jpre recorded about installing a JDK, stored next to the JDK's marker file. This is also
+/// the provenance record of the JDK, see `jpre sbom`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct InstallMetadata {
+    pub distribution: String,
+    pub java_version: JavaVersion,
+    pub download_uri: String,
+    /// The SHA-256 of the archive, in hex, as verified during the download.
+    #[serde(default)]
+    pub sha256: String,
+    /// Seconds since the Unix epoch.
+    pub installed_at: u64,
+    pub archive_size: u64,
+    /// Whether the archive came from the download cache instead of the network.
+    pub from_download_cache: bool,
+    pub download_secs: f64,
+    pub extraction_secs: f64,
+    pub installed_size: u64,
+}
+
+impl InstallMetadata {
+    /// Read the metadata of the JDK at `jdk_home`. Returns `None` if it was installed by a jpre
+    /// that didn't record any.
+    pub fn read(jdk_home: &Path) -> ESResult<Option<InstallMetadata>, InstallMetadataError> {
+        let path = jdk_home.join(INSTALL_METADATA_FILE_NAME);
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e)
+                    .change_context(InstallMetadataError)
+                    .attach_printable_lazy(|| {
+                        format!("Could not read install metadata at {:?}", path)
+                    })
+            }
+        };
+        serde_json::from_str(&contents)
+            .map(Some)
+            .change_context(InstallMetadataError)
+            .attach_printable_lazy(|| format!("Could not parse install metadata at {:?}", path))
+    }
+
+    pub fn write(&self, jdk_home: &Path) -> ESResult<(), InstallMetadataError> {
+        let path = jdk_home.join(INSTALL_METADATA_FILE_NAME);
+        let temp = tempfile::NamedTempFile::new_in(jdk_home)
+            .change_context(InstallMetadataError)
+            .attach_printable_lazy(|| {
+                format!(
+                    "Could not create temporary file for install metadata in {:?}",
+                    jdk_home
+                )
+            })?;
+        // Temporary files are private, but other users of a system store need to read this.
+        temp.as_file()
+            .set_permissions(std::fs::Permissions::from_mode(0o644))
+            .change_context(InstallMetadataError)
+            .attach_printable_lazy(|| format!("Could not set permissions of {:?}", temp.path()))?;
+        serde_json::to_writer_pretty(temp.as_file(), self)
+            .change_context(InstallMetadataError)
+            .attach_printable_lazy(|| {
+                format!("Could not write install metadata to {:?}", temp.path())
+            })?;
+        temp.persist(&path)
+            .change_context(InstallMetadataError)
+            .attach_printable_lazy(|| format!("Could not move install metadata to {:?}", path))?;
+        Ok(())
+    }
+
+    /// Average download speed in bytes per second, if the archive was actually downloaded.
+    pub fn download_speed(&self) -> Option<u64> {
+        (!self.from_download_cache && self.download_secs > 0.0)
+            .then(|| (self.archive_size as f64 / self.download_secs) as u64)
+    }
+
+    /// A one-line summary of the download and install.
+    pub fn summary(&self) -> String {
+        let download = match self.download_speed() {
+            Some(speed) => format!(
+                "downloaded in {} ({}/s)",
+                format_duration(Duration::from_secs_f64(self.download_secs)),
+                format_bytes(speed)
+            ),
+            None => "from download cache".to_string(),
+        };
+        format!(
+            "archive {} {}, extracted in {}, {} on disk",
+            format_bytes(self.archive_size),
+            download,
+            format_duration(Duration::from_secs_f64(self.extraction_secs)),
+            format_bytes(self.installed_size)
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    fn metadata(from_download_cache: bool) -> InstallMetadata {
+        InstallMetadata {
+            distribution: "temurin".to_string(),
+            java_version: JavaVersion::from_str("21.0.4+7").unwrap(),
+            download_uri: "https://example.com/jdk.tar.gz".to_string(),
+            sha256: "ab".repeat(32),
+            installed_at: 0,
+            archive_size: 200 * 1024 * 1024,
+            from_download_cache,
+            download_secs: 4.0,
+            extraction_secs: 1.5,
+            installed_size: 300 * 1024 * 1024,
+        }
+    }
+
+    #[test]
+    fn test_summary() {
+        assert_eq!(
+            "archive 200.0 MiB downloaded in 4.0s (50.0 MiB/s), extracted in 1.5s, 300.0 MiB on disk",
+            metadata(false).summary()
+        );
+        assert_eq!(
+            "archive 200.0 MiB from download cache, extracted in 1.5s, 300.0 MiB on disk",
+            metadata(true).summary()
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let json = serde_json::to_string(&metadata(false)).unwrap();
+        let read: InstallMetadata = serde_json::from_str(&json).unwrap();
+        assert_eq!("21.0.4+7", read.java_version.to_string());
+        assert_eq!(Some(50 * 1024 * 1024), read.download_speed());
+    }
+}
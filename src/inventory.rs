@@ -0,0 +1,95 @@
+use crate::context_id::base_state_dir;
+use crate::error::ESResult;
+use crate::java_version::key::VersionKey;
+use crate::java_version::JavaVersion;
+use crate::jdk_manager::{jdk_store_path, store, JdkManager, JdkStore};
+use crate::json_output::SchemaVersion;
+use derive_more::Display;
+use error_stack::{Context, ResultExt};
+use serde::Serialize;
+use std::path::PathBuf;
+
+#[derive(Debug, Display)]
+#[display("Inventory error")]
+pub struct InventoryError;
+
+impl Context for InventoryError {}
+
+const INVENTORY_FILE_NAME: &str = "inventory.json";
+/// The inventory of the system store, kept apart so `--system` doesn't overwrite the user's.
+const SYSTEM_INVENTORY_FILE_NAME: &str = "inventory-system.json";
+
+/// The installed JDKs, for tools that watch this file instead of scanning the store. It's replaced
+/// whole whenever a JDK is installed, updated or removed, so it's never seen half-written.
+#[derive(Debug, Serialize)]
+pub struct Inventory {
+    schema_version: SchemaVersion,
+    store: PathBuf,
+    jdks: Vec<InventoryEntry>,
+}
+
+#[derive(Debug, Serialize)]
+struct InventoryEntry {
+    key: VersionKey,
+    /// Unknown for JDKs installed by old jpre versions.
+    version: Option<JavaVersion>,
+    path: PathBuf,
+    /// Unknown for JDKs that weren't downloaded by jpre.
+    distribution: Option<String>,
+}
+
+/// The inventory of the store in use.
+pub fn inventory_path() -> PathBuf {
+    base_state_dir().join(match store() {
+        JdkStore::User => INVENTORY_FILE_NAME,
+        JdkStore::System => SYSTEM_INVENTORY_FILE_NAME,
+    })
+}
+
+impl Inventory {
+    pub fn collect(manager: &JdkManager) -> ESResult<Inventory, InventoryError> {
+        let mut installed = manager
+            .get_installed_jdks()
+            .change_context(InventoryError)
+            .attach_printable("Could not list installed JDKs")?;
+        installed.sort();
+        let jdks = installed
+            .into_iter()
+            .map(|key| InventoryEntry {
+                version: manager.get_full_version(&key).ok().flatten(),
+                path: manager.get_jdk_install_path(&key),
+                distribution: manager
+                    .get_install_metadata(&key)
+                    .ok()
+                    .flatten()
+                    .map(|metadata| metadata.distribution),
+                key,
+            })
+            .collect();
+        Ok(Inventory {
+            schema_version: SchemaVersion,
+            store: jdk_store_path().to_path_buf(),
+            jdks,
+        })
+    }
+
+    pub fn write(&self) -> ESResult<(), InventoryError> {
+        let path = inventory_path();
+        let dir = path.parent().unwrap();
+        std::fs::create_dir_all(dir)
+            .change_context(InventoryError)
+            .attach_printable_lazy(|| format!("Could not create {:?}", dir))?;
+        let temp = tempfile::NamedTempFile::new_in(dir)
+            .change_context(InventoryError)
+            .attach_printable_lazy(|| {
+                format!("Could not create temporary file for inventory in {:?}", dir)
+            })?;
+        serde_json::to_writer_pretty(std::io::BufWriter::new(temp.as_file()), self)
+            .change_context(InventoryError)
+            .attach_printable_lazy(|| format!("Could not write inventory to {:?}", temp.path()))?;
+        temp.persist(&path)
+            .change_context(InventoryError)
+            .attach_printable_lazy(|| format!("Could not move inventory to {:?}", path))?;
+        Ok(())
+    }
+}
@@ -1,13 +1,45 @@
 use crate::command::Context;
-use crate::context_id::get_context_path;
+use crate::config::JpreConfig;
+use crate::context_id::{base_state_dir, get_context_path};
 use crate::error::{ESResult, JpreError};
+use crate::fs_util::lock_exclusive;
 use crate::java_version::key::VersionKey;
-use crate::jdk_manager::JDK_MANAGER;
+use crate::jdk_manager::{install_error, jdk_store_path, JDK_MANAGER};
 use error_stack::ResultExt;
+use std::fs::File;
+use std::path::{Path, PathBuf};
 use tracing::debug;
 
-pub fn clear_context_path() -> ESResult<(), JpreError> {
-    let path = get_context_path();
+/// The lock file in the context links directory, see [lock_context_links].
+const CONTEXT_LINKS_LOCK_FILE_NAME: &str = ".lock";
+
+/// `JAVA_HOME` in the environment, when it's set to something jpre doesn't manage. The shell then
+/// keeps using that JDK whatever `use` links. A JDK in the store, as `direnv` exports, or another
+/// context's link, like a temporary context's, was set by jpre on purpose.
+pub fn conflicting_java_home(config: &JpreConfig) -> Option<PathBuf> {
+    let java_home = PathBuf::from(std::env::var_os("JAVA_HOME").filter(|v| !v.is_empty())?);
+    let managed = java_home == get_context_path(config)
+        || java_home.starts_with(jdk_store_path())
+        || java_home.starts_with(base_state_dir());
+    (!managed).then_some(java_home)
+}
+
+/// What to tell the user about a `JAVA_HOME` found by [conflicting_java_home].
+pub fn java_home_conflict_message(config: &JpreConfig, java_home: &Path) -> String {
+    format!(
+        "JAVA_HOME is set to {}, not jpre's {}, so this shell keeps using that JDK. Remove it \
+         from your shell's startup files and set `JAVA_HOME=\"$(jpre java-home)\"` instead",
+        java_home.display(),
+        get_context_path(config).display()
+    )
+}
+
+pub fn clear_context_path(config: &JpreConfig) -> ESResult<(), JpreError> {
+    let path = get_context_path(config);
+    let _lock = match path.parent().map(Path::exists) {
+        Some(true) => Some(lock_context_links(&path)?),
+        _ => None,
+    };
     debug!("Removing Java home path file '{:?}'", path);
     match std::fs::remove_file(&path) {
         Ok(_) => Ok(()),
@@ -21,13 +53,14 @@ pub fn clear_context_path() -> ESResult<(), JpreError> {
 pub fn set_context_path_to_java_home(
     context: &Context,
     jdk: &VersionKey,
+    distribution: Option<&str>,
 ) -> ESResult<(), JpreError> {
     debug!("Setting Java home path to JDK '{}'", jdk);
     let jdk = JDK_MANAGER
-        .get_jdk_path(&context.config, jdk)
-        .change_context(JpreError::Unexpected)
+        .get_jdk_path(&context.config, &context.platform, jdk, distribution)
+        .map_err(install_error)
         .attach_printable_lazy(|| format!("Failed to get path for JDK {}", jdk))?;
-    let path = get_context_path();
+    let path = get_context_path(&context.config);
     let parent = path.parent().unwrap();
     debug!("Creating directories to '{}'", parent.display());
     std::fs::create_dir_all(parent)
@@ -35,13 +68,25 @@ pub fn set_context_path_to_java_home(
         .attach_printable_lazy(|| {
             format!("Failed to create directories to {}", parent.display())
         })?;
-    clear_context_path()?;
+    // Prompts in split panes can share a context and run this at the same time.
+    let _lock = lock_context_links(&path)?;
+    if std::fs::read_link(&path).is_ok_and(|target| target == jdk) {
+        debug!("'{}' already links to '{}'", path.display(), jdk.display());
+        return Ok(());
+    }
     debug!(
         "Creating symlink from '{}' to '{}'",
         jdk.display(),
         path.display()
     );
-    std::os::unix::fs::symlink(&jdk, &path)
+    // Link next to the final path and rename it over, so the context never has no link.
+    let temp = sibling_path(&path, &format!("tmp{}", std::process::id()));
+    let _ = std::fs::remove_file(&temp);
+    std::os::unix::fs::symlink(&jdk, &temp)
+        .and_then(|()| std::fs::rename(&temp, &path))
+        .inspect_err(|_| {
+            let _ = std::fs::remove_file(&temp);
+        })
         .change_context(JpreError::Unexpected)
         .attach_printable_lazy(|| {
             format!(
@@ -53,3 +98,33 @@ pub fn set_context_path_to_java_home(
 
     Ok(())
 }
+
+/// A hidden file next to the context link `path`, e.g. `.<context id>.tmp<pid>` for the new link
+/// that replaces it.
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let name = path.file_name().expect("context paths have a file name");
+    path.with_file_name(format!(".{}.{}", name.to_string_lossy(), suffix))
+}
+
+/// Take the advisory lock shared by the context links next to `path`, released when the file is
+/// dropped. One lock for the whole directory, so no lock file is left behind per context.
+fn lock_context_links(path: &Path) -> ESResult<File, JpreError> {
+    let lock_path = path.with_file_name(CONTEXT_LINKS_LOCK_FILE_NAME);
+    lock_exclusive(&lock_path, true)
+        .map(|file| file.expect("waited for the lock"))
+        .change_context(JpreError::Unexpected)
+        .attach_printable_lazy(|| format!("Failed to lock {:?}", lock_path))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sibling_path() {
+        assert_eq!(
+            Path::new("/state/java-home-by-pid/.1234.tmp5678"),
+            sibling_path(Path::new("/state/java-home-by-pid/1234"), "tmp5678")
+        );
+    }
+}
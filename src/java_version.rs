@@ -1,11 +1,17 @@
 pub mod key;
+pub mod range;
+mod vendor;
 
 use crate::error::ESResult;
 use crate::java_version::key::VersionKey;
 use crate::string::SplittingExt;
 use derive_more::Display;
 use error_stack::{Context, Report, ResultExt};
-use serde::Deserialize;
+use itertools::Itertools;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Metadata, Schema, SchemaObject};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::str::{FromStr, Split};
 
@@ -45,9 +51,125 @@ pub struct NewScheme {
 }
 
 impl JavaVersion {
+    /// The major version, i.e. what a [VersionKey] is based on.
+    pub fn major(&self) -> u32 {
+        match self {
+            JavaVersion::OldScheme(OldScheme { minor, .. }) => *minor,
+            JavaVersion::NewScheme(NewScheme { feature, .. }) => *feature,
+        }
+    }
+
+    pub fn update(&self) -> u32 {
+        match self {
+            JavaVersion::OldScheme(OldScheme { update, .. })
+            | JavaVersion::NewScheme(NewScheme { update, .. }) => *update,
+        }
+    }
+
+    pub fn build(&self) -> Option<u32> {
+        match self {
+            JavaVersion::OldScheme(OldScheme { build, .. })
+            | JavaVersion::NewScheme(NewScheme { build, .. }) => *build,
+        }
+    }
+
+    pub fn is_pre_release(&self) -> bool {
+        match self {
+            JavaVersion::OldScheme(_) => false,
+            JavaVersion::NewScheme(NewScheme { pre_release, .. }) => {
+                *pre_release != PreRelease::None
+            }
+        }
+    }
+
     /// Compare two Java versions. Certain [PartialEq::ne] elements may be [Ordering::Equal].
     /// For example, [Self::NewScheme] `opt` information is not considered in the comparison.
     pub fn compare(&self, other: &Self) -> Ordering {
+        self.compare_with(other, OptComparison::Ignore)
+    }
+
+    /// Compare two Java versions, deciding how to treat `opt` with `opt_comparison`.
+    pub fn compare_with(&self, other: &Self, opt_comparison: OptComparison) -> Ordering {
+        let ordering = self.compare_ignoring_opt(other);
+        match (opt_comparison, self, other) {
+            (OptComparison::Natural, JavaVersion::NewScheme(a), JavaVersion::NewScheme(b)) => {
+                ordering.then_with(|| compare_opt_naturally(&a.opt, &b.opt))
+            }
+            _ => ordering,
+        }
+    }
+
+    /// The most significant part of the version that differs from `other`, if any.
+    pub fn changed_component(&self, other: &Self) -> Option<VersionComponent> {
+        use VersionComponent::*;
+        let changes = match (self, other) {
+            (JavaVersion::OldScheme(a), JavaVersion::OldScheme(b)) => vec![
+                (a.minor != b.minor, Feature),
+                (a.patch != b.patch, Patch),
+                (a.update != b.update, Update),
+                (a.build != b.build, Build),
+            ],
+            (JavaVersion::NewScheme(a), JavaVersion::NewScheme(b)) => vec![
+                (a.feature != b.feature, Feature),
+                (a.interim != b.interim, Interim),
+                (a.update != b.update, Update),
+                (a.patch != b.patch, Patch),
+                (a.trailing != b.trailing, Trailing),
+                (a.pre_release != b.pre_release, VersionComponent::PreRelease),
+                (a.build != b.build, Build),
+                (a.opt != b.opt, Opt),
+            ],
+            _ => vec![(true, Feature)],
+        };
+        changes
+            .into_iter()
+            .find(|(changed, _)| *changed)
+            .map(|(_, component)| component)
+    }
+
+    /// Each part of the version and its value, `-` if it's missing. Old scheme versions only
+    /// have some of the parts.
+    pub fn components(&self) -> Vec<(VersionComponent, String)> {
+        fn or_dash(value: Option<String>) -> String {
+            value.unwrap_or_else(|| "-".to_string())
+        }
+        match self {
+            JavaVersion::OldScheme(v) => vec![
+                (VersionComponent::Feature, v.minor.to_string()),
+                (VersionComponent::Patch, v.patch.to_string()),
+                (VersionComponent::Update, v.update.to_string()),
+                (
+                    VersionComponent::Build,
+                    or_dash(v.build.map(|b| b.to_string())),
+                ),
+            ],
+            JavaVersion::NewScheme(v) => vec![
+                (VersionComponent::Feature, v.feature.to_string()),
+                (VersionComponent::Interim, v.interim.to_string()),
+                (VersionComponent::Update, v.update.to_string()),
+                (VersionComponent::Patch, v.patch.to_string()),
+                (
+                    VersionComponent::Trailing,
+                    or_dash((!v.trailing.is_empty()).then(|| v.trailing.iter().join("."))),
+                ),
+                (
+                    VersionComponent::PreRelease,
+                    or_dash(match &v.pre_release {
+                        PreRelease::None => None,
+                        PreRelease::Numeric(n) => Some(n.to_string()),
+                        PreRelease::Other(s) => Some(s.clone()),
+                    }),
+                ),
+                (
+                    VersionComponent::Build,
+                    or_dash(v.build.map(|b| b.to_string())),
+                ),
+                (VersionComponent::Opt, or_dash(v.opt.clone())),
+            ],
+        }
+    }
+
+    fn compare_ignoring_opt(&self, other: &Self) -> Ordering {
         match (self, other) {
             // Old scheme vs old scheme
             (
@@ -150,6 +272,109 @@ impl JavaVersion {
     }
 }
 
+/// A part of a [JavaVersion], see [JavaVersion::changed_component].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Display)]
+pub enum VersionComponent {
+    #[display("feature")]
+    Feature,
+    #[display("interim")]
+    Interim,
+    #[display("update")]
+    Update,
+    #[display("patch")]
+    Patch,
+    #[display("trailing")]
+    Trailing,
+    #[display("pre-release")]
+    PreRelease,
+    #[display("build")]
+    Build,
+    #[display("opt")]
+    Opt,
+}
+
+/// Split the string form of `new` into segments (`17`, `.0`, `.10`, `+7`), each marked as changed
+/// if it differs from the segment at the same position in `old`.
+pub fn diff_segments(old: &JavaVersion, new: &JavaVersion) -> Vec<(String, bool)> {
+    fn segments(s: &str) -> Vec<&str> {
+        let mut result = Vec::new();
+        let mut start = 0;
+        for (i, c) in s.char_indices().skip(1) {
+            if matches!(c, '.' | '_' | '+' | '-') {
+                result.push(&s[start..i]);
+                start = i;
+            }
+        }
+        result.push(&s[start..]);
+        result
+    }
+    let old = old.to_string();
+    let new = new.to_string();
+    let old_segments = segments(&old);
+    segments(&new)
+        .into_iter()
+        .enumerate()
+        .map(|(i, segment)| {
+            (
+                segment.to_string(),
+                old_segments.get(i).is_none_or(|old| *old != segment),
+            )
+        })
+        .collect()
+}
+
+/// How [JavaVersion::compare_with] treats the `opt` part of a version.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum OptComparison {
+    /// `opt` has no defined ordering, so it's ignored. Right for most builds.
+    Ignore,
+    /// Compare `opt` naturally, i.e. runs of digits as numbers. Special builds such as CRaC put
+    /// their own build number in it.
+    Natural,
+}
+
+/// Compare `opt` values, with runs of digits compared as numbers so `crac.10` > `crac.9`.
+fn compare_opt_naturally(a: &Option<String>, b: &Option<String>) -> Ordering {
+    fn chunks(s: &str) -> Vec<Result<u64, &str>> {
+        let mut result = Vec::new();
+        let mut rest = s;
+        while let Some(first) = rest.chars().next() {
+            let is_digit = first.is_ascii_digit();
+            let end = rest
+                .find(|c: char| c.is_ascii_digit() != is_digit)
+                .unwrap_or(rest.len());
+            let (chunk, tail) = rest.split_at(end);
+            result.push(match chunk.parse::<u64>() {
+                Ok(n) if is_digit => Ok(n),
+                _ => Err(chunk),
+            });
+            rest = tail;
+        }
+        result
+    }
+    match (a, b) {
+        (Some(a), Some(b)) => chunks(a).cmp(&chunks(b)),
+        _ => a.is_some().cmp(&b.is_some()),
+    }
+}
+
+/// Orders like [JavaVersion::compare], but falls back to comparing `opt` so that the ordering
+/// agrees with [Eq]. Use [JavaVersion::compare] to check if something is an update.
+impl Ord for JavaVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.compare(other).then_with(|| match (self, other) {
+            (JavaVersion::NewScheme(a), JavaVersion::NewScheme(b)) => a.opt.cmp(&b.opt),
+            _ => Ordering::Equal,
+        })
+    }
+}
+
+impl PartialOrd for JavaVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl Display for JavaVersion {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -398,6 +623,33 @@ impl<'de> Deserialize<'de> for JavaVersion {
     }
 }
 
+impl Serialize for JavaVersion {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl JsonSchema for JavaVersion {
+    fn schema_name() -> String {
+        "JavaVersion".to_string()
+    }
+
+    fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            metadata: Some(Box::new(Metadata {
+                description: Some("A full Java version, e.g. `21.0.2+13`.".to_string()),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
 impl From<JavaVersion> for VersionKey {
     fn from(value: JavaVersion) -> Self {
         match value {
@@ -443,6 +695,61 @@ impl FromStr for PreRelease {
 mod test {
     use super::*;
 
+    #[test]
+    fn test_changed_component_and_diff_segments() {
+        let v = |s: &str| s.parse::<JavaVersion>().unwrap();
+        assert_eq!(
+            Some(VersionComponent::Update),
+            v("17.0.10+7").changed_component(&v("17.0.9+9"))
+        );
+        assert_eq!(
+            Some(VersionComponent::Build),
+            v("21.0.4+8").changed_component(&v("21.0.4+7"))
+        );
+        assert_eq!(None, v("21.0.4+7").changed_component(&v("21.0.4+7")));
+        assert_eq!(
+            Some(VersionComponent::Update),
+            v("1.8.0_412-b08").changed_component(&v("1.8.0_402-b06"))
+        );
+        let changed = |old: &str, new: &str| {
+            diff_segments(&v(old), &v(new))
+                .into_iter()
+                .map(|(s, changed)| if changed { format!("[{}]", s) } else { s })
+                .collect::<String>()
+        };
+        assert_eq!("17.0[.10][+7]", changed("17.0.9+9", "17.0.10+7"));
+        assert_eq!("21.0.4[+8]", changed("21.0.4+7", "21.0.4+8"));
+        assert_eq!("21[.0][.1][+12]", changed("21+35", "21.0.1+12"));
+        assert_eq!(
+            "1.8.0[_412][-b08]",
+            changed("1.8.0_402-b06", "1.8.0_412-b08")
+        );
+    }
+
+    #[test]
+    fn test_components() {
+        let components = |s: &str| {
+            s.parse::<JavaVersion>()
+                .unwrap()
+                .components()
+                .into_iter()
+                .map(|(c, v)| format!("{}={}", c, v))
+                .join(" ")
+        };
+        assert_eq!(
+            "feature=21 interim=0 update=4 patch=0 trailing=- pre-release=- build=7 opt=crac",
+            components("21.0.4+7-crac")
+        );
+        assert_eq!(
+            "feature=23 interim=0 update=0 patch=0 trailing=- pre-release=ea build=12 opt=-",
+            components("23-ea+12")
+        );
+        assert_eq!(
+            "feature=8 patch=0 update=402 build=6",
+            components("1.8.0_402-b06")
+        );
+    }
+
     fn assert_round_trip(v: &str, expected: JavaVersion) {
         let jv: JavaVersion = v.parse().unwrap();
         assert_eq!(expected, jv);
@@ -631,4 +938,51 @@ mod test {
         assert_compare_both_ways("9-ea", "9-ea+1", Ordering::Less);
         assert_compare_both_ways("9", "10", Ordering::Less);
     }
+
+    #[test]
+    fn test_ord() {
+        let parse = |v: &str| v.parse::<JavaVersion>().unwrap();
+        assert_eq!(Ordering::Equal, parse("17+-a").compare(&parse("17+-b")));
+        assert!(parse("17+-a") < parse("17+-b"));
+        assert_eq!(parse("17+-a").cmp(&parse("17+-a")), Ordering::Equal);
+
+        let mut versions = [
+            parse("17.0.1"),
+            parse("1.8.0_292"),
+            parse("17-ea"),
+            parse("11"),
+        ];
+        versions.sort();
+        assert_eq!(
+            vec!["1.8.0_292", "11", "17-ea", "17.0.1"],
+            versions.iter().map(|v| v.to_string()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_compare_with_opt() {
+        let parse = |v: &str| v.parse::<JavaVersion>().unwrap();
+        let a = parse("21.0.2+14-crac.9");
+        let b = parse("21.0.2+14-crac.10");
+        assert_eq!(Ordering::Equal, a.compare(&b));
+        assert_eq!(Ordering::Less, a.compare_with(&b, OptComparison::Natural));
+        assert_eq!(
+            Ordering::Greater,
+            parse("21.0.2+14-crac").compare_with(&parse("21.0.2+14"), OptComparison::Natural)
+        );
+        assert_eq!(
+            Ordering::Less,
+            parse("21.0.1+5-crac.20").compare_with(&b, OptComparison::Natural)
+        );
+    }
+
+    #[test]
+    fn test_accessors() {
+        let parse = |v: &str| v.parse::<JavaVersion>().unwrap();
+        assert_eq!(8, parse("1.8.0_292-b10").major());
+        assert_eq!(21, parse("21.0.2+13").major());
+        assert!(parse("22-ea+5").is_pre_release());
+        assert!(!parse("21.0.2+13").is_pre_release());
+        assert!(!parse("1.8.0").is_pre_release());
+    }
 }
@@ -1,6 +1,9 @@
 use crate::java_version::PreRelease;
 use crate::string::SplittingExt;
 use derive_more::Display;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Metadata, Schema, SchemaObject};
+use schemars::JsonSchema;
 use serde::{Deserialize, Deserializer, Serialize};
 use std::num::ParseIntError;
 use std::str::FromStr;
@@ -19,6 +22,38 @@ pub struct VersionKey {
     pub pre_release: PreRelease,
 }
 
+impl VersionKey {
+    /// The Foojay `release_status` of this key. Only `ea` is a release status, other named
+    /// pre-release tags select special builds (see [Self::feature]), which are released as GA.
+    pub fn release_status(&self) -> String {
+        match &self.pre_release {
+            PreRelease::None => "ga".to_string(),
+            PreRelease::Numeric(v) => v.to_string(),
+            PreRelease::Other(v) if v == "ea" => v.clone(),
+            PreRelease::Other(_) => "ga".to_string(),
+        }
+    }
+
+    /// Whether this is a long-term support release. Only GA builds count; special builds such as
+    /// `21-crac` have their own support schedules.
+    pub fn is_lts(&self) -> bool {
+        self.pre_release == PreRelease::None && is_lts_major(self.major)
+    }
+
+    /// The Foojay `feature` of this key, e.g. `crac` for `21-crac`.
+    pub fn feature(&self) -> Option<&str> {
+        match &self.pre_release {
+            PreRelease::Other(v) if v != "ea" => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// 8 and 11 were LTS, and since 17 every fourth (two-yearly) release is.
+fn is_lts_major(major: u32) -> bool {
+    major == 8 || major == 11 || (major >= 17 && (major - 17).is_multiple_of(4))
+}
+
 #[derive(Debug, Error)]
 pub enum VersionKeyParseError {
     #[error("Failed to parse major version number: {input}")]
@@ -58,6 +93,24 @@ impl Serialize for VersionKey {
     }
 }
 
+impl JsonSchema for VersionKey {
+    fn schema_name() -> String {
+        "VersionKey".to_string()
+    }
+
+    fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            metadata: Some(Box::new(Metadata {
+                description: Some("A version key, e.g. `21` or `24-ea`.".to_string()),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
 impl<'de> Deserialize<'de> for VersionKey {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -67,3 +120,32 @@ impl<'de> Deserialize<'de> for VersionKey {
         VersionKey::from_str(&s).map_err(serde::de::Error::custom)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_release_status_and_feature() {
+        let key = |s: &str| VersionKey::from_str(s).unwrap();
+        assert_eq!("ga", key("21").release_status());
+        assert_eq!(None, key("21").feature());
+        assert_eq!("ea", key("23-ea").release_status());
+        assert_eq!(None, key("23-ea").feature());
+        assert_eq!("ga", key("21-crac").release_status());
+        assert_eq!(Some("crac"), key("21-crac").feature());
+    }
+
+    #[test]
+    fn test_is_lts() {
+        let key = |s: &str| VersionKey::from_str(s).unwrap();
+        let lts = [8, 11, 17, 21, 25, 29]
+            .into_iter()
+            .map(|m| key(&m.to_string()))
+            .collect::<Vec<_>>();
+        assert!(lts.iter().all(VersionKey::is_lts));
+        for non_lts in ["7", "9", "16", "22", "23", "21-ea", "21-crac"] {
+            assert!(!key(non_lts).is_lts(), "{} should not be LTS", non_lts);
+        }
+    }
+}
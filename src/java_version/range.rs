@@ -0,0 +1,217 @@
+use crate::java_version::key::{VersionKey, VersionKeyParseError};
+use crate::java_version::PreRelease;
+use derive_more::Display;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+use std::num::ParseIntError;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// A range of GA major versions, like `>=17, <22` or `17..21`. Both ends of `..` are included,
+/// and either can be left out.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct VersionRange {
+    bounds: Vec<Bound>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Bound {
+    Eq(u32),
+    Gt(u32),
+    Ge(u32),
+    Lt(u32),
+    Le(u32),
+}
+
+impl Bound {
+    fn allows(self, major: u32) -> bool {
+        match self {
+            Bound::Eq(v) => major == v,
+            Bound::Gt(v) => major > v,
+            Bound::Ge(v) => major >= v,
+            Bound::Lt(v) => major < v,
+            Bound::Le(v) => major <= v,
+        }
+    }
+}
+
+impl fmt::Display for Bound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Bound::Eq(v) => write!(f, "={}", v),
+            Bound::Gt(v) => write!(f, ">{}", v),
+            Bound::Ge(v) => write!(f, ">={}", v),
+            Bound::Lt(v) => write!(f, "<{}", v),
+            Bound::Le(v) => write!(f, "<={}", v),
+        }
+    }
+}
+
+impl VersionRange {
+    /// Whether `key` is a GA key in the range. Early access and special builds never are, those
+    /// need an exact version key.
+    pub fn contains(&self, key: &VersionKey) -> bool {
+        key.pre_release == PreRelease::None && self.bounds.iter().all(|b| b.allows(key.major))
+    }
+
+    /// The newest of `keys` in the range.
+    pub fn best<'a>(&self, keys: impl IntoIterator<Item = &'a VersionKey>) -> Option<VersionKey> {
+        keys.into_iter().filter(|k| self.contains(k)).max().cloned()
+    }
+}
+
+impl fmt::Display for VersionRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bounds = self.bounds.iter().map(Bound::to_string).collect::<Vec<_>>();
+        f.write_str(&bounds.join(", "))
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum VersionRangeParseError {
+    #[error("Empty version range")]
+    Empty,
+    #[error("Invalid major version in range: {input}")]
+    MajorNotNumeric {
+        input: String,
+        source: ParseIntError,
+    },
+}
+
+fn parse_major(s: &str) -> Result<u32, VersionRangeParseError> {
+    let s = s.trim();
+    s.parse()
+        .map_err(|e| VersionRangeParseError::MajorNotNumeric {
+            input: s.to_string(),
+            source: e,
+        })
+}
+
+impl FromStr for VersionRange {
+    type Err = VersionRangeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bounds = Vec::new();
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            if let Some((low, high)) = part.split_once("..") {
+                if !low.trim().is_empty() {
+                    bounds.push(Bound::Ge(parse_major(low)?));
+                }
+                if !high.trim().is_empty() {
+                    bounds.push(Bound::Le(parse_major(high)?));
+                }
+                continue;
+            }
+            // Longer operators first, so `>=` isn't read as `>`.
+            let bound = if let Some(v) = part.strip_prefix(">=") {
+                Bound::Ge(parse_major(v)?)
+            } else if let Some(v) = part.strip_prefix("<=") {
+                Bound::Le(parse_major(v)?)
+            } else if let Some(v) = part.strip_prefix('>') {
+                Bound::Gt(parse_major(v)?)
+            } else if let Some(v) = part.strip_prefix('<') {
+                Bound::Lt(parse_major(v)?)
+            } else {
+                Bound::Eq(parse_major(part.strip_prefix('=').unwrap_or(part))?)
+            };
+            bounds.push(bound);
+        }
+        if bounds.is_empty() {
+            return Err(VersionRangeParseError::Empty);
+        }
+        Ok(VersionRange { bounds })
+    }
+}
+
+/// What a project needs: an exact version key, or a range of versions.
+#[derive(Debug, Clone, Eq, PartialEq, Display)]
+pub enum JdkRequirement {
+    Key(VersionKey),
+    Range(VersionRange),
+}
+
+impl FromStr for JdkRequirement {
+    type Err = VersionRangeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.contains(['<', '>', '=', ',']) || s.contains("..") {
+            return VersionRange::from_str(s).map(JdkRequirement::Range);
+        }
+        VersionKey::from_str(s)
+            .map(JdkRequirement::Key)
+            .map_err(|e| match e {
+                VersionKeyParseError::MajorNotNumeric { input, source } => {
+                    VersionRangeParseError::MajorNotNumeric { input, source }
+                }
+            })
+    }
+}
+
+impl Serialize for JdkRequirement {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for JdkRequirement {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        JdkRequirement::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key(s: &str) -> VersionKey {
+        VersionKey::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_parse_range() {
+        let range = VersionRange::from_str(">=17, <22").unwrap();
+        assert_eq!(">=17, <22", range.to_string());
+        assert!(range.contains(&key("17")));
+        assert!(range.contains(&key("21")));
+        assert!(!range.contains(&key("22")));
+        assert!(!range.contains(&key("11")));
+        assert!(!range.contains(&key("21-crac")));
+
+        let range = VersionRange::from_str("17..21").unwrap();
+        assert_eq!(">=17, <=21", range.to_string());
+        assert!(range.contains(&key("21")));
+        assert!(!range.contains(&key("22")));
+        assert!(VersionRange::from_str("21..").unwrap().contains(&key("25")));
+
+        assert!(VersionRange::from_str("").is_err());
+        assert!(VersionRange::from_str(">=seventeen").is_err());
+    }
+
+    #[test]
+    fn test_best() {
+        let range = VersionRange::from_str("17..21").unwrap();
+        let keys = [key("11"), key("17"), key("21"), key("22"), key("21-ea")];
+        assert_eq!(Some(key("21")), range.best(&keys));
+        assert_eq!(None, range.best(&keys[..1]));
+    }
+
+    #[test]
+    fn test_parse_requirement() {
+        assert_eq!(
+            JdkRequirement::Key(key("21-crac")),
+            JdkRequirement::from_str("21-crac").unwrap()
+        );
+        assert!(matches!(
+            JdkRequirement::from_str(">=17").unwrap(),
+            JdkRequirement::Range(_)
+        ));
+    }
+}
@@ -0,0 +1,127 @@
+use crate::java_version::{JavaVersion, NewScheme, PreRelease};
+use itertools::Itertools;
+
+impl JavaVersion {
+    /// Normalize a version reported for `distribution` into the OpenJDK layout, so that versions
+    /// from different distributions can be compared. Vendor-specific extra numbers are moved into
+    /// `opt`, which [JavaVersion::compare] ignores.
+    pub fn normalize_for_distribution(&self, distribution: &str) -> JavaVersion {
+        let JavaVersion::NewScheme(v) = self else {
+            return self.clone();
+        };
+        let normalized = match distribution {
+            "corretto" => normalize_corretto(v),
+            "dragonwell" => normalize_trailing_to_opt(v),
+            _ => None,
+        };
+        normalized.map_or_else(|| self.clone(), JavaVersion::NewScheme)
+    }
+}
+
+/// Corretto appends the build and its own revision as plain version numbers:
+/// - JDK 8: `8.<update>.<build>.<revision>`, e.g. `8.402.08.1`
+/// - JDK 11+: `<feature>.<interim>.<update>.<build>.<revision>`, e.g. `17.0.10.7.1`
+fn normalize_corretto(v: &NewScheme) -> Option<NewScheme> {
+    if v.pre_release != PreRelease::None || v.build.is_some() || v.opt.is_some() {
+        return None;
+    }
+    if v.feature == 8 && v.interim != 0 {
+        return Some(NewScheme {
+            feature: 8,
+            interim: 0,
+            update: v.interim,
+            patch: 0,
+            trailing: Vec::new(),
+            pre_release: PreRelease::None,
+            build: Some(v.update),
+            opt: Some(std::iter::once(&v.patch).chain(&v.trailing).join(".")),
+        });
+    }
+    if v.trailing.is_empty() {
+        return None;
+    }
+    Some(NewScheme {
+        feature: v.feature,
+        interim: v.interim,
+        update: v.update,
+        patch: 0,
+        trailing: Vec::new(),
+        pre_release: PreRelease::None,
+        build: Some(v.patch),
+        opt: Some(v.trailing.iter().join(".")),
+    })
+}
+
+/// Dragonwell adds its own release number after the patch, e.g. `17.0.10.0.11+7`.
+fn normalize_trailing_to_opt(v: &NewScheme) -> Option<NewScheme> {
+    if v.trailing.is_empty() || v.opt.is_some() {
+        return None;
+    }
+    Some(NewScheme {
+        trailing: Vec::new(),
+        opt: Some(v.trailing.iter().join(".")),
+        ..v.clone()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cmp::Ordering;
+
+    fn normalized(v: &str, distribution: &str) -> JavaVersion {
+        v.parse::<JavaVersion>()
+            .unwrap()
+            .normalize_for_distribution(distribution)
+    }
+
+    fn assert_same_release(a: &JavaVersion, b: &str) {
+        let b: JavaVersion = b.parse().unwrap();
+        assert_eq!(Ordering::Equal, a.compare(&b), "{} vs {}", a, b);
+    }
+
+    #[test]
+    fn test_corretto() {
+        let v = normalized("17.0.10.7.1", "corretto");
+        assert_eq!("17.0.10+7-1", v.to_string());
+        assert_same_release(&v, "17.0.10+7");
+        assert_eq!(v, v.to_string().parse().unwrap());
+        assert_eq!(
+            Ordering::Less,
+            v.compare(&normalized("17.0.11.9.1", "corretto"))
+        );
+
+        let v = normalized("8.402.08.1", "corretto");
+        assert_eq!("8.0.402+8-1", v.to_string());
+        assert_same_release(&v, "8.0.402+8");
+
+        // Already in the OpenJDK layout.
+        assert_eq!("21.0.2+13", normalized("21.0.2+13", "corretto").to_string());
+    }
+
+    #[test]
+    fn test_dragonwell() {
+        let v = normalized("17.0.10.0.11+7", "dragonwell");
+        assert_eq!("17.0.10+7-11", v.to_string());
+        assert_same_release(&v, "17.0.10+7");
+    }
+
+    #[test]
+    fn test_zulu() {
+        let v = normalized("17.0.10+7-LTS", "zulu");
+        assert_eq!("17.0.10+7-LTS", v.to_string());
+        assert_same_release(&v, "17.0.10+7");
+        assert_same_release(&normalized("8.0.402+6", "zulu"), "8.0.402+6");
+    }
+
+    #[test]
+    fn test_semeru() {
+        let v = normalized("17.0.10+7-openj9-0.43.0", "semeru");
+        assert_eq!("17.0.10+7-openj9-0.43.0", v.to_string());
+        assert_same_release(&v, "17.0.10+7");
+        assert_eq!(
+            Ordering::Greater,
+            normalized("17.0.10.1+1", "semeru").compare(&v)
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use crate::archive;
+use crate::error::ESResult;
+use crate::foojay::ArchiveType;
+use derive_more::Display;
+use error_stack::{Context, Report, ResultExt};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use indicatif::ProgressBar;
+use std::fs::File;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use tar::HeaderMode;
+
+#[derive(Debug, Display)]
+#[display("JDK archive error")]
+pub struct JdkArchiveError;
+
+impl Context for JdkArchiveError {}
+
+/// Write the JDK at `jdk_home`, including jpre's marker and install metadata, to a `.tar.gz` at
+/// `output`, under a top-level directory named `name`. Entries are sorted, so packing the same JDK
+/// twice gives the same archive if `reproducible` also fixes mtimes and owners.
+pub fn pack(
+    jdk_home: &Path,
+    name: &str,
+    output: &Path,
+    reproducible: bool,
+) -> ESResult<(), JdkArchiveError> {
+    // A JDK imported with `--link` is a symlink in the store, pack what it points to.
+    let jdk_home = jdk_home
+        .canonicalize()
+        .change_context(JdkArchiveError)
+        .attach_printable_lazy(|| format!("Could not resolve {:?}", jdk_home))?;
+    let mut paths = Vec::new();
+    collect_sorted(&jdk_home, &mut paths)
+        .change_context(JdkArchiveError)
+        .attach_printable_lazy(|| format!("Could not list files of {:?}", jdk_home))?;
+
+    let output_dir = match output.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let temp = tempfile::NamedTempFile::new_in(output_dir)
+        .change_context(JdkArchiveError)
+        .attach_printable_lazy(|| format!("Could not create temporary file in {:?}", output_dir))?;
+    let mut builder = tar::Builder::new(GzEncoder::new(temp, Compression::default()));
+    builder.mode(if reproducible {
+        HeaderMode::Deterministic
+    } else {
+        HeaderMode::Complete
+    });
+    builder.follow_symlinks(false);
+    for path in paths {
+        let relative = path
+            .strip_prefix(&jdk_home)
+            .expect("collected paths are in the JDK");
+        let archive_name = Path::new(name).join(relative);
+        builder
+            .append_path_with_name(&path, &archive_name)
+            .change_context(JdkArchiveError)
+            .attach_printable_lazy(|| format!("Could not add {:?} to the archive", path))?;
+    }
+    let temp = builder
+        .into_inner()
+        .and_then(|gz| gz.finish())
+        .change_context(JdkArchiveError)
+        .attach_printable("Could not finish the archive")?;
+    // Temporary files are only readable by their owner, but the archive is meant to be shared.
+    temp.as_file()
+        .set_permissions(std::fs::Permissions::from_mode(0o644))
+        .change_context(JdkArchiveError)
+        .attach_printable_lazy(|| format!("Could not set permissions of {:?}", temp.path()))?;
+    temp.persist(output)
+        .change_context(JdkArchiveError)
+        .attach_printable_lazy(|| format!("Could not write archive to {:?}", output))?;
+    Ok(())
+}
+
+/// `dir` and everything in it, parents before their children and siblings sorted by name.
+fn collect_sorted(dir: &Path, paths: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    paths.push(dir.to_owned());
+    let mut entries = std::fs::read_dir(dir)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+    for entry in entries {
+        if std::fs::symlink_metadata(&entry)?.is_dir() {
+            collect_sorted(&entry, paths)?;
+        } else {
+            paths.push(entry);
+        }
+    }
+    Ok(())
+}
+
+/// Extract an archive written by [pack] into `unpack_dir`, and return the JDK directory in it.
+pub fn unpack(archive: &Path, unpack_dir: &Path) -> ESResult<PathBuf, JdkArchiveError> {
+    let file = File::open(archive)
+        .change_context(JdkArchiveError)
+        .attach_printable_lazy(|| format!("Could not open {:?}", archive))?;
+    archive::extract(
+        &ArchiveType::TarGz,
+        file,
+        unpack_dir,
+        &ProgressBar::hidden(),
+    )
+    .change_context(JdkArchiveError)
+    .attach_printable_lazy(|| format!("Could not extract {:?}", archive))?;
+
+    let mut roots = std::fs::read_dir(unpack_dir)
+        .change_context(JdkArchiveError)
+        .attach_printable_lazy(|| format!("Could not read {:?}", unpack_dir))?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()
+        .change_context(JdkArchiveError)
+        .attach_printable_lazy(|| format!("Could not read {:?}", unpack_dir))?;
+    match roots.len() {
+        1 => Ok(roots.remove(0)),
+        _ => Err(Report::new(JdkArchiveError).attach_printable(format!(
+            "Expected a single JDK directory in {:?}, found {:?}",
+            archive, roots
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::fake_jdk;
+
+    #[test]
+    fn test_pack_unpack_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let home = fake_jdk(dir.path());
+        let archive = dir.path().join("jdk.tar.gz");
+        pack(&home, "17", &archive, false).unwrap();
+
+        let unpack_dir = dir.path().join("unpacked");
+        std::fs::create_dir(&unpack_dir).unwrap();
+        let root = unpack(&archive, &unpack_dir).unwrap();
+        assert_eq!(unpack_dir.join("17"), root);
+        assert_eq!(
+            "17.0.2+8",
+            std::fs::read_to_string(root.join(".jdk_marker_with_version")).unwrap()
+        );
+        let mode = std::fs::metadata(root.join("bin/java"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(0o755, mode & 0o777);
+        assert_eq!(
+            Path::new("lib/modules"),
+            std::fs::read_link(root.join("modules")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_pack_reproducible() {
+        let dir = tempfile::tempdir().unwrap();
+        let home = fake_jdk(dir.path());
+        let first = dir.path().join("first.tar.gz");
+        pack(&home, "17", &first, true).unwrap();
+        let later = std::time::SystemTime::now() + std::time::Duration::from_secs(60);
+        File::open(home.join("lib/modules"))
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        let second = dir.path().join("second.tar.gz");
+        pack(&home, "17", &second, true).unwrap();
+        assert_eq!(
+            std::fs::read(first).unwrap(),
+            std::fs::read(second).unwrap()
+        );
+    }
+}
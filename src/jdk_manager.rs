@@ -1,44 +1,209 @@
-use crate::checksum_verifier::ChecksumVerifier;
+use crate::archive;
+use crate::checksum_verifier::{self, Checksum, ChecksumVerifier};
 use crate::config::{JpreConfig, PROJECT_DIRS};
-use crate::error::ESResult;
-use crate::foojay::{
-    ArchiveType, ChecksumType, FoojayPackageInfo, FoojayPackageListInfo, FOOJAY_API,
+use crate::content_disposition::ContentDisposition;
+use crate::context_id::base_state_dir;
+use crate::download_failure::DownloadFailure;
+use crate::error::{ESResult, JpreError, UserMessage};
+use crate::foojay::{ChecksumType, FoojayPackageInfo, FoojayPackageListInfo, FOOJAY_API};
+use crate::fs_util::{
+    available_space, copy_dir_all, is_writable, lock_exclusive, remove_dir_all_within,
 };
-use crate::http_client::new_http_client;
+use crate::http_client::DOWNLOAD_CLIENT;
+use crate::http_fixtures;
+use crate::install_metadata::InstallMetadata;
+use crate::inventory::Inventory;
 use crate::java_version::key::VersionKey;
 use crate::java_version::JavaVersion;
-use crate::tui::new_progress_bar;
+use crate::jdk_archive;
+use crate::jobs;
+use crate::jobs::CancellableRead;
+use crate::journal::{self, JournalEvent};
+use crate::package_manifest::ManifestPackage;
+use crate::platform::{self, Platform, ResolvedPlatform};
+use crate::policy;
+use crate::progress_events;
+use crate::progress_events::{EventBytes, ProgressEvent, Stage};
+use crate::release_file::ReleaseFile;
+use crate::rosetta;
+use crate::tree_manifest::TreeManifest;
+use crate::tui::{
+    eprint_above_progress, format_bytes, format_duration, new_progress_bar, record_downloaded_bytes,
+};
 use derive_more::Display;
-use digest::Digest;
 use error_stack::{Context, Report, ResultExt};
-use indicatif::MultiProgress;
 use owo_colors::{OwoColorize, Stream};
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Deref;
+use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use std::sync::LazyLock;
-use tempfile::TempDir;
-use tracing::warn;
-use ureq::Response;
+use std::sync::{LazyLock, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+use tempfile::{TempDir, TempPath};
+use tracing::{debug, warn};
 
 #[derive(Debug, Display)]
 pub struct JdkManagerError;
 
 impl Context for JdkManagerError {}
 
-static JDK_STORE_PATH: LazyLock<PathBuf> = LazyLock::new(|| PROJECT_DIRS.cache_dir().join("jdks"));
+/// Directory names in the cache dir, for the installed JDKs and the downloads.
+pub const JDK_STORE_DIR_NAME: &str = "jdks";
+pub const JDK_DOWNLOADS_DIR_NAME: &str = "downloads";
+
+/// Where JDKs are installed in [JdkStore::System] mode.
+pub const SYSTEM_JDK_STORE_PATH: &str = "/usr/local/lib/jpre/jdks";
+
+/// Which JDK store to use. Downloads, contexts and everything else stay per-user either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JdkStore {
+    /// In the user's cache directory.
+    User,
+    /// At [SYSTEM_JDK_STORE_PATH], shared by all users. Only users that can write there can
+    /// install or remove JDKs.
+    System,
+}
+
+static STORE: OnceLock<JdkStore> = OnceLock::new();
+
+/// How many times `--retry-resume` tries a download before giving up.
+const DOWNLOAD_ATTEMPTS: u32 = 5;
+/// How long to wait before retrying a download, multiplied by the number of failed attempts.
+const DOWNLOAD_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+static RETRY_RESUME: OnceLock<bool> = OnceLock::new();
+
+/// Retry broken downloads, resuming where they stopped, instead of failing the install.
+pub fn configure_retry_resume(enabled: bool) {
+    let _ = RETRY_RESUME.set(enabled);
+}
+
+static ACCEPT_LICENSE: OnceLock<bool> = OnceLock::new();
+static ALLOW_EMULATED: OnceLock<bool> = OnceLock::new();
+
+/// Accept the license of distributions whose packages need it, see `accepted_licenses`.
+pub fn configure_accept_license(accept: bool) {
+    let _ = ACCEPT_LICENSE.set(accept);
+}
+
+/// Install x64 JDKs on Apple Silicon, where they run emulated by Rosetta 2, even when that's only
+/// because jpre itself runs under Rosetta 2.
+pub fn configure_allow_emulated(allow: bool) {
+    let _ = ALLOW_EMULATED.set(allow);
+}
+
+/// Set which JDK store to use. Must be called before the store is first used, later calls are
+/// ignored.
+pub fn configure_store(store: JdkStore) {
+    if STORE.set(store).is_err() {
+        warn!("JDK store was already configured, ignoring {:?}", store);
+    }
+}
+
+pub fn store() -> JdkStore {
+    *STORE.get_or_init(|| JdkStore::User)
+}
+
+static JDK_STORE_PATH: LazyLock<PathBuf> = LazyLock::new(|| match store() {
+    JdkStore::User => PROJECT_DIRS.cache_dir().join(JDK_STORE_DIR_NAME),
+    JdkStore::System => PathBuf::from(SYSTEM_JDK_STORE_PATH),
+});
+
+pub fn jdk_store_path() -> &'static Path {
+    &JDK_STORE_PATH
+}
 static JDK_DOWNLOADS_PATH: LazyLock<PathBuf> =
-    LazyLock::new(|| PROJECT_DIRS.cache_dir().join("downloads"));
+    LazyLock::new(|| PROJECT_DIRS.cache_dir().join(JDK_DOWNLOADS_DIR_NAME));
 
 // Why not '.jdk_marker'? Old jpre didn't emit the version number in the marker file, so we need to
 // use a new marker file to ensure we know which version of the JDK is installed.
-const JDK_VALID_MARKER_FILE_NAME: &str = ".jdk_marker_with_version";
+pub const JDK_VALID_MARKER_FILE_NAME: &str = ".jdk_marker_with_version";
 // We'll inspect the legacy one and use it as a valid JDK, but when updating we'll always overwrite.
-const LEGACY_JDK_MARKER_FILE_NAME: &str = ".jdk_marker";
+pub const LEGACY_JDK_MARKER_FILE_NAME: &str = ".jdk_marker";
+
+/// Loose files in the downloads directory and unpack directories in the store younger than this are
+/// never cleaned up, they probably belong to a running install.
+pub const IN_PROGRESS_GRACE: Duration = Duration::from_secs(60 * 60);
+
+/// Held while JDKs are moved into, around or out of the store, see [JdkManager::lock_store].
+pub const STORE_LOCK_FILE_NAME: &str = ".jpre.lock";
+
+/// How often `auto_clean_downloads` runs, and the file in the state dir recording when it last did.
+const AUTO_CLEAN_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+const AUTO_CLEAN_MARKER_FILE_NAME: &str = "downloads-cleaned";
+
+/// An installed JDK is moved to `<key>.previous` while its replacement is put in place.
+pub const PREVIOUS_JDK_SUFFIX: &str = ".previous";
+/// Suffix of the marker of a linked JDK, next to its link. See [marker_path].
+pub const LINK_RECORD_SUFFIX: &str = ".link";
+/// Old versions kept after an update are at `<key>.previous-<version>`.
+pub const RETAINED_JDK_INFIX: &str = ".previous-";
 
 fn jdk_path(jdk: &VersionKey) -> PathBuf {
     JDK_STORE_PATH.join(jdk.to_string())
 }
 
+/// Where the versioned marker of the JDK at `path` is. A JDK imported with `--link` isn't jpre's to
+/// write into, so its marker is a `<key>.link` file next to the link instead.
+pub fn marker_path(path: &Path) -> PathBuf {
+    if path.is_symlink() {
+        link_record_path(path)
+    } else {
+        path.join(JDK_VALID_MARKER_FILE_NAME)
+    }
+}
+
+/// The marker of the JDK at `path` if it's linked, see [marker_path].
+pub fn link_record_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(LINK_RECORD_SUFFIX);
+    path.with_file_name(name)
+}
+
+/// Whether the JDK at `path` was completely installed.
+fn has_valid_marker(path: &Path) -> bool {
+    marker_path(path).exists() || path.join(LEGACY_JDK_MARKER_FILE_NAME).exists()
+}
+
+fn previous_jdk_path(jdk: &VersionKey) -> PathBuf {
+    JDK_STORE_PATH.join(format!("{}{}", jdk, PREVIOUS_JDK_SUFFIX))
+}
+
+/// Where an old version of a JDK is kept after an update, see `keep_previous_versions`.
+fn retained_jdk_path(jdk: &VersionKey, version: &JavaVersion) -> PathBuf {
+    JDK_STORE_PATH.join(format!("{}{}{}", jdk, RETAINED_JDK_INFIX, version))
+}
+
+/// An old version of a JDK kept after an update, see `keep_previous_versions`.
+#[derive(Debug, Clone)]
+pub struct RetainedJdk {
+    pub jdk: VersionKey,
+    pub version: JavaVersion,
+    pub path: PathBuf,
+}
+
+/// The old versions retained in the JDK store at `store`, newest first.
+pub fn retained_jdks_in(store: &Path) -> Vec<RetainedJdk> {
+    let Ok(entries) = std::fs::read_dir(store) else {
+        return Vec::new();
+    };
+    let mut retained = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let name = e.file_name();
+            let (jdk, version) = name.to_str()?.split_once(RETAINED_JDK_INFIX)?;
+            Some(RetainedJdk {
+                jdk: VersionKey::from_str(jdk).ok()?,
+                version: JavaVersion::from_str(version).ok()?,
+                path: e.path(),
+            })
+        })
+        .collect::<Vec<_>>();
+    retained.sort_by(|a, b| b.version.cmp(&a.version).then_with(|| a.jdk.cmp(&b.jdk)));
+    retained
+}
+
 pub static JDK_MANAGER: LazyLock<JdkManager> = LazyLock::new(JdkManager::new);
 
 pub struct JdkManager {
@@ -48,7 +213,7 @@ pub struct JdkManager {
 impl JdkManager {
     pub fn new() -> Self {
         Self {
-            client: new_http_client(),
+            client: DOWNLOAD_CLIENT.clone(),
         }
     }
 
@@ -75,9 +240,7 @@ impl JdkManager {
             let Ok(key) = VersionKey::from_str(name) else {
                 continue;
             };
-            let marker = ent.path().join(JDK_VALID_MARKER_FILE_NAME);
-            let legacy_marker = ent.path().join(LEGACY_JDK_MARKER_FILE_NAME);
-            if !marker.exists() && !legacy_marker.exists() {
+            if !has_valid_marker(&ent.path()) {
                 continue;
             }
             result.push(key);
@@ -85,6 +248,12 @@ impl JdkManager {
         Ok(result)
     }
 
+    /// Whether `jdk` is installed. Only looks at that JDK, unlike [Self::get_installed_jdks], so
+    /// it's cheap enough for every prompt.
+    pub fn is_installed(&self, jdk: &VersionKey) -> bool {
+        has_valid_marker(&jdk_path(jdk))
+    }
+
     pub fn get_full_version(
         &self,
         jdk: &VersionKey,
@@ -96,7 +265,7 @@ impl JdkManager {
         &self,
         path: &Path,
     ) -> ESResult<Option<JavaVersion>, JdkManagerError> {
-        let marker = path.join(JDK_VALID_MARKER_FILE_NAME);
+        let marker = marker_path(path);
         if !marker.exists() {
             return Ok(None);
         }
@@ -109,13 +278,42 @@ impl JdkManager {
         Ok(Some(version))
     }
 
+    /// The default JDK of `config`. With `default_auto_latest_lts`, that's the latest installed LTS
+    /// JDK, if any is installed.
+    pub fn resolve_default_jdk(
+        &self,
+        config: &JpreConfig,
+    ) -> ESResult<Option<VersionKey>, JdkManagerError> {
+        if config.default_auto_latest_lts {
+            let latest_lts = self.latest_installed_lts()?;
+            if latest_lts.is_some() {
+                return Ok(latest_lts);
+            }
+            debug!("No LTS JDK installed, falling back to default_jdk");
+        }
+        Ok(config.default_jdk.clone())
+    }
+
+    pub fn latest_installed_lts(&self) -> ESResult<Option<VersionKey>, JdkManagerError> {
+        Ok(self
+            .get_installed_jdks()?
+            .into_iter()
+            .filter(VersionKey::is_lts)
+            .max())
+    }
+
+    /// Get the path of a JDK, downloading it if it's not installed. `distribution` overrides the
+    /// configured distributions for the download.
     pub fn get_jdk_path(
         &self,
         config: &JpreConfig,
+        platform: &ResolvedPlatform,
         jdk: &VersionKey,
+        distribution: Option<&str>,
     ) -> ESResult<PathBuf, JdkManagerError> {
-        if !self.get_installed_jdks()?.into_iter().any(|k| &k == jdk) {
-            self.download_jdk(config, jdk)?;
+        if !self.is_installed(jdk) {
+            let platform = platform::require(platform).change_context(JdkManagerError)?;
+            self.download_jdk(config, platform, jdk, distribution)?;
         }
         Ok(jdk_path(jdk))
     }
@@ -124,66 +322,58 @@ impl JdkManager {
     pub fn download_jdk(
         &self,
         config: &JpreConfig,
+        platform: &Platform,
         jdk: &VersionKey,
+        distribution: Option<&str>,
     ) -> ESResult<(), JdkManagerError> {
-        let path = jdk_path(jdk);
-        if path.exists() {
-            std::fs::remove_dir_all(&path)
-                .change_context(JdkManagerError)
-                .attach_printable_lazy(|| {
-                    format!("Could not remove JDK install folder at {:?}", path)
-                })?;
-        }
-        std::fs::create_dir_all(&path)
-            .change_context(JdkManagerError)
-            .attach_printable_lazy(|| {
-                format!("Could not create directory for JDK at {:?}", path)
-            })?;
+        self.prepare_store_for_writing()?;
         let (list_info, info) = FOOJAY_API
-            .get_latest_package_info_using_priority(config, jdk)
+            .get_latest_package_info_using_priority(config, platform, jdk, distribution)
             .change_context(JdkManagerError)
             .attach_printable_lazy(|| {
                 format!("Could not get latest JDK package info for {}", jdk)
             })?;
+        self.install_package(config, platform, jdk, list_info, info)
+    }
 
-        let response = self
-            .client
-            .get(info.direct_download_uri.as_str())
-            .call()
-            .change_context(JdkManagerError)
-            .attach_printable_lazy(|| {
-                format!(
-                    "Could not download JDK package from {}",
-                    info.direct_download_uri
-                )
-            })?;
-        std::fs::create_dir_all(&*JDK_DOWNLOADS_PATH)
-            .change_context(JdkManagerError)
-            .attach_printable_lazy(|| {
-                format!(
-                    "Could not create JDK downloads directory at {:?}",
-                    JDK_DOWNLOADS_PATH
-                )
-            })?;
-        let download_path = tempfile::NamedTempFile::new_in(&*JDK_DOWNLOADS_PATH)
-            .change_context(JdkManagerError)
-            .attach_printable_lazy(|| {
-                format!(
-                    "Could not create temporary file for JDK download in {:?}",
-                    path
-                )
-            })?
-            .into_temp_path();
-        if let Err(e) = Self::download_jdk_to_file(&list_info, &info, response, &download_path) {
-            let path = download_path.to_owned();
-            if let Err(delete_err) = download_path.close() {
-                warn!(
-                    "Could not delete potentially invalid download at {:?}: {}",
-                    path, delete_err
-                );
-            }
-            return Err(e);
-        }
+    /// Install a JDK from an entry of a package manifest, without asking Foojay anything.
+    pub fn install_from_manifest(
+        &self,
+        config: &JpreConfig,
+        platform: &Platform,
+        jdk: &VersionKey,
+        package: &ManifestPackage,
+    ) -> ESResult<(), JdkManagerError> {
+        self.prepare_store_for_writing()?;
+        let (list_info, info) = package.to_package_info();
+        self.install_package(config, platform, jdk, list_info, info)
+    }
+
+    /// Download and install the package as `jdk`, overwriting any existing JDK with the same
+    /// version.
+    fn install_package(
+        &self,
+        config: &JpreConfig,
+        platform: &Platform,
+        jdk: &VersionKey,
+        list_info: FoojayPackageListInfo,
+        info: FoojayPackageInfo,
+    ) -> ESResult<(), JdkManagerError> {
+        // Ctrl-C stops the download or extraction, so the temporary files are cleaned up and the
+        // existing JDK is kept.
+        let _interruptible = jobs::interruptible();
+        let path = jdk_path(jdk);
+
+        Self::check_emulation(platform, &list_info)?;
+        policy::current()
+            .check_distribution(&list_info.distribution)
+            .change_context(JdkManagerError)?;
+        Self::check_license(config, &list_info, &info)?;
+        Self::check_free_space(&list_info)?;
+        let download_start = Instant::now();
+        let download_path = self.download_archive(config, &list_info, &info)?;
+        let download_time = download_start.elapsed();
+        let extraction_start = Instant::now();
         let unpack_dir = tempfile::tempdir_in(&*JDK_STORE_PATH)
             .change_context(JdkManagerError)
             .attach_printable("Could not create temporary directory for JDK unpacking")?;
@@ -201,30 +391,143 @@ impl JdkManager {
                 return Err(e);
             }
         };
+        Self::check_release_version(&list_info, &root);
+        if let Err(e) = Self::post_unpack(config, &root) {
+            Self::cleanup_unpack_dir(unpack_dir);
+            return Err(e);
+        }
 
-        if let Err(e) = std::fs::rename(&root, &path)
-            .change_context(JdkManagerError)
-            .attach_printable_lazy(|| format!("Could not move JDK from {:?} to {:?}", root, path))
-        {
+        if let Err(e) = jobs::check_cancelled().change_context(JdkManagerError) {
             Self::cleanup_unpack_dir(unpack_dir);
             return Err(e);
         }
+
+        // Past this point the install is finished even on Ctrl-C, it's only a few renames.
+        let _lock = match self.lock_store() {
+            Ok(lock) => lock,
+            Err(e) => {
+                Self::cleanup_unpack_dir(unpack_dir);
+                return Err(e);
+            }
+        };
+        let previous_version = self.get_full_version(jdk).ok().flatten();
+        let previous = previous_jdk_path(jdk);
+        if path.exists() {
+            if let Err(e) = std::fs::rename(&path, &previous)
+                .change_context(JdkManagerError)
+                .attach_printable_lazy(|| {
+                    format!("Could not move old JDK from {:?} to {:?}", path, previous)
+                })
+            {
+                Self::cleanup_unpack_dir(unpack_dir);
+                return Err(e);
+            }
+        }
+        let mut metadata = InstallMetadata {
+            distribution: list_info.distribution.clone(),
+            java_version: list_info.java_version.clone(),
+            download_uri: info.direct_download_uri.to_string(),
+            sha256: info.checksum.to_ascii_lowercase(),
+            installed_at: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            archive_size: std::fs::metadata(&*download_path)
+                .map(|m| m.len())
+                .unwrap_or(list_info.size),
+            from_download_cache: matches!(download_path, DownloadedArchive::Reused(_)),
+            download_secs: download_time.as_secs_f64(),
+            extraction_secs: extraction_start.elapsed().as_secs_f64(),
+            installed_size: 0,
+        };
+        let result = Self::finish_install(
+            &list_info,
+            &root,
+            &path,
+            &mut metadata,
+            config.tree_manifest,
+        );
         Self::cleanup_unpack_dir(unpack_dir);
+        if let Err(e) = result {
+            Self::restore_previous(&path, &previous);
+            return Err(e);
+        }
+        Self::retire_previous(config, jdk, &previous, previous_version.as_ref(), &metadata);
+        // Replacing a linked JDK leaves its record behind.
+        let _ = std::fs::remove_file(link_record_path(&path));
+        journal::record(JournalEvent::Installed {
+            jdk: jdk.clone(),
+            distribution: list_info.distribution.clone(),
+            version: list_info.java_version.clone(),
+            previous: previous_version,
+        });
+
+        progress_events::emit(&ProgressEvent::Installed {
+            jdk: &list_info.java_version.to_string(),
+            path: &path,
+        });
+        self.update_inventory();
+        eprint_above_progress(format!(
+            "Installed JDK {}: {}",
+            list_info.java_version,
+            metadata.summary()
+        ));
+        Ok(())
+    }
 
-        let marker_temp = tempfile::NamedTempFile::new_in(&path)
+    /// Move the unpacked JDK at `root` to `path` and mark it as installed, recording a manifest of
+    /// its files if `tree_manifest`.
+    fn finish_install(
+        list_info: &FoojayPackageListInfo,
+        root: &Path,
+        path: &Path,
+        metadata: &mut InstallMetadata,
+        tree_manifest: bool,
+    ) -> ESResult<(), JdkManagerError> {
+        std::fs::rename(root, path)
+            .change_context(JdkManagerError)
+            .attach_printable_lazy(|| {
+                format!("Could not move JDK from {:?} to {:?}", root, path)
+            })?;
+        metadata.installed_size = dir_size(path);
+        metadata
+            .write(path)
+            .change_context(JdkManagerError)
+            .attach_printable_lazy(|| format!("Could not write install metadata to {:?}", path))?;
+        if tree_manifest {
+            TreeManifest::compute(path, true)
+                .and_then(|manifest| manifest.write(path))
+                .change_context(JdkManagerError)
+                .attach_printable_lazy(|| format!("Could not record manifest of {:?}", path))?;
+        }
+
+        Self::write_marker(path, &list_info.java_version)
+    }
+
+    /// Mark the JDK at `path` as completely installed, with version `version`.
+    pub fn write_marker(path: &Path, version: &JavaVersion) -> ESResult<(), JdkManagerError> {
+        let marker_path = marker_path(path);
+        let dir = marker_path.parent().expect("markers are in a directory");
+        let marker_temp = tempfile::NamedTempFile::new_in(dir)
             .change_context(JdkManagerError)
             .attach_printable_lazy(|| {
                 format!(
                     "Could not create temporary file for JDK marker in {:?}",
-                    path
+                    dir
                 )
             })?;
-        std::fs::write(marker_temp.path(), list_info.java_version.to_string())
+        marker_temp
+            .as_file()
+            .set_permissions(std::fs::Permissions::from_mode(0o644))
+            .change_context(JdkManagerError)
+            .attach_printable_lazy(|| {
+                format!("Could not set permissions of {:?}", marker_temp.path())
+            })?;
+        std::fs::write(marker_temp.path(), version.to_string())
             .change_context(JdkManagerError)
             .attach_printable_lazy(|| {
                 format!("Could not write JDK version to {:?}", marker_temp.path())
             })?;
-        let marker_path = path.join(JDK_VALID_MARKER_FILE_NAME);
         std::fs::rename(marker_temp.path(), &marker_path)
             .change_context(JdkManagerError)
             .attach_printable_lazy(|| {
@@ -234,191 +537,1069 @@ impl JdkManager {
                     marker_path
                 )
             })?;
-
         Ok(())
     }
 
-    fn cleanup_unpack_dir(unpack_dir: TempDir) {
-        let path = unpack_dir.path().to_owned();
-        if let Err(delete_err) = unpack_dir.close() {
-            warn!(
-                "Could not delete invalid download dir at {:?}: {}",
-                path, delete_err
+    /// Add a JDK installed by something else to the store, as `jdk`. With `link`, the store only
+    /// links to `java_home`, and nothing is written into it, see [marker_path]. Otherwise it's
+    /// copied.
+    pub fn import_jdk(
+        &self,
+        jdk: &VersionKey,
+        java_home: &Path,
+        version: &JavaVersion,
+        link: bool,
+    ) -> ESResult<(), JdkManagerError> {
+        let path = jdk_path(jdk);
+        if std::fs::symlink_metadata(&path).is_ok() {
+            return Err(
+                Report::new(JdkManagerError).attach_printable(format!("{:?} already exists", path))
             );
         }
+        self.prepare_store_for_writing()?;
+        let _lock = self.lock_store()?;
+        if link {
+            std::os::unix::fs::symlink(java_home, &path)
+                .change_context(JdkManagerError)
+                .attach_printable_lazy(|| {
+                    format!("Could not link {:?} to {:?}", path, java_home)
+                })?;
+            if let Err(e) = Self::write_marker(&path, version) {
+                let _ = std::fs::remove_file(&path);
+                return Err(e);
+            }
+            self.update_inventory();
+            return Ok(());
+        }
+
+        let copy_dir = tempfile::tempdir_in(&*JDK_STORE_PATH)
+            .change_context(JdkManagerError)
+            .attach_printable("Could not create temporary directory for JDK copy")?;
+        let root = copy_dir.path().join("jdk");
+        let result = copy_dir_all(java_home, &root)
+            .change_context(JdkManagerError)
+            .attach_printable_lazy(|| format!("Could not copy {:?} to {:?}", java_home, root))
+            .and_then(|()| {
+                std::fs::rename(&root, &path)
+                    .change_context(JdkManagerError)
+                    .attach_printable_lazy(|| {
+                        format!("Could not move JDK from {:?} to {:?}", root, path)
+                    })
+            })
+            .and_then(|()| Self::write_marker(&path, version));
+        Self::cleanup_unpack_dir(copy_dir);
+        match result {
+            Ok(()) => self.update_inventory(),
+            Err(_) => remove_dir_if_exists(&path),
+        }
+        result
     }
 
-    fn download_jdk_to_file(
-        list_info: &FoojayPackageListInfo,
-        info: &FoojayPackageInfo,
-        response: Response,
-        download_path: &Path,
-    ) -> ESResult<(), JdkManagerError> {
-        let mut file = std::fs::File::create(download_path)
+    /// Install a JDK from an archive written by `jpre pack`, with the install metadata it was packed
+    /// with. The key comes from the archive's top-level directory and the version from the packed
+    /// marker.
+    pub fn install_packed_jdk(
+        &self,
+        archive: &Path,
+    ) -> ESResult<(VersionKey, JavaVersion), JdkManagerError> {
+        self.prepare_store_for_writing()?;
+        let unpack_dir = tempfile::tempdir_in(&*JDK_STORE_PATH)
             .change_context(JdkManagerError)
-            .attach_printable_lazy(|| {
-                format!(
-                    "Could not create file for JDK download at {:?}",
-                    download_path
-                )
-            })?;
-        let mut checksum_verifier = ChecksumVerifier::new(
-            &info.checksum,
-            match info.checksum_type {
-                ChecksumType::Sha256 => Box::new(sha2::Sha256::new()),
-                ChecksumType::Unknown(ref ct) => {
-                    unreachable!(
-                        "JDKs listed should not contain unknown checksum type {}",
-                        ct
-                    )
-                }
-            },
-            &mut file,
-        );
-        let progress_bar = new_progress_bar(
-            response
-                .header("Content-Length")
-                .and_then(|s| s.parse().ok()),
-        )
-        .with_message(
-            format!("Downloading JDK {}", list_info.java_version)
-                .if_supports_color(Stream::Stderr, |s| s.green())
-                .to_string(),
-        );
-        std::io::copy(
-            &mut response.into_reader(),
-            &mut progress_bar.wrap_write(&mut checksum_verifier),
-        )
-        .change_context(JdkManagerError)
-        .attach_printable_lazy(|| format!("Could not write JDK package to {:?}", download_path))?;
-        if !checksum_verifier.verify() {
-            return Err(Report::new(JdkManagerError)
-                .attach_printable(format!("Checksum failed for {}", info.direct_download_uri)));
+            .attach_printable("Could not create temporary directory for JDK unpack")?;
+        let result = self
+            .lock_store()
+            .and_then(|_lock| self.install_unpacked_archive(archive, unpack_dir.path()));
+        Self::cleanup_unpack_dir(unpack_dir);
+        if result.is_ok() {
+            self.update_inventory();
         }
-        progress_bar.abandon_with_message(
-            format!("Downloaded JDK {} archive", list_info.java_version)
-                .if_supports_color(Stream::Stderr, |s| s.green())
-                .to_string(),
-        );
-        Ok(())
+        result
     }
 
-    fn unpack_jdk(
-        list_info: &FoojayPackageListInfo,
-        download_path: &Path,
+    fn install_unpacked_archive(
+        &self,
+        archive: &Path,
         unpack_dir: &Path,
-    ) -> ESResult<(), JdkManagerError> {
-        let all_bars = MultiProgress::new();
-        let archive_size = std::fs::metadata(download_path)
+    ) -> ESResult<(VersionKey, JavaVersion), JdkManagerError> {
+        let root = jdk_archive::unpack(archive, unpack_dir).change_context(JdkManagerError)?;
+        let Some(version) = self.get_full_version_from_path(&root)? else {
+            return Err(Report::new(JdkManagerError).attach(UserMessage {
+                message: format!(
+                    "{} has no jpre marker, it was not made by `jpre pack`",
+                    archive.display()
+                ),
+            }));
+        };
+        // `pack` names the directory after the key, which may have a feature like `21-crac` that
+        // the version doesn't tell.
+        let jdk = root
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| VersionKey::from_str(name).ok())
+            .filter(|jdk| jdk.major == version.major())
+            .ok_or_else(|| {
+                Report::new(JdkManagerError).attach(UserMessage {
+                    message: format!(
+                        "{} has no JDK directory named after its version, it was not made by \
+                         `jpre pack`",
+                        archive.display()
+                    ),
+                })
+            })?;
+        let path = jdk_path(&jdk);
+        if std::fs::symlink_metadata(&path).is_ok() {
+            return Err(Report::new(JdkManagerError).attach(UserMessage {
+                message: format!(
+                    "JDK {} is already installed, remove it first to unpack {}",
+                    jdk,
+                    archive.display()
+                ),
+            }));
+        }
+        // The marker goes last, so an interrupted move doesn't look installed.
+        let marker = root.join(JDK_VALID_MARKER_FILE_NAME);
+        std::fs::remove_file(&marker)
+            .change_context(JdkManagerError)
+            .attach_printable_lazy(|| format!("Could not remove {:?}", marker))?;
+        std::fs::rename(&root, &path)
             .change_context(JdkManagerError)
             .attach_printable_lazy(|| {
-                format!(
-                    "Could not get metadata for JDK download at {:?}",
-                    download_path
-                )
-            })?
-            .len();
-        let archive_bar = all_bars.add(new_progress_bar(Some(archive_size)));
-        let writing_bar = all_bars.add(new_progress_bar(None));
-        match list_info.archive_type {
-            ArchiveType::TarGz => {
-                let gz_decode = flate2::read::GzDecoder::new(
-                    archive_bar.wrap_read(
-                        std::fs::File::open(download_path)
-                            .change_context(JdkManagerError)
-                            .attach_printable_lazy(|| {
-                                format!("Could not open JDK download at {:?}", download_path)
-                            })?,
-                    ),
-                );
-                let mut archive = tar::Archive::new(writing_bar.wrap_read(gz_decode));
-                archive.set_preserve_permissions(true);
-                archive.set_overwrite(true);
-                for entry in archive.entries().unwrap() {
-                    let mut file = entry.unwrap();
-                    let archive_path = file.path().unwrap().into_owned();
-                    writing_bar.set_message(
-                        format!(
-                            "Extracting {}",
-                            archive_path
-                                .display()
-                                .if_supports_color(Stream::Stderr, |s| s.cyan())
-                        )
-                        .if_supports_color(Stream::Stderr, |s| s.green())
-                        .to_string(),
-                    );
-                    if !file.unpack_in(unpack_dir).unwrap() {
-                        warn!("Not extracting file with unsafe path: {:?}", archive_path);
+                format!("Could not move JDK from {:?} to {:?}", root, path)
+            })?;
+        if let Err(e) = Self::write_marker(&path, &version) {
+            remove_dir_if_exists(&path);
+            return Err(e);
+        }
+        Ok((jdk, version))
+    }
+
+    /// Put the JDK moved aside to `previous` back at `path`, dropping whatever is at `path`.
+    fn restore_previous(path: &Path, previous: &Path) {
+        remove_dir_if_exists(path);
+        if !previous.exists() {
+            return;
+        }
+        match std::fs::rename(previous, path) {
+            Ok(()) => debug!("Restored previous JDK at {:?}", path),
+            Err(e) => warn!(
+                "Could not restore previous JDK from {:?} to {:?}: {}",
+                previous, path, e
+            ),
+        }
+    }
+
+    /// Keep the JDK an install replaced if the config retains old versions, else remove it. Then
+    /// prune the retained versions beyond `keep_previous_versions`, with their cached archives.
+    fn retire_previous(
+        config: &JpreConfig,
+        jdk: &VersionKey,
+        previous: &Path,
+        previous_version: Option<&JavaVersion>,
+        installed: &InstallMetadata,
+    ) {
+        if previous.exists() {
+            let previous_metadata = InstallMetadata::read(previous).ok().flatten();
+            let retained = previous_version
+                .filter(|v| config.keep_previous_versions > 0 && **v != installed.java_version)
+                .map(|v| retained_jdk_path(jdk, v));
+            let kept = retained.is_some_and(|retained| {
+                remove_dir_if_exists(&retained);
+                match std::fs::rename(previous, &retained) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        warn!(
+                            "Could not keep previous JDK from {:?} at {:?}: {}",
+                            previous, retained, e
+                        );
+                        false
                     }
                 }
-            }
-            ArchiveType::Zip => {
-                let mut archive = zip::ZipArchive::new(
-                    archive_bar.wrap_read(
-                        std::fs::File::open(download_path)
-                            .change_context(JdkManagerError)
-                            .attach_printable_lazy(|| {
-                                format!("Could not open JDK download at {:?}", download_path)
-                            })?,
-                    ),
-                )
-                .change_context(JdkManagerError)
-                .attach_printable_lazy(|| {
-                    format!(
-                        "Could not read JDK download as ZIP archive at {:?}",
-                        download_path
-                    )
-                })?;
-                for i in 0..archive.len() {
-                    let mut file = archive.by_index(i).unwrap();
-                    let Some(archive_path) = file.enclosed_name() else {
-                        warn!("Not extracting file with unsafe path: {:?}", file.name());
-                        continue;
-                    };
-                    writing_bar.set_message(
-                        format!(
-                            "Extracting {}",
-                            file.name().if_supports_color(Stream::Stderr, |s| s.cyan())
-                        )
-                        .if_supports_color(Stream::Stderr, |s| s.green())
-                        .to_string(),
-                    );
-                    let mut extracted_file = std::fs::File::create(unpack_dir.join(&archive_path))
-                        .change_context(JdkManagerError)
-                        .attach_printable_lazy(|| {
-                            format!(
-                                "Could not create file for extracted JDK at {:?}",
-                                unpack_dir.join(&archive_path)
-                            )
-                        })?;
-                    std::io::copy(&mut file, &mut extracted_file)
-                        .change_context(JdkManagerError)
-                        .attach_printable_lazy(|| {
-                            format!(
-                                "Could not write extracted JDK file to {:?}",
-                                unpack_dir.join(archive_path)
-                            )
-                        })?;
-                }
-            }
-            ArchiveType::Unknown(ref at) => {
-                unreachable!("JDKs listed should not contain unknown archive type {}", at)
+            });
+            if !kept {
+                remove_dir_if_exists(previous);
+                remove_cached_archive(previous_metadata.as_ref(), installed);
             }
         }
-        archive_bar.finish();
-        writing_bar.abandon_with_message(
-            "Done extracting!"
-                .if_supports_color(Stream::Stderr, |s| s.green())
-                .to_string(),
-        );
-        Ok(())
+
+        // Newest first, those are the ones to keep.
+        let retained = retained_jdks_in(&JDK_STORE_PATH)
+            .into_iter()
+            .filter(|retained| retained.jdk == *jdk);
+        for retained in retained.skip(config.keep_previous_versions) {
+            debug!(
+                "Removing previous version {} of JDK {}",
+                retained.version, jdk
+            );
+            let metadata = InstallMetadata::read(&retained.path).ok().flatten();
+            remove_dir_if_exists(&retained.path);
+            remove_cached_archive(metadata.as_ref(), installed);
+        }
     }
 
-    fn determine_jdk_root(unpack_dir: &Path) -> ESResult<PathBuf, JdkManagerError> {
-        let entries = std::fs::read_dir(unpack_dir)
-            .change_context(JdkManagerError)
-            .attach_printable_lazy(|| {
-                format!("Could not read JDK unpack directory at {:?}", unpack_dir)
+    /// Give JDKs that only have the legacy marker a versioned one, with the version from their
+    /// `release` file, so `update` doesn't have to reinstall them to learn it.
+    pub fn migrate_legacy_markers(
+        &self,
+    ) -> ESResult<Vec<(VersionKey, MarkerMigration)>, JdkManagerError> {
+        let mut migrations = Vec::new();
+        for jdk in self.get_installed_jdks()? {
+            let path = jdk_path(&jdk);
+            if marker_path(&path).exists() {
+                continue;
+            }
+            let version = ReleaseFile::read(&path)
+                .ok()
+                .flatten()
+                .and_then(|r| r.java_version().ok().flatten())
+                .filter(|version| version.major() == jdk.major);
+            let migration = match version {
+                None => MarkerMigration::NoVersion,
+                Some(version) => match Self::write_marker(&path, &version) {
+                    Ok(()) => {
+                        let legacy = path.join(LEGACY_JDK_MARKER_FILE_NAME);
+                        if let Err(e) = std::fs::remove_file(&legacy) {
+                            debug!("Could not remove legacy marker {:?}: {}", legacy, e);
+                        }
+                        MarkerMigration::Migrated(version)
+                    }
+                    Err(e) => MarkerMigration::Failed(e),
+                },
+            };
+            migrations.push((jdk, migration));
+        }
+        if migrations
+            .iter()
+            .any(|(_, migration)| matches!(migration, MarkerMigration::Migrated(_)))
+        {
+            self.update_inventory();
+        }
+        Ok(migrations)
+    }
+
+    /// Finish up after installs that were killed before they could clean up: put back JDKs that
+    /// were moved aside for a new install, and remove leftover unpack directories. Skipped while
+    /// another jpre holds the store lock, its install may just be in the middle of that.
+    pub fn recover_interrupted_installs(&self) {
+        if !JDK_STORE_PATH.exists() {
+            return;
+        }
+        let lock_path = JDK_STORE_PATH.join(STORE_LOCK_FILE_NAME);
+        let _lock = match lock_exclusive(&lock_path, false) {
+            Ok(Some(lock)) => lock,
+            Ok(None) => {
+                debug!("JDK store is locked, not recovering interrupted installs");
+                return;
+            }
+            Err(e) => {
+                debug!("Could not lock {:?}: {}", lock_path, e);
+                return;
+            }
+        };
+        let Ok(entries) = std::fs::read_dir(&*JDK_STORE_PATH) else {
+            return;
+        };
+        let mut restored = false;
+        for entry in entries.filter_map(|e| e.ok()) {
+            let entry_path = entry.path();
+            let Some(name) = entry.file_name().to_str().map(str::to_owned) else {
+                continue;
+            };
+            if let Some(key) = name.strip_suffix(PREVIOUS_JDK_SUFFIX) {
+                let path = JDK_STORE_PATH.join(key);
+                if path.join(JDK_VALID_MARKER_FILE_NAME).exists() {
+                    debug!(
+                        "Removing JDK replaced by a finished install at {:?}",
+                        entry_path
+                    );
+                    remove_dir_if_exists(&entry_path);
+                } else {
+                    warn!("Restoring JDK {} after an interrupted install", key);
+                    Self::restore_previous(&path, &entry_path);
+                    restored = true;
+                }
+            } else if name.starts_with(".tmp") && is_older_than(&entry_path, IN_PROGRESS_GRACE) {
+                debug!("Removing leftover unpack directory {:?}", entry_path);
+                remove_dir_if_exists(&entry_path);
+            }
+        }
+        if restored {
+            self.update_inventory();
+        }
+    }
+
+    /// Create the store if needed, and check that this user may change it.
+    pub fn prepare_store_for_writing(&self) -> ESResult<(), JdkManagerError> {
+        let system = store() == JdkStore::System;
+        if system && !is_writable(&JDK_STORE_PATH) {
+            return Err(Report::new(JdkManagerError).attach(UserMessage {
+                message: format!(
+                    "The system JDK store at {} is not writable by this user, install and remove \
+                     JDKs as root or a user of its group",
+                    JDK_STORE_PATH.display()
+                ),
+            }));
+        }
+        std::fs::create_dir_all(&*JDK_STORE_PATH)
+            .change_context(JdkManagerError)
+            .attach_printable_lazy(|| {
+                format!("Could not create JDK store at {:?}", *JDK_STORE_PATH)
+            })?;
+        if system {
+            // Whatever the umask, other users need to read the store.
+            std::fs::set_permissions(&*JDK_STORE_PATH, std::fs::Permissions::from_mode(0o755))
+                .change_context(JdkManagerError)
+                .attach_printable_lazy(|| {
+                    format!("Could not set permissions of {:?}", *JDK_STORE_PATH)
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Take the store lock, waiting for other jpre processes to finish with it. It's held while
+    /// JDKs are moved around, so that nothing mistakes a move in progress for an interrupted one.
+    /// Released when dropped. The store must exist, see [Self::prepare_store_for_writing].
+    pub fn lock_store(&self) -> ESResult<StoreLock, JdkManagerError> {
+        let path = JDK_STORE_PATH.join(STORE_LOCK_FILE_NAME);
+        let file = lock_exclusive(&path, true)
+            .change_context(JdkManagerError)
+            .attach_printable_lazy(|| format!("Could not lock the JDK store with {:?}", path))?;
+        Ok(StoreLock {
+            _file: file.expect("waited for the lock"),
+        })
+    }
+
+    /// Remove an installed JDK.
+    pub fn remove_jdk(&self, jdk: &VersionKey) -> ESResult<(), JdkManagerError> {
+        self.prepare_store_for_writing()?;
+        let _lock = self.lock_store()?;
+        let path = jdk_path(jdk);
+        let marker = marker_path(&path);
+        remove_dir_all_within(&JDK_STORE_PATH, &path)
+            .change_context(JdkManagerError)
+            .attach_printable_lazy(|| format!("Could not remove JDK at {:?}", path))?;
+        if marker == link_record_path(&path) {
+            if let Err(e) = std::fs::remove_file(&marker) {
+                warn!("Could not remove {:?}: {}", marker, e);
+            }
+        }
+        self.update_inventory();
+        Ok(())
+    }
+
+    /// The old versions of JDKs retained in the store after updates, newest first.
+    pub fn get_retained_jdks(&self) -> Vec<RetainedJdk> {
+        retained_jdks_in(&JDK_STORE_PATH)
+    }
+
+    /// Remove an old version of a JDK retained after an update.
+    pub fn remove_retained_jdk(&self, retained: &RetainedJdk) -> ESResult<(), JdkManagerError> {
+        self.prepare_store_for_writing()?;
+        let _lock = self.lock_store()?;
+        remove_dir_all_within(&JDK_STORE_PATH, &retained.path)
+            .change_context(JdkManagerError)
+            .attach_printable_lazy(|| {
+                format!("Could not remove previous JDK at {:?}", retained.path)
+            })
+    }
+
+    /// Rewrite the inventory of installed JDKs. Only warns if that fails, the store is fine anyway.
+    pub fn update_inventory(&self) {
+        if let Err(e) = Inventory::collect(self).and_then(|inventory| inventory.write()) {
+            warn!("Could not update the inventory of installed JDKs: {:?}", e);
+        }
+    }
+
+    /// What was recorded when the JDK was installed, if anything.
+    pub fn get_install_metadata(
+        &self,
+        jdk: &VersionKey,
+    ) -> ESResult<Option<InstallMetadata>, JdkManagerError> {
+        InstallMetadata::read(&jdk_path(jdk))
+            .change_context(JdkManagerError)
+            .attach_printable_lazy(|| format!("Could not read install metadata for {}", jdk))
+    }
+
+    /// Download the archive for a package, or re-use it from the download cache if it's kept there.
+    fn download_archive(
+        &self,
+        config: &JpreConfig,
+        list_info: &FoojayPackageListInfo,
+        info: &FoojayPackageInfo,
+    ) -> ESResult<DownloadedArchive, JdkManagerError> {
+        // Checked before anything is downloaded, so there's nothing to clean up.
+        checksum_verifier::validate(&info.checksums())
+            .change_context(JdkManagerError)
+            .attach_printable_lazy(|| {
+                format!(
+                    "JDK {} package has an unusable checksum",
+                    list_info.java_version
+                )
+            })?;
+        std::fs::create_dir_all(&*JDK_DOWNLOADS_PATH)
+            .change_context(JdkManagerError)
+            .attach_printable_lazy(|| {
+                format!(
+                    "Could not create JDK downloads directory at {:?}",
+                    JDK_DOWNLOADS_PATH
+                )
+            })?;
+        let cache_dir = config
+            .keep_downloads
+            .then(|| archive_cache_dir(info))
+            .flatten();
+        if let Some(cached) = cache_dir.as_deref().and_then(find_cached_archive) {
+            if Self::verify_cached_archive(info, &cached) {
+                debug!("Using cached JDK archive at {:?}", cached);
+                // Download cleanup goes by age, so mark the archive as recently used.
+                if let Err(e) = std::fs::File::options()
+                    .write(true)
+                    .open(&cached)
+                    .and_then(|f| f.set_modified(SystemTime::now()))
+                {
+                    debug!("Could not update modification time of {:?}: {}", cached, e);
+                }
+                return Ok(DownloadedArchive::Reused(cached));
+            }
+            warn!("Discarding corrupted cached JDK archive at {:?}", cached);
+            if let Err(e) = std::fs::remove_file(&cached) {
+                warn!("Could not delete cached archive at {:?}: {}", cached, e);
+            }
+        }
+
+        if list_info.size > 0 {
+            eprint_above_progress(format!(
+                "Downloading JDK {} from {} ({})",
+                list_info.java_version,
+                list_info.distribution,
+                format_bytes(list_info.size)
+            ));
+        }
+        let mut attempts = DownloadAttempts::new();
+        let body = loop {
+            match self.get_download(info, 0) {
+                Ok(body) => break body,
+                Err(e) if attempts.retry_request(&e, 0) => {}
+                Err(e) => return Err(e),
+            }
+        };
+        let download_name = download_file_name(body.content_disposition.as_deref(), info);
+        let download_path = tempfile::Builder::new()
+            .prefix(&format!("{}.", download_name))
+            .tempfile_in(&*JDK_DOWNLOADS_PATH)
+            .change_context(JdkManagerError)
+            .attach_printable_lazy(|| {
+                format!(
+                    "Could not create temporary file for JDK download in {:?}",
+                    JDK_DOWNLOADS_PATH
+                )
+            })?
+            .into_temp_path();
+        if let Err(e) =
+            self.download_jdk_to_file(list_info, info, body, &download_path, &mut attempts)
+        {
+            let path = download_path.to_owned();
+            if let Err(delete_err) = download_path.close() {
+                warn!(
+                    "Could not delete potentially invalid download at {:?}: {}",
+                    path, delete_err
+                );
+            }
+            return Err(e);
+        }
+
+        http_fixtures::record_download(&info.direct_download_uri, &download_path);
+
+        let Some(cache_dir) = cache_dir else {
+            return Ok(DownloadedArchive::Temporary(download_path));
+        };
+        std::fs::create_dir_all(&cache_dir)
+            .change_context(JdkManagerError)
+            .attach_printable_lazy(|| {
+                format!(
+                    "Could not create download cache directory at {:?}",
+                    cache_dir
+                )
+            })?;
+        let cached = cache_dir.join(download_name);
+        download_path
+            .persist(&cached)
+            .change_context(JdkManagerError)
+            .attach_printable_lazy(|| format!("Could not keep JDK archive at {:?}", cached))?;
+        Ok(DownloadedArchive::Cached(cached))
+    }
+
+    /// x64 JDKs run emulated on Apple Silicon. That's allowed when asked for with
+    /// `forced_architecture` or `--arch`, but usually it's by accident, from running jpre under
+    /// Rosetta 2.
+    fn check_emulation(
+        platform: &Platform,
+        list_info: &FoojayPackageListInfo,
+    ) -> ESResult<(), JdkManagerError> {
+        let arch = &platform.arch;
+        if !rosetta::is_emulated_foojay_arch(arch) {
+            return Ok(());
+        }
+        if platform.arch_forced || ALLOW_EMULATED.get().copied().unwrap_or(false) {
+            warn!(
+                "JDK {} is built for {}, it will run emulated by Rosetta 2 on this Mac",
+                list_info.java_version, arch
+            );
+            return Ok(());
+        }
+        Err(Report::new(JdkManagerError).attach(UserMessage {
+            message: format!(
+                "jpre runs under Rosetta 2, so it picked an {} build of JDK {}, which would run \
+                 emulated on this Mac. Run an arm64 jpre, set forced_architecture = \"arm64\" in \
+                 the config, or pass --allow-emulated to install it anyway",
+                arch, list_info.java_version
+            ),
+        }))
+    }
+
+    /// Packages that aren't free to use in production are only downloaded once their
+    /// distribution's license was accepted, which `--accept-license` does for good.
+    fn check_license(
+        config: &JpreConfig,
+        list_info: &FoojayPackageListInfo,
+        info: &FoojayPackageInfo,
+    ) -> ESResult<(), JdkManagerError> {
+        let distribution = &list_info.distribution;
+        if list_info.free_use_in_production || config.accepted_licenses.contains(distribution) {
+            return Ok(());
+        }
+        let license = info
+            .license_uri(distribution)
+            .unwrap_or("the vendor's website");
+        if !ACCEPT_LICENSE.get().copied().unwrap_or(false) {
+            return Err(Report::new(JdkManagerError).attach(UserMessage {
+                message: format!(
+                    "JDK {} from {} isn't free to use in production, its license has to be \
+                     accepted first: {}. Pass --accept-license to accept it for all downloads \
+                     from {}",
+                    list_info.java_version, distribution, license, distribution
+                ),
+            }));
+        }
+        config
+            .clone()
+            .accept_license(distribution)
+            .change_context(JdkManagerError)
+            .attach_printable("Could not record the accepted license")?;
+        eprint_above_progress(format!(
+            "Accepted the license of {}: {}",
+            distribution, license
+        ));
+        Ok(())
+    }
+
+    /// Fail early if the archive, its unpacked contents and the move into place are unlikely to
+    /// fit, rather than running out of space halfway through unpacking.
+    fn check_free_space(list_info: &FoojayPackageListInfo) -> ESResult<(), JdkManagerError> {
+        if list_info.size == 0 {
+            return Ok(());
+        }
+        let required = list_info.size.saturating_mul(5) / 2;
+        let available = match available_space(&JDK_STORE_PATH) {
+            Ok(available) => available,
+            Err(e) => {
+                debug!("Could not check free space in {:?}: {}", *JDK_STORE_PATH, e);
+                return Ok(());
+            }
+        };
+        if available < required {
+            return Err(Report::new(JdkManagerError).attach(UserMessage {
+                message: format!(
+                    "Not enough disk space in {}: JDK {} needs about {}, only {} available",
+                    JDK_STORE_PATH.display(),
+                    list_info.java_version,
+                    format_bytes(required),
+                    format_bytes(available)
+                ),
+            }));
+        }
+        Ok(())
+    }
+
+    /// Delete download artifacts (cached archives and leftovers from interrupted downloads) that
+    /// are older than the policy's maximum age, then the oldest ones until the rest fit in the size
+    /// budget. A cache directory is as old as its newest file, and nothing younger than
+    /// [IN_PROGRESS_GRACE] is deleted.
+    pub fn clean_downloads(
+        &self,
+        policy: &DownloadCleanupPolicy,
+    ) -> ESResult<DownloadCleanupReport, JdkManagerError> {
+        let mut report = DownloadCleanupReport::default();
+        let dir = match std::fs::read_dir(&*JDK_DOWNLOADS_PATH) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(report),
+            Err(e) => {
+                return Err(e)
+                    .change_context(JdkManagerError)
+                    .attach_printable_lazy(|| {
+                        format!(
+                            "Could not read downloads directory {:?}",
+                            *JDK_DOWNLOADS_PATH
+                        )
+                    })
+            }
+        };
+        let mut entries = Vec::new();
+        for entry in dir {
+            let entry = entry
+                .change_context(JdkManagerError)
+                .attach_printable_lazy(|| {
+                    format!(
+                        "Could not read downloads directory {:?}",
+                        *JDK_DOWNLOADS_PATH
+                    )
+                })?;
+            let path = entry.path();
+            let metadata = entry
+                .metadata()
+                .change_context(JdkManagerError)
+                .attach_printable_lazy(|| format!("Could not read metadata of {:?}", path))?;
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            // Reusing a cached archive only touches the archive, not its directory.
+            let (modified, size) = if metadata.is_dir() {
+                (newest_modified(&path).max(modified), dir_size(&path))
+            } else {
+                (modified, metadata.len())
+            };
+            entries.push((path, modified, size));
+        }
+        // Oldest first, so those are the first to go when over budget.
+        entries.sort_by_key(|(_, modified, _)| *modified);
+
+        let now = SystemTime::now();
+        let mut total_size: u64 = entries.iter().map(|(_, _, size)| size).sum();
+        for (path, modified, size) in entries {
+            let too_old = policy
+                .max_age
+                .is_some_and(|max_age| now.duration_since(modified).unwrap_or_default() > max_age);
+            let over_budget = policy.max_size.is_some_and(|max| total_size > max);
+            if !too_old && !over_budget {
+                continue;
+            }
+            if now.duration_since(modified).unwrap_or_default() < IN_PROGRESS_GRACE {
+                // Probably a download that another jpre is still writing, or just cached.
+                continue;
+            }
+            debug!("Removing download artifact {:?}", path);
+            let result = if path.is_dir() {
+                remove_dir_all_within(&JDK_DOWNLOADS_PATH, &path)
+            } else {
+                std::fs::remove_file(&path)
+            };
+            match result {
+                Ok(()) => {
+                    report.removed += 1;
+                    report.reclaimed_bytes += size;
+                    total_size -= size;
+                }
+                Err(e) => warn!("Could not remove download artifact {:?}: {}", path, e),
+            }
+        }
+        Ok(report)
+    }
+
+    /// [Self::clean_downloads] for `auto_clean_downloads`, at most once a day, as it has to size the
+    /// whole download cache.
+    pub fn auto_clean_downloads(&self, policy: &DownloadCleanupPolicy) {
+        let marker = base_state_dir().join(AUTO_CLEAN_MARKER_FILE_NAME);
+        if marker.exists() && !is_older_than(&marker, AUTO_CLEAN_INTERVAL) {
+            return;
+        }
+        if let Err(e) = std::fs::create_dir_all(marker.parent().unwrap())
+            .and_then(|()| std::fs::write(&marker, ""))
+        {
+            debug!(
+                "Not cleaning up downloads, could not write {:?}: {}",
+                marker, e
+            );
+            return;
+        }
+        match self.clean_downloads(policy) {
+            Ok(report) if report.removed > 0 => debug!(
+                "Cleaned up {} download artifact(s), reclaimed {}",
+                report.removed,
+                format_bytes(report.reclaimed_bytes)
+            ),
+            Ok(_) => {}
+            Err(e) => warn!("Could not clean up downloads: {:?}", e),
+        }
+    }
+
+    /// Check every cached archive against the checksum its cache directory is named after, and
+    /// remove the ones that don't match, so a later install downloads them again instead of
+    /// failing on them.
+    pub fn verify_downloads(&self) -> ESResult<Vec<CachedArchiveCheck>, JdkManagerError> {
+        let dirs = match std::fs::read_dir(&*JDK_DOWNLOADS_PATH) {
+            Ok(dir) => dir
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|path| path.is_dir())
+                .collect::<Vec<_>>(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e)
+                    .change_context(JdkManagerError)
+                    .attach_printable_lazy(|| {
+                        format!(
+                            "Could not read downloads directory {:?}",
+                            *JDK_DOWNLOADS_PATH
+                        )
+                    })
+            }
+        };
+        let checks = jobs::run_bounded(dirs, jobs::DEFAULT_CONCURRENCY, |dir| {
+            let archive = find_cached_archive(&dir)?;
+            let size = std::fs::metadata(&archive).map(|m| m.len()).unwrap_or(0);
+            let status = Self::check_cached_archive(&dir, &archive);
+            if status == CachedArchiveStatus::Corrupted {
+                debug!("Removing corrupted cached archive {:?}", archive);
+                remove_dir_if_exists(&dir);
+            }
+            Some(CachedArchiveCheck {
+                archive,
+                size,
+                status,
+            })
+        })
+        .change_context(JdkManagerError)?;
+        Ok(checks.into_iter().flatten().collect())
+    }
+
+    fn check_cached_archive(cache_dir: &Path, archive: &Path) -> CachedArchiveStatus {
+        let Some(value) = cache_dir.file_name().and_then(|n| n.to_str()) else {
+            return CachedArchiveStatus::Unverifiable;
+        };
+        // The directory doesn't say which algorithm the checksum is from, but its length does.
+        let checksum_type = match value.len() {
+            64 => ChecksumType::Sha256,
+            128 => ChecksumType::Unknown("sha512".to_string()),
+            40 => ChecksumType::Unknown("sha1".to_string()),
+            _ => return CachedArchiveStatus::Unverifiable,
+        };
+        let checksums = [Checksum {
+            checksum_type,
+            value: value.to_string(),
+        }];
+        let Ok(mut verifier) = ChecksumVerifier::new(&checksums, std::io::sink()) else {
+            return CachedArchiveStatus::Unverifiable;
+        };
+        let copied = std::fs::File::open(archive)
+            .and_then(|mut file| std::io::copy(&mut CancellableRead(&mut file), &mut verifier));
+        match copied {
+            Ok(_) if verifier.verify().is_ok() => CachedArchiveStatus::Valid,
+            Ok(_) => CachedArchiveStatus::Corrupted,
+            Err(e) => {
+                debug!("Could not read cached archive {:?}: {}", archive, e);
+                CachedArchiveStatus::Corrupted
+            }
+        }
+    }
+
+    fn verify_cached_archive(info: &FoojayPackageInfo, path: &Path) -> bool {
+        let Ok(mut file) = std::fs::File::open(path) else {
+            return false;
+        };
+        let Ok(mut checksum_verifier) = ChecksumVerifier::new(&info.checksums(), std::io::sink())
+        else {
+            return false;
+        };
+        std::io::copy(&mut file, &mut checksum_verifier).is_ok()
+            && checksum_verifier.verify().is_ok()
+    }
+
+    fn cleanup_unpack_dir(unpack_dir: TempDir) {
+        let path = unpack_dir.path().to_owned();
+        if let Err(delete_err) = unpack_dir.close() {
+            warn!(
+                "Could not delete invalid download dir at {:?}: {}",
+                path, delete_err
+            );
+        }
+    }
+
+    /// Start downloading the archive from `offset`, or open its fixture when replaying HTTP
+    /// fixtures.
+    fn get_download(
+        &self,
+        info: &FoojayPackageInfo,
+        offset: u64,
+    ) -> ESResult<DownloadBody, JdkManagerError> {
+        let url = &info.direct_download_uri;
+        if let Some(replayed) = http_fixtures::replay_download(url) {
+            let mut file = replayed.change_context(JdkManagerError)?;
+            file.seek(SeekFrom::Start(offset))
+                .change_context(JdkManagerError)?;
+            return Ok(DownloadBody {
+                content_length: file.metadata().ok().map(|m| m.len()),
+                content_disposition: None,
+                reader: Box::new(file),
+            });
+        }
+        let mut request = self.client.get(url.as_str());
+        if offset > 0 {
+            request = request.set("Range", &format!("bytes={}-", offset));
+        }
+        let response = match request.call() {
+            Ok(response) => response,
+            Err(e) => {
+                let message = DownloadFailure::of(&e).user_message(url);
+                let mut report = Report::new(e)
+                    .change_context(JdkManagerError)
+                    .attach_printable(format!("Could not download JDK package from {}", url));
+                if let Some(message) = message {
+                    report = report.attach(UserMessage { message });
+                }
+                return Err(report);
+            }
+        };
+        let partial = response.status() == 206;
+        let content_length = response
+            .header("Content-Length")
+            .and_then(|s| s.parse::<u64>().ok())
+            .map(|length| if partial { offset + length } else { length });
+        let content_disposition = response.header("Content-Disposition").map(str::to_string);
+        let mut reader = response.into_reader();
+        if offset > 0 && !partial {
+            debug!(
+                "Server ignored the range request, skipping {} bytes",
+                offset
+            );
+            std::io::copy(&mut (&mut reader).take(offset), &mut std::io::sink())
+                .change_context(JdkManagerError)
+                .attach_printable_lazy(|| format!("Could not resume download from {}", url))?;
+        }
+        Ok(DownloadBody {
+            content_length,
+            content_disposition,
+            reader,
+        })
+    }
+
+    /// Download `body` to `download_path`, verifying the checksum. Broken transfers are resumed
+    /// while `attempts` allows it, the checksum covers the resumed parts as well.
+    fn download_jdk_to_file(
+        &self,
+        list_info: &FoojayPackageListInfo,
+        info: &FoojayPackageInfo,
+        body: DownloadBody,
+        download_path: &Path,
+        attempts: &mut DownloadAttempts,
+    ) -> ESResult<(), JdkManagerError> {
+        let file = std::fs::File::create(download_path)
+            .change_context(JdkManagerError)
+            .attach_printable_lazy(|| {
+                format!(
+                    "Could not create file for JDK download at {:?}",
+                    download_path
+                )
+            })?;
+        let mut checksum_verifier =
+            ChecksumVerifier::new(&info.checksums(), &file).change_context(JdkManagerError)?;
+        // Foojay's size is good enough for the progress bar if the server doesn't send one.
+        let content_length = body
+            .content_length
+            .or((list_info.size > 0).then_some(list_info.size));
+        let jdk = list_info.java_version.to_string();
+        progress_events::emit(&ProgressEvent::Started {
+            stage: Stage::Download,
+            jdk: &jdk,
+            total_bytes: content_length,
+        });
+        let progress_bar = new_progress_bar(content_length).with_message(
+            format!("Downloading JDK {}", list_info.java_version)
+                .if_supports_color(Stream::Stderr, |s| s.green())
+                .to_string(),
+        );
+        let mut sink = progress_bar.wrap_write(EventBytes::new(
+            Stage::Download,
+            content_length,
+            &mut checksum_verifier,
+        ));
+        let mut reader = body.reader;
+        loop {
+            let copied = std::io::copy(&mut CancellableRead(reader), &mut sink);
+            jobs::check_cancelled().change_context(JdkManagerError)?;
+            let Err(e) = copied else {
+                break;
+            };
+            // Everything written so far is in the file, so it's where to resume from.
+            let written = file.metadata().map(|m| m.len()).unwrap_or(0);
+            if !attempts.retry(&e, written) {
+                return Err(Report::new(e)
+                    .change_context(JdkManagerError)
+                    .attach_printable(format!(
+                        "Could not write JDK package to {:?}",
+                        download_path
+                    )));
+            }
+            reader = loop {
+                match self.get_download(info, written) {
+                    Ok(body) => break body.reader,
+                    Err(e) if attempts.retry_request(&e, written) => {}
+                    Err(e) => return Err(e),
+                }
+            };
+        }
+        drop(sink);
+        let downloaded = file
+            .metadata()
+            .change_context(JdkManagerError)
+            .attach_printable_lazy(|| format!("Could not read JDK package at {:?}", download_path))?
+            .len();
+        record_downloaded_bytes(downloaded);
+        progress_events::emit(&ProgressEvent::Finished {
+            stage: Stage::Download,
+            bytes: downloaded,
+        });
+        let size_mismatch = (list_info.size > 0 && downloaded != list_info.size).then(|| {
+            format!(
+                "Downloaded {} bytes, but Foojay lists {} bytes",
+                downloaded, list_info.size
+            )
+        });
+        if let Err(checksum) = checksum_verifier.verify() {
+            let mut report = Report::new(JdkManagerError).attach_printable(format!(
+                "Checksum {} failed for {}",
+                checksum, info.direct_download_uri
+            ));
+            if let Some(size_mismatch) = size_mismatch {
+                report = report.attach_printable(size_mismatch);
+            }
+            return Err(report);
+        }
+        // The checksum is what counts, Foojay's size may be out of date.
+        if let Some(size_mismatch) = size_mismatch {
+            warn!("{}, but the checksum matches", size_mismatch);
+        }
+        progress_bar.abandon_with_message(
+            format!(
+                "Downloaded JDK {} archive, {} in {}",
+                list_info.java_version,
+                format_bytes(downloaded),
+                format_duration(progress_bar.elapsed())
+            )
+            .if_supports_color(Stream::Stderr, |s| s.green())
+            .to_string(),
+        );
+        Ok(())
+    }
+
+    fn unpack_jdk(
+        list_info: &FoojayPackageListInfo,
+        download_path: &Path,
+        unpack_dir: &Path,
+    ) -> ESResult<(), JdkManagerError> {
+        let archive_size = std::fs::metadata(download_path)
+            .change_context(JdkManagerError)
+            .attach_printable_lazy(|| {
+                format!(
+                    "Could not get metadata for JDK download at {:?}",
+                    download_path
+                )
+            })?
+            .len();
+        let jdk = list_info.java_version.to_string();
+        progress_events::emit(&ProgressEvent::Started {
+            stage: Stage::Extract,
+            jdk: &jdk,
+            total_bytes: Some(archive_size),
+        });
+        let archive_bar = new_progress_bar(Some(archive_size));
+        let writing_bar = new_progress_bar(None);
+        let archive = std::fs::File::open(download_path)
+            .change_context(JdkManagerError)
+            .attach_printable_lazy(|| {
+                format!("Could not open JDK download at {:?}", download_path)
+            })?;
+        archive::extract(
+            &list_info.archive_type,
+            archive_bar.wrap_read(EventBytes::new(Stage::Extract, Some(archive_size), archive)),
+            unpack_dir,
+            &writing_bar,
+        )
+        .change_context(JdkManagerError)
+        .attach_printable_lazy(|| {
+            format!("Could not extract JDK download at {:?}", download_path)
+        })?;
+        archive_bar.finish();
+        progress_events::emit(&ProgressEvent::Finished {
+            stage: Stage::Extract,
+            bytes: archive_size,
+        });
+        writing_bar.abandon_with_message(
+            format!("Extracted in {}", format_duration(writing_bar.elapsed()))
+                .if_supports_color(Stream::Stderr, |s| s.green())
+                .to_string(),
+        );
+        Ok(())
+    }
+
+    /// Compare the version the JDK reports about itself to what Foojay told us. Vendors don't always
+    /// agree on the format, so this is only informational.
+    fn check_release_version(list_info: &FoojayPackageListInfo, jdk_root: &Path) {
+        match ReleaseFile::read(jdk_root).and_then(|r| match r {
+            Some(r) => r.java_version(),
+            None => Ok(None),
+        }) {
+            Ok(Some(version)) if version != list_info.java_version => debug!(
+                "JDK release file reports version {}, expected {}",
+                version, list_info.java_version
+            ),
+            Ok(_) => {}
+            Err(e) => debug!("Could not read JDK release file: {:?}", e),
+        }
+    }
+
+    /// Apply platform-specific fixes and checks to a freshly unpacked JDK.
+    fn post_unpack(config: &JpreConfig, jdk_root: &Path) -> ESResult<(), JdkManagerError> {
+        if config.install_native_image {
+            install_native_image(jdk_root);
+        }
+        if std::env::consts::OS != "macos" {
+            return Ok(());
+        }
+        if config.clear_quarantine {
+            clear_quarantine(jdk_root);
+        }
+        if config.verify_codesign {
+            verify_codesign(jdk_root).change_context(JdkManagerError)?;
+        }
+        Ok(())
+    }
+
+    /// Whether the JDK has GraalVM's `native-image` tool.
+    pub fn has_native_image(&self, jdk: &VersionKey) -> bool {
+        find_native_image(&jdk_path(jdk)).is_some()
+    }
+
+    pub fn get_jdk_install_path(&self, jdk: &VersionKey) -> PathBuf {
+        jdk_path(jdk)
+    }
+
+    /// How much space an installed JDK takes, as recorded at install time if it was.
+    pub fn retained_size(&self, retained: &RetainedJdk) -> u64 {
+        match InstallMetadata::read(&retained.path) {
+            Ok(Some(metadata)) if metadata.installed_size > 0 => metadata.installed_size,
+            _ => dir_size(&retained.path),
+        }
+    }
+
+    pub fn installed_size(&self, jdk: &VersionKey) -> u64 {
+        match InstallMetadata::read(&jdk_path(jdk)) {
+            Ok(Some(metadata)) if metadata.installed_size > 0 => metadata.installed_size,
+            _ => dir_size(&jdk_path(jdk)),
+        }
+    }
+
+    fn determine_jdk_root(unpack_dir: &Path) -> ESResult<PathBuf, JdkManagerError> {
+        let entries = std::fs::read_dir(unpack_dir)
+            .change_context(JdkManagerError)
+            .attach_printable_lazy(|| {
+                format!("Could not read JDK unpack directory at {:?}", unpack_dir)
             })?
             .map(|r| r.map(|e| e.path()))
             .filter(|r| match r {
@@ -458,3 +1639,383 @@ impl JdkManager {
         }
     }
 }
+
+/// The lock of [JdkManager::lock_store].
+pub struct StoreLock {
+    _file: std::fs::File,
+}
+
+/// A downloaded JDK archive. Temporary archives are deleted when dropped.
+enum DownloadedArchive {
+    /// Taken from the download cache without downloading.
+    Reused(PathBuf),
+    /// Downloaded and kept in the download cache.
+    Cached(PathBuf),
+    Temporary(TempPath),
+}
+
+impl Deref for DownloadedArchive {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        match self {
+            DownloadedArchive::Reused(path) | DownloadedArchive::Cached(path) => path,
+            DownloadedArchive::Temporary(path) => path,
+        }
+    }
+}
+
+/// What [JdkManager::clean_downloads] removes. `None` means no limit.
+#[derive(Debug, Default)]
+pub struct DownloadCleanupPolicy {
+    pub max_age: Option<Duration>,
+    pub max_size: Option<u64>,
+}
+
+impl DownloadCleanupPolicy {
+    pub fn from_config(config: &JpreConfig) -> Self {
+        Self {
+            max_age: config
+                .downloads_max_age_days
+                .map(|days| Duration::from_secs(days * 24 * 60 * 60)),
+            max_size: config.downloads_max_size_mb.map(|mb| mb * 1024 * 1024),
+        }
+    }
+}
+
+/// What [JdkManager::migrate_legacy_markers] did to one JDK.
+#[derive(Debug)]
+pub enum MarkerMigration {
+    /// Now has a versioned marker.
+    Migrated(JavaVersion),
+    /// Its `release` file has no usable version, so only reinstalling it helps.
+    NoVersion,
+    Failed(Report<JdkManagerError>),
+}
+
+/// The result of checking one cached archive.
+#[derive(Debug)]
+pub struct CachedArchiveCheck {
+    pub archive: PathBuf,
+    pub size: u64,
+    pub status: CachedArchiveStatus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachedArchiveStatus {
+    Valid,
+    /// Didn't match its checksum, and was removed.
+    Corrupted,
+    /// Its checksum's algorithm isn't supported by this build.
+    Unverifiable,
+}
+
+#[derive(Debug, Default)]
+pub struct DownloadCleanupReport {
+    pub removed: usize,
+    pub reclaimed_bytes: u64,
+}
+
+/// Remove a directory in the store or the downloads, without following symlinks out of them.
+fn remove_dir_if_exists(path: &Path) {
+    let Some(root) = [&*JDK_STORE_PATH, &*JDK_DOWNLOADS_PATH]
+        .into_iter()
+        .find(|root| path.starts_with(root))
+    else {
+        warn!(
+            "Not removing {:?}, it's outside of jpre's directories",
+            path
+        );
+        return;
+    };
+    match remove_dir_all_within(root, path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => warn!("Could not remove {:?}: {}", path, e),
+    }
+}
+
+pub fn is_older_than(path: &Path, age: Duration) -> bool {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .is_ok_and(|modified| {
+            SystemTime::now()
+                .duration_since(modified)
+                .unwrap_or_default()
+                > age
+        })
+}
+
+/// The latest modification time of anything in `dir`, or the epoch if it's empty.
+fn newest_modified(dir: &Path) -> SystemTime {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return SystemTime::UNIX_EPOCH;
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let metadata = e.metadata().ok()?;
+            let modified = metadata.modified().ok()?;
+            Some(if metadata.is_dir() {
+                newest_modified(&e.path()).max(modified)
+            } else {
+                modified
+            })
+        })
+        .max()
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let metadata = e.metadata().ok()?;
+            Some(if metadata.is_dir() {
+                dir_size(&e.path())
+            } else {
+                metadata.len()
+            })
+        })
+        .sum()
+}
+
+/// The download cache is keyed by checksum, so a changed upstream archive is never mixed up with
+/// an old one. Returns `None` if the checksum isn't safe to use as a directory name.
+fn archive_cache_dir(info: &FoojayPackageInfo) -> Option<PathBuf> {
+    let checksum = info.checksum.to_ascii_lowercase();
+    if checksum.is_empty() || !checksum.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(JDK_DOWNLOADS_PATH.join(checksum))
+}
+
+/// Remove the cached archive of a JDK that's no longer installed, unless `installed` uses the same
+/// archive.
+fn remove_cached_archive(removed: Option<&InstallMetadata>, installed: &InstallMetadata) {
+    let Some(removed) = removed else {
+        return;
+    };
+    let sha256 = removed.sha256.to_ascii_lowercase();
+    if sha256.is_empty()
+        || !sha256.chars().all(|c| c.is_ascii_hexdigit())
+        || sha256 == installed.sha256.to_ascii_lowercase()
+    {
+        return;
+    }
+    let cache_dir = JDK_DOWNLOADS_PATH.join(sha256);
+    if cache_dir.exists() {
+        debug!(
+            "Removing cached archive of superseded JDK at {:?}",
+            cache_dir
+        );
+        remove_dir_if_exists(&cache_dir);
+    }
+}
+
+fn find_cached_archive(cache_dir: &Path) -> Option<PathBuf> {
+    std::fs::read_dir(cache_dir)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .find(|p| p.is_file())
+}
+
+/// The body of a JDK download.
+struct DownloadBody {
+    /// The size of the whole archive, if known.
+    content_length: Option<u64>,
+    content_disposition: Option<String>,
+    reader: Box<dyn Read + Send>,
+}
+
+/// Counts the attempts of one download, see [configure_retry_resume].
+struct DownloadAttempts {
+    failed: u32,
+    max: u32,
+}
+
+impl DownloadAttempts {
+    fn new() -> Self {
+        Self {
+            failed: 0,
+            max: if RETRY_RESUME.get().copied().unwrap_or(false) {
+                DOWNLOAD_ATTEMPTS
+            } else {
+                1
+            },
+        }
+    }
+
+    /// Record a failed attempt, and if there are attempts left, say so and wait before the next
+    /// one, which resumes from `position`.
+    fn retry(&mut self, error: impl std::fmt::Display, position: u64) -> bool {
+        self.retry_after(error, position, DOWNLOAD_RETRY_DELAY * (self.failed + 1))
+    }
+
+    /// Like [Self::retry], for a failed request. Gives up early when the response says retrying
+    /// won't help, and waits as long as a rate limit asks.
+    fn retry_request(&mut self, report: &Report<JdkManagerError>, position: u64) -> bool {
+        let delay = DOWNLOAD_RETRY_DELAY * (self.failed + 1);
+        let delay = match report
+            .downcast_ref::<ureq::Error>()
+            .map(DownloadFailure::of)
+        {
+            Some(failure) => match failure.retry_delay(delay) {
+                Some(delay) => delay,
+                None => return false,
+            },
+            None => delay,
+        };
+        self.retry_after(request_failure(report), position, delay)
+    }
+
+    fn retry_after(
+        &mut self,
+        error: impl std::fmt::Display,
+        position: u64,
+        delay: Duration,
+    ) -> bool {
+        self.failed += 1;
+        if self.failed >= self.max || jobs::is_cancelled() {
+            return false;
+        }
+        eprint_above_progress(format!(
+            "Download failed after {}: {}. Retrying ({}/{})",
+            format_bytes(position),
+            error,
+            self.failed + 1,
+            self.max
+        ));
+        let until = Instant::now() + delay;
+        while Instant::now() < until && !jobs::is_cancelled() {
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        !jobs::is_cancelled()
+    }
+}
+
+/// Why a download request failed, for retry messages.
+fn request_failure(report: &Report<JdkManagerError>) -> String {
+    report
+        .downcast_ref::<ureq::Error>()
+        .map_or_else(|| report.to_string(), ToString::to_string)
+}
+
+/// Pick a name for the downloaded archive, preferring the server-provided one.
+fn download_file_name(content_disposition: Option<&str>, info: &FoojayPackageInfo) -> String {
+    let from_header = content_disposition.and_then(|header| {
+        ContentDisposition::parse(header)
+            .inspect_err(|e| debug!("Ignoring Content-Disposition header {:?}: {:?}", header, e))
+            .ok()?
+            .filename
+    });
+    let name = from_header.or_else(|| {
+        info.direct_download_uri
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .map(str::to_string)
+    });
+    // Never trust the name to be a plain file name.
+    name.as_deref()
+        .map(Path::new)
+        .and_then(Path::file_name)
+        .and_then(|n| n.to_str())
+        .filter(|n| !n.is_empty())
+        .unwrap_or("jdk")
+        .to_string()
+}
+
+fn find_native_image(jdk_root: &Path) -> Option<PathBuf> {
+    // Older GraalVM releases install components under `lib/svm` and only link them into `bin`.
+    ["bin/native-image", "lib/svm/bin/native-image"]
+        .iter()
+        .map(|p| jdk_root.join(p))
+        .find(|p| p.is_file())
+}
+
+/// Older GraalVM builds (before 23) ship `native-image` as a separate component, installed with
+/// `gu`. Failures are not fatal, the JDK itself still works.
+fn install_native_image(jdk_root: &Path) {
+    let gu = jdk_root.join("bin/gu");
+    if !gu.is_file() || find_native_image(jdk_root).is_some() {
+        return;
+    }
+    debug!("Installing native-image using {:?}", gu);
+    match std::process::Command::new(&gu)
+        .args(["install", "native-image"])
+        .status()
+    {
+        Ok(status) if !status.success() => {
+            warn!("'gu install native-image' failed with {}", status)
+        }
+        Ok(_) => {}
+        Err(e) => warn!("Could not run {:?}: {}", gu, e),
+    }
+}
+
+/// Remove the quarantine attribute from the whole tree, so Gatekeeper doesn't prompt for every
+/// binary. Failures are not fatal, the JDK still works, it's just more annoying to use.
+fn clear_quarantine(jdk_root: &Path) {
+    debug!("Clearing quarantine attribute from {:?}", jdk_root);
+    match std::process::Command::new("xattr")
+        .args(["-r", "-d", "com.apple.quarantine"])
+        .arg(jdk_root)
+        .stderr(std::process::Stdio::null())
+        .status()
+    {
+        Ok(status) if !status.success() => {
+            // Also happens when no file had the attribute, so don't make noise about it.
+            debug!("xattr exited with {}", status);
+        }
+        Ok(_) => {}
+        Err(e) => warn!("Could not run xattr to clear quarantine attribute: {}", e),
+    }
+}
+
+/// The code signature of a JDK didn't verify, see `verify_codesign` in the config. That's for the
+/// user to look into, see [install_error].
+#[derive(Debug, Display)]
+#[display("Code signature verification failed")]
+pub struct CodesignError;
+
+impl Context for CodesignError {}
+
+/// Turn a failed install into a [JpreError::UserError] if it's one the user has to look into,
+/// like a JDK whose code signature didn't verify, else into a [JpreError::Unexpected].
+pub fn install_error(report: Report<JdkManagerError>) -> Report<JpreError> {
+    if report.contains::<CodesignError>() {
+        report.change_context(JpreError::UserError)
+    } else {
+        report.change_context(JpreError::Unexpected)
+    }
+}
+
+fn verify_codesign(jdk_root: &Path) -> ESResult<(), CodesignError> {
+    let java = jdk_root.join("bin/java");
+    debug!("Verifying code signature of {:?}", java);
+    let output = std::process::Command::new("codesign")
+        .args(["--verify", "--strict"])
+        .arg(&java)
+        .output()
+        .change_context(CodesignError)
+        .attach_printable("Could not run codesign")?;
+    if !output.status.success() {
+        return Err(Report::new(CodesignError)
+            .attach_printable(format!(
+                "codesign output: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+            .attach(UserMessage {
+                message: format!("Code signature verification failed for {}", java.display()),
+            })
+            .attach(UserMessage {
+                message: "Set 'verify_codesign = false' in the config to skip this check"
+                    .to_string(),
+            }));
+    }
+    Ok(())
+}
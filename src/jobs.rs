@@ -0,0 +1,146 @@
+use derive_more::Display;
+use error_stack::Context;
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, Once};
+use tracing::{debug, warn};
+
+/// Returned when Ctrl-C was pressed during an [Interruptible] operation.
+#[derive(Debug, Display)]
+#[display("Cancelled")]
+pub struct Cancelled;
+
+impl Context for Cancelled {}
+
+/// The exit code when jpre stops because of Ctrl-C, like shells use for SIGINT.
+pub const CANCELLED_EXIT_CODE: i32 = 130;
+
+/// How many requests to Foojay or file operations to run at once, unless a command knows better.
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+/// How many [Interruptible] guards are alive.
+static INTERRUPTIBLE: AtomicUsize = AtomicUsize::new(0);
+static INSTALL_HANDLER: Once = Once::new();
+
+/// Ctrl-C while something [Interruptible] runs only asks it to stop, so it can clean up. Otherwise,
+/// or on a second Ctrl-C, jpre exits right away like it would without a handler.
+fn install_interrupt_handler() {
+    INSTALL_HANDLER.call_once(|| {
+        let result = ctrlc::set_handler(|| {
+            if INTERRUPTIBLE.load(Ordering::SeqCst) == 0 || CANCELLED.swap(true, Ordering::SeqCst) {
+                std::process::exit(CANCELLED_EXIT_CODE);
+            }
+            eprintln!("Cancelling, press Ctrl-C again to exit immediately...");
+        });
+        if let Err(e) = result {
+            warn!("Could not install Ctrl-C handler: {}", e);
+        }
+    });
+}
+
+/// While alive, Ctrl-C doesn't exit but sets [is_cancelled], which the holder must check.
+#[must_use]
+pub struct Interruptible(());
+
+pub fn interruptible() -> Interruptible {
+    install_interrupt_handler();
+    INTERRUPTIBLE.fetch_add(1, Ordering::SeqCst);
+    Interruptible(())
+}
+
+impl Drop for Interruptible {
+    fn drop(&mut self) {
+        INTERRUPTIBLE.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::SeqCst)
+}
+
+pub fn check_cancelled() -> Result<(), Cancelled> {
+    if is_cancelled() {
+        Err(Cancelled)
+    } else {
+        Ok(())
+    }
+}
+
+/// A reader that fails once Ctrl-C was pressed, to stop long copies.
+pub struct CancellableRead<R>(pub R);
+
+impl<R: Read> Read for CancellableRead<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if is_cancelled() {
+            // Not `ErrorKind::Interrupted`, `io::copy` retries those.
+            return Err(std::io::Error::other("Cancelled"));
+        }
+        self.0.read(buf)
+    }
+}
+
+/// Run `job` for each item with at most `concurrency` running at once. Results are in the same
+/// order as `items`. If Ctrl-C is pressed, no new jobs are started and [Cancelled] is returned
+/// once the running ones finish.
+pub fn run_bounded<T, R, F>(items: Vec<T>, concurrency: usize, job: F) -> Result<Vec<R>, Cancelled>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> R + Sync,
+{
+    let interruptible = interruptible();
+    check_cancelled()?;
+    let count = items.len();
+    let items = Mutex::new(items.into_iter().enumerate());
+    let results = Mutex::new((0..count).map(|_| None).collect::<Vec<Option<R>>>());
+    std::thread::scope(|s| {
+        for _ in 0..concurrency.clamp(1, count.max(1)) {
+            s.spawn(|| loop {
+                if is_cancelled() {
+                    break;
+                }
+                let Some((i, item)) = items.lock().unwrap().next() else {
+                    break;
+                };
+                let result = job(item);
+                results.lock().unwrap()[i] = Some(result);
+            });
+        }
+    });
+    drop(interruptible);
+    let results = results.into_inner().unwrap();
+    if is_cancelled() {
+        debug!(
+            "Cancelled after {} of {} jobs",
+            results.iter().filter(|r| r.is_some()).count(),
+            count
+        );
+        return Err(Cancelled);
+    }
+    Ok(results
+        .into_iter()
+        .map(|r| r.expect("every job ran"))
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_run_bounded_keeps_order() {
+        let running = AtomicUsize::new(0);
+        let max_running = AtomicUsize::new(0);
+        let results = run_bounded((0..50).collect(), 4, |i: u64| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            max_running.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            running.fetch_sub(1, Ordering::SeqCst);
+            i * 2
+        })
+        .unwrap();
+        assert_eq!((0..50).map(|i| i * 2).collect::<Vec<_>>(), results);
+        assert!(max_running.load(Ordering::SeqCst) <= 4);
+    }
+}
@@ -0,0 +1,119 @@
+use crate::context_id::state_dir;
+use crate::error::ESResult;
+use crate::java_version::key::VersionKey;
+use crate::java_version::JavaVersion;
+use derive_more::Display;
+use error_stack::{Context, ResultExt};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::SystemTime;
+use tracing::debug;
+
+#[derive(Debug, Display)]
+pub struct JournalError;
+
+impl Context for JournalError {}
+
+const JOURNAL_FILE_NAME: &str = "journal.jsonl";
+
+static ENABLED: OnceLock<bool> = OnceLock::new();
+
+/// Turn recording on or off, from the `usage_journal` config. Off until configured.
+pub fn configure(enabled: bool) {
+    let _ = ENABLED.set(enabled);
+}
+
+fn is_enabled() -> bool {
+    ENABLED.get().copied().unwrap_or(false)
+}
+
+fn journal_path() -> PathBuf {
+    state_dir().join(JOURNAL_FILE_NAME)
+}
+
+/// Something jpre did, as recorded in the journal. Only versions and distributions are recorded,
+/// no paths or anything else about the machine.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)] // Journals are small, boxing the versions isn't worth it.
+pub enum JournalEvent {
+    Installed {
+        jdk: VersionKey,
+        distribution: String,
+        version: JavaVersion,
+        /// The version that was replaced, if this was an update.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        previous: Option<JavaVersion>,
+    },
+    Used {
+        jdk: VersionKey,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct JournalEntry {
+    /// Seconds since the Unix epoch.
+    pub at: u64,
+    #[serde(flatten)]
+    pub event: JournalEvent,
+}
+
+/// Append `event` to the journal, if it's enabled. Failing to record isn't worth failing the
+/// command for.
+pub fn record(event: JournalEvent) {
+    if !is_enabled() {
+        return;
+    }
+    let entry = JournalEntry {
+        at: SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        event,
+    };
+    if let Err(e) = append(&entry) {
+        debug!("Could not record {:?}: {:?}", entry, e);
+    }
+}
+
+fn append(entry: &JournalEntry) -> ESResult<(), JournalError> {
+    let path = journal_path();
+    std::fs::create_dir_all(path.parent().unwrap())
+        .change_context(JournalError)
+        .attach_printable_lazy(|| format!("Could not create {:?}", path.parent()))?;
+    let mut line = serde_json::to_vec(entry).change_context(JournalError)?;
+    line.push(b'\n');
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut f| f.write_all(&line))
+        .change_context(JournalError)
+        .attach_printable_lazy(|| format!("Could not write to {:?}", path))
+}
+
+/// Read every entry of the journal. Lines that can't be parsed, e.g. from a newer jpre, are
+/// skipped.
+pub fn read() -> ESResult<Vec<JournalEntry>, JournalError> {
+    let path = journal_path();
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(e)
+                .change_context(JournalError)
+                .attach_printable_lazy(|| format!("Could not read {:?}", path))
+        }
+    };
+    Ok(contents
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .filter_map(|l| {
+            serde_json::from_str(l)
+                .inspect_err(|e| debug!("Skipping journal line {:?}: {}", l, e))
+                .ok()
+        })
+        .collect())
+}
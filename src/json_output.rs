@@ -0,0 +1,84 @@
+use crate::error::{ESResult, JpreError};
+use error_stack::ResultExt;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Metadata, Schema, SchemaObject};
+use schemars::JsonSchema;
+use serde::{Serialize, Serializer};
+
+/// The version of the JSON jpre writes for scripts. Bump it when a field is removed, renamed or
+/// changes meaning; new fields don't need a bump.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A `schema_version` field, always [SCHEMA_VERSION], so tools can tell which schema a document
+/// follows.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SchemaVersion;
+
+impl Serialize for SchemaVersion {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u32(SCHEMA_VERSION)
+    }
+}
+
+impl JsonSchema for SchemaVersion {
+    fn schema_name() -> String {
+        "SchemaVersion".to_string()
+    }
+
+    fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::Integer.into()),
+            const_value: Some(SCHEMA_VERSION.into()),
+            metadata: Some(Box::new(Metadata {
+                description: Some(
+                    "Changes when a field is removed, renamed or changes meaning.".to_string(),
+                ),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+/// Print the JSON Schema of `T` to stdout, for `--schema`.
+pub fn print_schema<T: JsonSchema>() -> ESResult<(), JpreError> {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&schemars::schema_for!(T))
+            .change_context(JpreError::Unexpected)
+            .attach_printable("Failed to serialize JSON Schema")?
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Serialize, JsonSchema)]
+    struct Document {
+        schema_version: SchemaVersion,
+    }
+
+    #[test]
+    fn test_schema_version() {
+        assert_eq!(
+            format!(r#"{{"schema_version":{}}}"#, SCHEMA_VERSION),
+            serde_json::to_string(&Document {
+                schema_version: SchemaVersion
+            })
+            .unwrap()
+        );
+        let schema = serde_json::to_value(schemars::schema_for!(Document)).unwrap();
+        assert_eq!(
+            SCHEMA_VERSION,
+            schema["definitions"]["SchemaVersion"]["const"]
+                .as_u64()
+                .unwrap() as u32
+        );
+    }
+}
@@ -1,21 +1,66 @@
+use crate::command::api_dump::ApiDump;
+use crate::command::available::Available;
+use crate::command::bench::Bench;
+use crate::command::cache::Cache;
+use crate::command::clean_downloads::CleanDownloads;
+use crate::command::complete_versions::CompleteVersions;
 use crate::command::current::Current;
 use crate::command::debug::Debug;
+use crate::command::direnv::Direnv;
+use crate::command::doctor::Doctor;
+use crate::command::explain::Explain;
+use crate::command::gc::Gc;
 use crate::command::get_context_id::GetContextId;
+use crate::command::hook_env::HookEnv;
+use crate::command::info::Info;
+use crate::command::install::Install;
 use crate::command::java_home::JavaHome;
 use crate::command::list_distributions::ListDistributions;
 use crate::command::list_installed::ListInstalled;
 use crate::command::list_versions::ListVersions;
+use crate::command::migrate_from::MigrateFrom;
+use crate::command::migrate_markers::MigrateMarkers;
+use crate::command::pack::Pack;
+use crate::command::print_context_path::PrintContextPath;
+use crate::command::prompt_segment::PromptSegment;
+use crate::command::relocate::Relocate;
 use crate::command::remove_jdk::RemoveJdk;
+use crate::command::sbom::Sbom;
+use crate::command::search::Search;
+use crate::command::serve::Serve;
 use crate::command::set_default::SetDefault;
 use crate::command::set_distributions::SetDistributions;
+use crate::command::shell::Shell;
+use crate::command::stats::Stats;
+use crate::command::unpack::Unpack;
 use crate::command::update::UpdateInstalled;
 use crate::command::use_jdk::UseJdk;
+use crate::command::verify::Verify;
+use crate::command::verify_store::VerifyStore;
 use crate::command::{Context, JpreCommand};
-use crate::config::JpreConfig;
+use crate::config::{
+    active_profile, configure_config_path, configure_profile, has_system_config,
+    is_alternate_config, JpreConfig,
+};
 use crate::error::{ESResult, JpreError, UserMessage};
+use crate::http_client::{configure_prefer_ipv4, configure_timeouts, HttpTimeouts};
+use crate::inventory::inventory_path;
+use crate::java_version::key::VersionKey;
+use crate::jdk_manager::{
+    configure_accept_license, configure_allow_emulated, configure_retry_resume, configure_store,
+    DownloadCleanupPolicy, JdkStore, MarkerMigration, JDK_MANAGER,
+};
+use crate::jobs::{Cancelled, CANCELLED_EXIT_CODE};
+use crate::platform::Platform;
+use crate::policy::Policy;
+use clap::error::{ContextKind, ContextValue};
 use clap::{Parser, Subcommand};
 use enum_dispatch::enum_dispatch;
-use tracing::error;
+use error_stack::ResultExt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tracing::{debug, error};
 use tracing_subscriber::fmt::format::{DefaultFields, Format};
 use tracing_subscriber::fmt::FormatEvent;
 use tracing_subscriber::Registry;
@@ -23,18 +68,54 @@ use tracing_subscriber::Registry;
 #[cfg(not(unix))]
 compile_error!("Only unix is supported");
 
+mod archive;
 mod checksum_verifier;
 mod command;
+mod completion_index;
 mod config;
+mod content_disposition;
 mod context_id;
+mod download_failure;
+mod eol;
 mod error;
 mod foojay;
+mod fs_util;
+mod http_cache;
 mod http_client;
+mod http_fixtures;
+mod install_metadata;
+mod inventory;
 mod java_home_management;
 mod java_version;
+mod jdk_archive;
 mod jdk_manager;
+mod jobs;
+mod journal;
+mod json_output;
+mod migration;
+mod notifications;
+mod package_manifest;
+mod platform;
+mod policy;
+mod progress_events;
+mod project_pin;
+mod release_file;
+mod release_notes;
+mod relocation;
+mod remembered_jdk;
+mod rosetta;
+mod sbom;
+mod stats;
+mod store_check;
 mod string;
+mod temp_context;
+#[cfg(test)]
+mod test_support;
+mod tree_manifest;
 mod tui;
+mod update_notice;
+mod update_schedule;
+mod update_state;
 
 /// java-preloader-reloadeder. A tool to manage Java installations.
 #[derive(Debug, Parser)]
@@ -44,6 +125,47 @@ struct Jpre {
     /// Verbosity level, repeat to increase.
     #[clap(short, long, action = clap::ArgAction::Count)]
     verbose: u8,
+    /// Timeout in seconds for network operations, overrides the config.
+    #[clap(long, global = true)]
+    timeout: Option<u64>,
+    /// Write JSON progress events to this open file descriptor, one per line, instead of drawing
+    /// progress bars.
+    #[clap(long, global = true, value_name = "FD")]
+    progress_fd: Option<i32>,
+    /// Use the system-wide JDK store shared by all users, like the `system_store` config.
+    #[clap(long, global = true)]
+    system: bool,
+    /// Fail on Foojay responses that don't match the known schema, instead of tolerating them.
+    /// For debugging API changes.
+    #[clap(long, global = true)]
+    strict_api: bool,
+    /// Retry broken JDK downloads a few times, resuming where they stopped, instead of failing
+    /// on the first broken transfer.
+    #[clap(long, global = true)]
+    retry_resume: bool,
+    /// Accept the license of the distribution when a JDK needs it, and remember that in the
+    /// config's `accepted_licenses`.
+    #[clap(long, global = true)]
+    accept_license: bool,
+    /// Install x64 JDKs on Apple Silicon even though they'd run emulated by Rosetta 2, when jpre
+    /// picks them because it runs under Rosetta 2 itself.
+    #[clap(long, global = true)]
+    allow_emulated: bool,
+    /// Use this config file instead of the default one. Also read from `JPRE_CONFIG`.
+    #[clap(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// Merge this `[profiles.<name>]` table of the config over the rest of it. Also read from
+    /// `JPRE_PROFILE`.
+    #[clap(long, global = true, value_name = "NAME")]
+    profile: Option<String>,
+    /// Download JDKs for this OS, by Foojay's name, instead of the detected one. Overrides
+    /// `forced_os` in the config.
+    #[clap(long, global = true)]
+    os: Option<String>,
+    /// Download JDKs for this architecture, by Foojay's name, instead of the detected one.
+    /// Overrides `forced_architecture` in the config.
+    #[clap(long, global = true)]
+    arch: Option<String>,
 }
 
 #[derive(Debug, Subcommand)]
@@ -51,6 +173,7 @@ struct Jpre {
 enum JpreCommandEnum {
     ListDistributions(ListDistributions),
     ListVersions(ListVersions),
+    Available(Available),
     ListInstalled(ListInstalled),
     SetDistributions(SetDistributions),
     Default(SetDefault),
@@ -59,18 +182,44 @@ enum JpreCommandEnum {
     Remove(RemoveJdk),
     GetContextId(GetContextId),
     JavaHome(JavaHome),
+    PrintContextPath(PrintContextPath),
     Current(Current),
     Update(UpdateInstalled),
+    ApiDump(ApiDump),
+    CleanDownloads(CleanDownloads),
+    Cache(Cache),
+    Search(Search),
+    HookEnv(HookEnv),
+    Install(Install),
+    Info(Info),
+    Relocate(Relocate),
+    Sbom(Sbom),
+    Gc(Gc),
+    Direnv(Direnv),
+    Bench(Bench),
+    MigrateFrom(MigrateFrom),
+    MigrateMarkers(MigrateMarkers),
+    Pack(Pack),
+    Unpack(Unpack),
+    Verify(Verify),
+    VerifyStore(VerifyStore),
+    Doctor(Doctor),
+    Stats(Stats),
+    Shell(Shell),
+    PromptSegment(PromptSegment),
+    Explain(Explain),
+    Serve(Serve),
+    #[command(name = "__complete-versions", hide = true)]
+    CompleteVersions(CompleteVersions),
 }
 
 fn main() {
-    if !sysinfo::IS_SUPPORTED_SYSTEM {
-        error!("Unsupported system: {}", std::env::consts::OS);
-        std::process::exit(1);
-    }
-
     match main_with_result() {
         Ok(()) => (),
+        Err(e) if e.contains::<Cancelled>() => {
+            error!("Cancelled");
+            std::process::exit(CANCELLED_EXIT_CODE);
+        }
         Err(e) if matches!(e.current_context(), JpreError::UserError) => {
             if !e.contains::<UserMessage>() {
                 error!("Critical error, user error missing message:\n{:?}", e);
@@ -90,13 +239,44 @@ fn main() {
         }
         Err(e) => {
             error!("{:?}", e);
+            // The report only shows hints of lower layers as opaque attachments.
+            for m in e
+                .frames()
+                .filter_map(|f| f.downcast_ref::<UserMessage>())
+                .collect::<Vec<_>>()
+                .into_iter()
+                .rev()
+            {
+                error!("Hint: {}", m.message);
+            }
             std::process::exit(2);
         }
     }
 }
 
+/// Parse the arguments, treating a bare version key in place of the command as `use`, so
+/// `jpre 17` is short for `jpre use 17`.
+fn parse_args() -> Jpre {
+    let args = std::env::args_os().collect::<Vec<_>>();
+    let err = match Jpre::try_parse_from(&args) {
+        Ok(parsed) => return parsed,
+        Err(err) => err,
+    };
+    if err.kind() == clap::error::ErrorKind::InvalidSubcommand {
+        if let Some(ContextValue::String(token)) = err.get(ContextKind::InvalidSubcommand) {
+            let position = args.iter().skip(1).position(|a| a == token.as_str());
+            if let (Some(position), Ok(_)) = (position, VersionKey::from_str(token)) {
+                let mut args = args.clone();
+                args.insert(position + 1, "use".into());
+                return Jpre::parse_from(args);
+            }
+        }
+    }
+    err.exit()
+}
+
 fn main_with_result() -> ESResult<(), JpreError> {
-    let args = Jpre::parse();
+    let args = parse_args();
 
     let mut env_filt = tracing_subscriber::filter::EnvFilter::builder()
         .with_default_directive(
@@ -134,13 +314,159 @@ fn main_with_result() -> ESResult<(), JpreError> {
         install_with_event_format(Format::default(), env_filt);
     }
 
+    foojay::configure_strict_api(args.strict_api);
+
+    if let Some(fd) = args.progress_fd {
+        progress_events::configure_fd(fd)
+            .change_context(JpreError::UserError)
+            .attach(UserMessage {
+                message: format!("Cannot write progress events to file descriptor {}", fd),
+            })?;
+    }
+
+    let config_override = args.config.or_else(|| {
+        std::env::var_os("JPRE_CONFIG")
+            .filter(|p| !p.is_empty())
+            .map(PathBuf::from)
+    });
+    if let Some(path) = config_override {
+        let path = std::path::absolute(&path)
+            .change_context(JpreError::UserError)
+            .attach(UserMessage {
+                message: format!("Invalid config path {}", path.display()),
+            })?;
+        configure_config_path(path);
+    }
+
+    let profile = args
+        .profile
+        .or_else(|| std::env::var("JPRE_PROFILE").ok().filter(|p| !p.is_empty()));
+    if let Some(profile) = profile {
+        configure_profile(profile);
+    }
+
+    // Runs for every prompt, so skip the config and maintenance.
+    if let JpreCommandEnum::PromptSegment(prompt_segment) = &args.command {
+        prompt_segment.print();
+        return Ok(());
+    }
+    // Promises not to write anything, so skip re-saving the config and the maintenance.
+    if let JpreCommandEnum::PrintContextPath(print_context_path) = &args.command {
+        return print_context_path.print(&JpreConfig::load_read_only()?);
+    }
+
     let config = JpreConfig::load()?;
-    // re-save config to ensure it's up-to-date
-    config.save()?;
+    // re-save config to ensure it's up-to-date, but leave alternate configs as they were written.
+    // With a profile or a system config, the loaded config has their options merged in, which
+    // don't belong in the user's config.
+    if !is_alternate_config() && active_profile().is_none() && !has_system_config() {
+        config.save()?;
+    }
+
+    policy::configure(Policy::load(&config)?);
+    journal::configure(config.usage_journal);
+    notifications::configure(config.notifications);
+
+    configure_timeouts(HttpTimeouts {
+        metadata: Duration::from_secs(args.timeout.unwrap_or(config.metadata_timeout_secs)),
+        download_read: Duration::from_secs(args.timeout.unwrap_or(config.download_timeout_secs)),
+    });
+    configure_prefer_ipv4(config.prefer_ipv4);
+
+    configure_store(if args.system || config.system_store {
+        JdkStore::System
+    } else {
+        JdkStore::User
+    });
+    configure_retry_resume(args.retry_resume);
+    configure_accept_license(args.accept_license);
+    configure_allow_emulated(args.allow_emulated);
+    // Runs for every prompt too, so leave the maintenance to other commands.
+    if !matches!(args.command, JpreCommandEnum::HookEnv(_)) {
+        relocation::check_locations();
+        // Only the commands that write the store recover it, the others shouldn't touch it.
+        if matches!(
+            args.command,
+            JpreCommandEnum::Use(_)
+                | JpreCommandEnum::Shell(_)
+                | JpreCommandEnum::Install(_)
+                | JpreCommandEnum::Update(_)
+                | JpreCommandEnum::Remove(_)
+                | JpreCommandEnum::Unpack(_)
+                | JpreCommandEnum::MigrateFrom(_)
+                | JpreCommandEnum::VerifyStore(_)
+        ) {
+            JDK_MANAGER.recover_interrupted_installs();
+        }
+        // Only the commands that go over the store anyway pay for the scan. `migrate-markers`
+        // reports what it migrates itself.
+        if matches!(
+            args.command,
+            JpreCommandEnum::ListInstalled(_)
+                | JpreCommandEnum::Update(_)
+                | JpreCommandEnum::Install(_)
+                | JpreCommandEnum::VerifyStore(_)
+        ) {
+            migrate_legacy_markers();
+        }
+        if !inventory_path().exists() {
+            JDK_MANAGER.update_inventory();
+        }
+
+        if config.auto_clean_downloads {
+            JDK_MANAGER.auto_clean_downloads(&DownloadCleanupPolicy::from_config(&config));
+        }
+    }
 
     let context = Context {
         config: config.clone(),
+        platform: Platform::resolve(
+            args.os.as_deref().or(config.forced_os.as_deref()),
+            args.arch
+                .as_deref()
+                .or(config.forced_architecture.as_deref()),
+        ),
     };
 
-    args.command.run(context)
+    // Only commands that download can take long enough to be worth a notification.
+    let notify_as = match &args.command {
+        JpreCommandEnum::Update(_) => Some("update"),
+        JpreCommandEnum::Install(_) => Some("install"),
+        JpreCommandEnum::Use(use_jdk) if !use_jdk.is_temp() => Some("use"),
+        _ => None,
+    };
+    // `update` just checked, and the others run too often or aren't read by people.
+    let update_notice = !matches!(
+        args.command,
+        JpreCommandEnum::Update(_)
+            | JpreCommandEnum::HookEnv(_)
+            | JpreCommandEnum::Serve(_)
+            | JpreCommandEnum::CompleteVersions(_)
+    );
+    let started = Instant::now();
+    let result = args.command.run(context);
+    if let Some(command) = notify_as {
+        notifications::notify_finished(command, started, &result);
+    }
+    if update_notice && result.is_ok() {
+        update_notice::show_after_command(&config);
+    }
+    result
+}
+
+/// Migrate legacy markers on the side, failures only matter to `migrate-markers`.
+fn migrate_legacy_markers() {
+    match JDK_MANAGER.migrate_legacy_markers() {
+        Ok(migrations) => {
+            for (jdk, migration) in migrations {
+                if let MarkerMigration::Migrated(version) = migration {
+                    debug!(
+                        "Migrated legacy marker of JDK {} to version {}",
+                        jdk, version
+                    );
+                }
+            }
+        }
+        Err(e) => debug!("Could not migrate legacy markers: {:?}", e),
+    }
 }
@@ -0,0 +1,226 @@
+use crate::error::ESResult;
+use crate::java_version::key::VersionKey;
+use crate::java_version::JavaVersion;
+use crate::release_file::ReleaseFile;
+use clap::ValueEnum;
+use derive_more::Display;
+use error_stack::{Context, Report, ResultExt};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tracing::debug;
+
+#[derive(Debug, Display)]
+pub struct MigrationError;
+
+impl Context for MigrationError {}
+
+/// Another tool that manages JDKs.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum MigrationSource {
+    Sdkman,
+    Jenv,
+    Asdf,
+}
+
+impl MigrationSource {
+    /// The directory holding one JDK per entry.
+    fn jdks_dir(self) -> ESResult<PathBuf, MigrationError> {
+        let (env_var, default_dir, sub_dir) = match self {
+            MigrationSource::Sdkman => ("SDKMAN_DIR", ".sdkman", "candidates/java"),
+            MigrationSource::Jenv => ("JENV_ROOT", ".jenv", "versions"),
+            MigrationSource::Asdf => ("ASDF_DATA_DIR", ".asdf", "installs/java"),
+        };
+        let root = match std::env::var_os(env_var) {
+            Some(root) => PathBuf::from(root),
+            None => directories::BaseDirs::new()
+                .ok_or_else(|| {
+                    Report::new(MigrationError).attach_printable("Could not find home directory")
+                })?
+                .home_dir()
+                .join(default_dir),
+        };
+        Ok(root.join(sub_dir))
+    }
+}
+
+/// A JDK installed by a [MigrationSource].
+#[derive(Debug)]
+pub struct ForeignJdk {
+    /// The name the other tool uses for it.
+    pub name: String,
+    pub java_home: PathBuf,
+    pub version: JavaVersion,
+}
+
+/// Find the JDKs installed by `source`. Entries that aren't JDKs, like SDKMAN's `current` link,
+/// are skipped, as are duplicates like jenv's aliases.
+pub fn discover(source: MigrationSource) -> ESResult<Vec<ForeignJdk>, MigrationError> {
+    let dir = source.jdks_dir()?;
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(e)
+                .change_context(MigrationError)
+                .attach_printable_lazy(|| format!("Could not read {:?}", dir))
+        }
+    };
+    let mut jdks: Vec<ForeignJdk> = Vec::new();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name == "current" {
+            continue;
+        }
+        let Ok(path) = entry.path().canonicalize() else {
+            debug!("Skipping dangling {:?}", entry.path());
+            continue;
+        };
+        // macOS bundles keep the actual home inside.
+        let java_home = [path.join("Contents/Home"), path]
+            .into_iter()
+            .find(|p| p.join("bin/java").exists());
+        let Some(java_home) = java_home else {
+            debug!("Skipping {:?}, not a JDK", entry.path());
+            continue;
+        };
+        if jdks.iter().any(|j| j.java_home == java_home) {
+            continue;
+        }
+        let version = ReleaseFile::read(&java_home)
+            .change_context(MigrationError)?
+            .map(|r| r.java_version())
+            .transpose()
+            .change_context(MigrationError)?
+            .flatten();
+        let Some(version) = version else {
+            debug!("Skipping {:?}, no version in its release file", java_home);
+            continue;
+        };
+        jdks.push(ForeignJdk {
+            name,
+            java_home,
+            version,
+        });
+    }
+    jdks.sort_by(|a, b| a.version.compare(&b.version).then(a.name.cmp(&b.name)));
+    Ok(jdks)
+}
+
+/// A version file of another tool, found in a project.
+#[derive(Debug)]
+pub struct VersionFile {
+    pub path: PathBuf,
+    pub jdk: VersionKey,
+}
+
+/// Find `.sdkmanrc` and `.java-version` files under `root`, skipping hidden directories.
+pub fn find_version_files(root: &Path) -> ESResult<Vec<VersionFile>, MigrationError> {
+    let mut found = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let entries = std::fs::read_dir(&dir)
+            .change_context(MigrationError)
+            .attach_printable_lazy(|| format!("Could not read {:?}", dir))?;
+        for entry in entries.filter_map(|e| e.ok()) {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                if !name.starts_with('.') {
+                    dirs.push(entry.path());
+                }
+                continue;
+            }
+            let parse: fn(&str) -> Option<VersionKey> = match name.as_str() {
+                ".sdkmanrc" => parse_sdkmanrc,
+                ".java-version" => parse_java_version_file,
+                _ => continue,
+            };
+            let path = entry.path();
+            match std::fs::read_to_string(&path)
+                .ok()
+                .as_deref()
+                .and_then(parse)
+            {
+                Some(jdk) => found.push(VersionFile { path, jdk }),
+                None => debug!("No Java version found in {:?}", path),
+            }
+        }
+    }
+    found.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(found)
+}
+
+/// The Java version of an `.sdkmanrc`, from its `java=<version>` line.
+fn parse_sdkmanrc(contents: &str) -> Option<VersionKey> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.starts_with('#'))
+        .filter_map(|l| l.split_once('='))
+        .find(|(k, _)| k.trim() == "java")
+        .and_then(|(_, v)| parse_foreign_version(v.trim()))
+}
+
+/// The version of a jenv or asdf `.java-version` file: its first line.
+fn parse_java_version_file(contents: &str) -> Option<VersionKey> {
+    contents
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty() && !l.starts_with('#'))
+        .and_then(parse_foreign_version)
+}
+
+/// The version key of another tool's JDK name, like `21.0.2-tem` (SDKMAN), `temurin-17.0.10+7`
+/// (asdf), or `openjdk64-11.0.2` and `1.8` (jenv). The version is the first `-`-separated part
+/// starting with a digit.
+fn parse_foreign_version(name: &str) -> Option<VersionKey> {
+    let version = name
+        .split('-')
+        .find(|part| part.starts_with(|c: char| c.is_ascii_digit()))?;
+    let version = version.strip_prefix("1.").unwrap_or(version);
+    let major = version
+        .split(|c: char| !c.is_ascii_digit())
+        .next()?
+        .parse::<u32>()
+        .ok()?;
+    let is_ea = name.split(['-', '.', '+']).any(|p| p == "ea");
+    VersionKey::from_str(&format!("{}{}", major, if is_ea { "-ea" } else { "" })).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key(s: &str) -> Option<String> {
+        parse_foreign_version(s).map(|k| k.to_string())
+    }
+
+    #[test]
+    fn test_parse_foreign_version() {
+        assert_eq!(Some("21"), key("21.0.2-tem").as_deref());
+        assert_eq!(Some("8"), key("8.0.402-zulu").as_deref());
+        assert_eq!(Some("23-ea"), key("23.ea.10-open").as_deref());
+        assert_eq!(Some("17"), key("temurin-17.0.10+7").as_deref());
+        assert_eq!(Some("21"), key("oracle-graalvm-21.0.2").as_deref());
+        assert_eq!(Some("11"), key("openjdk64-11.0.2").as_deref());
+        assert_eq!(Some("8"), key("1.8").as_deref());
+        assert_eq!(Some("17"), key("17").as_deref());
+        assert_eq!(None, key("system"));
+    }
+
+    #[test]
+    fn test_parse_version_files() {
+        assert_eq!(
+            Some("21".to_string()),
+            parse_sdkmanrc("# Enable auto-env\njava=21.0.2-tem\nmaven=3.9.6\n")
+                .map(|k| k.to_string())
+        );
+        assert_eq!(None, parse_sdkmanrc("maven=3.9.6\n"));
+        assert_eq!(
+            Some("17".to_string()),
+            parse_java_version_file("\n17.0\n").map(|k| k.to_string())
+        );
+    }
+}
@@ -0,0 +1,62 @@
+use crate::error::{ESResult, JpreError};
+use crate::jobs::Cancelled;
+use crate::tui::format_duration;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Commands that run at least this long notify when they finish.
+const SLOW_COMMAND: Duration = Duration::from_secs(30);
+
+static ENABLED: OnceLock<bool> = OnceLock::new();
+
+/// Turn notifications on or off, from the `notifications` config. They stay off outside of
+/// desktop sessions, where nobody would see them, and in builds without the `notifications`
+/// feature.
+pub fn configure(enabled: bool) {
+    if enabled && !cfg!(feature = "notifications") {
+        warn!("Notifications are enabled in the config, but jpre was built without them");
+    }
+    let _ = ENABLED.set(enabled && cfg!(feature = "notifications") && is_desktop_session());
+}
+
+fn is_enabled() -> bool {
+    ENABLED.get().copied().unwrap_or(false)
+}
+
+fn is_desktop_session() -> bool {
+    let set = |name| std::env::var_os(name).is_some_and(|v| !v.is_empty());
+    if set("SSH_CONNECTION") || set("SSH_TTY") || set("CI") {
+        return false;
+    }
+    cfg!(target_os = "macos") || set("DISPLAY") || set("WAYLAND_DISPLAY")
+}
+
+/// Notify that `command` finished, if it was slow enough that the user may have looked away.
+pub fn notify_finished(command: &str, started: Instant, result: &ESResult<(), JpreError>) {
+    let elapsed = started.elapsed();
+    if !is_enabled() || elapsed < SLOW_COMMAND {
+        return;
+    }
+    let summary = match result {
+        Ok(()) => format!("jpre {} finished", command),
+        Err(e) if e.contains::<Cancelled>() => return,
+        Err(_) => format!("jpre {} failed", command),
+    };
+    show(&summary, &format!("After {}", format_duration(elapsed)));
+}
+
+#[cfg(feature = "notifications")]
+fn show(summary: &str, body: &str) {
+    let shown = notify_rust::Notification::new()
+        .appname("jpre")
+        .summary(summary)
+        .body(body)
+        .show();
+    if let Err(e) = shown {
+        tracing::debug!("Could not show notification: {}", e);
+    }
+}
+
+#[cfg(not(feature = "notifications"))]
+fn show(_summary: &str, _body: &str) {}
@@ -0,0 +1,135 @@
+use crate::error::ESResult;
+use crate::foojay::{ArchiveType, FoojayPackageInfo, FoojayPackageLinks, FoojayPackageListInfo};
+use crate::java_version::key::VersionKey;
+use crate::java_version::JavaVersion;
+use derive_more::Display;
+use error_stack::{Context, ResultExt};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use url::Url;
+
+#[derive(Debug, Display)]
+#[display("Package manifest error")]
+pub struct PackageManifestError;
+
+impl Context for PackageManifestError {}
+
+/// Where to download JDKs from without asking Foojay, for machines that can't reach it. Written by
+/// `jpre info --manifest` on a machine that can, usually with the URLs then changed to point to an
+/// internal mirror.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct PackageManifest {
+    #[serde(default)]
+    pub jdks: BTreeMap<VersionKey, ManifestPackage>,
+}
+
+/// The package to install for one version key.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ManifestPackage {
+    pub distribution: String,
+    pub version: JavaVersion,
+    pub archive_type: ArchiveType,
+    pub url: Url,
+    /// The SHA-256 of the archive, in hex.
+    pub sha256: String,
+    /// Size of the archive in bytes, 0 if unknown.
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default = "default_free_use")]
+    pub free_use_in_production: bool,
+}
+
+fn default_free_use() -> bool {
+    true
+}
+
+impl PackageManifest {
+    pub fn read(path: &Path) -> ESResult<PackageManifest, PackageManifestError> {
+        let contents = std::fs::read_to_string(path)
+            .change_context(PackageManifestError)
+            .attach_printable_lazy(|| format!("Could not read package manifest {:?}", path))?;
+        toml::from_str(&contents)
+            .change_context(PackageManifestError)
+            .attach_printable_lazy(|| format!("Could not parse package manifest {:?}", path))
+    }
+
+    /// A manifest with only `package` for `jdk`, as TOML.
+    pub fn single_to_toml(
+        jdk: &VersionKey,
+        package: ManifestPackage,
+    ) -> ESResult<String, PackageManifestError> {
+        let manifest = PackageManifest {
+            jdks: BTreeMap::from([(jdk.clone(), package)]),
+        };
+        toml::to_string(&manifest)
+            .change_context(PackageManifestError)
+            .attach_printable("Could not serialize package manifest")
+    }
+}
+
+impl ManifestPackage {
+    pub fn from_package_info(list_info: &FoojayPackageListInfo, info: &FoojayPackageInfo) -> Self {
+        ManifestPackage {
+            distribution: list_info.distribution.clone(),
+            version: list_info.java_version.clone(),
+            archive_type: list_info.archive_type.clone(),
+            url: info.direct_download_uri.clone(),
+            sha256: info.checksum.to_ascii_lowercase(),
+            size: list_info.size,
+            free_use_in_production: list_info.free_use_in_production,
+        }
+    }
+
+    /// The package as if Foojay had listed it, so it's installed like any other.
+    pub fn to_package_info(&self) -> (FoojayPackageListInfo, FoojayPackageInfo) {
+        let list_info = FoojayPackageListInfo {
+            archive_type: self.archive_type.clone(),
+            distribution: self.distribution.clone(),
+            java_version: self.version.clone(),
+            distribution_version: None,
+            release_notes_uri: None,
+            latest_build_available: true,
+            links: FoojayPackageLinks {
+                pkg_info_uri: self.url.clone(),
+            },
+            size: self.size,
+            free_use_in_production: self.free_use_in_production,
+        };
+        let info = FoojayPackageInfo::direct(self.url.clone(), self.sha256.clone());
+        (list_info, info)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_round_trip() {
+        let jdk = VersionKey::from_str("17").unwrap();
+        let package = ManifestPackage {
+            distribution: "temurin".to_string(),
+            version: JavaVersion::from_str("17.0.10+7").unwrap(),
+            archive_type: ArchiveType::TarGz,
+            url: Url::parse("https://mirror.example.com/jdk-17.0.10.tar.gz").unwrap(),
+            sha256: "a1b2".to_string(),
+            size: 42,
+            free_use_in_production: true,
+        };
+        let toml = PackageManifest::single_to_toml(&jdk, package).unwrap();
+        assert!(toml.starts_with("[jdks.17]\n"), "{}", toml);
+        assert!(toml.contains("archive_type = \"tar.gz\"\n"), "{}", toml);
+
+        let manifest = toml::from_str::<PackageManifest>(&toml).unwrap();
+        let (list_info, info) = manifest.jdks[&jdk].to_package_info();
+        assert_eq!("17.0.10+7", list_info.java_version.to_string());
+        assert_eq!(42, list_info.size);
+        assert_eq!(
+            "https://mirror.example.com/jdk-17.0.10.tar.gz",
+            info.direct_download_uri.as_str()
+        );
+        assert_eq!("sha256 a1b2", info.checksums()[0].to_string());
+    }
+}
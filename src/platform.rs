@@ -0,0 +1,210 @@
+use crate::error::{ESResult, UserMessage};
+use derive_more::Display;
+use error_stack::{Context, Report};
+
+#[derive(Debug, Display)]
+#[display("Unsupported platform")]
+pub struct PlatformError;
+
+impl Context for PlatformError {}
+
+/// The architectures we can detect, by their Foojay name.
+const SUPPORTED_ARCHITECTURES: &[&str] = &[
+    "x64", "x86", "arm64", "arm", "riscv64", "ppc64le", "ppc64", "s390x",
+];
+
+/// The C library a Linux JDK is built against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum Libc {
+    #[display("glibc")]
+    Glibc,
+    #[display("musl")]
+    Musl,
+}
+
+/// The platform JDKs are downloaded for, by Foojay's names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Platform {
+    pub os: String,
+    pub arch: String,
+    /// Only known for Linux.
+    pub libc: Option<Libc>,
+    /// Whether the architecture was forced, instead of detected.
+    pub arch_forced: bool,
+}
+
+/// Why the platform couldn't be detected. Only an error once JDKs are looked up for it, commands
+/// that don't need it still work.
+#[derive(Debug, Clone)]
+pub struct UnsupportedPlatform {
+    message: String,
+}
+
+/// The platform of a run as resolved at startup, see [Platform::resolve].
+pub type ResolvedPlatform = Result<Platform, UnsupportedPlatform>;
+
+impl Platform {
+    /// Detect the platform, except for what's forced by `--os` and `--arch` or by `forced_os` and
+    /// `forced_architecture` in the config. A forced OS of `linux-musl` also picks musl.
+    pub fn resolve(forced_os: Option<&str>, forced_arch: Option<&str>) -> ResolvedPlatform {
+        let (os, libc) = match forced_os {
+            Some(os) => parse_os(os),
+            None => detected_os()?,
+        };
+        let arch = match forced_arch {
+            Some(arch) => arch.to_string(),
+            None => detected_arch()?.to_string(),
+        };
+        Ok(Platform {
+            os,
+            arch,
+            libc,
+            arch_forced: forced_arch.is_some(),
+        })
+    }
+
+    /// Foojay's `operating_system`, which has its own name for Linux with musl.
+    pub fn foojay_os(&self) -> String {
+        match self.libc {
+            Some(Libc::Musl) => format!("{}-musl", self.os),
+            _ => self.os.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for Platform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.os, self.arch)?;
+        if let Some(libc) = self.libc {
+            write!(f, " ({})", libc)?;
+        }
+        Ok(())
+    }
+}
+
+/// The resolved platform, or an error saying why there is none, for when JDKs are looked up.
+pub fn require(platform: &ResolvedPlatform) -> ESResult<&Platform, PlatformError> {
+    platform.as_ref().map_err(|e| {
+        Report::new(PlatformError).attach(UserMessage {
+            message: e.message.clone(),
+        })
+    })
+}
+
+/// A forced OS, which may name the libc like Foojay does.
+fn parse_os(os: &str) -> (String, Option<Libc>) {
+    match os.strip_suffix("-musl") {
+        Some(os) => (os.to_string(), Some(Libc::Musl)),
+        None if os == "linux" => (os.to_string(), Some(Libc::Glibc)),
+        None => (os.to_string(), None),
+    }
+}
+
+/// The Foojay name of a Rust target OS, and its libc.
+fn foojay_os(os: &str, musl: bool) -> Option<(&'static str, Option<Libc>)> {
+    match os {
+        "macos" => Some(("macos", None)),
+        "linux" if musl => Some(("linux", Some(Libc::Musl))),
+        "linux" => Some(("linux", Some(Libc::Glibc))),
+        _ => None,
+    }
+}
+
+/// The Foojay name of a Rust target architecture. Foojay only has hard-float builds for 32-bit
+/// ARM.
+fn foojay_arch(arch: &str, hard_float: bool, little_endian: bool) -> Option<&'static str> {
+    match arch {
+        "x86" => Some("x86"),
+        "x86_64" => Some("x64"),
+        "aarch64" => Some("arm64"),
+        "arm" if hard_float => Some("arm"),
+        "riscv64" => Some("riscv64"),
+        "powerpc64" if little_endian => Some("ppc64le"),
+        "powerpc64" => Some("ppc64"),
+        "s390x" => Some("s390x"),
+        _ => None,
+    }
+}
+
+fn detected_os() -> Result<(String, Option<Libc>), UnsupportedPlatform> {
+    let os = std::env::consts::OS;
+    foojay_os(os, cfg!(target_env = "musl"))
+        .map(|(os, libc)| (os.to_string(), libc))
+        .ok_or_else(|| UnsupportedPlatform {
+            message: format!(
+                "Unsupported OS: {}. Set forced_os in the config to one of: linux, linux-musl, macos",
+                os
+            ),
+        })
+}
+
+fn detected_arch() -> Result<&'static str, UnsupportedPlatform> {
+    foojay_arch(
+        std::env::consts::ARCH,
+        cfg!(target_abi = "eabihf"),
+        cfg!(target_endian = "little"),
+    )
+    .ok_or_else(|| UnsupportedPlatform {
+        message: format!(
+            "Unsupported architecture: {}. Set forced_architecture in the config to one of: {}",
+            std::env::consts::ARCH,
+            SUPPORTED_ARCHITECTURES.join(", ")
+        ),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_foojay_arch() {
+        assert_eq!(Some("x64"), foojay_arch("x86_64", false, true));
+        assert_eq!(Some("arm64"), foojay_arch("aarch64", false, true));
+        assert_eq!(Some("arm"), foojay_arch("arm", true, true));
+        assert_eq!(None, foojay_arch("arm", false, true));
+        assert_eq!(Some("ppc64le"), foojay_arch("powerpc64", false, true));
+        assert_eq!(Some("ppc64"), foojay_arch("powerpc64", false, false));
+        assert_eq!(Some("riscv64"), foojay_arch("riscv64", false, true));
+        assert_eq!(Some("s390x"), foojay_arch("s390x", false, false));
+        assert_eq!(None, foojay_arch("mips", false, false));
+        for arch in ["x86", "x86_64", "aarch64", "riscv64", "s390x"] {
+            let name = foojay_arch(arch, false, true).unwrap();
+            assert!(SUPPORTED_ARCHITECTURES.contains(&name));
+        }
+    }
+
+    #[test]
+    fn test_foojay_os() {
+        assert_eq!(Some(("macos", None)), foojay_os("macos", false));
+        assert_eq!(
+            Some(("linux", Some(Libc::Glibc))),
+            foojay_os("linux", false)
+        );
+        assert_eq!(Some(("linux", Some(Libc::Musl))), foojay_os("linux", true));
+        assert_eq!(None, foojay_os("freebsd", false));
+    }
+
+    #[test]
+    fn test_parse_os() {
+        assert_eq!(("linux".to_string(), Some(Libc::Glibc)), parse_os("linux"));
+        assert_eq!(
+            ("linux".to_string(), Some(Libc::Musl)),
+            parse_os("linux-musl")
+        );
+        assert_eq!(("macos".to_string(), None), parse_os("macos"));
+        assert_eq!(("windows".to_string(), None), parse_os("windows"));
+    }
+
+    #[test]
+    fn test_resolve_forced() {
+        let platform = Platform::resolve(Some("linux-musl"), Some("arm64")).unwrap();
+        assert_eq!("linux-musl", platform.foojay_os());
+        assert_eq!("arm64", platform.arch);
+        assert!(platform.arch_forced);
+        assert_eq!("linux arm64 (musl)", platform.to_string());
+        let platform = Platform::resolve(Some("macos"), Some("x64")).unwrap();
+        assert_eq!("macos", platform.foojay_os());
+        assert_eq!("macos x64", platform.to_string());
+    }
+}
@@ -0,0 +1,163 @@
+use crate::config::JpreConfig;
+use crate::error::{ESResult, JpreError, UserMessage};
+use derive_more::Display;
+use error_stack::{Context, Report, ResultExt};
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::OnceLock;
+use tracing::warn;
+
+#[derive(Debug, Display)]
+#[display("Distribution policy violated")]
+pub struct PolicyError;
+
+impl Context for PolicyError {}
+
+/// A policy set up by an administrator, which the user's config can only make stricter.
+const SYSTEM_POLICY_PATH: &str = "/etc/jpre/policy.toml";
+
+static POLICY: OnceLock<Policy> = OnceLock::new();
+
+/// Which distributions JDKs may be installed from. Names are Foojay's, compared ignoring case.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DistributionRules {
+    /// If set, only these distributions are allowed.
+    #[serde(default)]
+    allowed_distributions: Option<Vec<String>>,
+    #[serde(default)]
+    blocked_distributions: Vec<String>,
+}
+
+impl DistributionRules {
+    fn allows(&self, distribution: &str) -> bool {
+        let matches = |d: &String| d.eq_ignore_ascii_case(distribution);
+        !self.blocked_distributions.iter().any(matches)
+            && self
+                .allowed_distributions
+                .as_ref()
+                .is_none_or(|allowed| allowed.iter().any(matches))
+    }
+}
+
+/// The rules of the system policy and the config. A distribution must be allowed by both.
+#[derive(Debug, Default)]
+pub struct Policy {
+    /// Where the rules come from, for error messages.
+    rules: Vec<(String, DistributionRules)>,
+}
+
+impl Policy {
+    pub fn load(config: &JpreConfig) -> ESResult<Policy, JpreError> {
+        let mut rules = Vec::new();
+        if let Some(system) = read_rules(Path::new(SYSTEM_POLICY_PATH))? {
+            rules.push((SYSTEM_POLICY_PATH.to_string(), system));
+        }
+        rules.push((
+            "the config".to_string(),
+            DistributionRules {
+                allowed_distributions: config.allowed_distributions.clone(),
+                blocked_distributions: config.blocked_distributions.clone(),
+            },
+        ));
+        Ok(Policy { rules })
+    }
+
+    /// Fails with a message naming the policy that doesn't allow `distribution`.
+    pub fn check_distribution(&self, distribution: &str) -> ESResult<(), PolicyError> {
+        let Some((source, rules)) = self.rules.iter().find(|(_, r)| !r.allows(distribution)) else {
+            return Ok(());
+        };
+        let mut message = format!(
+            "Distribution '{}' is not allowed by {}",
+            distribution, source
+        );
+        if let Some(allowed) = &rules.allowed_distributions {
+            message += &format!(". Allowed distributions: {}", allowed.join(", "));
+        }
+        Err(Report::new(PolicyError).attach(UserMessage { message }))
+    }
+}
+
+fn read_rules(path: &Path) -> ESResult<Option<DistributionRules>, JpreError> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(Report::new(e)
+                .change_context(JpreError::UserError)
+                .attach(UserMessage {
+                    message: format!("Could not read the policy at {}", path.display()),
+                }))
+        }
+    };
+    // A broken policy must not mean no policy.
+    toml::from_str(&contents)
+        .map(Some)
+        .change_context(JpreError::UserError)
+        .attach_lazy(|| UserMessage {
+            message: format!("Invalid policy at {}", path.display()),
+        })
+}
+
+/// Enforce `policy` for the rest of this run.
+pub fn configure(policy: Policy) {
+    if POLICY.set(policy).is_err() {
+        warn!("Policy was already configured, ignoring the new one");
+    }
+}
+
+/// The policy of this run, allowing everything if [configure] wasn't called.
+pub fn current() -> &'static Policy {
+    POLICY.get_or_init(Policy::default)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rules(toml: &str) -> DistributionRules {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn test_allows() {
+        let allowlist = rules(r#"allowed_distributions = ["temurin", "corretto"]"#);
+        assert!(allowlist.allows("temurin"));
+        assert!(allowlist.allows("Corretto"));
+        assert!(!allowlist.allows("zulu"));
+        let blocklist = rules(r#"blocked_distributions = ["oracle"]"#);
+        assert!(blocklist.allows("temurin"));
+        assert!(!blocklist.allows("oracle"));
+        assert!(rules("").allows("zulu"));
+    }
+
+    #[test]
+    fn test_system_policy_wins() {
+        let policy = Policy {
+            rules: vec![
+                (
+                    "/etc/jpre/policy.toml".to_string(),
+                    rules(r#"allowed_distributions = ["temurin"]"#),
+                ),
+                (
+                    "the config".to_string(),
+                    rules(r#"allowed_distributions = ["temurin", "zulu"]"#),
+                ),
+            ],
+        };
+        assert!(policy.check_distribution("temurin").is_ok());
+        let error = policy.check_distribution("zulu").unwrap_err();
+        let message = &error.downcast_ref::<UserMessage>().unwrap().message;
+        assert_eq!(
+            "Distribution 'zulu' is not allowed by /etc/jpre/policy.toml. Allowed \
+             distributions: temurin",
+            message
+        );
+    }
+
+    #[test]
+    fn test_unknown_key_is_rejected() {
+        assert!(toml::from_str::<DistributionRules>("allowed = [\"temurin\"]").is_err());
+    }
+}
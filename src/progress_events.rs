@@ -0,0 +1,198 @@
+use crate::json_output::SchemaVersion;
+use crate::tui::PROGRESS;
+use indicatif::ProgressDrawTarget;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::os::fd::{FromRawFd, RawFd};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Progress events go here instead of being drawn as bars, if configured.
+static SINK: OnceLock<Mutex<File>> = OnceLock::new();
+
+/// `progress` events are sent at most this often per stream.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A line written to the progress file descriptor.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct EventLine<'a> {
+    schema_version: SchemaVersion,
+    #[serde(flatten)]
+    event: &'a ProgressEvent<'a>,
+}
+
+/// A machine-readable progress event, written as one JSON object per line.
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent<'a> {
+    Started {
+        stage: Stage,
+        jdk: &'a str,
+        /// Unknown if the server doesn't say.
+        total_bytes: Option<u64>,
+    },
+    Progress {
+        stage: Stage,
+        bytes: u64,
+        total_bytes: Option<u64>,
+    },
+    Finished {
+        stage: Stage,
+        bytes: u64,
+    },
+    Installed {
+        jdk: &'a str,
+        path: &'a Path,
+    },
+    /// A line jpre would print for the user.
+    Message {
+        text: &'a str,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    Download,
+    Extract,
+}
+
+/// Send progress events to the open file descriptor `fd` and stop drawing progress bars.
+pub fn configure_fd(fd: RawFd) -> std::io::Result<()> {
+    // SAFETY: only checks whether `fd` is open.
+    if unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // SAFETY: `fd` is open, and is only used through this `File` from now on.
+    let file = unsafe { File::from_raw_fd(fd) };
+    if SINK.set(Mutex::new(file)).is_err() {
+        return Err(std::io::Error::other(
+            "progress events were already configured",
+        ));
+    }
+    PROGRESS.set_draw_target(ProgressDrawTarget::hidden());
+    Ok(())
+}
+
+pub fn is_enabled() -> bool {
+    SINK.get().is_some()
+}
+
+pub fn emit(event: &ProgressEvent) {
+    let Some(sink) = SINK.get() else {
+        return;
+    };
+    let mut line = serde_json::to_vec(&EventLine {
+        schema_version: SchemaVersion,
+        event,
+    })
+    .expect("events serialize");
+    line.push(b'\n');
+    // The reader going away shouldn't stop the install.
+    if let Err(e) = sink.lock().unwrap().write_all(&line) {
+        debug!("Could not write progress event: {}", e);
+    }
+}
+
+/// Wraps a reader or writer, sending [ProgressEvent::Progress] for the bytes passing through.
+pub struct EventBytes<T> {
+    inner: T,
+    stage: Stage,
+    total_bytes: Option<u64>,
+    bytes: u64,
+    last_event: Option<Instant>,
+}
+
+impl<T> EventBytes<T> {
+    pub fn new(stage: Stage, total_bytes: Option<u64>, inner: T) -> Self {
+        EventBytes {
+            inner,
+            stage,
+            total_bytes,
+            bytes: 0,
+            last_event: None,
+        }
+    }
+
+    fn add(&mut self, bytes: usize) {
+        self.bytes += bytes as u64;
+        if !is_enabled()
+            || self
+                .last_event
+                .is_some_and(|t| t.elapsed() < PROGRESS_INTERVAL)
+        {
+            return;
+        }
+        self.last_event = Some(Instant::now());
+        emit(&ProgressEvent::Progress {
+            stage: self.stage,
+            // Seeking readers can pass the same bytes twice.
+            bytes: self.total_bytes.map_or(self.bytes, |t| self.bytes.min(t)),
+            total_bytes: self.total_bytes,
+        });
+    }
+}
+
+impl<R: Read> Read for EventBytes<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.add(n);
+        Ok(n)
+    }
+}
+
+impl<W: Write> Write for EventBytes<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.add(n);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<S: Seek> Seek for EventBytes<S> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_event_json() {
+        let json = |e: &ProgressEvent| serde_json::to_string(e).unwrap();
+        assert_eq!(
+            r#"{"event":"started","stage":"download","jdk":"21.0.2+13","total_bytes":null}"#,
+            json(&ProgressEvent::Started {
+                stage: Stage::Download,
+                jdk: "21.0.2+13",
+                total_bytes: None,
+            })
+        );
+        assert_eq!(
+            r#"{"event":"progress","stage":"extract","bytes":10,"total_bytes":100}"#,
+            json(&ProgressEvent::Progress {
+                stage: Stage::Extract,
+                bytes: 10,
+                total_bytes: Some(100),
+            })
+        );
+        assert_eq!(
+            r#"{"schema_version":1,"event":"message","text":"hi"}"#,
+            serde_json::to_string(&EventLine {
+                schema_version: SchemaVersion,
+                event: &ProgressEvent::Message { text: "hi" },
+            })
+            .unwrap()
+        );
+    }
+}
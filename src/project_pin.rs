@@ -0,0 +1,101 @@
+use crate::error::ESResult;
+use crate::java_version::key::VersionKey;
+use crate::java_version::range::JdkRequirement;
+use derive_more::Display;
+use error_stack::{Context, Report, ResultExt};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Debug, Display)]
+pub struct ProjectPinError;
+
+impl Context for ProjectPinError {}
+
+/// The file a project uses to say which JDK it needs.
+pub const PROJECT_PIN_FILE_NAME: &str = ".jpre-version";
+
+/// The JDK a project is pinned to, by a [PROJECT_PIN_FILE_NAME] file in it or a parent directory.
+#[derive(Debug, Clone)]
+pub struct ProjectPin {
+    /// The pin file.
+    pub path: PathBuf,
+    /// A version key, or a range like `>=17, <22`.
+    pub requirement: JdkRequirement,
+}
+
+impl ProjectPin {
+    /// Find the nearest pin file in `start` or its parents.
+    pub fn find(start: &Path) -> ESResult<Option<ProjectPin>, ProjectPinError> {
+        for dir in start.ancestors() {
+            let path = dir.join(PROJECT_PIN_FILE_NAME);
+            let contents = match std::fs::read_to_string(&path) {
+                Ok(contents) => contents,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => {
+                    return Err(e)
+                        .change_context(ProjectPinError)
+                        .attach_printable_lazy(|| format!("Could not read {:?}", path))
+                }
+            };
+            let requirement = parse_pin_file(&contents)
+                .change_context(ProjectPinError)
+                .attach_printable_lazy(|| format!("Invalid pin file {:?}", path))?;
+            return Ok(Some(ProjectPin { path, requirement }));
+        }
+        Ok(None)
+    }
+
+    /// Pin the project in `dir` to `jdk`, replacing any pin file there.
+    pub fn write(dir: &Path, jdk: &VersionKey) -> ESResult<ProjectPin, ProjectPinError> {
+        let path = dir.join(PROJECT_PIN_FILE_NAME);
+        std::fs::write(&path, format!("{}\n", jdk))
+            .change_context(ProjectPinError)
+            .attach_printable_lazy(|| format!("Could not write {:?}", path))?;
+        Ok(ProjectPin {
+            path,
+            requirement: JdkRequirement::Key(jdk.clone()),
+        })
+    }
+
+    /// Find the nearest pin file from the working directory.
+    pub fn find_from_current_dir() -> ESResult<Option<ProjectPin>, ProjectPinError> {
+        let cwd = std::env::current_dir()
+            .change_context(ProjectPinError)
+            .attach_printable("Could not get the working directory")?;
+        Self::find(&cwd)
+    }
+}
+
+/// The requirement in a pin file: the first line that isn't empty or a `#` comment.
+fn parse_pin_file(contents: &str) -> ESResult<JdkRequirement, ProjectPinError> {
+    let line = contents
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty() && !l.starts_with('#'))
+        .ok_or_else(|| Report::new(ProjectPinError).attach_printable("No version key in file"))?;
+    JdkRequirement::from_str(line)
+        .change_context(ProjectPinError)
+        .attach_printable_lazy(|| format!("Invalid version key or range {:?}", line))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_pin_file() {
+        assert_eq!("21", parse_pin_file("21\n").unwrap().to_string());
+        assert_eq!(
+            "23-ea",
+            parse_pin_file("# Needs the new API\n\n  23-ea  \n17\n")
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            ">=17, <22",
+            parse_pin_file(">=17, <22\n").unwrap().to_string()
+        );
+        assert!(parse_pin_file("# nothing\n").is_err());
+        assert!(parse_pin_file("latest\n").is_err());
+    }
+}
@@ -0,0 +1,97 @@
+use crate::error::ESResult;
+use crate::java_version::JavaVersion;
+use derive_more::Display;
+use error_stack::{Context, ResultExt};
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, Display)]
+pub struct ReleaseFileError;
+
+impl Context for ReleaseFileError {}
+
+/// The `release` file shipped in the root of (almost) every JDK, a list of `KEY="value"` lines.
+#[derive(Debug, Default)]
+pub struct ReleaseFile {
+    entries: HashMap<String, String>,
+}
+
+impl ReleaseFile {
+    /// Read the `release` file of the JDK at `jdk_home`. Returns `None` if there is no such file.
+    pub fn read(jdk_home: &Path) -> ESResult<Option<ReleaseFile>, ReleaseFileError> {
+        let path = jdk_home.join("release");
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e)
+                    .change_context(ReleaseFileError)
+                    .attach_printable_lazy(|| format!("Could not read release file at {:?}", path))
+            }
+        };
+        Ok(Some(Self::parse(&contents)))
+    }
+
+    pub fn parse(contents: &str) -> ReleaseFile {
+        let entries = contents
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| {
+                let value = value.trim();
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .unwrap_or(value);
+                (key.trim().to_string(), value.to_string())
+            })
+            .collect();
+        ReleaseFile { entries }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.get(key).map(String::as_str)
+    }
+
+    /// The full version of the JDK. `JAVA_RUNTIME_VERSION` includes the build number, but isn't
+    /// present in older JDKs, so fall back to `JAVA_VERSION`.
+    pub fn java_version(&self) -> ESResult<Option<JavaVersion>, ReleaseFileError> {
+        let Some(version) = self
+            .get("JAVA_RUNTIME_VERSION")
+            .or_else(|| self.get("JAVA_VERSION"))
+        else {
+            return Ok(None);
+        };
+        JavaVersion::from_str(version)
+            .map(Some)
+            .change_context(ReleaseFileError)
+            .attach_printable_lazy(|| format!("Could not parse release file version {}", version))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_java_version() {
+        let release = ReleaseFile::parse(
+            "IMPLEMENTOR=\"Eclipse Adoptium\"\n\
+             JAVA_RUNTIME_VERSION=\"17.0.10+7\"\n\
+             JAVA_VERSION=\"17.0.10\"\n",
+        );
+        assert_eq!(Some("Eclipse Adoptium"), release.get("IMPLEMENTOR"));
+        assert_eq!(
+            "17.0.10+7",
+            release.java_version().unwrap().unwrap().to_string()
+        );
+
+        let release = ReleaseFile::parse("JAVA_VERSION=\"1.8.0_402\"\n");
+        assert_eq!(
+            "1.8.0_402",
+            release.java_version().unwrap().unwrap().to_string()
+        );
+
+        assert!(ReleaseFile::parse("").java_version().unwrap().is_none());
+    }
+}
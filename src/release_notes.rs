@@ -0,0 +1,93 @@
+use crate::foojay::FoojayPackageListInfo;
+use crate::java_version::JavaVersion;
+
+/// Where to read about what changed in a package: Foojay's link if it has one, otherwise the
+/// vendor's page for the version, for the vendors whose URLs follow a pattern.
+pub fn release_notes_url(list_info: &FoojayPackageListInfo) -> Option<String> {
+    if let Some(uri) = list_info
+        .release_notes_uri
+        .as_deref()
+        .filter(|u| !u.is_empty())
+    {
+        return Some(uri.to_string());
+    }
+    vendor_release_notes_url(
+        &list_info.distribution,
+        &list_info.java_version,
+        list_info.distribution_version.as_deref(),
+    )
+}
+
+fn vendor_release_notes_url(
+    distribution: &str,
+    version: &JavaVersion,
+    distribution_version: Option<&str>,
+) -> Option<String> {
+    let major = version.major();
+    match distribution {
+        "temurin" => {
+            let release = if major == 8 {
+                format!("jdk8u{}-b{:02}", version.update(), version.build()?)
+            } else {
+                format!("jdk-{}", version)
+            };
+            Some(format!(
+                "https://adoptium.net/temurin/release-notes/?version={}",
+                release.replace('+', "%2B")
+            ))
+        }
+        "corretto" => Some(match distribution_version.filter(|v| !v.is_empty()) {
+            Some(tag) => format!(
+                "https://github.com/corretto/corretto-{}/releases/tag/{}",
+                major, tag
+            ),
+            None => format!(
+                "https://github.com/corretto/corretto-{}/blob/develop/CHANGELOG.md",
+                major
+            ),
+        }),
+        "zulu" | "zulu_prime" => {
+            Some("https://docs.azul.com/core/zulu-openjdk/release-notes".to_string())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    fn url(
+        distribution: &str,
+        version: &str,
+        distribution_version: Option<&str>,
+    ) -> Option<String> {
+        vendor_release_notes_url(
+            distribution,
+            &JavaVersion::from_str(version).unwrap(),
+            distribution_version,
+        )
+    }
+
+    #[test]
+    fn test_vendor_release_notes_url() {
+        assert_eq!(
+            Some("https://adoptium.net/temurin/release-notes/?version=jdk-21.0.2%2B13"),
+            url("temurin", "21.0.2+13", None).as_deref()
+        );
+        assert_eq!(
+            Some("https://adoptium.net/temurin/release-notes/?version=jdk8u402-b06"),
+            url("temurin", "8.0.402+6", None).as_deref()
+        );
+        assert_eq!(
+            Some("https://github.com/corretto/corretto-21/releases/tag/21.0.2.13.1"),
+            url("corretto", "21.0.2+13", Some("21.0.2.13.1")).as_deref()
+        );
+        assert_eq!(
+            Some("https://github.com/corretto/corretto-17/blob/develop/CHANGELOG.md"),
+            url("corretto", "17.0.10+7", None).as_deref()
+        );
+        assert_eq!(None, url("liberica", "21.0.2+14", None));
+    }
+}
@@ -0,0 +1,248 @@
+use crate::config::PROJECT_DIRS;
+use crate::context_id::{base_state_dir, CONTEXT_LINKS_DIR_NAME};
+use crate::error::ESResult;
+use crate::fs_util::move_path;
+use crate::jdk_manager::{JDK_DOWNLOADS_DIR_NAME, JDK_MANAGER, JDK_STORE_DIR_NAME};
+use crate::tui::{eprint_above_progress, new_spinner};
+use derive_more::Display;
+use error_stack::{Context, ResultExt};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use tracing::{debug, warn};
+
+#[derive(Debug, Display)]
+pub struct RelocationError;
+
+impl Context for RelocationError {}
+
+/// Where the locations of the last run are recorded. The config dir is used as it's the one
+/// directory that doesn't move along with the store.
+static LOCATIONS_PATH: LazyLock<PathBuf> =
+    LazyLock::new(|| PROJECT_DIRS.preference_dir().join("locations.json"));
+
+/// The directories jpre keeps its data in. These follow `XDG_CACHE_HOME` and `XDG_STATE_HOME`, so
+/// they move when those change.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct Locations {
+    pub cache_dir: PathBuf,
+    pub state_dir: PathBuf,
+}
+
+impl Locations {
+    pub fn current() -> Locations {
+        Locations {
+            cache_dir: PROJECT_DIRS.cache_dir().to_path_buf(),
+            state_dir: base_state_dir().into_owned(),
+        }
+    }
+
+    /// The locations of the last run, if they were recorded.
+    pub fn recorded() -> Option<Locations> {
+        let contents = std::fs::read_to_string(&*LOCATIONS_PATH).ok()?;
+        serde_json::from_str(&contents)
+            .inspect_err(|e| debug!("Ignoring invalid {:?}: {}", *LOCATIONS_PATH, e))
+            .ok()
+    }
+
+    pub fn jdk_store(&self) -> PathBuf {
+        self.cache_dir.join(JDK_STORE_DIR_NAME)
+    }
+
+    pub fn context_links(&self) -> PathBuf {
+        self.state_dir.join(CONTEXT_LINKS_DIR_NAME)
+    }
+
+    fn has_jdks(&self) -> bool {
+        std::fs::read_dir(self.jdk_store()).is_ok_and(|mut entries| entries.next().is_some())
+    }
+
+    fn record(&self) -> ESResult<(), RelocationError> {
+        let contents = serde_json::to_string_pretty(self)
+            .change_context(RelocationError)
+            .attach_printable("Could not serialize locations")?;
+        std::fs::write(&*LOCATIONS_PATH, contents)
+            .change_context(RelocationError)
+            .attach_printable_lazy(|| format!("Could not write locations to {:?}", *LOCATIONS_PATH))
+    }
+}
+
+/// Record the current locations, or warn if the JDK store moved and the old one still has JDKs.
+/// In that case, the old locations are kept so `relocate` can find them.
+pub fn check_locations() {
+    let current = Locations::current();
+    match Locations::recorded() {
+        Some(recorded) if recorded == current => {}
+        Some(recorded) if recorded.cache_dir != current.cache_dir && recorded.has_jdks() => {
+            warn!(
+                "JDKs are stored in {}, but jpre now uses {} (did XDG_CACHE_HOME change?). Run `jpre relocate` to move them.",
+                recorded.jdk_store().display(),
+                current.jdk_store().display()
+            );
+        }
+        _ => {
+            if let Err(e) = current.record() {
+                debug!("Could not record locations: {:?}", e);
+            }
+        }
+    }
+}
+
+/// Old locations that still have a JDK store or context links, most likely first.
+pub fn find_old_locations() -> Vec<Locations> {
+    let current = Locations::current();
+    let mut candidates = Vec::new();
+    candidates.extend(Locations::recorded());
+    // The defaults when the XDG variables aren't set.
+    #[cfg(target_os = "linux")]
+    if let Some(home) = std::env::var_os("HOME").filter(|h| !h.is_empty()) {
+        let home = PathBuf::from(home);
+        candidates.push(Locations {
+            cache_dir: home.join(".cache/jpre"),
+            state_dir: home.join(".local/state/jpre"),
+        });
+    }
+    candidates.dedup();
+    candidates
+        .into_iter()
+        .filter(|old| {
+            (old.cache_dir != current.cache_dir && old.jdk_store().exists())
+                || (old.state_dir != current.state_dir && old.context_links().exists())
+        })
+        .collect()
+}
+
+#[derive(Debug, Default)]
+pub struct RelocationReport {
+    pub moved: usize,
+    pub skipped: usize,
+    pub relinked: usize,
+}
+
+/// Move the JDK store, downloads and context links from `old` to the current locations, and point
+/// context links at the moved JDKs.
+pub fn relocate(old: &Locations) -> ESResult<RelocationReport, RelocationError> {
+    let new = Locations::current();
+    let mut report = RelocationReport::default();
+    if old.cache_dir != new.cache_dir {
+        for dir_name in [JDK_STORE_DIR_NAME, JDK_DOWNLOADS_DIR_NAME] {
+            move_dir_contents(
+                &old.cache_dir.join(dir_name),
+                &new.cache_dir.join(dir_name),
+                &mut report,
+            )?;
+        }
+    }
+
+    let mut link_dirs = vec![new.context_links()];
+    if old.state_dir != new.state_dir {
+        link_dirs.push(old.context_links());
+    }
+    std::fs::create_dir_all(new.context_links())
+        .change_context(RelocationError)
+        .attach_printable_lazy(|| format!("Could not create {:?}", new.context_links()))?;
+    for link_dir in link_dirs {
+        let Ok(entries) = std::fs::read_dir(&link_dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let link = entry.path();
+            let Ok(target) = std::fs::read_link(&link) else {
+                continue;
+            };
+            let new_target = relocated_target(&target, &old.jdk_store(), &new.jdk_store());
+            let new_link = new.context_links().join(entry.file_name());
+            if new_target.is_none() && link == new_link {
+                continue;
+            }
+            let new_target = new_target.unwrap_or(target);
+            debug!("Linking {:?} to {:?}", new_link, new_target);
+            let _ = std::fs::remove_file(&new_link);
+            std::os::unix::fs::symlink(&new_target, &new_link)
+                .change_context(RelocationError)
+                .attach_printable_lazy(|| {
+                    format!("Could not link {:?} to {:?}", new_link, new_target)
+                })?;
+            if link != new_link {
+                if let Err(e) = std::fs::remove_file(&link) {
+                    warn!("Could not remove old context link {:?}: {}", link, e);
+                }
+            }
+            report.relinked += 1;
+        }
+    }
+
+    new.record()?;
+    // The JDKs' paths changed.
+    JDK_MANAGER.update_inventory();
+    Ok(report)
+}
+
+fn move_dir_contents(
+    from: &Path,
+    to: &Path,
+    report: &mut RelocationReport,
+) -> ESResult<(), RelocationError> {
+    let Ok(entries) = std::fs::read_dir(from) else {
+        return Ok(());
+    };
+    let entries = entries.filter_map(|e| e.ok()).collect::<Vec<_>>();
+    std::fs::create_dir_all(to)
+        .change_context(RelocationError)
+        .attach_printable_lazy(|| format!("Could not create {:?}", to))?;
+    let spinner = new_spinner(format!("Moving {}", from.display()));
+    spinner.set_length(entries.len() as u64);
+    for entry in entries {
+        let target = to.join(entry.file_name());
+        spinner.set_message(format!("Moving {}", entry.path().display()));
+        if std::fs::symlink_metadata(&target).is_ok() {
+            eprint_above_progress(format!(
+                "Skipping {}, {} already exists",
+                entry.path().display(),
+                target.display()
+            ));
+            report.skipped += 1;
+        } else {
+            move_path(&entry.path(), &target)
+                .change_context(RelocationError)
+                .attach_printable_lazy(|| {
+                    format!("Could not move {:?} to {:?}", entry.path(), target)
+                })?;
+            report.moved += 1;
+        }
+        spinner.inc(1);
+    }
+    if let Err(e) = std::fs::remove_dir(from) {
+        debug!("Not removing {:?}: {}", from, e);
+    }
+    Ok(())
+}
+
+/// Where a context link to `target` should point after moving the JDK store, or `None` if it
+/// doesn't point into the old store.
+fn relocated_target(target: &Path, old_store: &Path, new_store: &Path) -> Option<PathBuf> {
+    target
+        .strip_prefix(old_store)
+        .ok()
+        .map(|rest| new_store.join(rest))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_relocated_target() {
+        let old = Path::new("/home/me/.cache/jpre/jdks");
+        let new = Path::new("/data/cache/jpre/jdks");
+        assert_eq!(
+            Some(PathBuf::from("/data/cache/jpre/jdks/21")),
+            relocated_target(Path::new("/home/me/.cache/jpre/jdks/21"), old, new)
+        );
+        assert_eq!(
+            None,
+            relocated_target(Path::new("/home/me/.cache/jpre/jdks-other/21"), old, new)
+        );
+        assert_eq!(None, relocated_target(Path::new("/opt/jdk"), old, new));
+    }
+}
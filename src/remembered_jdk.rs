@@ -0,0 +1,76 @@
+use crate::config::JpreConfig;
+use crate::context_id::{get_remembered_jdk_path, is_process_alive, remembered_jdks_dir};
+use crate::error::ESResult;
+use crate::java_version::key::VersionKey;
+use derive_more::Display;
+use error_stack::{Context, ResultExt};
+use std::str::FromStr;
+use tracing::{debug, warn};
+
+#[derive(Debug, Display)]
+pub struct RememberedJdkError;
+
+impl Context for RememberedJdkError {}
+
+/// The JDK `use --remember` chose for the current context, if there is a readable one.
+pub fn read(config: &JpreConfig) -> Option<VersionKey> {
+    let contents = std::fs::read_to_string(get_remembered_jdk_path(config)).ok()?;
+    VersionKey::from_str(contents.trim()).ok()
+}
+
+/// Make `java-home` pick `jdk` for the current context from now on, instead of the default.
+pub fn remember(config: &JpreConfig, jdk: &VersionKey) -> ESResult<(), RememberedJdkError> {
+    let path = get_remembered_jdk_path(config);
+    std::fs::create_dir_all(path.parent().unwrap())
+        .change_context(RememberedJdkError)
+        .attach_printable_lazy(|| format!("Could not create {:?}", path.parent()))?;
+    std::fs::write(&path, format!("{}\n", jdk))
+        .change_context(RememberedJdkError)
+        .attach_printable_lazy(|| format!("Could not write {:?}", path))
+}
+
+/// Go back to the default for the current context. Returns whether a JDK was remembered.
+pub fn forget(config: &JpreConfig) -> ESResult<bool, RememberedJdkError> {
+    let path = get_remembered_jdk_path(config);
+    match std::fs::remove_file(&path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e)
+            .change_context(RememberedJdkError)
+            .attach_printable_lazy(|| format!("Could not remove {:?}", path)),
+    }
+}
+
+/// Remove JDKs remembered for contexts named by a PID whose process is gone. `use --remember`
+/// refuses those now, but older versions wrote them. Returns how many were removed.
+pub fn gc() -> ESResult<usize, RememberedJdkError> {
+    let dir = remembered_jdks_dir();
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => {
+            return Err(e)
+                .change_context(RememberedJdkError)
+                .attach_printable_lazy(|| format!("Could not read {:?}", dir))
+        }
+    };
+    let mut removed = 0;
+    for entry in entries.filter_map(|e| e.ok()) {
+        let Some(pid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|n| n.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        if is_process_alive(pid) {
+            continue;
+        }
+        debug!("Removing JDK remembered for exited process {}", pid);
+        match std::fs::remove_file(entry.path()) {
+            Ok(()) => removed += 1,
+            Err(e) => warn!("Could not remove {:?}: {}", entry.path(), e),
+        }
+    }
+    Ok(removed)
+}
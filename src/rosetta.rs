@@ -0,0 +1,62 @@
+use std::process::{Command, Stdio};
+use std::sync::LazyLock;
+use tracing::debug;
+
+static APPLE_SILICON: LazyLock<bool> =
+    LazyLock::new(|| cfg!(target_os = "macos") && sysctl("hw.optional.arm64") == Some(1));
+
+/// Whether this is a Mac with Apple Silicon. Also true when jpre itself runs under Rosetta 2,
+/// unlike checking the architecture jpre was built for.
+pub fn is_apple_silicon() -> bool {
+    *APPLE_SILICON
+}
+
+/// Whether jpre runs translated by Rosetta 2, e.g. when started from an x86_64 shell.
+pub fn is_translated() -> bool {
+    cfg!(target_os = "macos") && sysctl("sysctl.proc_translated") == Some(1)
+}
+
+/// Whether JDKs for the Foojay architecture `arch` run emulated on this machine.
+pub fn is_emulated_foojay_arch(arch: &str) -> bool {
+    is_apple_silicon() && arch == "x64"
+}
+
+/// Whether a JDK with `OS_ARCH` from its `release` file runs emulated on this machine.
+pub fn is_emulated_release_arch(os_arch: &str) -> bool {
+    is_apple_silicon() && is_x64_release_arch(os_arch)
+}
+
+fn is_x64_release_arch(os_arch: &str) -> bool {
+    matches!(os_arch, "x86_64" | "amd64" | "x64")
+}
+
+/// An integer `sysctl` value, `None` if it doesn't exist here.
+fn sysctl(name: &str) -> Option<i64> {
+    let output = Command::new("sysctl")
+        .args(["-n", name])
+        .stderr(Stdio::null())
+        .output();
+    match output {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+        }
+        Ok(_) => None,
+        Err(e) => {
+            debug!("Could not run sysctl: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_x64_release_arch() {
+        assert!(is_x64_release_arch("x86_64"));
+        assert!(is_x64_release_arch("amd64"));
+        assert!(!is_x64_release_arch("aarch64"));
+        assert!(!is_x64_release_arch("arm64"));
+    }
+}
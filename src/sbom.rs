@@ -0,0 +1,111 @@
+use crate::install_metadata::InstallMetadata;
+use crate::java_version::key::VersionKey;
+use serde_json::{json, Value};
+
+/// A CycloneDX 1.5 document describing where each JDK came from.
+pub fn cyclonedx_bom(jdks: &[(VersionKey, InstallMetadata)], now: u64) -> Value {
+    let components = jdks
+        .iter()
+        .map(|(jdk, metadata)| {
+            let mut component = json!({
+                "type": "platform",
+                "bom-ref": format!("jdk-{}", jdk),
+                "group": metadata.distribution,
+                "name": "jdk",
+                "version": metadata.java_version.to_string(),
+                "externalReferences": [
+                    { "type": "distribution", "url": metadata.download_uri },
+                ],
+                "properties": [
+                    { "name": "jpre:version-key", "value": jdk.to_string() },
+                    { "name": "jpre:installed-at", "value": rfc3339(metadata.installed_at) },
+                ],
+            });
+            if !metadata.sha256.is_empty() {
+                component["hashes"] = json!([{ "alg": "SHA-256", "content": metadata.sha256 }]);
+            }
+            component
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "version": 1,
+        "metadata": {
+            "timestamp": rfc3339(now),
+            "tools": {
+                "components": [{
+                    "type": "application",
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                }],
+            },
+        },
+        "components": components,
+    })
+}
+
+/// Format seconds since the Unix epoch as an RFC 3339 UTC timestamp.
+fn rfc3339(unix_secs: u64) -> String {
+    let days = (unix_secs / 86400) as i64;
+    let secs_of_day = unix_secs % 86400;
+    // Days to civil date, from https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::java_version::JavaVersion;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_rfc3339() {
+        assert_eq!("1970-01-01T00:00:00Z", rfc3339(0));
+        assert_eq!("2000-02-29T12:34:56Z", rfc3339(951827696));
+        assert_eq!("2024-12-31T23:59:59Z", rfc3339(1735689599));
+    }
+
+    #[test]
+    fn test_cyclonedx_bom() {
+        let metadata = InstallMetadata {
+            distribution: "temurin".to_string(),
+            java_version: JavaVersion::from_str("21.0.4+7").unwrap(),
+            download_uri: "https://example.com/jdk.tar.gz".to_string(),
+            sha256: String::new(),
+            installed_at: 0,
+            archive_size: 1,
+            from_download_cache: false,
+            download_secs: 1.0,
+            extraction_secs: 1.0,
+            installed_size: 1,
+        };
+        let bom = cyclonedx_bom(&[(VersionKey::from_str("21").unwrap(), metadata)], 0);
+        let component = &bom["components"][0];
+        assert_eq!("jdk-21", component["bom-ref"]);
+        assert_eq!("21.0.4+7", component["version"]);
+        assert_eq!(
+            "https://example.com/jdk.tar.gz",
+            component["externalReferences"][0]["url"]
+        );
+        // Unknown checksums are left out rather than written empty.
+        assert!(component.get("hashes").is_none());
+    }
+}
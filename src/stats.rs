@@ -0,0 +1,119 @@
+use crate::java_version::key::VersionKey;
+use crate::journal::{JournalEntry, JournalEvent};
+use std::collections::BTreeMap;
+
+const SECS_PER_DAY: f64 = 24.0 * 60.0 * 60.0;
+
+/// A summary of the usage journal.
+#[derive(Debug, Default, PartialEq)]
+pub struct UsageStats {
+    /// Seconds since the Unix epoch of the first entry.
+    pub since: Option<u64>,
+    pub installs_per_distribution: BTreeMap<String, usize>,
+    pub uses_per_jdk: BTreeMap<VersionKey, usize>,
+    /// Installs that replaced an older build.
+    pub updates: usize,
+    /// Mean days between updates, if there were at least two.
+    pub mean_days_between_updates: Option<f64>,
+}
+
+impl UsageStats {
+    pub fn from_entries(entries: &[JournalEntry]) -> UsageStats {
+        let mut stats = UsageStats {
+            since: entries.iter().map(|e| e.at).min(),
+            ..UsageStats::default()
+        };
+        let mut update_times = Vec::new();
+        for entry in entries {
+            match &entry.event {
+                JournalEvent::Installed {
+                    distribution,
+                    previous,
+                    ..
+                } => {
+                    *stats
+                        .installs_per_distribution
+                        .entry(distribution.clone())
+                        .or_default() += 1;
+                    if previous.is_some() {
+                        update_times.push(entry.at);
+                    }
+                }
+                JournalEvent::Used { jdk } => {
+                    *stats.uses_per_jdk.entry(jdk.clone()).or_default() += 1;
+                }
+            }
+        }
+        stats.updates = update_times.len();
+        update_times.sort();
+        if let (Some(first), Some(last)) = (update_times.first(), update_times.last()) {
+            if update_times.len() > 1 {
+                stats.mean_days_between_updates =
+                    Some((last - first) as f64 / SECS_PER_DAY / (update_times.len() - 1) as f64);
+            }
+        }
+        stats
+    }
+
+    /// The most used JDK, preferring the newer one on ties.
+    pub fn most_used_jdk(&self) -> Option<(&VersionKey, usize)> {
+        self.uses_per_jdk
+            .iter()
+            .max_by_key(|(jdk, uses)| (**uses, *jdk))
+            .map(|(jdk, uses)| (jdk, *uses))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::java_version::JavaVersion;
+    use std::str::FromStr;
+
+    fn installed(at: u64, distribution: &str, previous: Option<&str>) -> JournalEntry {
+        JournalEntry {
+            at,
+            event: JournalEvent::Installed {
+                jdk: VersionKey::from_str("21").unwrap(),
+                distribution: distribution.to_string(),
+                version: JavaVersion::from_str("21.0.2+13").unwrap(),
+                previous: previous.map(|p| JavaVersion::from_str(p).unwrap()),
+            },
+        }
+    }
+
+    fn used(at: u64, jdk: &str) -> JournalEntry {
+        JournalEntry {
+            at,
+            event: JournalEvent::Used {
+                jdk: VersionKey::from_str(jdk).unwrap(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_usage_stats() {
+        let day = SECS_PER_DAY as u64;
+        let stats = UsageStats::from_entries(&[
+            installed(day, "temurin", None),
+            used(day, "21"),
+            installed(11 * day, "temurin", Some("21.0.1+12")),
+            used(12 * day, "17"),
+            used(13 * day, "21"),
+            installed(31 * day, "zulu", Some("21.0.1+12")),
+        ]);
+        assert_eq!(Some(day), stats.since);
+        assert_eq!(
+            BTreeMap::from([("temurin".to_string(), 2), ("zulu".to_string(), 1)]),
+            stats.installs_per_distribution
+        );
+        assert_eq!(2, stats.updates);
+        assert_eq!(Some(20.0), stats.mean_days_between_updates);
+        assert_eq!(
+            Some((&VersionKey::from_str("21").unwrap(), 2)),
+            stats.most_used_jdk()
+        );
+
+        assert_eq!(UsageStats::default(), UsageStats::from_entries(&[]));
+    }
+}
@@ -0,0 +1,342 @@
+use crate::error::ESResult;
+use crate::fs_util::resolve_within;
+use crate::java_version::key::VersionKey;
+use crate::java_version::JavaVersion;
+use crate::jdk_manager::{
+    is_older_than, marker_path, JdkManager, IN_PROGRESS_GRACE, LEGACY_JDK_MARKER_FILE_NAME,
+    LINK_RECORD_SUFFIX, PREVIOUS_JDK_SUFFIX, RETAINED_JDK_INFIX, STORE_LOCK_FILE_NAME,
+};
+use crate::release_file::ReleaseFile;
+use derive_more::Display;
+use error_stack::{Context, ResultExt};
+use std::collections::BTreeMap;
+use std::fmt::Formatter;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Debug, Display)]
+pub struct StoreCheckError;
+
+impl Context for StoreCheckError {}
+
+/// Something wrong in the JDK store.
+#[derive(Debug)]
+pub struct Finding {
+    pub path: PathBuf,
+    pub problem: String,
+    /// How to repair it, if it can be repaired automatically.
+    pub repair: Option<Repair>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Repair {
+    Remove,
+    /// Write a marker with this version, taken from the JDK's `release` file.
+    RewriteMarker(JavaVersion),
+    /// Move the directory back to this path, replacing what's there.
+    RestoreTo(PathBuf),
+}
+
+impl std::fmt::Display for Repair {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Repair::Remove => write!(f, "remove it"),
+            Repair::RewriteMarker(version) => write!(f, "rewrite the marker as {}", version),
+            Repair::RestoreTo(path) => write!(f, "restore it to {}", path.display()),
+        }
+    }
+}
+
+impl Finding {
+    fn new(path: PathBuf, problem: impl Into<String>, repair: Option<Repair>) -> Self {
+        Finding {
+            path,
+            problem: problem.into(),
+            repair,
+        }
+    }
+
+    /// Repair the finding in the JDK store at `store`. Like removing JDKs, what's removed or moved
+    /// must not lead out of the store through a symlink, see [resolve_within].
+    pub fn apply_repair(&self, store: &Path) -> ESResult<(), StoreCheckError> {
+        match &self.repair {
+            None => Ok(()),
+            Some(Repair::Remove) => remove_within(store, &self.path)
+                .change_context(StoreCheckError)
+                .attach_printable_lazy(|| format!("Could not remove {:?}", self.path)),
+            Some(Repair::RewriteMarker(version)) => JdkManager::write_marker(&self.path, version)
+                .change_context(StoreCheckError)
+                .attach_printable_lazy(|| format!("Could not rewrite marker in {:?}", self.path)),
+            Some(Repair::RestoreTo(target)) => {
+                if std::fs::symlink_metadata(target).is_ok() {
+                    remove_within(store, target)
+                        .change_context(StoreCheckError)
+                        .attach_printable_lazy(|| format!("Could not remove {:?}", target))?;
+                }
+                resolve_within(store, &self.path)
+                    .and_then(|from| std::fs::rename(from, resolve_within(store, target)?))
+                    .change_context(StoreCheckError)
+                    .attach_printable_lazy(|| {
+                        format!("Could not move {:?} to {:?}", self.path, target)
+                    })
+            }
+        }
+    }
+}
+
+/// Remove the directory, file or symlink at `path` inside `store`.
+fn remove_within(store: &Path, path: &Path) -> std::io::Result<()> {
+    let resolved = resolve_within(store, path)?;
+    if std::fs::symlink_metadata(&resolved)?.is_dir() {
+        std::fs::remove_dir_all(&resolved)
+    } else {
+        std::fs::remove_file(&resolved)
+    }
+}
+
+/// An entry of the store that is named like a version key.
+struct KeyEntry {
+    path: PathBuf,
+    name: String,
+    key: VersionKey,
+    version: Option<JavaVersion>,
+}
+
+/// Check every entry of the JDK store at `store`.
+pub fn check_store(store: &Path) -> ESResult<Vec<Finding>, StoreCheckError> {
+    let entries = match std::fs::read_dir(store) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(e)
+                .change_context(StoreCheckError)
+                .attach_printable_lazy(|| format!("Could not read {:?}", store))
+        }
+    };
+    let mut findings = Vec::new();
+    let mut by_key = BTreeMap::<String, Vec<KeyEntry>>::new();
+    for entry in entries {
+        let entry = entry
+            .change_context(StoreCheckError)
+            .attach_printable_lazy(|| format!("Could not read entry in {:?}", store))?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name == STORE_LOCK_FILE_NAME {
+            continue;
+        }
+        if name.starts_with(".tmp") {
+            if is_older_than(&path, IN_PROGRESS_GRACE) {
+                findings.push(Finding::new(
+                    path,
+                    "leftover unpack directory",
+                    Some(Repair::Remove),
+                ));
+            }
+            continue;
+        }
+        if name.contains(RETAINED_JDK_INFIX) {
+            // An old version kept by `keep_previous_versions`, pruned by updates.
+            continue;
+        }
+        if let Some(key_name) = name.strip_suffix(LINK_RECORD_SUFFIX) {
+            // The marker of a linked JDK, checked with it.
+            if !store.join(key_name).is_symlink() {
+                findings.push(Finding::new(
+                    path,
+                    "record of a linked JDK that is gone",
+                    Some(Repair::Remove),
+                ));
+            }
+            continue;
+        }
+        if let Some(key_name) = name.strip_suffix(PREVIOUS_JDK_SUFFIX) {
+            let jdk_path = store.join(key_name);
+            if marker_path(&jdk_path).exists() {
+                findings.push(Finding::new(
+                    path,
+                    "old JDK left behind by an update",
+                    Some(Repair::Remove),
+                ));
+            } else {
+                findings.push(Finding::new(
+                    path,
+                    "JDK moved aside by an interrupted update",
+                    Some(Repair::RestoreTo(jdk_path)),
+                ));
+            }
+            continue;
+        }
+        let Ok(key) = VersionKey::from_str(&name) else {
+            findings.push(Finding::new(path, "not a JDK of jpre", None));
+            continue;
+        };
+
+        let version = match std::fs::read_to_string(marker_path(&path)) {
+            Ok(contents) => match JavaVersion::from_str(contents.trim()) {
+                Ok(version) => Some(version),
+                Err(_) => {
+                    let release_version = ReleaseFile::read(&path)
+                        .ok()
+                        .flatten()
+                        .and_then(|r| r.java_version().ok().flatten());
+                    let repair = match release_version {
+                        Some(version) => Repair::RewriteMarker(version),
+                        None => Repair::Remove,
+                    };
+                    findings.push(Finding::new(
+                        path,
+                        format!("marker has an invalid version {:?}", contents.trim()),
+                        Some(repair),
+                    ));
+                    continue;
+                }
+            },
+            Err(_) if path.join(LEGACY_JDK_MARKER_FILE_NAME).exists() => None,
+            Err(_) => {
+                findings.push(Finding::new(
+                    path,
+                    "no marker, the install never finished",
+                    Some(Repair::Remove),
+                ));
+                continue;
+            }
+        };
+        if let Some(version) = &version {
+            if VersionKey::from(version.clone()).major != key.major {
+                findings.push(Finding::new(
+                    path.clone(),
+                    format!("contains JDK {}, which doesn't match its name", version),
+                    None,
+                ));
+            }
+        }
+        by_key
+            .entry(key.to_string().to_ascii_lowercase())
+            .or_default()
+            .push(KeyEntry {
+                path,
+                name,
+                key,
+                version,
+            });
+    }
+
+    for (canonical, mut entries) in by_key {
+        if entries.len() < 2 {
+            continue;
+        }
+        // Keep the newest, preferring the canonical name.
+        entries.sort_by(|a, b| {
+            let version_order = match (&a.version, &b.version) {
+                (Some(a), Some(b)) => a.compare(b),
+                (a, b) => a.is_some().cmp(&b.is_some()),
+            };
+            version_order.then((a.name == canonical).cmp(&(b.name == canonical)))
+        });
+        let kept = entries.pop().expect("at least two entries");
+        for entry in entries {
+            findings.push(Finding::new(
+                entry.path,
+                format!("duplicate of JDK {} at {}", entry.key, kept.path.display()),
+                Some(Repair::Remove),
+            ));
+        }
+    }
+    findings.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(findings)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::jdk_manager::JDK_VALID_MARKER_FILE_NAME;
+
+    fn jdk(store: &Path, name: &str, marker: Option<&str>) {
+        let dir = store.join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        if let Some(marker) = marker {
+            std::fs::write(dir.join(JDK_VALID_MARKER_FILE_NAME), marker).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_check_store() {
+        let store = tempfile::tempdir().unwrap();
+        let store = store.path();
+        jdk(store, "21", Some("21.0.2+13"));
+        jdk(store, "17", None);
+        jdk(store, "11", Some("not a version"));
+        std::fs::write(store.join("11/release"), "JAVA_VERSION=\"11.0.22\"\n").unwrap();
+        jdk(store, "8", Some("garbage"));
+        jdk(store, "22-ea", Some("22-ea+30"));
+        jdk(store, "22-EA", Some("22-ea+20"));
+        jdk(store, "23.previous", Some("23+37"));
+        jdk(store, "notes", None);
+        let linked = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(linked.path(), store.join("25")).unwrap();
+        std::fs::write(store.join("25.link"), "25+36").unwrap();
+        std::fs::write(store.join("24.link"), "24+36").unwrap();
+
+        let findings = check_store(store).unwrap();
+        let summary = findings
+            .iter()
+            .map(|f| {
+                (
+                    f.path.file_name().unwrap().to_string_lossy().into_owned(),
+                    f.repair.as_ref().map(|r| r.to_string()),
+                )
+            })
+            .collect::<Vec<_>>();
+        let remove = Some("remove it".to_string());
+        assert_eq!(
+            vec![
+                (
+                    "11".to_string(),
+                    Some("rewrite the marker as 11.0.22".to_string())
+                ),
+                ("17".to_string(), remove.clone()),
+                ("22-EA".to_string(), remove.clone()),
+                (
+                    "23.previous".to_string(),
+                    Some(format!("restore it to {}", store.join("23").display()))
+                ),
+                ("24.link".to_string(), remove.clone()),
+                ("8".to_string(), remove),
+                ("notes".to_string(), None),
+            ],
+            summary
+        );
+    }
+
+    #[test]
+    fn test_repairs_stay_in_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = dir.path().join("store");
+        let outside = dir.path().join("outside");
+        jdk(&store, "23.previous", Some("23+37"));
+        jdk(&outside, "23", Some("23+30"));
+        std::os::unix::fs::symlink(&outside, store.join("nested")).unwrap();
+
+        let finding = Finding::new(store.join("nested/23"), "test", Some(Repair::Remove));
+        assert!(finding.apply_repair(&store).is_err());
+        let finding = Finding::new(
+            store.join("23.previous"),
+            "test",
+            Some(Repair::RestoreTo(store.join("nested/23"))),
+        );
+        assert!(finding.apply_repair(&store).is_err());
+        assert!(outside.join("23").join(JDK_VALID_MARKER_FILE_NAME).exists());
+        assert!(store.join("23.previous").exists());
+
+        let finding = Finding::new(
+            store.join("23.previous"),
+            "test",
+            Some(Repair::RestoreTo(store.join("23"))),
+        );
+        finding.apply_repair(&store).unwrap();
+        assert!(store.join("23").join(JDK_VALID_MARKER_FILE_NAME).exists());
+        let finding = Finding::new(store.join("nested"), "test", Some(Repair::Remove));
+        finding.apply_repair(&store).unwrap();
+        assert!(outside.join("23").exists());
+    }
+}
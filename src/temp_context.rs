@@ -0,0 +1,155 @@
+use crate::context_id::{is_process_alive, state_dir, CONTEXT_LINKS_DIR_NAME};
+use crate::error::ESResult;
+use derive_more::Display;
+use error_stack::{Context, ResultExt};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, warn};
+
+#[derive(Debug, Display)]
+pub struct TempContextError;
+
+impl Context for TempContextError {}
+
+/// Directory name in the state dir for temporary contexts. Each context is a symlink to its JDK
+/// plus a `.json` file saying when it expires.
+const TEMP_CONTEXTS_DIR_NAME: &str = "java-home-temp";
+
+fn temp_contexts_dir() -> PathBuf {
+    state_dir().join(TEMP_CONTEXTS_DIR_NAME)
+}
+
+/// Context IDs of `jpre shell` subshells start with this, followed by the PID of the jpre that
+/// started them.
+const SHELL_CONTEXT_PREFIX: &str = "shell-";
+
+/// The context ID of a `jpre shell` subshell started by `owner_pid`. Its link is a regular context
+/// link, so `jpre use` in the subshell can change it, and [gc] removes it once `owner_pid` exits.
+pub fn shell_context_id(owner_pid: u32) -> String {
+    format!("{}{}", SHELL_CONTEXT_PREFIX, owner_pid)
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct TempContextState {
+    /// Seconds since the Unix epoch.
+    expires_at: u64,
+    /// The context is also removed once this process is gone.
+    owner_pid: u32,
+}
+
+fn unix_now() -> Duration {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// Create a temporary context linking to `jdk_path`, returning the link. It lives until `ttl`
+/// passes or `owner_pid` exits, whichever is first, after which [gc] removes it.
+pub fn create(
+    jdk_path: &Path,
+    ttl: Duration,
+    owner_pid: u32,
+) -> ESResult<PathBuf, TempContextError> {
+    let dir = temp_contexts_dir();
+    std::fs::create_dir_all(&dir)
+        .change_context(TempContextError)
+        .attach_printable_lazy(|| format!("Could not create {:?}", dir))?;
+    let now = unix_now();
+    let name = format!("{}-{}", owner_pid, now.as_nanos());
+    let link = dir.join(&name);
+    let state = TempContextState {
+        expires_at: (now + ttl).as_secs(),
+        owner_pid,
+    };
+    // State first, so gc never sees a link without knowing when it expires.
+    let state_path = dir.join(format!("{}.json", name));
+    std::fs::write(
+        &state_path,
+        serde_json::to_string(&state).change_context(TempContextError)?,
+    )
+    .change_context(TempContextError)
+    .attach_printable_lazy(|| format!("Could not write {:?}", state_path))?;
+    std::os::unix::fs::symlink(jdk_path, &link)
+        .change_context(TempContextError)
+        .attach_printable_lazy(|| format!("Could not link {:?} to {:?}", link, jdk_path))?;
+    Ok(link)
+}
+
+/// Remove a temporary context created by [create].
+pub fn remove(link: &Path) {
+    let state_path = link.with_extension("json");
+    for path in [link, &state_path] {
+        match std::fs::remove_file(path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Could not remove temporary context file {:?}: {}", path, e),
+        }
+    }
+}
+
+/// Remove temporary contexts that expired or whose owner exited, including those of `jpre shell`.
+/// Returns how many were removed.
+pub fn gc() -> ESResult<usize, TempContextError> {
+    let mut removed = gc_shell_contexts();
+    let dir = temp_contexts_dir();
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(removed),
+        Err(e) => {
+            return Err(e)
+                .change_context(TempContextError)
+                .attach_printable_lazy(|| format!("Could not read {:?}", dir))
+        }
+    };
+    let now = unix_now().as_secs();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.extension().is_none_or(|e| e != "json") {
+            // A link whose state is gone can't be tracked anymore.
+            if !path.with_extension("json").exists() && std::fs::read_link(&path).is_ok() {
+                remove(&path);
+                removed += 1;
+            }
+            continue;
+        }
+        let expired = match std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str::<TempContextState>(&s).ok())
+        {
+            Some(state) => state.expires_at <= now || !is_process_alive(state.owner_pid),
+            None => true,
+        };
+        if expired {
+            debug!("Removing temporary context {:?}", path);
+            remove(&path.with_extension(""));
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Remove the context links of `jpre shell` subshells whose jpre exited without removing them.
+fn gc_shell_contexts() -> usize {
+    let dir = state_dir().join(CONTEXT_LINKS_DIR_NAME);
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return 0;
+    };
+    let mut removed = 0;
+    for entry in entries.filter_map(|e| e.ok()) {
+        let owner = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.strip_prefix(SHELL_CONTEXT_PREFIX))
+            .and_then(|pid| pid.parse::<u32>().ok());
+        if owner.is_none_or(is_process_alive) {
+            continue;
+        }
+        debug!("Removing context of exited jpre shell {:?}", entry.path());
+        match std::fs::remove_file(entry.path()) {
+            Ok(()) => removed += 1,
+            Err(e) => warn!("Could not remove context link {:?}: {}", entry.path(), e),
+        }
+    }
+    removed
+}
@@ -0,0 +1,23 @@
+//! Fixtures shared by the unit tests.
+
+use crate::jdk_manager::JDK_VALID_MARKER_FILE_NAME;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+/// A tiny JDK 17.0.2+8 at `<dir>/home`, with an executable, a regular file, a symlink and a jpre
+/// marker.
+pub fn fake_jdk(dir: &Path) -> PathBuf {
+    let home = dir.join("home");
+    std::fs::create_dir_all(home.join("bin")).unwrap();
+    std::fs::create_dir_all(home.join("lib")).unwrap();
+    std::fs::write(home.join("bin/java"), "#!/bin/sh\n").unwrap();
+    std::fs::set_permissions(
+        home.join("bin/java"),
+        std::fs::Permissions::from_mode(0o755),
+    )
+    .unwrap();
+    std::fs::write(home.join("lib/modules"), "modules").unwrap();
+    std::fs::write(home.join(JDK_VALID_MARKER_FILE_NAME), "17.0.2+8").unwrap();
+    std::os::unix::fs::symlink("lib/modules", home.join("modules")).unwrap();
+    home
+}
@@ -0,0 +1,255 @@
+use crate::error::ESResult;
+use crate::jobs;
+use derive_more::Display;
+use error_stack::{Context, Report, ResultExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Display)]
+#[display("Tree manifest error")]
+pub struct TreeManifestError;
+
+impl Context for TreeManifestError {}
+
+const TREE_MANIFEST_FILE_NAME: &str = ".jpre_manifest.json";
+
+/// What each file of an installed JDK looked like when it was installed, stored next to the JDK's
+/// marker file. jpre's own files in the JDK's root, like the marker, aren't included.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TreeManifest {
+    /// Paths relative to the JDK's root.
+    pub entries: BTreeMap<PathBuf, ManifestEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ManifestEntry {
+    File {
+        size: u64,
+        /// In hex.
+        sha256: String,
+    },
+    Symlink(PathBuf),
+}
+
+/// A way the tree differs from its manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+pub enum TreeDifference {
+    #[display("{} is missing", _0.display())]
+    Missing(PathBuf),
+    #[display("{} was modified", _0.display())]
+    Modified(PathBuf),
+    #[display("{} was added", _0.display())]
+    Added(PathBuf),
+}
+
+impl TreeManifest {
+    /// Hash every file in the JDK at `jdk_home`. With `hash` off, only sizes and link targets are
+    /// recorded, which is enough for a quick check.
+    pub fn compute(jdk_home: &Path, hash: bool) -> ESResult<TreeManifest, TreeManifestError> {
+        let mut paths = Vec::new();
+        collect_files(jdk_home, Path::new(""), &mut paths)
+            .change_context(TreeManifestError)
+            .attach_printable_lazy(|| format!("Could not list files of {:?}", jdk_home))?;
+        let entries = jobs::run_bounded(paths, jobs::DEFAULT_CONCURRENCY, |relative| {
+            let entry = manifest_entry(&jdk_home.join(&relative), hash);
+            (relative, entry)
+        })
+        .change_context(TreeManifestError)?
+        .into_iter()
+        .map(|(relative, entry)| {
+            entry
+                .change_context(TreeManifestError)
+                .attach_printable_lazy(|| format!("Could not read {:?}", relative))
+                .map(|entry| (relative, entry))
+        })
+        .collect::<ESResult<_, _>>()?;
+        Ok(TreeManifest { entries })
+    }
+
+    /// Read the manifest of the JDK at `jdk_home`. Returns `None` if none was recorded.
+    pub fn read(jdk_home: &Path) -> ESResult<Option<TreeManifest>, TreeManifestError> {
+        let path = jdk_home.join(TREE_MANIFEST_FILE_NAME);
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e)
+                    .change_context(TreeManifestError)
+                    .attach_printable_lazy(|| format!("Could not read manifest at {:?}", path))
+            }
+        };
+        serde_json::from_reader(std::io::BufReader::new(file))
+            .map(Some)
+            .change_context(TreeManifestError)
+            .attach_printable_lazy(|| format!("Could not parse manifest at {:?}", path))
+    }
+
+    pub fn write(&self, jdk_home: &Path) -> ESResult<(), TreeManifestError> {
+        let path = jdk_home.join(TREE_MANIFEST_FILE_NAME);
+        let temp = tempfile::NamedTempFile::new_in(jdk_home)
+            .change_context(TreeManifestError)
+            .attach_printable_lazy(|| {
+                format!(
+                    "Could not create temporary file for manifest in {:?}",
+                    jdk_home
+                )
+            })?;
+        // Temporary files are private, but other users of a system store need to read this.
+        temp.as_file()
+            .set_permissions(std::fs::Permissions::from_mode(0o644))
+            .change_context(TreeManifestError)
+            .attach_printable_lazy(|| format!("Could not set permissions of {:?}", temp.path()))?;
+        serde_json::to_writer(std::io::BufWriter::new(temp.as_file()), self)
+            .change_context(TreeManifestError)
+            .attach_printable_lazy(|| format!("Could not write manifest to {:?}", temp.path()))?;
+        temp.persist(&path)
+            .change_context(TreeManifestError)
+            .attach_printable_lazy(|| format!("Could not move manifest to {:?}", path))?;
+        Ok(())
+    }
+
+    /// How `actual` differs from this manifest. Without hashes in `actual`, file contents are
+    /// only compared by size.
+    pub fn differences(&self, actual: &TreeManifest) -> Vec<TreeDifference> {
+        let mut differences = Vec::new();
+        for (path, expected) in &self.entries {
+            let modified = match (expected, actual.entries.get(path)) {
+                (_, None) => {
+                    differences.push(TreeDifference::Missing(path.clone()));
+                    continue;
+                }
+                (
+                    ManifestEntry::File { size, sha256 },
+                    Some(ManifestEntry::File {
+                        size: actual_size,
+                        sha256: actual_sha256,
+                    }),
+                ) => size != actual_size || (!actual_sha256.is_empty() && sha256 != actual_sha256),
+                (expected, Some(actual)) => expected != actual,
+            };
+            if modified {
+                differences.push(TreeDifference::Modified(path.clone()));
+            }
+        }
+        differences.extend(
+            actual
+                .entries
+                .keys()
+                .filter(|path| !self.entries.contains_key(*path))
+                .map(|path| TreeDifference::Added(path.clone())),
+        );
+        differences
+    }
+}
+
+/// Files and symlinks under `dir`, relative to the JDK root. `relative` is `dir` relative to it.
+fn collect_files(dir: &Path, relative: &Path, paths: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if relative.as_os_str().is_empty() && is_jpre_file(&name.to_string_lossy()) {
+            continue;
+        }
+        let relative = relative.join(&name);
+        if entry.file_type()?.is_dir() {
+            collect_files(&entry.path(), &relative, paths)?;
+        } else {
+            paths.push(relative);
+        }
+    }
+    Ok(())
+}
+
+/// The marker, install metadata and this manifest, plus temporary files jpre writes them with.
+fn is_jpre_file(name: &str) -> bool {
+    name.starts_with(".jpre_") || name.starts_with(".jdk_marker") || name.starts_with(".tmp")
+}
+
+fn manifest_entry(path: &Path, hash: bool) -> std::io::Result<ManifestEntry> {
+    let metadata = std::fs::symlink_metadata(path)?;
+    if metadata.is_symlink() {
+        return Ok(ManifestEntry::Symlink(std::fs::read_link(path)?));
+    }
+    let sha256 = if hash {
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut File::open(path)?, &mut hasher)?;
+        hex::encode(hasher.finalize())
+    } else {
+        String::new()
+    };
+    Ok(ManifestEntry::File {
+        size: metadata.len(),
+        sha256,
+    })
+}
+
+/// Check the JDK at `jdk_home` against its manifest, re-hashing every file if `deep`.
+pub fn verify(jdk_home: &Path, deep: bool) -> ESResult<Vec<TreeDifference>, TreeManifestError> {
+    let Some(expected) = TreeManifest::read(jdk_home)? else {
+        return Err(Report::new(TreeManifestError)
+            .attach_printable(format!("No manifest recorded for {:?}", jdk_home)));
+    };
+    let actual = TreeManifest::compute(jdk_home, deep)?;
+    Ok(expected.differences(&actual))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::fake_jdk;
+
+    #[test]
+    fn test_compute_skips_jpre_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let home = fake_jdk(dir.path());
+        let manifest = TreeManifest::compute(&home, true).unwrap();
+        assert_eq!(
+            vec![
+                PathBuf::from("bin/java"),
+                PathBuf::from("lib/modules"),
+                PathBuf::from("modules")
+            ],
+            manifest.entries.keys().cloned().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            ManifestEntry::Symlink(PathBuf::from("lib/modules")),
+            manifest.entries[Path::new("modules")]
+        );
+    }
+
+    #[test]
+    fn test_verify_detects_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let home = fake_jdk(dir.path());
+        TreeManifest::compute(&home, true)
+            .unwrap()
+            .write(&home)
+            .unwrap();
+        assert_eq!(Vec::<TreeDifference>::new(), verify(&home, true).unwrap());
+
+        // Same size, so only a deep check notices.
+        std::fs::write(home.join("lib/modules"), "MODULES").unwrap();
+        std::fs::remove_file(home.join("bin/java")).unwrap();
+        std::fs::write(home.join("lib/gradle.lock"), "").unwrap();
+        assert_eq!(
+            vec![
+                TreeDifference::Missing(PathBuf::from("bin/java")),
+                TreeDifference::Added(PathBuf::from("lib/gradle.lock")),
+            ],
+            verify(&home, false).unwrap()
+        );
+        assert_eq!(
+            vec![
+                TreeDifference::Missing(PathBuf::from("bin/java")),
+                TreeDifference::Modified(PathBuf::from("lib/modules")),
+                TreeDifference::Added(PathBuf::from("lib/gradle.lock")),
+            ],
+            verify(&home, true).unwrap()
+        );
+    }
+}
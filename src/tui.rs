@@ -1,12 +1,29 @@
-use console::style;
-use indicatif::{ProgressBar, ProgressStyle};
+use crate::progress_events;
+use crate::progress_events::ProgressEvent;
+use console::{strip_ansi_codes, style};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use owo_colors::{AnsiColors, DynColor};
+use std::fmt::Display;
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+/// All progress bars are drawn through this, so bars from different steps stack instead of
+/// overwriting each other.
+pub static PROGRESS: LazyLock<MultiProgress> = LazyLock::new(MultiProgress::new);
+
+/// Redraw interval, keeps the rate and ETA moving even when no data arrives.
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
+
+static OVERALL_BAR: Mutex<Option<ProgressBar>> = Mutex::new(None);
+static OVERALL_BYTES: AtomicU64 = AtomicU64::new(0);
 
 pub fn new_progress_bar(bar_length: Option<u64>) -> ProgressBar {
     let bar_style = match bar_length {
         Some(_) => ProgressStyle::default_bar()
             .template(
-                "{percent:>3}%[{bar:60.cyan/blue}] {bytes:>8}/{total_bytes} {bytes_per_sec} {wide_msg}",
+                "{percent:>3}%[{bar:60.cyan/blue}] {bytes:>8}/{total_bytes} {bytes_per_sec} ETA {eta} {wide_msg}",
             )
             .unwrap()
             .progress_chars("#|-"),
@@ -17,9 +34,224 @@ pub fn new_progress_bar(bar_length: Option<u64>) -> ProgressBar {
             .unwrap(),
     };
 
-    ProgressBar::new(bar_length.unwrap_or(!0)).with_style(bar_style)
+    let bar = PROGRESS.add(ProgressBar::new(bar_length.unwrap_or(!0)).with_style(bar_style));
+    bar.enable_steady_tick(TICK_INTERVAL);
+    bar
+}
+
+/// A spinner with a counter, for work where the total is known but the size of each step isn't.
+pub fn new_spinner(message: String) -> ProgressBar {
+    let bar = PROGRESS.add(
+        ProgressBar::new_spinner()
+            .with_style(
+                ProgressStyle::default_spinner()
+                    .template("{spinner:.cyan} {pos}/{len} {wide_msg}")
+                    .unwrap(),
+            )
+            .with_message(message)
+            .with_finish(indicatif::ProgressFinish::AndClear),
+    );
+    bar.enable_steady_tick(TICK_INTERVAL);
+    bar
+}
+
+/// Progress over several JDKs, drawn above the per-file bars. Downloads made while this is alive
+/// are added to its byte count.
+pub struct OverallProgress {
+    bar: ProgressBar,
+}
+
+impl OverallProgress {
+    pub fn start(total: u64, message: &str) -> OverallProgress {
+        let bar = PROGRESS.insert(
+            0,
+            ProgressBar::new(total).with_style(
+                ProgressStyle::default_bar()
+                    .template("{prefix} [{bar:30.green/white}] {pos}/{len} JDKs, {msg} ({elapsed})")
+                    .unwrap()
+                    .progress_chars("=> "),
+            ),
+        );
+        bar.set_prefix(message.to_string());
+        OVERALL_BYTES.store(0, Ordering::Relaxed);
+        bar.set_message(format!("{} downloaded", format_bytes(0)));
+        bar.enable_steady_tick(TICK_INTERVAL);
+        *OVERALL_BAR.lock().unwrap() = Some(bar.clone());
+        OverallProgress { bar }
+    }
+
+    pub fn inc(&self) {
+        self.bar.inc(1);
+    }
+}
+
+impl Drop for OverallProgress {
+    fn drop(&mut self) {
+        OVERALL_BAR.lock().unwrap().take();
+        self.bar.finish();
+    }
+}
+
+/// Add downloaded bytes to the [OverallProgress], if there is one.
+pub fn record_downloaded_bytes(bytes: u64) {
+    let total = OVERALL_BYTES.fetch_add(bytes, Ordering::Relaxed) + bytes;
+    if let Some(bar) = &*OVERALL_BAR.lock().unwrap() {
+        bar.set_message(format!("{} downloaded", format_bytes(total)));
+    }
+}
+
+/// Print a line to stderr without garbling any progress bars. It's also sent as a progress event.
+pub fn eprint_above_progress(line: impl Display) {
+    let line = line.to_string();
+    if progress_events::is_enabled() {
+        progress_events::emit(&ProgressEvent::Message {
+            text: &strip_ansi_codes(&line),
+        });
+    }
+    PROGRESS.suspend(|| eprintln!("{}", line));
+}
+
+/// Ask a yes/no question on the terminal, defaulting to no. Without a terminal to ask on, the
+/// answer is always no.
+pub fn confirm(question: impl Display) -> bool {
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        return false;
+    }
+    let mut answer = String::new();
+    PROGRESS
+        .suspend(|| {
+            eprint!("{} [y/N] ", question);
+            let _ = std::io::stderr().flush();
+            std::io::stdin().read_line(&mut answer)
+        })
+        .is_ok_and(|_| matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes"))
 }
 
 pub fn jdk_color() -> impl DynColor {
     AnsiColors::BrightBlue
 }
+
+/// A size in binary units, e.g. `1.5 GiB`, using the locale's decimal separator.
+pub fn format_bytes(bytes: u64) -> String {
+    format_bytes_with(bytes, decimal_separator())
+}
+
+fn format_bytes_with(bytes: u64, separator: char) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    // Round first, so e.g. 1023.96 KiB becomes 1.0 MiB instead of 1024.0 KiB.
+    while (value * 10.0).round() >= 1024.0 * 10.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    with_separator(format!("{:.1} {}", value, UNITS[unit]), separator)
+}
+
+/// A duration as people say it, e.g. `350ms`, `4.2s`, `1m 32s` or `2h 5m`, using the locale's
+/// decimal separator.
+pub fn format_duration(duration: Duration) -> String {
+    format_duration_with(duration, decimal_separator())
+}
+
+fn format_duration_with(duration: Duration, separator: char) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0 => format!("{}ms", duration.as_millis()),
+        1..60 => with_separator(format!("{:.1}s", duration.as_secs_f64()), separator),
+        60..3600 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+fn with_separator(formatted: String, separator: char) -> String {
+    if separator == '.' {
+        formatted
+    } else {
+        formatted.replacen('.', &separator.to_string(), 1)
+    }
+}
+
+/// The decimal separator of the locale numbers are formatted in, from `LC_ALL`, `LC_NUMERIC` or
+/// `LANG`. Only tells apart languages that use a comma, which covers most of them.
+fn decimal_separator() -> char {
+    // Keep test expectations independent of the locale of the machine running them.
+    if cfg!(test) {
+        return '.';
+    }
+    static SEPARATOR: LazyLock<char> = LazyLock::new(|| {
+        let locale = ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .into_iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        separator_for_locale(&locale)
+    });
+    *SEPARATOR
+}
+
+fn separator_for_locale(locale: &str) -> char {
+    const COMMA_LANGUAGES: &[&str] = &[
+        "bg", "ca", "cs", "da", "de", "el", "es", "et", "fi", "fr", "hr", "hu", "id", "it", "lt",
+        "lv", "nb", "nl", "nn", "no", "pl", "pt", "ro", "ru", "sk", "sl", "sr", "sv", "tr", "uk",
+        "vi",
+    ];
+    let language = locale
+        .split(['_', '.', '@', '-'])
+        .next()
+        .unwrap_or_default();
+    if COMMA_LANGUAGES.contains(&language) {
+        ','
+    } else {
+        '.'
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!("0 B", format_bytes_with(0, '.'));
+        assert_eq!("1023 B", format_bytes_with(1023, '.'));
+        assert_eq!("1.0 KiB", format_bytes_with(1024, '.'));
+        assert_eq!("1.5 MiB", format_bytes_with(3 * 512 * 1024, '.'));
+        assert_eq!("1.0 MiB", format_bytes_with(1024 * 1024 - 1, '.'));
+        assert_eq!("187.3 MiB", format_bytes_with(196_400_000, '.'));
+        assert_eq!("2,0 GiB", format_bytes_with(2 * 1024 * 1024 * 1024, ','));
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(
+            "350ms",
+            format_duration_with(Duration::from_millis(350), '.')
+        );
+        assert_eq!(
+            "4.2s",
+            format_duration_with(Duration::from_millis(4200), '.')
+        );
+        assert_eq!(
+            "4,2s",
+            format_duration_with(Duration::from_millis(4200), ',')
+        );
+        assert_eq!("1m 32s", format_duration_with(Duration::from_secs(92), '.'));
+        assert_eq!(
+            "2h 5m",
+            format_duration_with(Duration::from_secs(7530), '.')
+        );
+    }
+
+    #[test]
+    fn test_separator_for_locale() {
+        assert_eq!(',', separator_for_locale("de_DE.UTF-8"));
+        assert_eq!(',', separator_for_locale("fr"));
+        assert_eq!('.', separator_for_locale("en_US.UTF-8"));
+        assert_eq!('.', separator_for_locale("C"));
+        assert_eq!('.', separator_for_locale(""));
+    }
+}
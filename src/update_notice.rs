@@ -0,0 +1,127 @@
+use crate::config::{active_profile, config_path, is_alternate_config, JpreConfig};
+use crate::context_id::{get_context_path, state_dir};
+use crate::java_version::key::VersionKey;
+use crate::update_state::UpdateState;
+use owo_colors::{OwoColorize, Stream};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+use tracing::debug;
+
+/// How often the active JDK is checked for updates in the background.
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Set for the background check, so it neither shows notices nor starts checks itself.
+const BACKGROUND_CHECK_ENV: &str = "JPRE_BACKGROUND_UPDATE_CHECK";
+
+/// Touched whenever a background check starts, so failing checks aren't retried on every command.
+const CHECK_STARTED_FILE_NAME: &str = "update-check-started";
+
+/// After a command, point out an update of the active JDK found by the last check, and start a
+/// new check in the background if the last one is a day old. Only with `update_notices` in the
+/// config.
+pub fn show_after_command(config: &JpreConfig) {
+    if !config.update_notices || std::env::var_os(BACKGROUND_CHECK_ENV).is_some() {
+        return;
+    }
+    let Some(jdk) = active_jdk(config) else {
+        return;
+    };
+    let state = UpdateState::read();
+    if let Some(latest) = state.as_ref().and_then(|state| state.available.get(&jdk)) {
+        let notice = format!(
+            "JDK {} is available, run `jpre update {}` to install it",
+            latest, jdk
+        );
+        eprintln!(
+            "{}",
+            notice.if_supports_color(Stream::Stderr, |s| s.dimmed())
+        );
+    }
+    let checked_at = state.map_or(0, |state| state.checked_at);
+    if is_check_due(unix_secs(SystemTime::now()), checked_at, check_started_at()) {
+        start_background_check(&jdk);
+    }
+}
+
+/// The installed JDK the context links to.
+fn active_jdk(config: &JpreConfig) -> Option<VersionKey> {
+    let path = get_context_path(config);
+    if !path.exists() {
+        return None;
+    }
+    let target = std::fs::read_link(path).ok()?;
+    VersionKey::from_str(target.file_name()?.to_str()?).ok()
+}
+
+fn is_check_due(now: u64, checked_at: u64, started_at: u64) -> bool {
+    now.saturating_sub(checked_at.max(started_at)) >= CHECK_INTERVAL.as_secs()
+}
+
+fn check_started_path() -> PathBuf {
+    state_dir().join(CHECK_STARTED_FILE_NAME)
+}
+
+fn check_started_at() -> u64 {
+    std::fs::metadata(check_started_path())
+        .and_then(|metadata| metadata.modified())
+        .map_or(0, unix_secs)
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Run `jpre update --check` for `jdk` without waiting for it. It records what it finds for the
+/// next command to show.
+fn start_background_check(jdk: &VersionKey) {
+    let started = check_started_path();
+    if let Err(e) = std::fs::create_dir_all(started.parent().unwrap())
+        .and_then(|()| std::fs::write(&started, ""))
+    {
+        debug!("Not checking for updates in the background: {}", e);
+        return;
+    }
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            debug!("Could not find jpre to check for updates: {}", e);
+            return;
+        }
+    };
+    let mut command = Command::new(exe);
+    command
+        .args(["update", "--check", "--quiet", &jdk.to_string()])
+        .env(BACKGROUND_CHECK_ENV, "1")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    if is_alternate_config() {
+        command.env("JPRE_CONFIG", config_path());
+    }
+    if let Some(profile) = active_profile() {
+        command.env("JPRE_PROFILE", profile);
+    }
+    match command.spawn() {
+        Ok(_) => debug!("Checking for updates of JDK {} in the background", jdk),
+        Err(e) => debug!("Could not start update check: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_check_due() {
+        let day = CHECK_INTERVAL.as_secs();
+        assert!(is_check_due(10 * day, 0, 0));
+        assert!(!is_check_due(10 * day, 10 * day - 60, 0));
+        // A check that was started recently but didn't record anything isn't retried yet.
+        assert!(!is_check_due(10 * day, 5 * day, 10 * day - 60));
+        assert!(is_check_due(10 * day, 5 * day, 9 * day));
+    }
+}
@@ -0,0 +1,376 @@
+use crate::error::ESResult;
+use clap::ValueEnum;
+use derive_more::Display;
+use error_stack::{Context, Report, ResultExt};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tracing::debug;
+
+#[derive(Debug, Display)]
+#[display("Update schedule error")]
+pub struct UpdateScheduleError;
+
+impl Context for UpdateScheduleError {}
+
+/// Name of the systemd units and suffix of the launchd label.
+const UNIT_NAME: &str = "jpre-update";
+const LAUNCHD_LABEL: &str = "net.octyl.jpre.update";
+/// Marks the crontab line jpre owns, so it can be replaced and removed.
+const CRON_MARKER: &str = "# jpre update schedule";
+
+/// How often scheduled updates run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, ValueEnum)]
+pub enum Frequency {
+    #[display("daily")]
+    Daily,
+    #[display("weekly")]
+    Weekly,
+    #[display("monthly")]
+    Monthly,
+}
+
+/// What runs scheduled updates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum Scheduler {
+    #[display("systemd user timer")]
+    Systemd,
+    #[display("launchd agent")]
+    Launchd,
+    #[display("crontab entry")]
+    Cron,
+}
+
+impl Scheduler {
+    /// launchd on macOS, a systemd user timer where a user manager runs, otherwise cron.
+    pub fn detect() -> Self {
+        if cfg!(target_os = "macos") {
+            return Scheduler::Launchd;
+        }
+        let systemd_user = Command::new("systemctl")
+            .args(["--user", "show-environment"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success());
+        if systemd_user {
+            Scheduler::Systemd
+        } else {
+            Scheduler::Cron
+        }
+    }
+
+    /// Run `jpre update all --quiet` with `frequency`, replacing an earlier schedule.
+    pub fn install(self, frequency: Frequency) -> ESResult<(), UpdateScheduleError> {
+        let jpre = std::env::current_exe()
+            .and_then(|exe| exe.canonicalize())
+            .change_context(UpdateScheduleError)
+            .attach_printable("Could not find the jpre executable")?;
+        match self {
+            Scheduler::Systemd => {
+                let dir = systemd_unit_dir()?;
+                write_file(
+                    &dir.join(format!("{}.service", UNIT_NAME)),
+                    &systemd_service(&jpre),
+                )?;
+                write_file(
+                    &dir.join(format!("{}.timer", UNIT_NAME)),
+                    &systemd_timer(frequency),
+                )?;
+                run(Command::new("systemctl").args(["--user", "daemon-reload"]))?;
+                run(Command::new("systemctl").args([
+                    "--user",
+                    "enable",
+                    "--now",
+                    &format!("{}.timer", UNIT_NAME),
+                ]))
+            }
+            Scheduler::Launchd => {
+                let plist = launchd_plist_path()?;
+                // Unload the old agent first, launchd keeps its schedule otherwise.
+                if plist.exists() {
+                    let _ = run(Command::new("launchctl").arg("unload").arg(&plist));
+                }
+                write_file(&plist, &launchd_plist(&jpre, frequency))?;
+                run(Command::new("launchctl").args(["load", "-w"]).arg(&plist))
+            }
+            Scheduler::Cron => {
+                let entry = cron_entry(&jpre, frequency);
+                write_crontab(&with_cron_entry(&read_crontab()?, Some(&entry)))
+            }
+        }
+    }
+
+    /// Remove the schedule, returning whether there was one.
+    pub fn remove(self) -> ESResult<bool, UpdateScheduleError> {
+        match self {
+            Scheduler::Systemd => {
+                let dir = systemd_unit_dir()?;
+                let timer = dir.join(format!("{}.timer", UNIT_NAME));
+                if !timer.exists() {
+                    return Ok(false);
+                }
+                run(Command::new("systemctl").args([
+                    "--user",
+                    "disable",
+                    "--now",
+                    &format!("{}.timer", UNIT_NAME),
+                ]))?;
+                remove_file(&timer)?;
+                remove_file(&dir.join(format!("{}.service", UNIT_NAME)))?;
+                run(Command::new("systemctl").args(["--user", "daemon-reload"]))?;
+                Ok(true)
+            }
+            Scheduler::Launchd => {
+                let plist = launchd_plist_path()?;
+                if !plist.exists() {
+                    return Ok(false);
+                }
+                run(Command::new("launchctl").args(["unload", "-w"]).arg(&plist))?;
+                remove_file(&plist)?;
+                Ok(true)
+            }
+            Scheduler::Cron => {
+                let crontab = read_crontab()?;
+                let updated = with_cron_entry(&crontab, None);
+                if updated == crontab {
+                    return Ok(false);
+                }
+                write_crontab(&updated)?;
+                Ok(true)
+            }
+        }
+    }
+}
+
+fn home_dir() -> ESResult<PathBuf, UpdateScheduleError> {
+    Ok(directories::BaseDirs::new()
+        .ok_or_else(|| {
+            Report::new(UpdateScheduleError).attach_printable("Could not find home directory")
+        })?
+        .home_dir()
+        .to_owned())
+}
+
+fn systemd_unit_dir() -> ESResult<PathBuf, UpdateScheduleError> {
+    Ok(directories::BaseDirs::new()
+        .ok_or_else(|| {
+            Report::new(UpdateScheduleError).attach_printable("Could not find config directory")
+        })?
+        .config_dir()
+        .join("systemd/user"))
+}
+
+fn launchd_plist_path() -> ESResult<PathBuf, UpdateScheduleError> {
+    Ok(home_dir()?
+        .join("Library/LaunchAgents")
+        .join(format!("{}.plist", LAUNCHD_LABEL)))
+}
+
+fn systemd_service(jpre: &Path) -> String {
+    format!(
+        "[Unit]\n\
+         Description=Update JDKs installed by jpre\n\
+         \n\
+         [Service]\n\
+         Type=oneshot\n\
+         ExecStart=\"{}\" update all --quiet\n",
+        jpre.display()
+    )
+}
+
+fn systemd_timer(frequency: Frequency) -> String {
+    format!(
+        "[Unit]\n\
+         Description=Update JDKs installed by jpre {frequency}\n\
+         \n\
+         [Timer]\n\
+         OnCalendar={frequency}\n\
+         Persistent=true\n\
+         RandomizedDelaySec=1h\n\
+         \n\
+         [Install]\n\
+         WantedBy=timers.target\n"
+    )
+}
+
+fn launchd_plist(jpre: &Path, frequency: Frequency) -> String {
+    // Midday, when the machine is more likely to be awake than at midnight.
+    let interval = match frequency {
+        Frequency::Daily => "",
+        Frequency::Weekly => "<key>Weekday</key><integer>1</integer>",
+        Frequency::Monthly => "<key>Day</key><integer>1</integer>",
+    };
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{LAUNCHD_LABEL}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{}</string>
+        <string>update</string>
+        <string>all</string>
+        <string>--quiet</string>
+    </array>
+    <key>StartCalendarInterval</key>
+    <dict>{interval}<key>Hour</key><integer>12</integer><key>Minute</key><integer>0</integer></dict>
+</dict>
+</plist>
+"#,
+        xml_escape(&jpre.display().to_string())
+    )
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn cron_entry(jpre: &Path, frequency: Frequency) -> String {
+    format!(
+        "@{} '{}' update all --quiet {}",
+        frequency,
+        jpre.display().to_string().replace('\'', r"'\''"),
+        CRON_MARKER
+    )
+}
+
+/// `crontab` without jpre's entry, and with `entry` added if given.
+fn with_cron_entry(crontab: &str, entry: Option<&str>) -> String {
+    let mut lines = crontab
+        .lines()
+        .filter(|line| !line.ends_with(CRON_MARKER))
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+    lines.extend(entry.map(str::to_owned));
+    let mut updated = lines.join("\n");
+    // cron ignores a last line without a newline.
+    if !updated.is_empty() {
+        updated.push('\n');
+    }
+    updated
+}
+
+fn read_crontab() -> ESResult<String, UpdateScheduleError> {
+    let output = Command::new("crontab")
+        .arg("-l")
+        .output()
+        .change_context(UpdateScheduleError)
+        .attach_printable("Could not run crontab")?;
+    if output.status.success() {
+        return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stderr.contains("no crontab") {
+        debug!("No crontab yet: {}", stderr.trim());
+        return Ok(String::new());
+    }
+    Err(Report::new(UpdateScheduleError).attach_printable(format!(
+        "crontab -l failed with {}: {}",
+        output.status,
+        stderr.trim()
+    )))
+}
+
+fn write_crontab(crontab: &str) -> ESResult<(), UpdateScheduleError> {
+    let mut child = Command::new("crontab")
+        .arg("-")
+        .stdin(Stdio::piped())
+        .spawn()
+        .change_context(UpdateScheduleError)
+        .attach_printable("Could not run crontab")?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(crontab.as_bytes())
+        .change_context(UpdateScheduleError)
+        .attach_printable("Could not write to crontab")?;
+    let status = child
+        .wait()
+        .change_context(UpdateScheduleError)
+        .attach_printable("Could not run crontab")?;
+    if !status.success() {
+        return Err(Report::new(UpdateScheduleError)
+            .attach_printable(format!("crontab failed with {}", status)));
+    }
+    Ok(())
+}
+
+fn run(command: &mut Command) -> ESResult<(), UpdateScheduleError> {
+    debug!("Running {:?}", command);
+    let status = command
+        .status()
+        .change_context(UpdateScheduleError)
+        .attach_printable_lazy(|| format!("Could not run {:?}", command))?;
+    if !status.success() {
+        return Err(Report::new(UpdateScheduleError)
+            .attach_printable(format!("{:?} failed with {}", command, status)));
+    }
+    Ok(())
+}
+
+fn write_file(path: &Path, contents: &str) -> ESResult<(), UpdateScheduleError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .change_context(UpdateScheduleError)
+            .attach_printable_lazy(|| format!("Could not create directory {:?}", parent))?;
+    }
+    std::fs::write(path, contents)
+        .change_context(UpdateScheduleError)
+        .attach_printable_lazy(|| format!("Could not write {:?}", path))
+}
+
+fn remove_file(path: &Path) -> ESResult<(), UpdateScheduleError> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(Report::new(e)
+            .change_context(UpdateScheduleError)
+            .attach_printable(format!("Could not remove {:?}", path))),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_with_cron_entry() {
+        let jpre = Path::new("/home/me/bin/jpre");
+        let existing = "MAILTO=me\n0 * * * * backup\n";
+        let installed = with_cron_entry(existing, Some(&cron_entry(jpre, Frequency::Weekly)));
+        assert_eq!(
+            "MAILTO=me\n0 * * * * backup\n\
+             @weekly '/home/me/bin/jpre' update all --quiet # jpre update schedule\n",
+            installed
+        );
+        // Installing again replaces the entry.
+        let reinstalled = with_cron_entry(&installed, Some(&cron_entry(jpre, Frequency::Daily)));
+        assert_eq!(
+            "MAILTO=me\n0 * * * * backup\n\
+             @daily '/home/me/bin/jpre' update all --quiet # jpre update schedule\n",
+            reinstalled
+        );
+        assert_eq!(existing, with_cron_entry(&reinstalled, None));
+        assert_eq!("", with_cron_entry("", None));
+    }
+
+    #[test]
+    fn test_cron_entry_quotes_path() {
+        assert_eq!(
+            r"@monthly '/opt/it'\''s/jpre' update all --quiet # jpre update schedule",
+            cron_entry(Path::new("/opt/it's/jpre"), Frequency::Monthly)
+        );
+    }
+
+    #[test]
+    fn test_systemd_timer() {
+        let timer = systemd_timer(Frequency::Weekly);
+        assert!(timer.contains("\nOnCalendar=weekly\n"), "{}", timer);
+        assert!(timer.contains("\nPersistent=true\n"), "{}", timer);
+    }
+}
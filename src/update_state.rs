@@ -0,0 +1,76 @@
+use crate::context_id::state_dir;
+use crate::error::ESResult;
+use crate::java_version::key::VersionKey;
+use crate::java_version::JavaVersion;
+use derive_more::Display;
+use error_stack::{Context, ResultExt};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+#[derive(Debug, Display)]
+pub struct UpdateStateError;
+
+impl Context for UpdateStateError {}
+
+const UPDATE_STATE_FILE_NAME: &str = "updates.json";
+
+fn update_state_path() -> PathBuf {
+    state_dir().join(UPDATE_STATE_FILE_NAME)
+}
+
+/// The result of the last update check, so cheap commands like `prompt-segment` can show it
+/// without asking Foojay.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct UpdateState {
+    /// Seconds since the Unix epoch.
+    pub checked_at: u64,
+    /// The latest version of each installed JDK that has an update.
+    pub available: BTreeMap<VersionKey, JavaVersion>,
+}
+
+impl UpdateState {
+    /// The recorded state, if there is a readable one.
+    pub fn read() -> Option<UpdateState> {
+        let contents = std::fs::read_to_string(update_state_path()).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    /// Record the updates found for `checked`, keeping what was recorded for other JDKs.
+    pub fn record(
+        checked: &[VersionKey],
+        available: BTreeMap<VersionKey, JavaVersion>,
+    ) -> ESResult<(), UpdateStateError> {
+        let mut state = Self::read().unwrap_or_default();
+        state.available.retain(|jdk, _| !checked.contains(jdk));
+        state.available.extend(available);
+        state.checked_at = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = update_state_path();
+        let dir = path.parent().unwrap();
+        std::fs::create_dir_all(dir)
+            .change_context(UpdateStateError)
+            .attach_printable_lazy(|| format!("Could not create {:?}", dir))?;
+        // Write to a temporary file first, so prompts never read a partial state.
+        let temp = tempfile::NamedTempFile::new_in(dir)
+            .change_context(UpdateStateError)
+            .attach_printable_lazy(|| {
+                format!(
+                    "Could not create temporary file for update state in {:?}",
+                    dir
+                )
+            })?;
+        serde_json::to_writer(std::io::BufWriter::new(temp.as_file()), &state)
+            .change_context(UpdateStateError)
+            .attach_printable_lazy(|| {
+                format!("Could not write update state to {:?}", temp.path())
+            })?;
+        temp.persist(&path)
+            .change_context(UpdateStateError)
+            .attach_printable_lazy(|| format!("Could not move update state to {:?}", path))?;
+        Ok(())
+    }
+}
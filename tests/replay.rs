@@ -0,0 +1,728 @@
+//! End-to-end tests against recorded Foojay responses, see `JPRE_REPLAY_FIXTURES`. Each test runs
+//! jpre in its own home directory, so nothing is shared with the user's jpre or other tests.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+struct Sandbox {
+    home: tempfile::TempDir,
+}
+
+impl Sandbox {
+    fn new() -> Sandbox {
+        let home = tempfile::tempdir().unwrap();
+        let config_dir = home.path().join("config/jpre");
+        std::fs::create_dir_all(&config_dir).unwrap();
+        // The fixtures were recorded for linux x64, so don't depend on the machine running the tests.
+        std::fs::write(
+            config_dir.join("config.toml"),
+            "distributions = [\"temurin\"]\nforced_os = \"linux\"\nforced_architecture = \"x64\"\n",
+        )
+        .unwrap();
+        Sandbox { home }
+    }
+
+    fn path(&self, path: &str) -> PathBuf {
+        self.home.path().join(path)
+    }
+
+    /// A jpre command with the responses recorded in `tests/fixtures/replay/<fixtures>`.
+    fn command(&self, fixtures: &str, args: &[&str]) -> Command {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/replay")
+            .join(fixtures);
+        let mut command = Command::new(env!("CARGO_BIN_EXE_jpre"));
+        command
+            .args(args)
+            .env_clear()
+            .env("PATH", std::env::var_os("PATH").unwrap_or_default())
+            .env("HOME", self.home.path())
+            .env("XDG_CONFIG_HOME", self.path("config"))
+            .env("XDG_CACHE_HOME", self.path("cache"))
+            .env("XDG_STATE_HOME", self.path("state"))
+            .env("JPRE_CONTEXT_ID", "replay-test")
+            .env("JPRE_REPLAY_FIXTURES", fixtures);
+        command
+    }
+
+    /// Run jpre with the responses recorded in `tests/fixtures/replay/<fixtures>`, and extra
+    /// environment variables `env`. Doesn't check that it succeeded.
+    fn run(&self, fixtures: &str, args: &[&str], env: &[(&str, &str)]) -> Output {
+        self.command(fixtures, args)
+            .envs(env.iter().copied())
+            .output()
+            .unwrap()
+    }
+
+    /// Run jpre with the responses recorded in `tests/fixtures/replay/<fixtures>`.
+    fn jpre(&self, fixtures: &str, args: &[&str]) -> Output {
+        let output = self.run(fixtures, args, &[]);
+        assert!(
+            output.status.success(),
+            "jpre {:?} failed: {}\n{}",
+            args,
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        output
+    }
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn list_versions() {
+    let sandbox = Sandbox::new();
+    let output = sandbox.jpre("temurin-21.0.1", &["list-versions"]);
+    // Foojay's major versions only go back to 20.
+    assert_eq!("- 21\n- 11\n- 17\n", stdout(&output));
+    let headers = stderr(&output);
+    assert!(headers.contains("LTS:\n"), "{}", headers);
+    assert!(
+        headers.contains("Unknown term of support:\n"),
+        "{}",
+        headers
+    );
+    assert!(!headers.contains("Non-LTS:"), "{}", headers);
+}
+
+#[test]
+fn api_dump_shows_the_package_use_would_install() {
+    let sandbox = Sandbox::new();
+    let output = sandbox.jpre("temurin-21.0.1", &["api-dump", "21"]);
+    let dump: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    let temurin = &dump["temurin"];
+    assert_eq!(true, temurin["selected"]);
+    assert_eq!(
+        "OpenJDK21U-jdk_x64_linux_hotspot_21_0_1_12.tar.gz",
+        temurin["package_info"]["result"][0]["filename"]
+    );
+}
+
+#[test]
+fn list_versions_lts_only() {
+    let sandbox = Sandbox::new();
+    let output = sandbox.jpre("temurin-21.0.1", &["list-versions", "--lts-only"]);
+    assert_eq!("- 21\n", stdout(&output));
+    assert!(!stderr(&output).contains("Unknown term of support:"));
+}
+
+#[test]
+fn use_installs_and_links() {
+    let sandbox = Sandbox::new();
+    let output = sandbox.jpre("temurin-21.0.1", &["use", "21"]);
+    assert!(stderr(&output).contains("Installed JDK 21.0.1+12"));
+
+    let jdk = sandbox.path("cache/jpre/jdks/21");
+    assert_eq!(
+        "21.0.1+12",
+        std::fs::read_to_string(jdk.join(".jdk_marker_with_version")).unwrap()
+    );
+    assert!(jdk.join("bin/java").exists());
+    let link = sandbox.path("state/jpre/java-home-by-pid/replay-test");
+    assert_eq!(jdk, std::fs::read_link(link).unwrap());
+}
+
+#[test]
+fn update_installs_newer_build() {
+    let sandbox = Sandbox::new();
+    sandbox.jpre("temurin-21.0.1", &["use", "21"]);
+
+    let output = sandbox.jpre("temurin-21.0.1", &["update", "21"]);
+    assert!(stderr(&output).contains("Already up-to-date: 21.0.1+12"));
+
+    let output = sandbox.jpre("temurin-21.0.2", &["update", "21"]);
+    assert!(stderr(&output).contains("Installed JDK 21.0.2+13"));
+    let output = sandbox.jpre("temurin-21.0.2", &["list-installed"]);
+    assert!(stdout(&output).contains("21 (full: 21.0.2+13)"));
+}
+
+#[test]
+fn pack_and_unpack_elsewhere() {
+    let sandbox = Sandbox::new();
+    sandbox.jpre("temurin-21.0.1", &["use", "21"]);
+    let archive = sandbox.path("jdk21.tar.gz");
+    sandbox.jpre(
+        "temurin-21.0.1",
+        &["pack", "21", "-o", archive.to_str().unwrap()],
+    );
+
+    let other = Sandbox::new();
+    let output = other.jpre("temurin-21.0.1", &["unpack", archive.to_str().unwrap()]);
+    assert!(stderr(&output).contains("Installed JDK 21 (21.0.1+12)"));
+    let jdk = other.path("cache/jpre/jdks/21");
+    assert!(jdk.join("bin/java").exists());
+    let output = other.jpre("temurin-21.0.1", &["info", "--installed", "21"]);
+    assert!(stdout(&output).contains("Distribution: temurin"));
+}
+
+#[test]
+fn pack_and_unpack_keeps_feature_key() {
+    let sandbox = Sandbox::new();
+    sandbox.jpre("temurin-21.0.1", &["install", "21"]);
+    // Stands in for a CRaC build, which has the same kind of version as the regular one.
+    std::fs::rename(
+        sandbox.path("cache/jpre/jdks/21"),
+        sandbox.path("cache/jpre/jdks/21-crac"),
+    )
+    .unwrap();
+    let archive = sandbox.path("jdk21-crac.tar.gz");
+    sandbox.jpre(
+        "temurin-21.0.1",
+        &["pack", "21-crac", "-o", archive.to_str().unwrap()],
+    );
+
+    let other = Sandbox::new();
+    other.jpre("temurin-21.0.1", &["install", "21"]);
+    other.jpre("temurin-21.0.1", &["unpack", archive.to_str().unwrap()]);
+    let output = other.jpre("temurin-21.0.1", &["list-installed"]);
+    assert!(stdout(&output).contains("21-crac (full: 21.0.1+12)"));
+    assert!(stdout(&output).contains("21 (full: 21.0.1+12)"));
+}
+
+#[test]
+fn update_json_has_schema_version() {
+    let sandbox = Sandbox::new();
+    sandbox.jpre("temurin-21.0.1", &["use", "21"]);
+    let output = sandbox.jpre(
+        "temurin-21.0.2",
+        &["update", "21", "--check", "--output", "json"],
+    );
+    let report: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(1, report["schema_version"]);
+    assert_eq!("available", report["results"][0]["action"]);
+}
+
+#[test]
+fn use_warns_about_other_java_home() {
+    let sandbox = Sandbox::new();
+    let env = [("JAVA_HOME", "/opt/other-jdk")];
+    let output = sandbox.run("temurin-21.0.1", &["use", "21"], &env);
+    assert!(output.status.success());
+    // Warnings are logged to stdout.
+    assert!(stdout(&output).contains("JAVA_HOME is set to /opt/other-jdk"));
+    assert!(sandbox
+        .path("state/jpre/java-home-by-pid/replay-test")
+        .exists());
+
+    let output = sandbox.run("temurin-21.0.1", &["use", "21", "--force"], &env);
+    assert!(output.status.success());
+    assert!(!stdout(&output).contains("JAVA_HOME is set to"));
+
+    // Like `direnv` exports it.
+    let store_jdk = sandbox.path("cache/jpre/jdks/21");
+    let env = [("JAVA_HOME", store_jdk.to_str().unwrap())];
+    let output = sandbox.run("temurin-21.0.1", &["use", "21"], &env);
+    assert!(output.status.success());
+    assert!(!stdout(&output).contains("JAVA_HOME is set to"));
+}
+
+#[test]
+fn use_dry_run_downloads_nothing() {
+    let sandbox = Sandbox::new();
+    let output = sandbox.jpre("temurin-21.0.1", &["use", "21", "--dry-run"]);
+    assert!(stderr(&output).contains("Would download JDK 21 (21.0.1+12) from temurin"));
+    assert!(!sandbox.path("cache/jpre/jdks/21").exists());
+    assert!(!sandbox
+        .path("state/jpre/java-home-by-pid/replay-test")
+        .exists());
+}
+
+#[test]
+fn verify_deep_finds_modified_file() {
+    let sandbox = Sandbox::new();
+    let config = sandbox.path("config/jpre/config.toml");
+    let contents = std::fs::read_to_string(&config).unwrap();
+    std::fs::write(&config, contents + "tree_manifest = true\n").unwrap();
+    sandbox.jpre("temurin-21.0.1", &["install", "21"]);
+    let output = sandbox.jpre("temurin-21.0.1", &["verify", "--deep", "21"]);
+    assert!(stderr(&output).contains("JDK 21 matches its manifest"));
+
+    let release = sandbox.path("cache/jpre/jdks/21/release");
+    let contents = std::fs::read(&release).unwrap();
+    std::fs::write(&release, contents.to_ascii_lowercase()).unwrap();
+    let output = sandbox.run("temurin-21.0.1", &["verify", "21"], &[]);
+    assert!(output.status.success());
+    let output = sandbox.run("temurin-21.0.1", &["verify", "--deep", "21"], &[]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("release was modified"));
+}
+
+#[test]
+fn use_from_manifest_without_foojay() {
+    let online = Sandbox::new();
+    let output = online.jpre("temurin-21.0.1", &["info", "21", "--manifest"]);
+    let manifest = online.path("manifest.toml");
+    std::fs::write(&manifest, stdout(&output)).unwrap();
+
+    // Only the archive is available, every Foojay request would fail.
+    let offline = Sandbox::new();
+    let fixtures = offline.path("fixtures");
+    std::fs::create_dir(&fixtures).unwrap();
+    let recorded =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/replay/temurin-21.0.1");
+    for entry in std::fs::read_dir(recorded).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|e| e == "download") {
+            std::fs::copy(&path, fixtures.join(path.file_name().unwrap())).unwrap();
+        }
+    }
+    let output = offline.jpre(
+        fixtures.to_str().unwrap(),
+        &["use", "21", "--from-manifest", manifest.to_str().unwrap()],
+    );
+    assert!(stderr(&output).contains("Installed JDK 21.0.1+12"));
+    assert!(offline.path("cache/jpre/jdks/21/bin/java").exists());
+}
+
+#[test]
+fn cache_verify_downloads_removes_corrupted_archive() {
+    let sandbox = Sandbox::new();
+    let config = sandbox.path("config/jpre/config.toml");
+    let contents = std::fs::read_to_string(&config).unwrap();
+    std::fs::write(&config, contents + "keep_downloads = true\n").unwrap();
+    sandbox.jpre("temurin-21.0.1", &["install", "21"]);
+    let output = sandbox.jpre("temurin-21.0.1", &["cache", "verify-downloads"]);
+    assert!(stderr(&output).contains("Verified 1 cached archive(s), removed 0 corrupted"));
+
+    let downloads = sandbox.path("cache/jpre/downloads");
+    let cache_dir = std::fs::read_dir(&downloads)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .find(|p| p.is_dir())
+        .unwrap();
+    let archive = std::fs::read_dir(&cache_dir)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    std::fs::write(&archive, "not a JDK").unwrap();
+    let output = sandbox.jpre("temurin-21.0.1", &["cache", "verify-downloads"]);
+    assert!(stderr(&output).contains("Removed corrupted"));
+    assert!(!cache_dir.exists());
+}
+
+#[test]
+fn current_exit_code_without_active_jdk() {
+    let sandbox = Sandbox::new();
+    let output = sandbox.run("temurin-21.0.1", &["current", "--quiet"], &[]);
+    assert_eq!(Some(3), output.status.code());
+    assert_eq!("", stdout(&output));
+
+    sandbox.jpre("temurin-21.0.1", &["install", "21"]);
+    sandbox.jpre("temurin-21.0.1", &["default", "21"]);
+    let output = sandbox.run("temurin-21.0.1", &["current"], &[]);
+    assert_eq!(Some(3), output.status.code());
+    assert_eq!("<unknown>\n", stdout(&output));
+    let output = sandbox.jpre("temurin-21.0.1", &["current", "--fallback", "default"]);
+    assert_eq!("21.0.1+12\n", stdout(&output));
+}
+
+#[test]
+fn use_if_missing_stays_offline_when_installed() {
+    let sandbox = Sandbox::new();
+    sandbox.jpre("temurin-21.0.1", &["install", "21"]);
+    // No recorded responses, so any Foojay request fails.
+    let output = sandbox.jpre(
+        "missing",
+        &["use", "21", "--if-missing", "--distribution", "zulu"],
+    );
+    assert!(stderr(&output).contains("Using JDK 21"));
+}
+
+#[test]
+fn update_only_installed_skips_missing_key() {
+    let sandbox = Sandbox::new();
+    let output = sandbox.jpre("temurin-21.0.1", &["update", "17"]);
+    assert!(stdout(&output).contains("JDK 17 is not installed"));
+    let output = sandbox.jpre("temurin-21.0.1", &["update", "17", "--only-installed"]);
+    assert!(!stdout(&output).contains("not installed"));
+    assert!(!sandbox.path("cache/jpre/jdks/17").exists());
+}
+
+#[test]
+fn migrate_markers_reads_release_file() {
+    let sandbox = Sandbox::new();
+    sandbox.jpre("temurin-21.0.1", &["install", "21"]);
+    let jdk = sandbox.path("cache/jpre/jdks/21");
+    std::fs::remove_file(jdk.join(".jdk_marker_with_version")).unwrap();
+    std::fs::write(jdk.join(".jdk_marker"), "").unwrap();
+
+    let output = sandbox.jpre("temurin-21.0.1", &["migrate-markers"]);
+    assert!(stderr(&output).contains("Migrated JDK 21, recorded version 21.0.1"));
+    assert_eq!(
+        "21.0.1",
+        std::fs::read_to_string(jdk.join(".jdk_marker_with_version")).unwrap()
+    );
+    assert!(!jdk.join(".jdk_marker").exists());
+    let output = sandbox.jpre("temurin-21.0.1", &["migrate-markers"]);
+    assert!(stderr(&output).contains("No JDKs with a legacy marker"));
+}
+
+#[test]
+fn platform_flags_override_detection() {
+    let sandbox = Sandbox::new();
+    std::fs::write(
+        sandbox.path("config/jpre/config.toml"),
+        "distributions = [\"temurin\"]\n",
+    )
+    .unwrap();
+    let output = sandbox.jpre(
+        "temurin-21.0.1",
+        &["install", "21", "--os", "linux", "--arch", "x64"],
+    );
+    assert!(stderr(&output).contains("Installed JDK 21.0.1+12"));
+
+    // Only x64 is recorded, so the query for another architecture has no fixture.
+    let arch = if cfg!(target_arch = "aarch64") {
+        "x64"
+    } else {
+        "arm64"
+    };
+    let output = sandbox.run(
+        "temurin-21.0.1",
+        &["install", "17", "--os", "linux", "--arch", arch],
+        &[],
+    );
+    assert!(!output.status.success());
+    assert!(
+        stdout(&output).contains(&format!("operating_system=linux&architecture={}", arch)),
+        "{}",
+        stdout(&output)
+    );
+}
+
+#[test]
+fn serve_answers_queries_on_socket() {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+
+    let sandbox = Sandbox::new();
+    sandbox.jpre("temurin-21.0.1", &["use", "21"]);
+    let socket = sandbox.path("jpre.sock");
+    let mut server = sandbox
+        .command(
+            "temurin-21.0.1",
+            &["serve", "--socket", socket.to_str().unwrap()],
+        )
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let stream = (0..100)
+        .find_map(|_| {
+            UnixStream::connect(&socket).ok().or_else(|| {
+                std::thread::sleep(std::time::Duration::from_millis(50));
+                None
+            })
+        })
+        .expect("server didn't start");
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request = |line: &str| {
+        writeln!(&stream, "{}", line).unwrap();
+        let mut response = String::new();
+        reader.read_line(&mut response).unwrap();
+        response
+    };
+
+    let installed = request(r#"{"jsonrpc":"2.0","id":1,"method":"installed"}"#);
+    assert!(installed.contains(r#""key":"21""#), "{}", installed);
+    assert!(
+        installed.contains(r#""version":"21.0.1+12""#),
+        "{}",
+        installed
+    );
+    let current = request(
+        r#"{"jsonrpc":"2.0","id":2,"method":"current","params":{"context_id":"replay-test"}}"#,
+    );
+    assert!(current.contains(r#""key":"21""#), "{}", current);
+    let missing = request(r#"{"jsonrpc":"2.0","id":3,"method":"resolve","params":{"jdk":"17"}}"#);
+    assert!(missing.contains(r#""result":null"#), "{}", missing);
+    let unknown = request(r#"{"jsonrpc":"2.0","id":4,"method":"install"}"#);
+    assert!(unknown.contains("-32601"), "{}", unknown);
+
+    let default = r#"{"jsonrpc":"2.0","id":5,"method":"resolve","params":{"jdk":"default"}}"#;
+    assert!(request(default).contains(r#""result":null"#));
+    // The config is read for each request, so the server sees the new default.
+    sandbox.jpre("temurin-21.0.1", &["default", "21"]);
+    let response = request(default);
+    assert!(response.contains(r#""key":"21""#), "{}", response);
+
+    // Notifications get no response, the next line answers the request after it.
+    writeln!(&stream, r#"{{"jsonrpc":"2.0","method":"installed"}}"#).unwrap();
+    let response = request(r#"{"jsonrpc":"2.0","id":6,"method":"installed"}"#);
+    assert!(response.contains(r#""id":6"#), "{}", response);
+
+    server.kill().unwrap();
+    server.wait().unwrap();
+}
+
+#[test]
+fn serve_keeps_files_that_are_not_sockets() {
+    let sandbox = Sandbox::new();
+    let notes = sandbox.path("notes.txt");
+    std::fs::write(&notes, "keep me").unwrap();
+    let output = sandbox.run(
+        "temurin-21.0.1",
+        &["serve", "--socket", notes.to_str().unwrap()],
+        &[],
+    );
+    assert!(!output.status.success());
+    assert!(stdout(&output).contains("exists and is not a socket"));
+    assert_eq!("keep me", std::fs::read_to_string(&notes).unwrap());
+}
+
+#[test]
+fn inventory_follows_installs_and_removals() {
+    let sandbox = Sandbox::new();
+    sandbox.jpre("temurin-21.0.1", &["install", "21"]);
+    let inventory = sandbox.path("state/jpre/inventory.json");
+    let read = || -> serde_json::Value {
+        serde_json::from_str(&std::fs::read_to_string(&inventory).unwrap()).unwrap()
+    };
+    let jdks = read()["jdks"].clone();
+    assert_eq!("21", jdks[0]["key"]);
+    assert_eq!("21.0.1+12", jdks[0]["version"]);
+    assert_eq!("temurin", jdks[0]["distribution"]);
+
+    // As if an update was interrupted after moving the old JDK aside.
+    std::fs::rename(
+        sandbox.path("cache/jpre/jdks/21"),
+        sandbox.path("cache/jpre/jdks/21.previous"),
+    )
+    .unwrap();
+    std::fs::write(&inventory, r#"{"jdks":[]}"#).unwrap();
+    // Commands that only read the store leave it alone.
+    sandbox.jpre("temurin-21.0.1", &["get-context-id"]);
+    assert!(sandbox.path("cache/jpre/jdks/21.previous").exists());
+    sandbox.jpre("temurin-21.0.1", &["verify-store"]);
+    assert!(!sandbox.path("cache/jpre/jdks/21.previous").exists());
+    assert_eq!("21", read()["jdks"][0]["key"]);
+
+    sandbox.jpre("temurin-21.0.1", &["remove", "21", "--yes"]);
+    assert_eq!(serde_json::json!([]), read()["jdks"]);
+}
+
+#[test]
+fn allowed_distributions_are_enforced() {
+    let sandbox = Sandbox::new();
+    std::fs::write(
+        sandbox.path("config/jpre/config.toml"),
+        "distributions = [\"zulu\", \"temurin\"]\nallowed_distributions = [\"temurin\"]\n\
+         forced_os = \"linux\"\nforced_architecture = \"x64\"\n",
+    )
+    .unwrap();
+    let output = sandbox.run(
+        "temurin-21.0.1",
+        &["use", "21", "--distribution", "zulu"],
+        &[],
+    );
+    assert!(!output.status.success());
+    // Errors are logged to stdout.
+    assert!(stdout(&output).contains(
+        "Distribution 'zulu' is not allowed by the config. Allowed distributions: temurin"
+    ));
+    let output = sandbox.run("temurin-21.0.1", &["set-distributions", "zulu"], &[]);
+    assert!(stdout(&output).contains("Distribution 'zulu' is not allowed"));
+
+    // Configured distributions that aren't allowed are skipped.
+    let output = sandbox.jpre("temurin-21.0.1", &["install", "21"]);
+    assert!(stderr(&output).contains("Installed JDK 21.0.1+12"));
+}
+
+#[test]
+fn print_context_path_writes_nothing() {
+    let sandbox = Sandbox::new();
+    let config = std::fs::read_to_string(sandbox.path("config/jpre/config.toml")).unwrap();
+    let output = sandbox.jpre("temurin-21.0.1", &["print-context-path"]);
+    assert_eq!(
+        format!(
+            "{}\n",
+            sandbox
+                .path("state/jpre/java-home-by-pid/replay-test")
+                .display()
+        ),
+        stdout(&output)
+    );
+    let output = sandbox.run("temurin-21.0.1", &["print-context-path", "--resolve"], &[]);
+    assert_eq!(Some(3), output.status.code());
+    // Not even the config is re-saved.
+    assert_eq!(
+        config,
+        std::fs::read_to_string(sandbox.path("config/jpre/config.toml")).unwrap()
+    );
+    assert!(!sandbox.path("state").exists());
+    assert!(!sandbox.path("cache").exists());
+
+    sandbox.jpre("temurin-21.0.1", &["use", "21"]);
+    let output = sandbox.jpre("temurin-21.0.1", &["print-context-path", "--resolve"]);
+    assert_eq!(
+        format!("{}\n", sandbox.path("cache/jpre/jdks/21").display()),
+        stdout(&output)
+    );
+}
+
+#[test]
+fn update_notice_after_command() {
+    let sandbox = Sandbox::new();
+    sandbox.jpre("temurin-21.0.1", &["use", "21"]);
+    let config = sandbox.path("config/jpre/config.toml");
+    let contents = std::fs::read_to_string(&config).unwrap();
+    std::fs::write(&config, contents + "update_notices = true\n").unwrap();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    // A fresh check, so nothing is started in the background.
+    std::fs::write(
+        sandbox.path("state/jpre/updates.json"),
+        format!(
+            r#"{{"checked_at":{},"available":{{"21":"21.0.2+13"}}}}"#,
+            now
+        ),
+    )
+    .unwrap();
+
+    let output = sandbox.jpre("temurin-21.0.1", &["current"]);
+    assert!(
+        stderr(&output).contains("JDK 21.0.2+13 is available, run `jpre update 21` to install it"),
+        "{}",
+        stderr(&output)
+    );
+    assert!(!sandbox.path("state/jpre/update-check-started").exists());
+}
+
+#[test]
+fn list_installed_shows_contexts_in_use() {
+    let sandbox = Sandbox::new();
+    sandbox.jpre("temurin-21.0.1", &["use", "21"]);
+    let output = sandbox.jpre("temurin-21.0.1", &["list-installed"]);
+    assert!(
+        stdout(&output).contains("- 21 (full: 21.0.1+12) (in use by 1 shell)"),
+        "{}",
+        stdout(&output)
+    );
+
+    let output = sandbox.jpre("temurin-21.0.1", &["remove", "21", "--yes"]);
+    assert!(stderr(&output).contains(", in use by 1 shell)"));
+    assert!(stdout(&output).contains("left without a working JAVA_HOME"));
+}
+
+#[test]
+fn list_installed_works_offline() {
+    let sandbox = Sandbox::new();
+    sandbox.jpre("temurin-21.0.1", &["install", "21"]);
+    // No fixtures at all, like having no network.
+    let output = sandbox.jpre("offline", &["list-installed"]);
+    assert_eq!(stdout(&output), "- 21 (full: 21.0.1+12)\n");
+
+    let output = sandbox.run("offline", &["list-installed", "--fail-on-eol"], &[]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn hook_env_exports_once_per_shell() {
+    let sandbox = Sandbox::new();
+    sandbox.jpre("temurin-21.0.1", &["use", "21"]);
+    let output = sandbox.jpre("temurin-21.0.1", &["hook-env"]);
+    let code = stdout(&output);
+    assert!(code.contains("export JAVA_HOME="), "{}", code);
+    let state = code
+        .lines()
+        .find_map(|l| l.strip_prefix("export JPRE_HOOK_STATE="))
+        .unwrap()
+        .trim_end_matches(';');
+
+    let output = sandbox.run(
+        "temurin-21.0.1",
+        &["hook-env"],
+        &[("JPRE_HOOK_STATE", state)],
+    );
+    assert!(output.status.success());
+    assert_eq!("", stdout(&output));
+
+    // A new shell with the same context ID hasn't seen the export yet.
+    let output = sandbox.jpre("temurin-21.0.1", &["hook-env"]);
+    assert!(stdout(&output).contains("export JAVA_HOME="));
+}
+
+#[test]
+fn remember_needs_a_named_context() {
+    let sandbox = Sandbox::new();
+    // Without a name, the context is the shell's PID.
+    let output = sandbox.run(
+        "temurin-21.0.1",
+        &["use", "21", "--remember"],
+        &[("JPRE_CONTEXT_ID", "")],
+    );
+    assert!(!output.status.success());
+    assert!(stdout(&output).contains("identified by its shell's PID"));
+    assert!(!sandbox.path("state/jpre/remembered").exists());
+
+    sandbox.jpre("temurin-21.0.1", &["use", "21", "--remember"]);
+    // Left behind by an older version, for a PID that can't be running.
+    std::fs::write(sandbox.path("state/jpre/remembered/4294967295"), "21\n").unwrap();
+    sandbox.jpre("temurin-21.0.1", &["gc"]);
+    assert!(!sandbox.path("state/jpre/remembered/4294967295").exists());
+    assert!(sandbox.path("state/jpre/remembered/replay-test").exists());
+}
+
+#[test]
+fn shell_contexts_are_cleaned_up() {
+    let sandbox = Sandbox::new();
+    let links = sandbox.path("state/jpre/java-home-by-pid");
+    let output = sandbox.run(
+        "temurin-21.0.1",
+        &["shell", "21"],
+        &[("SHELL", "/bin/false")],
+    );
+    assert_eq!(Some(1), output.status.code());
+    assert!(stderr(&output).contains("Starting /bin/false with JDK 21"));
+    assert_eq!(0, std::fs::read_dir(&links).unwrap().count());
+
+    // Left behind by a jpre that was killed, with a PID that can't be running.
+    let jdk = sandbox.path("cache/jpre/jdks/21");
+    std::os::unix::fs::symlink(&jdk, links.join("shell-4294967295")).unwrap();
+    sandbox.jpre("temurin-21.0.1", &["gc"]);
+    assert!(!links.join("shell-4294967295").exists());
+}
+
+#[test]
+fn linked_imports_keep_jpre_files_out_of_the_jdk() {
+    let sandbox = Sandbox::new();
+    let sdkman_jdk = sandbox.path("sdkman/candidates/java/17.0.9-tem");
+    std::fs::create_dir_all(sdkman_jdk.join("bin")).unwrap();
+    std::fs::write(sdkman_jdk.join("bin/java"), "").unwrap();
+    std::fs::write(sdkman_jdk.join("release"), "JAVA_VERSION=\"17.0.9\"\n").unwrap();
+    let sdkman = sandbox.path("sdkman");
+    let env = [("SDKMAN_DIR", sdkman.to_str().unwrap())];
+    let output = sandbox.run(
+        "temurin-21.0.1",
+        &["migrate-from", "sdkman", "--link"],
+        &env,
+    );
+    assert!(output.status.success(), "{}", stdout(&output));
+
+    let mut files = std::fs::read_dir(&sdkman_jdk)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    files.sort();
+    assert_eq!(vec!["bin", "release"], files);
+    let output = sandbox.jpre("temurin-21.0.1", &["list-installed"]);
+    assert!(
+        stdout(&output).contains("- 17 (full: 17.0.9)"),
+        "{}",
+        stdout(&output)
+    );
+
+    sandbox.jpre("temurin-21.0.1", &["remove", "17", "--yes"]);
+    assert!(sdkman_jdk.join("release").exists());
+    assert!(!sandbox.path("cache/jpre/jdks/17.link").exists());
+}